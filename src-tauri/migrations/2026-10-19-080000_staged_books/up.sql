@@ -0,0 +1,9 @@
+-- Your SQL goes here
+CREATE TABLE staged_books
+(
+    isbn   VARCHAR NOT NULL PRIMARY KEY,
+    title  VARCHAR NOT NULL DEFAULT '',
+    author VARCHAR NOT NULL DEFAULT '',
+    items  INT     NOT NULL DEFAULT 1,
+    status VARCHAR NOT NULL DEFAULT 'pending' CHECK ( status IN ('pending', 'found', 'not_found', 'failed'))
+);
\ No newline at end of file
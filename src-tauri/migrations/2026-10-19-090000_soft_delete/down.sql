@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE books DROP COLUMN deletedAt;
+ALTER TABLE clients DROP COLUMN deletedAt;
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE books ADD COLUMN deletedAt TIMESTAMP;
+ALTER TABLE clients ADD COLUMN deletedAt TIMESTAMP;
\ No newline at end of file
@@ -0,0 +1,48 @@
+-- This file should undo anything in `up.sql`
+PRAGMA foreign_keys = OFF;
+BEGIN;
+
+DROP TABLE transfers;
+
+-- SQLite cannot drop a column used by a foreign key, so the table is copied without it
+CREATE TABLE users_without_branch
+(
+    username  VARCHAR NOT NULL PRIMARY KEY,
+    password  VARCHAR NOT NULL,
+    firstName VARCHAR NOT NULL,
+    lastName  VARCHAR NOT NULL,
+    role      VARCHAR NOT NULL CHECK ( role IN ('admin', 'user'))
+);
+
+INSERT INTO users_without_branch(username, password, firstName, lastName, role)
+SELECT username, password, firstName, lastName, role
+FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_without_branch RENAME TO users;
+
+CREATE TABLE borrows_without_branch
+(
+    id        INTEGER NOT NULL UNIQUE PRIMARY KEY,
+    clientID  VARCHAR NOT NULL,
+    bookISBN  VARCHAR NOT NULL,
+    startDate TEXT    NOT NULL,
+    endDate   TEXT    NOT NULL,
+    returned  INT     NOT NULL,
+    FOREIGN KEY (clientID) REFERENCES clients (id) ON DELETE CASCADE,
+    FOREIGN KEY (bookISBN) REFERENCES books (isbn) ON DELETE CASCADE
+);
+
+INSERT INTO borrows_without_branch(id, clientID, bookISBN, startDate, endDate, returned)
+SELECT id, clientID, bookISBN, startDate, endDate, returned
+FROM borrows;
+
+DROP TABLE borrows;
+ALTER TABLE borrows_without_branch RENAME TO borrows;
+
+DROP TRIGGER books_default_holding;
+DROP TABLE holdings;
+DROP TABLE branches;
+
+COMMIT;
+PRAGMA foreign_keys = ON;
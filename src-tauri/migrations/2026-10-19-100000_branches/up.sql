@@ -0,0 +1,79 @@
+-- Your SQL goes here
+
+-- borrows of clients or books deleted while foreign keys were not enforced are copied over as
+-- they are, for the integrity repair to report; foreign keys can only be turned off outside
+-- a transaction, so this migration opens its own (see metadata.toml)
+PRAGMA foreign_keys = OFF;
+BEGIN;
+
+CREATE TABLE branches
+(
+    id   INTEGER NOT NULL PRIMARY KEY,
+    code VARCHAR NOT NULL UNIQUE,
+    name VARCHAR NOT NULL
+);
+
+INSERT INTO branches(id, code, name)
+VALUES (1, 'CEN', 'Sediul central');
+
+CREATE TABLE holdings
+(
+    branchID INTEGER NOT NULL,
+    bookISBN VARCHAR NOT NULL,
+    items    INT     NOT NULL CHECK ( items >= 0 ),
+    PRIMARY KEY (branchID, bookISBN),
+    FOREIGN KEY (branchID) REFERENCES branches (id),
+    FOREIGN KEY (bookISBN) REFERENCES books (isbn) ON DELETE CASCADE
+);
+
+INSERT INTO holdings(branchID, bookISBN, items)
+SELECT 1, isbn, items
+FROM books;
+
+-- books added without naming a branch are stocked at the main branch
+CREATE TRIGGER books_default_holding
+    AFTER INSERT
+    ON books
+BEGIN
+    INSERT INTO holdings(branchID, bookISBN, items) VALUES (1, NEW.isbn, NEW.items);
+END;
+
+CREATE TABLE borrows_by_branch
+(
+    id        INTEGER NOT NULL UNIQUE PRIMARY KEY,
+    clientID  VARCHAR NOT NULL,
+    bookISBN  VARCHAR NOT NULL,
+    startDate TEXT    NOT NULL,
+    endDate   TEXT    NOT NULL,
+    returned  INT     NOT NULL,
+    branchID  INTEGER NOT NULL DEFAULT 1,
+    FOREIGN KEY (clientID) REFERENCES clients (id) ON DELETE CASCADE,
+    FOREIGN KEY (bookISBN) REFERENCES books (isbn) ON DELETE CASCADE,
+    FOREIGN KEY (branchID) REFERENCES branches (id)
+);
+
+INSERT INTO borrows_by_branch(id, clientID, bookISBN, startDate, endDate, returned, branchID)
+SELECT id, clientID, bookISBN, startDate, endDate, returned, 1
+FROM borrows;
+
+DROP TABLE borrows;
+ALTER TABLE borrows_by_branch RENAME TO borrows;
+
+ALTER TABLE users ADD COLUMN branchID INTEGER REFERENCES branches (id);
+
+CREATE TABLE transfers
+(
+    id         INTEGER   NOT NULL PRIMARY KEY,
+    bookISBN   VARCHAR   NOT NULL,
+    fromBranch INTEGER   NOT NULL,
+    toBranch   INTEGER   NOT NULL,
+    items      INT       NOT NULL CHECK ( items > 0 ),
+    date       TIMESTAMP NOT NULL,
+    username   VARCHAR   NOT NULL,
+    FOREIGN KEY (bookISBN) REFERENCES books (isbn) ON DELETE CASCADE,
+    FOREIGN KEY (fromBranch) REFERENCES branches (id),
+    FOREIGN KEY (toBranch) REFERENCES branches (id)
+);
+
+COMMIT;
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,18 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER borrows_track_delete;
+DROP TRIGGER borrows_track_update;
+DROP TRIGGER borrows_track_insert;
+DROP TRIGGER clients_track_delete;
+DROP TRIGGER clients_track_update;
+DROP TRIGGER clients_track_insert;
+DROP TRIGGER books_track_delete;
+DROP TRIGGER books_track_update;
+DROP TRIGGER books_track_insert;
+
+DROP INDEX borrows_sync_key;
+ALTER TABLE borrows DROP COLUMN syncKey;
+
+DROP TABLE sync_conflicts;
+DROP TABLE row_versions;
+DROP TABLE changes;
+DROP TABLE replica;
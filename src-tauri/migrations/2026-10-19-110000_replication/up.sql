@@ -0,0 +1,218 @@
+-- Your SQL goes here
+CREATE TABLE replica
+(
+    id       INTEGER NOT NULL PRIMARY KEY CHECK ( id = 1 ),
+    -- identifies this installation in the change log
+    site     VARCHAR NOT NULL,
+    -- set while a sync bundle is merged, so applied changes are not logged again as local ones
+    applying INT     NOT NULL DEFAULT 0
+);
+
+INSERT INTO replica(id, site, applying)
+VALUES (1, lower(hex(randomblob(8))), 0);
+
+CREATE TABLE changes
+(
+    seq       INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    tableName VARCHAR NOT NULL,
+    rowKey    VARCHAR NOT NULL,
+    -- a column name, or '*' when the row was deleted
+    field     VARCHAR NOT NULL,
+    value     TEXT,
+    changedAt VARCHAR NOT NULL,
+    origin    VARCHAR NOT NULL
+);
+
+-- two writes to a field within the same millisecond keep only the later value
+CREATE UNIQUE INDEX changes_identity ON changes (origin, tableName, rowKey, field, changedAt);
+CREATE INDEX changes_row ON changes (tableName, rowKey, field);
+
+CREATE TABLE row_versions
+(
+    tableName VARCHAR NOT NULL,
+    rowKey    VARCHAR NOT NULL,
+    version   INT     NOT NULL,
+    PRIMARY KEY (tableName, rowKey)
+);
+
+CREATE TABLE sync_conflicts
+(
+    id              INTEGER NOT NULL PRIMARY KEY,
+    tableName       VARCHAR NOT NULL,
+    rowKey          VARCHAR NOT NULL,
+    field           VARCHAR NOT NULL,
+    localValue      TEXT,
+    remoteValue     TEXT,
+    remoteChangedAt VARCHAR NOT NULL,
+    origin          VARCHAR NOT NULL
+);
+
+-- borrow ids are assigned by each installation, this key is the same everywhere
+ALTER TABLE borrows ADD COLUMN syncKey VARCHAR;
+UPDATE borrows SET syncKey = (SELECT site FROM replica) || '-' || id;
+CREATE UNIQUE INDEX borrows_sync_key ON borrows (syncKey);
+
+-- what the database holds today is the first change of every row
+INSERT INTO changes(tableName, rowKey, field, value, changedAt, origin)
+SELECT 'books', isbn, field, value, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+FROM (SELECT isbn, 'title' AS field, CAST(title AS TEXT) AS value FROM books
+      UNION ALL SELECT isbn, 'author', CAST(author AS TEXT) FROM books
+      UNION ALL SELECT isbn, 'items', CAST(items AS TEXT) FROM books
+      UNION ALL SELECT isbn, 'deletedAt', CAST(deletedAt AS TEXT) FROM books);
+
+INSERT INTO changes(tableName, rowKey, field, value, changedAt, origin)
+SELECT 'clients', id, field, value, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+FROM (SELECT id, 'firstName' AS field, CAST(firstName AS TEXT) AS value FROM clients
+      UNION ALL SELECT id, 'lastName', CAST(lastName AS TEXT) FROM clients
+      UNION ALL SELECT id, 'email', CAST(email AS TEXT) FROM clients
+      UNION ALL SELECT id, 'phone', CAST(phone AS TEXT) FROM clients
+      UNION ALL SELECT id, 'deletedAt', CAST(deletedAt AS TEXT) FROM clients);
+
+INSERT INTO changes(tableName, rowKey, field, value, changedAt, origin)
+SELECT 'borrows', syncKey, field, value, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+FROM (SELECT syncKey, 'clientID' AS field, CAST(clientID AS TEXT) AS value FROM borrows
+      UNION ALL SELECT syncKey, 'bookISBN', CAST(bookISBN AS TEXT) FROM borrows
+      UNION ALL SELECT syncKey, 'startDate', CAST(startDate AS TEXT) FROM borrows
+      UNION ALL SELECT syncKey, 'endDate', CAST(endDate AS TEXT) FROM borrows
+      UNION ALL SELECT syncKey, 'returned', CAST(returned AS TEXT) FROM borrows
+      UNION ALL SELECT syncKey, 'branchID', CAST(branchID AS TEXT) FROM borrows);
+
+INSERT INTO row_versions(tableName, rowKey, version)
+SELECT 'books', isbn, 1 FROM books
+UNION ALL SELECT 'clients', id, 1 FROM clients
+UNION ALL SELECT 'borrows', syncKey, 1 FROM borrows;
+
+CREATE TRIGGER books_track_insert
+    AFTER INSERT
+    ON books
+BEGIN
+    INSERT OR REPLACE INTO row_versions(tableName, rowKey, version) VALUES ('books', NEW.isbn, 1);
+
+    INSERT OR REPLACE INTO changes(tableName, rowKey, field, value, changedAt, origin)
+    SELECT 'books', NEW.isbn, field, value, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+    FROM (SELECT 'title' AS field, CAST(NEW.title AS TEXT) AS value
+          UNION ALL SELECT 'author', CAST(NEW.author AS TEXT)
+          UNION ALL SELECT 'items', CAST(NEW.items AS TEXT)
+          UNION ALL SELECT 'deletedAt', CAST(NEW.deletedAt AS TEXT))
+    WHERE (SELECT applying FROM replica) = 0;
+END;
+
+CREATE TRIGGER books_track_update
+    AFTER UPDATE OF title, author, items, deletedAt
+    ON books
+BEGIN
+    INSERT INTO row_versions(tableName, rowKey, version) VALUES ('books', NEW.isbn, 1)
+    ON CONFLICT (tableName, rowKey) DO UPDATE SET version = version + 1;
+
+    INSERT OR REPLACE INTO changes(tableName, rowKey, field, value, changedAt, origin)
+    SELECT 'books', NEW.isbn, field, value, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+    FROM (SELECT 'title' AS field, CAST(NEW.title AS TEXT) AS value, OLD.title IS NOT NEW.title AS changed
+          UNION ALL SELECT 'author', CAST(NEW.author AS TEXT), OLD.author IS NOT NEW.author
+          UNION ALL SELECT 'items', CAST(NEW.items AS TEXT), OLD.items IS NOT NEW.items
+          UNION ALL SELECT 'deletedAt', CAST(NEW.deletedAt AS TEXT), OLD.deletedAt IS NOT NEW.deletedAt)
+    WHERE changed AND (SELECT applying FROM replica) = 0;
+END;
+
+CREATE TRIGGER books_track_delete
+    AFTER DELETE
+    ON books
+BEGIN
+    DELETE FROM row_versions WHERE tableName = 'books' AND rowKey = OLD.isbn;
+
+    INSERT OR REPLACE INTO changes(tableName, rowKey, field, value, changedAt, origin)
+    SELECT 'books', OLD.isbn, '*', NULL, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+    WHERE (SELECT applying FROM replica) = 0;
+END;
+
+CREATE TRIGGER clients_track_insert
+    AFTER INSERT
+    ON clients
+BEGIN
+    INSERT OR REPLACE INTO row_versions(tableName, rowKey, version) VALUES ('clients', NEW.id, 1);
+
+    INSERT OR REPLACE INTO changes(tableName, rowKey, field, value, changedAt, origin)
+    SELECT 'clients', NEW.id, field, value, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+    FROM (SELECT 'firstName' AS field, CAST(NEW.firstName AS TEXT) AS value
+          UNION ALL SELECT 'lastName', CAST(NEW.lastName AS TEXT)
+          UNION ALL SELECT 'email', CAST(NEW.email AS TEXT)
+          UNION ALL SELECT 'phone', CAST(NEW.phone AS TEXT)
+          UNION ALL SELECT 'deletedAt', CAST(NEW.deletedAt AS TEXT))
+    WHERE (SELECT applying FROM replica) = 0;
+END;
+
+CREATE TRIGGER clients_track_update
+    AFTER UPDATE OF firstName, lastName, email, phone, deletedAt
+    ON clients
+BEGIN
+    INSERT INTO row_versions(tableName, rowKey, version) VALUES ('clients', NEW.id, 1)
+    ON CONFLICT (tableName, rowKey) DO UPDATE SET version = version + 1;
+
+    INSERT OR REPLACE INTO changes(tableName, rowKey, field, value, changedAt, origin)
+    SELECT 'clients', NEW.id, field, value, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+    FROM (SELECT 'firstName' AS field, CAST(NEW.firstName AS TEXT) AS value, OLD.firstName IS NOT NEW.firstName AS changed
+          UNION ALL SELECT 'lastName', CAST(NEW.lastName AS TEXT), OLD.lastName IS NOT NEW.lastName
+          UNION ALL SELECT 'email', CAST(NEW.email AS TEXT), OLD.email IS NOT NEW.email
+          UNION ALL SELECT 'phone', CAST(NEW.phone AS TEXT), OLD.phone IS NOT NEW.phone
+          UNION ALL SELECT 'deletedAt', CAST(NEW.deletedAt AS TEXT), OLD.deletedAt IS NOT NEW.deletedAt)
+    WHERE changed AND (SELECT applying FROM replica) = 0;
+END;
+
+CREATE TRIGGER clients_track_delete
+    AFTER DELETE
+    ON clients
+BEGIN
+    DELETE FROM row_versions WHERE tableName = 'clients' AND rowKey = OLD.id;
+
+    INSERT OR REPLACE INTO changes(tableName, rowKey, field, value, changedAt, origin)
+    SELECT 'clients', OLD.id, '*', NULL, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+    WHERE (SELECT applying FROM replica) = 0;
+END;
+
+CREATE TRIGGER borrows_track_insert
+    AFTER INSERT
+    ON borrows
+BEGIN
+    UPDATE borrows SET syncKey = (SELECT site FROM replica) || '-' || NEW.id WHERE id = NEW.id AND syncKey IS NULL;
+
+    INSERT OR REPLACE INTO row_versions(tableName, rowKey, version)
+    SELECT 'borrows', syncKey, 1 FROM borrows WHERE id = NEW.id;
+
+    INSERT OR REPLACE INTO changes(tableName, rowKey, field, value, changedAt, origin)
+    SELECT 'borrows', (SELECT syncKey FROM borrows WHERE id = NEW.id), field, value, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+    FROM (SELECT 'clientID' AS field, CAST(NEW.clientID AS TEXT) AS value
+          UNION ALL SELECT 'bookISBN', CAST(NEW.bookISBN AS TEXT)
+          UNION ALL SELECT 'startDate', CAST(NEW.startDate AS TEXT)
+          UNION ALL SELECT 'endDate', CAST(NEW.endDate AS TEXT)
+          UNION ALL SELECT 'returned', CAST(NEW.returned AS TEXT)
+          UNION ALL SELECT 'branchID', CAST(NEW.branchID AS TEXT))
+    WHERE (SELECT applying FROM replica) = 0;
+END;
+
+CREATE TRIGGER borrows_track_update
+    AFTER UPDATE OF clientID, bookISBN, startDate, endDate, returned, branchID
+    ON borrows
+BEGIN
+    INSERT INTO row_versions(tableName, rowKey, version) VALUES ('borrows', NEW.syncKey, 1)
+    ON CONFLICT (tableName, rowKey) DO UPDATE SET version = version + 1;
+
+    INSERT OR REPLACE INTO changes(tableName, rowKey, field, value, changedAt, origin)
+    SELECT 'borrows', NEW.syncKey, field, value, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+    FROM (SELECT 'clientID' AS field, CAST(NEW.clientID AS TEXT) AS value, OLD.clientID IS NOT NEW.clientID AS changed
+          UNION ALL SELECT 'bookISBN', CAST(NEW.bookISBN AS TEXT), OLD.bookISBN IS NOT NEW.bookISBN
+          UNION ALL SELECT 'startDate', CAST(NEW.startDate AS TEXT), OLD.startDate IS NOT NEW.startDate
+          UNION ALL SELECT 'endDate', CAST(NEW.endDate AS TEXT), OLD.endDate IS NOT NEW.endDate
+          UNION ALL SELECT 'returned', CAST(NEW.returned AS TEXT), OLD.returned IS NOT NEW.returned
+          UNION ALL SELECT 'branchID', CAST(NEW.branchID AS TEXT), OLD.branchID IS NOT NEW.branchID)
+    WHERE changed AND (SELECT applying FROM replica) = 0;
+END;
+
+CREATE TRIGGER borrows_track_delete
+    AFTER DELETE
+    ON borrows
+BEGIN
+    DELETE FROM row_versions WHERE tableName = 'borrows' AND rowKey = OLD.syncKey;
+
+    INSERT OR REPLACE INTO changes(tableName, rowKey, field, value, changedAt, origin)
+    SELECT 'borrows', OLD.syncKey, '*', NULL, strftime('%Y-%m-%dT%H:%M:%f', 'now'), (SELECT site FROM replica)
+    WHERE (SELECT applying FROM replica) = 0;
+END;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE client_id_sequences
+(
+    scope TEXT PRIMARY KEY NOT NULL,
+    next  INTEGER          NOT NULL CHECK (next > 0)
+);
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE client_photos
+(
+    clientID  TEXT PRIMARY KEY NOT NULL,
+    photo     BLOB             NOT NULL,
+    updatedAt TIMESTAMP        NOT NULL,
+    FOREIGN KEY (clientID) REFERENCES clients (id) ON DELETE CASCADE
+);
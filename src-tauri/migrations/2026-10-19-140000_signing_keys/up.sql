@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE signing_keys
+(
+    name TEXT PRIMARY KEY NOT NULL,
+    key  TEXT             NOT NULL
+);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE staged_books DROP COLUMN subjects;
+ALTER TABLE staged_books DROP COLUMN extent;
+ALTER TABLE staged_books DROP COLUMN publication;
+ALTER TABLE staged_books DROP COLUMN edition;
+
+DROP TABLE book_details;
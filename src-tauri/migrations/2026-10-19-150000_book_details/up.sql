@@ -0,0 +1,17 @@
+-- Your SQL goes here
+-- what MARC records say about a book besides its title and author
+CREATE TABLE book_details
+(
+    isbn        TEXT PRIMARY KEY NOT NULL,
+    edition     TEXT,
+    publication TEXT,
+    extent      TEXT,
+    -- one subject heading per line
+    subjects    TEXT             NOT NULL DEFAULT '',
+    FOREIGN KEY (isbn) REFERENCES books (isbn) ON DELETE CASCADE
+);
+
+ALTER TABLE staged_books ADD COLUMN edition TEXT;
+ALTER TABLE staged_books ADD COLUMN publication TEXT;
+ALTER TABLE staged_books ADD COLUMN extent TEXT;
+ALTER TABLE staged_books ADD COLUMN subjects TEXT NOT NULL DEFAULT '';
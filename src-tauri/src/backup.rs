@@ -0,0 +1,134 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::Local;
+use diesel::SqliteConnection;
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
+
+use crate::{Error, SerializedResult};
+
+/// Pages copied per backup step; the source is unlocked between steps so the app keeps working.
+const PAGES_PER_STEP: std::os::raw::c_int = 64;
+const STEP_PAUSE: Duration = Duration::from_millis(25);
+
+/// How often the scheduler wakes up to check whether an automatic backup is due.
+pub const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+const BACKUP_PREFIX: &str = "libra-";
+/// Kept apart from the scheduled backups so retention never deletes them.
+const PRE_MIGRATION_PREFIX: &str = "pre-migration-";
+const BACKUP_EXTENSION: &str = "sqlite";
+
+/// Opens `path` with rusqlite, keying the connection first when the database is encrypted.
+fn open(path: &Path, flags: OpenFlags, key: Option<&str>) -> SerializedResult<Connection> {
+    let connection = Connection::open_with_flags(path, flags)?;
+    if let Some(key) = key {
+        connection.pragma_update(None, "key", key)?;
+    }
+    Ok(connection)
+}
+
+/// Copies the live database at `source` into `destination` through the SQLite online backup API.
+/// A separate connection is used, so the connection serving commands is never blocked for long.
+/// Backups of an encrypted database are encrypted with the same `key`.
+pub fn backup_database(source: &Path, destination: &Path, key: Option<&str>) -> SerializedResult<()> {
+    let source = open(source, OpenFlags::SQLITE_OPEN_READ_ONLY, key)?;
+    let mut target = open(destination, OpenFlags::default(), key)?;
+
+    let backup = Backup::new(&source, &mut target)?;
+    backup.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None)?;
+    Ok(())
+}
+
+/// Checks that `path` is a LibraManager database whose migrations are all known to this build.
+pub fn validate_backup(path: &Path, key: Option<&str>) -> SerializedResult<()> {
+    let connection = open(path, OpenFlags::SQLITE_OPEN_READ_ONLY, key)?;
+
+    let applied = connection
+        .prepare("SELECT version FROM __diesel_schema_migrations")
+        .and_then(|mut statement| statement.query_map([], |row| row.get::<_, String>(0))?.collect::<Result<Vec<String>, _>>())
+        .map_err(|_| Error::IncompatibleSchema("the file is not a LibraManager database".to_string()))?;
+
+    let known = crate::database::embedded_versions();
+
+    if let Some(unknown) = applied.iter().find(|version| !known.contains(version)) {
+        return Err(Error::IncompatibleSchema(format!("migration {} was made by a newer version of LibraManager", unknown)));
+    }
+
+    Ok(())
+}
+
+/// Replaces the contents of the live database with the backup at `source`, copying the pages
+/// through a connection of its own. Nothing else may use the database meanwhile: the caller
+/// holds `connection` and every other one, as [`DatabaseConnection::restore`] does with the
+/// pool. Pending migrations are then applied in case the backup predates this build.
+///
+/// [`DatabaseConnection::restore`]: crate::database::DatabaseConnection::restore
+pub fn restore_database(connection: &mut SqliteConnection, live: &Path, source: &Path, key: Option<&str>) -> SerializedResult<()> {
+    validate_backup(source, key)?;
+
+    let backup = open(source, OpenFlags::SQLITE_OPEN_READ_ONLY, key)?;
+    let mut target = open(live, OpenFlags::default(), key)?;
+
+    Backup::new(&backup, &mut target)?.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None)?;
+    drop(target);
+
+    crate::database::run_migrations(connection)
+}
+
+fn backup_files(directory: &Path) -> SerializedResult<Vec<PathBuf>> {
+    let mut files = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            name.starts_with(BACKUP_PREFIX) && path.extension().is_some_and(|extension| extension == BACKUP_EXTENSION)
+        })
+        .collect::<Vec<PathBuf>>();
+
+    // file names embed the timestamp, so sorting by name sorts by age
+    files.sort();
+    Ok(files)
+}
+
+/// Runs an automatic backup into `directory` when the newest one is older than `interval`,
+/// keeping only the `retention` most recent files. Returns the path of the new backup, if any.
+pub fn scheduled_backup(database: &Path, directory: &Path, interval: Duration, retention: usize, key: Option<&str>) -> SerializedResult<Option<PathBuf>> {
+    std::fs::create_dir_all(directory)?;
+
+    let existing = backup_files(directory)?;
+    let newest = existing.last()
+        .and_then(|path| path.metadata().ok())
+        .and_then(|metadata| metadata.modified().ok());
+
+    if let Some(modified) = newest {
+        if SystemTime::now().duration_since(modified).unwrap_or_default() < interval {
+            return Ok(None);
+        }
+    }
+
+    let mut destination = directory.to_path_buf();
+    destination.push(format!("{}{}.{}", BACKUP_PREFIX, Local::now().format("%Y%m%d-%H%M%S"), BACKUP_EXTENSION));
+    backup_database(database, &destination, key)?;
+
+    let files = backup_files(directory)?;
+    if files.len() > retention.max(1) {
+        for old in &files[..files.len() - retention.max(1)] {
+            std::fs::remove_file(old)?;
+        }
+    }
+
+    Ok(Some(destination))
+}
+
+/// Saves the database before pending migrations change it, into the `backups` folder next to it.
+pub fn pre_migration_backup(database: &Path, key: Option<&str>) -> SerializedResult<PathBuf> {
+    let directory = database.with_file_name("backups");
+    std::fs::create_dir_all(&directory)?;
+
+    let mut destination = directory;
+    destination.push(format!("{}{}.{}", PRE_MIGRATION_PREFIX, Local::now().format("%Y%m%d-%H%M%S"), BACKUP_EXTENSION));
+    backup_database(database, &destination, key)?;
+
+    Ok(destination)
+}
@@ -0,0 +1,431 @@
+//! Badge templates, kept as `badge.toml` (or `badge.json`) next to `settings.toml` so each
+//! library can lay out its own badges: the texts and where they go, their fonts and colors,
+//! a logo, the photo frame and the barcode.
+
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use ab_glyph::{FontArc, PxScale};
+use chrono::NaiveDate;
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgba, RgbaImage};
+use imageproc::drawing::text_size;
+use serde::{Deserialize, Serialize};
+
+use crate::barcode::{Barcode, Symbology};
+use crate::drawing::Drawing;
+use crate::signing::SignedBadge;
+use crate::validation::ValidationError;
+use crate::{Error, SerializedResult};
+
+/// Badges are never drawn larger than this on either side.
+pub const MAX_SIDE: u32 = 4000;
+
+fn invalid(field: &str, message: String) -> Error {
+    Error::Validation(vec![ValidationError { field: field.to_string(), message }])
+}
+
+/// A color written as `#RRGGBB` or `#RRGGBBAA`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(pub Rgba<u8>);
+
+impl Color {
+    pub const BLACK: Color = Color(Rgba([0, 0, 0, 255]));
+    pub const WHITE: Color = Color(Rgba([255, 255, 255, 255]));
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let hex = value.strip_prefix('#').unwrap_or(&value);
+        let channels = match hex.len() {
+            6 | 8 if hex.is_ascii() => (0..hex.len()).step_by(2)
+                .map(|index| u8::from_str_radix(&hex[index..index + 2], 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .ok(),
+            _ => None,
+        };
+
+        match channels.as_deref() {
+            Some(&[r, g, b]) => Ok(Color(Rgba([r, g, b, 255]))),
+            Some(&[r, g, b, a]) => Ok(Color(Rgba([r, g, b, a]))),
+            _ => Err(format!("'{}' is not a color such as #1A2B3C", value)),
+        }
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        let [r, g, b, a] = color.0.0;
+        if a == 255 {
+            format!("#{:02X}{:02X}{:02X}", r, g, b)
+        } else {
+            format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, a)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    /// `x` is where the text starts.
+    #[default]
+    Left,
+    /// `x` is the middle of the text.
+    Center,
+    /// `x` is where the text ends.
+    Right,
+}
+
+/// A line of text. `{libraryName}`, `{clientId}`, `{clientName}` and `{issued}` are
+/// replaced with those of the client the badge is for.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TextField {
+    pub text: String,
+    pub x: i32,
+    pub y: i32,
+    /// `bold`, `regular`, or the path of a TrueType or OpenType font file,
+    /// relative to the folder of the template.
+    #[serde(default = "TextField::default_font")]
+    pub font: String,
+    #[serde(default = "TextField::default_size")]
+    pub size: f32,
+    #[serde(default = "TextField::default_color")]
+    pub color: Color,
+    #[serde(default)]
+    pub align: Align,
+}
+
+impl TextField {
+    fn default_font() -> String {
+        "regular".to_string()
+    }
+
+    fn default_size() -> f32 {
+        16.0
+    }
+
+    fn default_color() -> Color {
+        Color::BLACK
+    }
+
+    fn new(text: &str, x: i32, y: i32, font: &str, size: f32, align: Align) -> Self {
+        Self { text: text.to_string(), x, y, font: font.to_string(), size, color: Color::BLACK, align }
+    }
+}
+
+/// Frame the photo of the client is cropped to fill, left empty for clients without one.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoFrame {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Line of one pixel drawn around the photo.
+    pub border: Option<Color>,
+}
+
+/// An image printed on every badge, scaled to fit within `width` x `height`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Logo {
+    /// Relative to the folder of the template.
+    pub path: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The barcode carrying the client ID.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BarcodePlacement {
+    /// Left edge of the barcode, centered on the badge when left out.
+    pub x: Option<u32>,
+    pub y: u32,
+    /// Height of the bars, or side of a QR code or Data Matrix symbol.
+    pub height: u32,
+    /// Width of the narrowest bar, in pixels.
+    pub xdim: u32,
+    #[serde(default = "TextField::default_color")]
+    pub color: Color,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BadgeTemplate {
+    pub width: u32,
+    pub height: u32,
+    pub background: Color,
+    pub border_color: Color,
+    /// 0 for no border.
+    pub border_width: u32,
+    /// How `{issued}` is written, in the `strftime` syntax.
+    pub date_format: String,
+    pub photo: Option<PhotoFrame>,
+    pub logo: Option<Logo>,
+    pub barcode: BarcodePlacement,
+    pub fields: Vec<TextField>,
+}
+
+impl Default for BadgeTemplate {
+    /// The badge the app has always printed.
+    fn default() -> Self {
+        Self {
+            width: 450,
+            height: 250,
+            background: Color::WHITE,
+            border_color: Color::BLACK,
+            border_width: 2,
+            date_format: "%d.%m.%Y".to_string(),
+            photo: Some(PhotoFrame { x: 16, y: 48, width: 72, height: 96, border: Some(Color::BLACK) }),
+            logo: None,
+            barcode: BarcodePlacement { x: None, y: 155, height: 75, xdim: 2, color: Color::BLACK },
+            fields: vec![
+                TextField::new("Biblioteca {libraryName}", 225, 12, "bold", 24.0, Align::Center),
+                TextField::new("Nume complet", 104, 80, "bold", 18.0, Align::Left),
+                TextField::new("{clientName}", 104, 100, "regular", 16.0, Align::Left),
+                TextField::new("Emis pe", 410, 80, "bold", 18.0, Align::Right),
+                TextField::new("{issued}", 410, 100, "regular", 16.0, Align::Right),
+            ],
+        }
+    }
+}
+
+impl BadgeTemplate {
+    /// Reads a template from TOML, or from JSON when `json` is set.
+    pub fn parse(source: &str, json: bool) -> SerializedResult<Self> {
+        let template: Self = if json {
+            serde_json::from_str(source).map_err(|error| invalid("template", error.to_string()))?
+        } else {
+            toml::from_str(source).map_err(|error| invalid("template", error.to_string()))?
+        };
+        template.validate()?;
+        Ok(template)
+    }
+
+    pub fn to_source(&self, json: bool) -> String {
+        if json {
+            serde_json::to_string_pretty(self).unwrap()
+        } else {
+            toml::to_string_pretty(self).unwrap()
+        }
+    }
+
+    /// Refuses the sizes a badge cannot be drawn with.
+    pub fn validate(&self) -> SerializedResult<()> {
+        let mut errors = Vec::new();
+        let mut check = |valid: bool, field: &str, message: &str| {
+            if !valid {
+                errors.push(ValidationError { field: field.to_string(), message: message.to_string() });
+            }
+        };
+
+        check((1..=MAX_SIDE).contains(&self.width) && (1..=MAX_SIDE).contains(&self.height),
+              "size", &format!("Badges are between 1 and {} pixels on each side", MAX_SIDE));
+        check(self.border_width.checked_mul(2).is_some_and(|border| border < self.width.min(self.height)), "borderWidth", "The border leaves no room inside");
+        let side = |value: u32| (1..=MAX_SIDE).contains(&value);
+        check(side(self.barcode.height) && side(self.barcode.xdim), "barcode",
+              &format!("The barcode needs a height and a bar width of at most {} pixels", MAX_SIDE));
+        if let Some(photo) = &self.photo {
+            check(side(photo.width) && side(photo.height), "photo",
+                  &format!("The photo frame needs a width and a height of at most {} pixels", MAX_SIDE));
+        }
+        if let Some(logo) = &self.logo {
+            check(side(logo.width) && side(logo.height), "logo",
+                  &format!("The logo needs a width and a height of at most {} pixels", MAX_SIDE));
+        }
+        check(self.fields.iter().all(|field| field.size > 0.0), "fields", "Text needs a size above 0");
+
+        if errors.is_empty() { Ok(()) } else { Err(Error::Validation(errors)) }
+    }
+}
+
+/// What is printed on the badge of one client.
+pub struct BadgeData<'a> {
+    pub client_id: &'a str,
+    pub client_name: &'a str,
+    pub library_name: &'a str,
+    pub issued: NaiveDate,
+    pub photo: Option<&'a DynamicImage>,
+    /// Symbology of the barcode, set for badges in the settings.
+    pub symbology: Symbology,
+    /// What QR codes and Data Matrix symbols carry, the client ID signed together with the
+    /// name of the library; linear barcodes carry the bare client ID.
+    pub signed_id: &'a str,
+}
+
+fn fill(text: &str, template: &BadgeTemplate, data: &BadgeData) -> String {
+    text.replace("{libraryName}", data.library_name)
+        .replace("{clientId}", data.client_id)
+        .replace("{clientName}", data.client_name)
+        .replace("{issued}", &data.issued.format(&template.date_format).to_string())
+}
+
+fn load_font(name: &str, directory: &Path) -> SerializedResult<FontArc> {
+    match name {
+        "bold" => Ok(FontArc::try_from_slice(include_bytes!("assets/bold_font.otf")).unwrap()),
+        "regular" => Ok(FontArc::try_from_slice(include_bytes!("assets/regular_font.otf")).unwrap()),
+        path => {
+            let data = std::fs::read(directory.join(path))
+                .map_err(|error| invalid("fonts", format!("Cannot open {}: {}", path, error)))?;
+            FontArc::try_from_vec(data).map_err(|_| invalid("fonts", format!("{} is not a font file", path)))
+        }
+    }
+}
+
+fn draw_barcode(drawing: &mut Drawing, template: &BadgeTemplate, data: &BadgeData) -> SerializedResult<()> {
+    let placement = &template.barcode;
+    let code = if data.symbology.is_2d() { data.signed_id } else { data.client_id };
+    let barcode = Barcode::new(data.symbology, code, placement.xdim, placement.height)?;
+
+    let x = placement.x.unwrap_or(template.width.saturating_sub(barcode.width) / 2);
+    let fits = |start: u32, length: u32, side: u32| start.checked_add(length).is_some_and(|end| end <= side);
+    if !fits(x, barcode.width, template.width) || !fits(placement.y, barcode.height, template.height) {
+        return Err(invalid("barcode", format!("The barcode is {}x{} pixels and does not fit on the badge at {}, {}", barcode.width, barcode.height, x, placement.y)));
+    }
+    barcode.draw(drawing, x as i32, placement.y as i32, placement.color.0, template.background.0);
+    Ok(())
+}
+
+fn draw_logo(drawing: &mut Drawing, logo: &Logo, directory: &Path) -> SerializedResult<()> {
+    let picture = image::open(directory.join(&logo.path))
+        .map_err(|error| invalid("logo", format!("Cannot open {}: {}", logo.path, error)))?;
+    let fitted = picture.resize(logo.width, logo.height, FilterType::Lanczos3).to_rgba8();
+    drawing.draw_image(logo.x as i64, logo.y as i64, fitted);
+    Ok(())
+}
+
+fn draw_photo(drawing: &mut Drawing, frame: &PhotoFrame, photo: &DynamicImage) {
+    if let Some(border) = frame.border {
+        drawing.fill_rect(frame.x as i32 - 1, frame.y as i32 - 1, frame.width + 2, frame.height + 2, border.0);
+    }
+    // photos are kept as JPEG, so nothing of what is under shows through
+    let fitted = photo.resize_to_fill(frame.width, frame.height, FilterType::Lanczos3).to_rgba8();
+    drawing.draw_image(frame.x as i64, frame.y as i64, fitted);
+}
+
+/// Lays out the badge of `data` as `template` places it; fonts and the logo are looked up
+/// relative to `directory`.
+pub fn layout(template: &BadgeTemplate, data: &BadgeData, directory: &Path) -> SerializedResult<Drawing> {
+    template.validate()?;
+
+    let mut drawing = Drawing::new(template.width, template.height);
+    let inner = template.border_width;
+    drawing.fill_rect(0, 0, template.width, template.height, template.border_color.0);
+    drawing.fill_rect(inner as i32, inner as i32, template.width - 2 * inner, template.height - 2 * inner, template.background.0);
+
+    if let Some(logo) = &template.logo {
+        draw_logo(&mut drawing, logo, directory)?;
+    }
+    draw_barcode(&mut drawing, template, data)?;
+
+    if let (Some(frame), Some(photo)) = (&template.photo, data.photo) {
+        draw_photo(&mut drawing, frame, photo);
+    }
+
+    let mut fonts: HashMap<&str, FontArc> = HashMap::new();
+    for field in &template.fields {
+        if !fonts.contains_key(field.font.as_str()) {
+            fonts.insert(&field.font, load_font(&field.font, directory)?);
+        }
+        let font = &fonts[field.font.as_str()];
+
+        let text = fill(&field.text, template, data);
+        let scale = PxScale::from(field.size);
+        let (width, _) = text_size(scale, font, &text);
+        let x = match field.align {
+            Align::Left => field.x,
+            Align::Center => field.x - width as i32 / 2,
+            Align::Right => field.x - width as i32,
+        };
+        drawing.draw_text(x, field.y, scale, font, &text, field.color.0);
+    }
+
+    Ok(drawing)
+}
+
+/// Draws the badge of `data` as laid out by `template`, in pixels.
+pub fn render(template: &BadgeTemplate, data: &BadgeData, directory: &Path) -> SerializedResult<RgbaImage> {
+    Ok(layout(template, data, directory)?.to_image())
+}
+
+/// A made up client to show a template with before printing real badges; its QR code
+/// carries a blank signature.
+pub fn render_sample(template: &BadgeTemplate, library_name: &str, client_id: &str, symbology: Symbology, directory: &Path) -> SerializedResult<RgbaImage> {
+    let photo = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(300, 400, image::Rgb([200, 200, 200])));
+    let signed_id = SignedBadge { client_id: client_id.to_string(), library_name: library_name.to_string(), signature: "0".repeat(16) }.to_string();
+    let data = BadgeData {
+        client_id,
+        client_name: "Ana Popescu",
+        library_name,
+        issued: chrono::Local::now().date_naive(),
+        photo: Some(&photo),
+        symbology,
+        signed_id: &signed_id,
+    };
+    render(template, &data, directory)
+}
+
+/// Reads and writes the badge template of this desk, `badge.json` when there is one and
+/// `badge.toml` otherwise.
+pub struct TemplateLoader {
+    path: PathBuf,
+}
+
+impl TemplateLoader {
+    pub fn from(app_data_path: &Path) -> Self {
+        let json = app_data_path.join("badge.json");
+        let path = if json.exists() { json } else { app_data_path.join("badge.toml") };
+        Self { path }
+    }
+
+    fn is_json(&self) -> bool {
+        self.path.extension().is_some_and(|extension| extension == "json")
+    }
+
+    /// Folder fonts and logos of the template are relative to.
+    pub fn directory(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new(""))
+    }
+
+    /// The template as written in its file, which is created with the default one if missing.
+    pub fn source(&self) -> SerializedResult<String> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(source) => Ok(source),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let source = BadgeTemplate::default().to_source(self.is_json());
+                self.write(&source)?;
+                Ok(source)
+            }
+            Err(e) => Err(e.into())
+        }
+    }
+
+    pub fn parse(&self, source: &str) -> SerializedResult<BadgeTemplate> {
+        BadgeTemplate::parse(source, self.is_json())
+    }
+
+    pub fn load(&self) -> SerializedResult<BadgeTemplate> {
+        self.parse(&self.source()?)
+    }
+
+    /// Replaces the template with `source`, as long as it is a valid one.
+    pub fn store(&self, source: &str) -> SerializedResult<()> {
+        self.parse(source)?;
+        self.write(source)
+    }
+
+    fn write(&self, source: &str) -> SerializedResult<()> {
+        let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(&self.path)?;
+        file.write_all(source.as_bytes())?;
+        Ok(())
+    }
+}
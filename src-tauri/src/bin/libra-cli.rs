@@ -0,0 +1,266 @@
+//! Administration of a LibraManager database without the desktop app, for scripts and IT.
+
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use chrono::Local;
+use clap::{Parser, Subcommand, ValueEnum};
+use diesel::prelude::*;
+
+use libra_manager::csv_io::{ColumnMapping, Entity, ExportFilter};
+use libra_manager::database::DatabaseConnection;
+use libra_manager::models::database::User;
+use libra_manager::services::{book_service, client_service, user_service};
+use libra_manager::{Error, SerializedResult};
+
+#[derive(Parser)]
+#[command(name = "libra-cli", version, about = "Administers a LibraManager database")]
+struct Cli {
+    /// Database file to work on.
+    #[arg(short, long, env = "LIBRA_DATABASE")]
+    database: PathBuf,
+
+    /// Passphrase of an encrypted database.
+    #[arg(long, env = "LIBRA_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Applies pending migrations, after saving a copy of the database next to it.
+    Migrate,
+    /// Adds a user; the password is read from standard input when not given.
+    CreateUser {
+        username: String,
+        #[arg(long)]
+        first_name: String,
+        #[arg(long)]
+        last_name: String,
+        #[arg(long, value_enum, default_value = "user")]
+        role: Role,
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Sets a new password; it is read from standard input when not given.
+    ResetPassword {
+        username: String,
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Imports a CSV file, all rows or none.
+    ImportCsv {
+        #[arg(value_enum)]
+        entity: EntityArg,
+        file: PathBuf,
+        /// Only validate the file.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Exports to a CSV file.
+    ExportCsv {
+        #[arg(value_enum)]
+        entity: EntityArg,
+        file: PathBuf,
+        /// Text that must appear in one of the exported columns.
+        #[arg(long)]
+        query: Option<String>,
+        /// Only borrows which have not been returned.
+        #[arg(long)]
+        active_only: bool,
+    },
+    /// Copies the database to `destination` while it may be in use.
+    Backup {
+        destination: PathBuf,
+    },
+    /// Prints a report.
+    Report {
+        #[arg(value_enum)]
+        kind: Report,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Role {
+    Admin,
+    User,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EntityArg {
+    Books,
+    Clients,
+    Borrows,
+}
+
+impl From<EntityArg> for Entity {
+    fn from(entity: EntityArg) -> Self {
+        match entity {
+            EntityArg::Books => Entity::Book,
+            EntityArg::Clients => Entity::Client,
+            EntityArg::Borrows => Entity::Borrow,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Report {
+    /// Counts of books, copies, clients and loans.
+    Summary,
+    /// Loans past their end date.
+    Overdue,
+    /// Borrows pointing at missing books or clients.
+    Integrity,
+}
+
+impl Command {
+    /// Whether the command writes to the database; only `migrate` changes its schema.
+    fn writes(&self) -> bool {
+        !matches!(self, Command::ExportCsv { .. } | Command::Backup { .. } | Command::Report { .. })
+    }
+}
+
+fn open(cli: &Cli) -> SerializedResult<DatabaseConnection> {
+    let url = cli.database.to_string_lossy();
+
+    if cli.passphrase.is_none() && libra_manager::encryption::is_encrypted(&cli.database)? {
+        return Err(Error::AuthError);
+    }
+
+    match (&cli.command, &cli.passphrase) {
+        (Command::Migrate, Some(passphrase)) => DatabaseConnection::encrypted(&url, passphrase),
+        (Command::Migrate, None) => DatabaseConnection::from(&url),
+        (command, passphrase) => DatabaseConnection::unmigrated(&url, passphrase.as_deref(), !command.writes()).map_err(|error| match error {
+            Error::Migration(message) => Error::Migration(format!("{}, run `libra-cli migrate`", message)),
+            error => error,
+        }),
+    }
+}
+
+fn read_password(password: Option<String>) -> SerializedResult<String> {
+    if let Some(password) = password {
+        return Ok(password);
+    }
+
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn summary(connection: &mut SqliteConnection) -> SerializedResult<()> {
+    use libra_manager::schema::{books, borrows};
+
+    let today = Local::now().date_naive();
+
+    let titles = book_service::count_books(connection)?;
+    let copies: Option<i64> = books::table.filter(books::deletedAt.is_null()).select(diesel::dsl::sum(books::items)).first(connection)?;
+    let members = client_service::count_clients(connection)?;
+    let active: i64 = borrows::table.filter(borrows::returned.eq(false)).count().get_result(connection)?;
+    let overdue: i64 = borrows::table.filter(borrows::returned.eq(false)).filter(borrows::endDate.lt(today)).count().get_result(connection)?;
+
+    println!("Books:          {}", titles);
+    println!("Copies:         {}", copies.unwrap_or(0));
+    println!("Clients:        {}", members);
+    println!("Active loans:   {}", active);
+    println!("Overdue loans:  {}", overdue);
+    Ok(())
+}
+
+fn overdue(connection: &mut SqliteConnection) -> SerializedResult<()> {
+    use libra_manager::schema::{books, borrows, clients};
+
+    let today = Local::now().date_naive();
+
+    let rows = borrows::table
+        .inner_join(books::table)
+        .inner_join(clients::table)
+        .filter(borrows::returned.eq(false))
+        .filter(borrows::endDate.lt(today))
+        .order(borrows::endDate)
+        .select((borrows::endDate, clients::id, clients::firstName, clients::lastName, books::isbn, books::title))
+        .load::<(chrono::NaiveDate, String, String, String, String, String)>(connection)?;
+
+    for (end_date, id, first_name, last_name, isbn, title) in &rows {
+        println!("{}\t{}\t{} {}\t{}\t{}", end_date, id, first_name, last_name, isbn, title);
+    }
+    eprintln!("{} overdue loans", rows.len());
+    Ok(())
+}
+
+fn run(cli: Cli) -> SerializedResult<ExitCode> {
+    let database = open(&cli)?;
+    let connection = &mut *database.get()?;
+
+    match cli.command {
+        Command::Migrate => {
+            let applied = libra_manager::database::applied_versions(connection)?;
+            println!("{} migrations applied, latest {}", applied.len(), applied.iter().max().map(String::as_str).unwrap_or("none"));
+        }
+        Command::CreateUser { username, first_name, last_name, role, password } => {
+            let user = User {
+                username,
+                password: read_password(password)?,
+                first_name,
+                last_name,
+                role: match role {
+                    Role::Admin => "admin",
+                    Role::User => "user",
+                }.to_string(),
+                branch_id: None,
+            };
+            user_service::create_user(connection, &user)?;
+            println!("Created user {}", user.username);
+        }
+        Command::ResetPassword { username, password } => {
+            user_service::reset_password(connection, &username, &read_password(password)?)?;
+            println!("Password of {} changed", username);
+        }
+        Command::ImportCsv { entity, file, dry_run } => {
+            let report = libra_manager::csv_io::import_csv(connection, entity.into(), &file, &ColumnMapping::default(), dry_run)?;
+
+            for error in &report.errors {
+                eprintln!("line {}: {}{}", error.row, error.field.as_ref().map(|field| format!("{}: ", field)).unwrap_or_default(), error.message);
+            }
+            println!("{} of {} rows valid, {}", report.valid_rows, report.total_rows, if report.committed { "imported" } else { "nothing imported" });
+
+            if !report.errors.is_empty() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::ExportCsv { entity, file, query, active_only } => {
+            let filter = ExportFilter { query, active_only: Some(active_only) };
+            let written = libra_manager::csv_io::export_csv(connection, entity.into(), &file, &filter)?;
+            println!("Exported {} rows to {}", written, file.display());
+        }
+        Command::Backup { destination } => {
+            libra_manager::backup::backup_database(&database.path, &destination, database.key.as_deref())?;
+            println!("Saved {}", destination.display());
+        }
+        Command::Report { kind } => match kind {
+            Report::Summary => summary(connection)?,
+            Report::Overdue => overdue(connection)?,
+            Report::Integrity => {
+                let report = libra_manager::integrity::repair(connection, true)?;
+                for borrow in &report.orphaned_borrows {
+                    println!("borrow {}: client {}, book {}", borrow.id, borrow.client_id, borrow.book_isbn);
+                }
+                eprintln!("{} orphaned borrows", report.orphaned_borrows.len());
+            }
+        },
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(status) => status,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
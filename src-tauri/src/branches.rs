@@ -0,0 +1,166 @@
+use chrono::Local;
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+use crate::models::database::{Branch, Holding, NewBranch, NewTransfer, Transfer};
+use crate::validation::ValidationError;
+use crate::{Error, SerializedResult};
+
+/// The branch created by the migration; stock and borrows without a branch belong to it.
+pub const DEFAULT_BRANCH: i32 = 1;
+
+pub fn fetch_branches(connection: &mut SqliteConnection) -> SerializedResult<Vec<Branch>> {
+    use crate::schema::branches;
+
+    Ok(branches::table.order(branches::id).select(Branch::as_select()).load(connection)?)
+}
+
+pub fn create_branch(connection: &mut SqliteConnection, branch: &NewBranch) -> SerializedResult<Branch> {
+    use crate::schema::branches;
+
+    diesel::insert_into(branches::table).values(branch).execute(connection)?;
+
+    let created = branches::table
+        .filter(branches::code.eq(&branch.code))
+        .select(Branch::as_select())
+        .first(connection)?;
+    Ok(created)
+}
+
+pub fn update_branch(connection: &mut SqliteConnection, branch: &Branch) -> SerializedResult<()> {
+    diesel::update(branch).set(branch).execute(connection)?;
+    Ok(())
+}
+
+/// Deletes a branch, after which users who had picked it choose again. A branch which still
+/// holds copies, or which borrows or transfers refer to, is refused with what keeps it.
+pub fn delete_branch(connection: &mut SqliteConnection, id: i32) -> SerializedResult<()> {
+    use crate::schema::{borrows, branches, holdings, transfers, users};
+
+    connection.transaction::<_, Error, _>(|connection| {
+        // empty shelves left behind by transfers do not keep the branch alive
+        diesel::delete(holdings::table.filter(holdings::branchID.eq(id)).filter(holdings::items.eq(0))).execute(connection)?;
+
+        let held = holdings::table.filter(holdings::branchID.eq(id)).count().get_result::<i64>(connection)?;
+        let lent = borrows::table.filter(borrows::branchID.eq(id)).count().get_result::<i64>(connection)?;
+        let moved = transfers::table
+            .filter(transfers::fromBranch.eq(id).or(transfers::toBranch.eq(id)))
+            .count()
+            .get_result::<i64>(connection)?;
+
+        let errors = [
+            ("holdings", held, "books still have copies at this branch"),
+            ("borrows", lent, "borrows were made at this branch"),
+            ("transfers", moved, "transfers were made to or from this branch"),
+        ].into_iter()
+            .filter(|(_, count, _)| *count > 0)
+            .map(|(field, count, message)| ValidationError { field: field.to_string(), message: format!("{} {}", count, message) })
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Err(Error::Validation(errors));
+        }
+
+        diesel::update(users::table.filter(users::branchID.eq(id))).set(users::branchID.eq(None::<i32>)).execute(connection)?;
+        diesel::delete(branches::table.find(id)).execute(connection)?;
+        Ok(())
+    })
+}
+
+/// How many copies of `isbn` each branch holds.
+pub fn fetch_holdings(connection: &mut SqliteConnection, isbn: &str) -> SerializedResult<Vec<Holding>> {
+    use crate::schema::holdings;
+
+    let result = holdings::table
+        .filter(holdings::bookISBN.eq(isbn))
+        .order(holdings::branchID)
+        .select(Holding::as_select())
+        .load(connection)?;
+    Ok(result)
+}
+
+/// Adds `delta` copies of `isbn` to the shelves of `branch`, which may be negative.
+/// Only the per-branch count changes, the caller keeps `books.items` in step.
+pub fn adjust_holding(connection: &mut SqliteConnection, isbn: &str, branch: i32, delta: i32) -> SerializedResult<()> {
+    use crate::schema::holdings;
+
+    // not an upsert: SQLite checks the candidate row, negative for removals, before the conflict
+    let updated = diesel::update(holdings::table.find((branch, isbn)))
+        .set(holdings::items.eq(holdings::items + delta))
+        .execute(connection)?;
+
+    if updated == 0 {
+        diesel::insert_into(holdings::table)
+            .values(&Holding { branch_id: branch, book_isbn: isbn.to_string(), items: delta })
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+/// Moves the stock of a book that was just inserted from the default branch,
+/// where the database trigger shelves it, to `branch`.
+pub fn shelve_new_book(connection: &mut SqliteConnection, isbn: &str, branch: i32) -> SerializedResult<()> {
+    use crate::schema::holdings;
+
+    if branch != DEFAULT_BRANCH {
+        diesel::update(holdings::table.find((DEFAULT_BRANCH, isbn)))
+            .set(holdings::branchID.eq(branch))
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+/// Copies of `isbn` on the shelves of `branch`, not counting those on loan from it.
+pub fn available_copies(connection: &mut SqliteConnection, isbn: &str, branch: i32) -> SerializedResult<i64> {
+    use crate::schema::{borrows, holdings};
+
+    let held = holdings::table.find((branch, isbn))
+        .select(holdings::items)
+        .first::<i32>(connection)
+        .optional()?
+        .unwrap_or(0);
+
+    let lent = borrows::table
+        .filter(borrows::bookISBN.eq(isbn))
+        .filter(borrows::branchID.eq(branch))
+        .filter(borrows::returned.eq(false))
+        .count()
+        .get_result::<i64>(connection)?;
+
+    Ok(held as i64 - lent)
+}
+
+/// Moves `items` available copies of `isbn` from one branch to another and records the transfer.
+pub fn transfer(connection: &mut SqliteConnection, isbn: &str, from: i32, to: i32, items: i32, username: &str) -> SerializedResult<Transfer> {
+    use crate::schema::transfers;
+
+    if from == to {
+        return Err(Error::Validation(vec![ValidationError { field: "toBranch".to_string(), message: "Must differ from the branch the copies are moved from".to_string() }]));
+    }
+
+    connection.transaction::<_, Error, _>(|connection| {
+        let available = available_copies(connection, isbn, from)?;
+        if items <= 0 || (items as i64) > available {
+            return Err(Error::InsufficientStock { isbn: isbn.to_string(), available });
+        }
+
+        adjust_holding(connection, isbn, from, -items)?;
+        adjust_holding(connection, isbn, to, items)?;
+
+        let record = NewTransfer { book_isbn: isbn, from_branch: from, to_branch: to, items, date: Local::now().naive_local(), username };
+        diesel::insert_into(transfers::table).values(&record).execute(connection)?;
+
+        let created = transfers::table.order(transfers::id.desc()).select(Transfer::as_select()).first(connection)?;
+        Ok(created)
+    })
+}
+
+/// Transfer history, newest first, optionally limited to one book.
+pub fn fetch_transfers(connection: &mut SqliteConnection, isbn: Option<&str>) -> SerializedResult<Vec<Transfer>> {
+    use crate::schema::transfers;
+
+    let mut query = transfers::table.order(transfers::date.desc()).select(Transfer::as_select()).into_boxed();
+    if let Some(isbn) = isbn {
+        query = query.filter(transfers::bookISBN.eq(isbn));
+    }
+    Ok(query.load(connection)?)
+}
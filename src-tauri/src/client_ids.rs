@@ -0,0 +1,188 @@
+//! Client IDs made of a prefix, the code of the branch, a sequence number and a check
+//! digit, such as `LM-CEN-0000421`, so a badge scanned or typed wrong is caught instead
+//! of opening another client.
+
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::validation::ValidationError;
+use crate::{Error, SerializedResult};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckDigit {
+    None,
+    /// Luhn mod 10, as on payment cards.
+    #[default]
+    Luhn,
+    /// Weights 2 to 7 from the right, mod 11, with `X` standing for 10.
+    Mod11,
+}
+
+/// Letters count as two digits, `A` as 10 up to `Z` as 35; separators are skipped.
+fn digits(payload: &str) -> Vec<u32> {
+    payload.chars()
+        .filter_map(|c| c.to_digit(36))
+        .flat_map(|value| if value < 10 { vec![value] } else { vec![value / 10, value % 10] })
+        .collect()
+}
+
+impl CheckDigit {
+    /// The digit to append to `payload`, `None` when IDs carry no check digit.
+    pub fn compute(self, payload: &str) -> Option<char> {
+        let digits = digits(&payload.to_uppercase());
+
+        match self {
+            CheckDigit::None => None,
+            CheckDigit::Luhn => {
+                let sum: u32 = digits.iter().rev().enumerate()
+                    .map(|(index, &digit)| if index % 2 == 0 { let doubled = digit * 2; doubled / 10 + doubled % 10 } else { digit })
+                    .sum();
+                char::from_digit((10 - sum % 10) % 10, 10)
+            }
+            CheckDigit::Mod11 => {
+                let sum: u32 = digits.iter().rev().enumerate()
+                    .map(|(index, &digit)| digit * (index as u32 % 6 + 2))
+                    .sum();
+                match (11 - sum % 11) % 11 {
+                    10 => Some('X'),
+                    check => char::from_digit(check, 10),
+                }
+            }
+        }
+    }
+
+    /// Whether the last character of `id` is the check digit of the rest.
+    pub fn verify(self, id: &str) -> bool {
+        let mut payload = id.to_uppercase();
+        match (self, payload.pop()) {
+            (CheckDigit::None, _) => true,
+            (_, Some(last)) => self.compute(&payload) == Some(last),
+            (_, None) => false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ClientIdFormat {
+    /// Starts every generated ID; IDs without it were typed in by hand and are not checked.
+    pub prefix: String,
+    /// Put the code of the branch registering the client after the prefix,
+    /// which also gives every branch a sequence of its own.
+    pub branch_code: bool,
+    /// Width the sequence number is padded to with zeros.
+    pub digits: usize,
+    pub check_digit: CheckDigit,
+    pub separator: String,
+    /// Installations registering clients in the same sequences, such as branches whose
+    /// databases are synced, and which of them this is, from 1. Each takes every
+    /// `installations`-th number starting at `installation`, so their IDs never collide.
+    pub installations: u32,
+    pub installation: u32,
+}
+
+impl Default for ClientIdFormat {
+    fn default() -> Self {
+        Self {
+            prefix: "LM".to_string(),
+            branch_code: true,
+            digits: 6,
+            check_digit: CheckDigit::Luhn,
+            separator: "-".to_string(),
+            installations: 1,
+            installation: 1,
+        }
+    }
+}
+
+impl ClientIdFormat {
+    /// The part before the sequence number, which is also what sequences are kept by.
+    fn scope(&self, branch_code: &str) -> String {
+        let mut scope = String::new();
+        for part in [self.prefix.as_str(), branch_code] {
+            if !part.is_empty() {
+                scope.push_str(part);
+                scope.push_str(&self.separator);
+            }
+        }
+        scope
+    }
+
+    pub fn format(&self, branch_code: &str, sequence: u32) -> String {
+        let payload = format!("{}{:0width$}", self.scope(branch_code), sequence, width = self.digits);
+        match self.check_digit.compute(&payload) {
+            Some(check) => format!("{}{}", payload, check),
+            None => payload,
+        }
+    }
+
+    /// Whether `id` looks like one of ours, judged by the prefix.
+    pub fn is_generated(&self, id: &str) -> bool {
+        !self.prefix.is_empty() && id.to_uppercase().starts_with(&format!("{}{}", self.prefix, self.separator).to_uppercase())
+    }
+
+    /// The `count`-th number of the sequence reserved for this installation.
+    fn number(&self, count: u32) -> SerializedResult<u32> {
+        if self.installation == 0 || self.installation > self.installations {
+            return Err(Error::Validation(vec![ValidationError {
+                field: "installation".to_string(),
+                message: format!("must be between 1 and {}", self.installations),
+            }]));
+        }
+        Ok((count - 1) * self.installations + self.installation)
+    }
+
+    /// Refuses generated IDs whose check digit does not match the configured algorithm, other
+    /// IDs pass as they are. Accepting any algorithm would let almost twice as many misread
+    /// digits through; IDs issued under another one are found by lookup before this is asked.
+    pub fn verify(&self, id: &str) -> SerializedResult<()> {
+        if self.is_generated(id) && !self.check_digit.verify(id) {
+            return Err(Error::InvalidClientId(id.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Takes the next number of the sequence of `scope`, starting at 1.
+fn next_in_sequence(connection: &mut SqliteConnection, scope: &str) -> SerializedResult<u32> {
+    use crate::schema::client_id_sequences;
+
+    // bumping first takes the write lock, so two desks never read the same number
+    let updated = diesel::update(client_id_sequences::table.find(scope))
+        .set(client_id_sequences::next.eq(client_id_sequences::next + 1))
+        .execute(connection)?;
+
+    if updated == 0 {
+        diesel::insert_into(client_id_sequences::table)
+            .values((client_id_sequences::scope.eq(scope), client_id_sequences::next.eq(2)))
+            .execute(connection)?;
+        return Ok(1);
+    }
+
+    let next: i32 = client_id_sequences::table.find(scope).select(client_id_sequences::next).first(connection)?;
+    Ok(next as u32 - 1)
+}
+
+/// A new client ID for a client registered at `branch`, skipping any already taken.
+pub fn generate(connection: &mut SqliteConnection, format: &ClientIdFormat, branch: i32) -> SerializedResult<String> {
+    use crate::schema::{branches, clients};
+
+    connection.transaction::<_, Error, _>(|connection| {
+        let branch_code = if format.branch_code {
+            branches::table.find(branch).select(branches::code).first::<String>(connection)?
+        } else {
+            String::new()
+        };
+        let scope = format.scope(&branch_code);
+
+        loop {
+            let id = format.format(&branch_code, format.number(next_in_sequence(connection, &scope)?)?);
+            let taken: i64 = clients::table.filter(clients::id.eq(&id)).count().get_result(connection)?;
+            if taken == 0 {
+                return Ok(id);
+            }
+        }
+    })
+}
@@ -0,0 +1,387 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::models::database::{Book, Borrow, Client, NewBorrow};
+use crate::validation;
+use crate::SerializedResult;
+
+/// Date formats accepted when importing; the first one is also used on export.
+const DATE_FORMATS: [&str; 3] = ["%Y-%m-%d", "%d.%m.%Y", "%d/%m/%Y"];
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Entity {
+    Book,
+    Client,
+    Borrow,
+}
+
+impl Entity {
+    /// Field names as the frontend knows them, in export column order.
+    pub fn fields(&self) -> &'static [&'static str] {
+        match self {
+            Entity::Book => &["isbn", "title", "author", "items"],
+            Entity::Client => &["id", "firstName", "lastName", "email", "phone"],
+            Entity::Borrow => &["clientId", "bookIsbn", "startDate", "endDate", "returned"],
+        }
+    }
+}
+
+/// Maps an entity field name to the CSV header it is read from. Fields missing
+/// from the mapping are read from a column with the same name as the field.
+pub type ColumnMapping = HashMap<String, String>;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportFilter {
+    /// Case-insensitive text that must appear in one of the exported columns.
+    pub query: Option<String>,
+    /// Only export borrows which have not been returned yet.
+    pub active_only: Option<bool>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RowError {
+    /// Line in the CSV file, counting the header as line 1.
+    pub row: usize,
+    pub field: Option<String>,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub total_rows: usize,
+    pub valid_rows: usize,
+    pub errors: Vec<RowError>,
+    pub committed: bool,
+}
+
+enum Row {
+    Book(Book),
+    Client(Client),
+    Borrow(String, String, NaiveDate, NaiveDate, bool),
+}
+
+struct RowReader<'a> {
+    columns: HashMap<&'static str, usize>,
+    record: &'a csv::StringRecord,
+    line: usize,
+    errors: &'a mut Vec<RowError>,
+}
+
+impl<'a> RowReader<'a> {
+    fn error(&mut self, field: &str, message: String) {
+        self.errors.push(RowError { row: self.line, field: Some(field.to_string()), message });
+    }
+
+    fn text(&mut self, field: &'static str) -> Option<String> {
+        let value = self.columns.get(field).and_then(|index| self.record.get(*index)).map(|value| value.trim());
+
+        match value {
+            Some(value) if !value.is_empty() => Some(value.to_string()),
+            _ => {
+                self.error(field, "Missing value".to_string());
+                None
+            }
+        }
+    }
+
+    fn integer(&mut self, field: &'static str) -> Option<i32> {
+        let value = self.text(field)?;
+        match value.parse::<i32>() {
+            Ok(number) => Some(number),
+            Err(_) => {
+                self.error(field, format!("'{}' is not a number", value));
+                None
+            }
+        }
+    }
+
+    fn date(&mut self, field: &'static str) -> Option<NaiveDate> {
+        let value = self.text(field)?;
+        let parsed = DATE_FORMATS.iter().find_map(|format| NaiveDate::parse_from_str(&value, format).ok());
+        if parsed.is_none() {
+            self.error(field, format!("'{}' is not a valid date", value));
+        }
+        parsed
+    }
+
+    fn boolean(&mut self, field: &'static str) -> Option<bool> {
+        let value = self.text(field)?;
+        match value.to_lowercase().as_str() {
+            "true" | "1" | "da" | "yes" => Some(true),
+            "false" | "0" | "nu" | "no" => Some(false),
+            _ => {
+                self.error(field, format!("'{}' is not a boolean", value));
+                None
+            }
+        }
+    }
+}
+
+fn resolve_columns(entity: Entity, headers: &csv::StringRecord, mapping: &ColumnMapping) -> Result<HashMap<&'static str, usize>, RowError> {
+    let mut columns = HashMap::new();
+
+    for field in entity.fields() {
+        let header = mapping.get(*field).map(|header| header.as_str()).unwrap_or(field);
+        match headers.iter().position(|name| name.trim() == header) {
+            Some(index) => {
+                columns.insert(*field, index);
+            }
+            None => return Err(RowError {
+                row: 1,
+                field: Some(field.to_string()),
+                message: format!("Column '{}' not found", header),
+            })
+        }
+    }
+
+    Ok(columns)
+}
+
+fn read_row(entity: Entity, reader: &mut RowReader) -> Option<Row> {
+    match entity {
+        Entity::Book => {
+            let isbn = reader.text("isbn");
+            let title = reader.text("title");
+            let author = reader.text("author");
+            let items = reader.integer("items");
+            Some(Row::Book(Book { isbn: isbn?, title: title?, author: author?, items: items?, deleted_at: None }))
+        }
+        Entity::Client => {
+            let id = reader.text("id");
+            let first_name = reader.text("firstName");
+            let last_name = reader.text("lastName");
+            let email = reader.text("email");
+            let phone = reader.text("phone");
+            Some(Row::Client(Client { id: id?, first_name: first_name?, last_name: last_name?, email: email?, phone: phone?, deleted_at: None }))
+        }
+        Entity::Borrow => {
+            let client_id = reader.text("clientId");
+            let book_isbn = reader.text("bookIsbn");
+            let start_date = reader.date("startDate");
+            let end_date = reader.date("endDate");
+            let returned = reader.boolean("returned");
+            Some(Row::Borrow(client_id?, book_isbn?, start_date?, end_date?, returned?))
+        }
+    }
+}
+
+/// Values which have to stay unique, both within the file and against the database.
+struct UniqueKeys {
+    isbns: HashSet<String>,
+    ids: HashSet<String>,
+    emails: HashSet<String>,
+    phones: HashSet<String>,
+}
+
+impl UniqueKeys {
+    fn load(connection: &mut SqliteConnection) -> SerializedResult<Self> {
+        use crate::schema::{books, clients};
+
+        let isbns = books::table.select(books::isbn).load::<String>(connection)?;
+        let client_keys = clients::table
+            .select((clients::id, clients::email, clients::phone))
+            .load::<(String, String, String)>(connection)?;
+
+        // rows saved before the forms normalized them are compared as they would be saved today
+        Ok(Self {
+            isbns: isbns.into_iter().collect(),
+            ids: client_keys.iter().map(|(id, _, _)| id.clone()).collect(),
+            emails: client_keys.iter().map(|(_, email, _)| email.trim().to_lowercase()).collect(),
+            phones: client_keys.into_iter().map(|(_, _, phone)| validation::normalize_phone(&phone).unwrap_or(phone)).collect(),
+        })
+    }
+}
+
+/// Applies the checks of the forms to a row and returns it normalized. Client IDs are
+/// kept as they are, they usually come from the system the library is moving from; the
+/// ISBN of a borrow is brought to the ISBN-13 its book is saved under.
+fn normalize_row(row: Row, line: usize, errors: &mut Vec<RowError>) -> Option<Row> {
+    let result = match row {
+        Row::Book(book) => validation::validate_book(&book).map(Row::Book),
+        Row::Client(client) => validation::validate_client(&client, None).map(Row::Client),
+        Row::Borrow(client_id, book_isbn, start_date, end_date, returned) => {
+            let book_isbn = validation::to_isbn13(&book_isbn).unwrap_or(book_isbn);
+            Ok(Row::Borrow(client_id, book_isbn, start_date, end_date, returned))
+        }
+    };
+
+    result.map_err(|invalid| {
+        errors.extend(invalid.into_iter().map(|error| RowError { row: line, field: Some(error.field), message: error.message }));
+    }).ok()
+}
+
+fn validate_row(row: &Row, line: usize, keys: &mut UniqueKeys, errors: &mut Vec<RowError>) {
+    let mut duplicate = |set: &mut HashSet<String>, field: &str, value: &str| {
+        if !set.insert(value.to_string()) {
+            errors.push(RowError { row: line, field: Some(field.to_string()), message: format!("Duplicate {} '{}'", field, value) });
+        }
+    };
+
+    match row {
+        Row::Book(book) => {
+            duplicate(&mut keys.isbns, "isbn", &book.isbn);
+        }
+        Row::Client(client) => {
+            duplicate(&mut keys.ids, "id", &client.id);
+            duplicate(&mut keys.emails, "email", &client.email);
+            duplicate(&mut keys.phones, "phone", &client.phone);
+        }
+        Row::Borrow(client_id, book_isbn, start_date, end_date, _) => {
+            if !keys.ids.contains(client_id) {
+                errors.push(RowError { row: line, field: Some("clientId".to_string()), message: format!("Unknown client '{}'", client_id) });
+            }
+            if !keys.isbns.contains(book_isbn) {
+                errors.push(RowError { row: line, field: Some("bookIsbn".to_string()), message: format!("Unknown book '{}'", book_isbn) });
+            }
+            if end_date < start_date {
+                errors.push(RowError { row: line, field: Some("endDate".to_string()), message: "End date is before the start date".to_string() });
+            }
+        }
+    }
+}
+
+fn insert_row(connection: &mut SqliteConnection, row: &Row) -> SerializedResult<()> {
+    use crate::schema::{books, borrows, clients};
+
+    match row {
+        Row::Book(book) => diesel::insert_into(books::table).values(book).execute(connection)?,
+        Row::Client(client) => diesel::insert_into(clients::table).values(client).execute(connection)?,
+        Row::Borrow(client_id, book_isbn, start_date, end_date, returned) => {
+            let borrow = NewBorrow { client_id, book_isbn, start_date: *start_date, end_date: *end_date, returned: *returned, branch_id: crate::branches::DEFAULT_BRANCH };
+            diesel::insert_into(borrows::table).values(&borrow).execute(connection)?
+        }
+    };
+    Ok(())
+}
+
+/// Validates every row of the file and, unless `dry_run` is set, inserts all of them
+/// in a single transaction. Nothing is written when at least one row is invalid.
+pub fn import_csv(connection: &mut SqliteConnection, entity: Entity, path: &Path, mapping: &ColumnMapping, dry_run: bool) -> SerializedResult<ImportReport> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let headers = reader.headers()?.clone();
+
+    let mut report = ImportReport { total_rows: 0, valid_rows: 0, errors: Vec::new(), committed: false };
+
+    let columns = match resolve_columns(entity, &headers, mapping) {
+        Ok(columns) => columns,
+        Err(error) => {
+            report.errors.push(error);
+            return Ok(report);
+        }
+    };
+
+    let mut keys = UniqueKeys::load(connection)?;
+    let mut rows = Vec::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let line = index + 2;
+        report.total_rows += 1;
+
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                report.errors.push(RowError { row: line, field: None, message: error.to_string() });
+                continue;
+            }
+        };
+
+        let errors_before = report.errors.len();
+        let mut row_reader = RowReader { columns: columns.clone(), record: &record, line, errors: &mut report.errors };
+
+        if let Some(row) = read_row(entity, &mut row_reader).and_then(|row| normalize_row(row, line, &mut report.errors)) {
+            validate_row(&row, line, &mut keys, &mut report.errors);
+            if report.errors.len() == errors_before {
+                rows.push(row);
+            }
+        }
+    }
+
+    report.valid_rows = rows.len();
+
+    if dry_run || !report.errors.is_empty() {
+        return Ok(report);
+    }
+
+    connection.transaction::<_, crate::Error, _>(|connection| {
+        for row in &rows {
+            insert_row(connection, row)?;
+        }
+        Ok(())
+    })?;
+
+    report.committed = true;
+    Ok(report)
+}
+
+fn matches_query(values: &[&str], query: &Option<String>) -> bool {
+    match query {
+        Some(query) if !query.trim().is_empty() => {
+            let query = query.trim().to_lowercase();
+            values.iter().any(|value| value.to_lowercase().contains(&query))
+        }
+        _ => true
+    }
+}
+
+/// Writes every row of `entity` matching `filter` to `path` and returns how many were written.
+/// Archived books and clients, and their borrows, are left out and the columns are those
+/// [`import_csv`] reads, so an export imported elsewhere gives the same catalog.
+pub fn export_csv(connection: &mut SqliteConnection, entity: Entity, path: &Path, filter: &ExportFilter) -> SerializedResult<usize> {
+    use crate::schema::{books, borrows, clients};
+
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(entity.fields())?;
+    let mut count = 0;
+
+    match entity {
+        Entity::Book => {
+            for book in books::table.filter(books::deletedAt.is_null()).select(Book::as_select()).load(connection)? {
+                if matches_query(&[&book.isbn, &book.title, &book.author], &filter.query) {
+                    writer.write_record([book.isbn, book.title, book.author, book.items.to_string()])?;
+                    count += 1;
+                }
+            }
+        }
+        Entity::Client => {
+            for client in clients::table.filter(clients::deletedAt.is_null()).select(Client::as_select()).load(connection)? {
+                if matches_query(&[&client.id, &client.first_name, &client.last_name, &client.email, &client.phone], &filter.query) {
+                    writer.write_record([client.id, client.first_name, client.last_name, client.email, client.phone])?;
+                    count += 1;
+                }
+            }
+        }
+        Entity::Borrow => {
+            // borrows of archived books or clients would not import without them
+            let borrowed = borrows::table
+                .filter(borrows::bookISBN.eq_any(books::table.filter(books::deletedAt.is_null()).select(books::isbn)))
+                .filter(borrows::clientID.eq_any(clients::table.filter(clients::deletedAt.is_null()).select(clients::id)));
+
+            for borrow in borrowed.select(Borrow::as_select()).load(connection)? {
+                if filter.active_only.unwrap_or(false) && borrow.returned {
+                    continue;
+                }
+                if matches_query(&[&borrow.client_id, &borrow.book_isbn], &filter.query) {
+                    writer.write_record([
+                        borrow.client_id,
+                        borrow.book_isbn,
+                        borrow.start_date.format(DATE_FORMATS[0]).to_string(),
+                        borrow.end_date.format(DATE_FORMATS[0]).to_string(),
+                        borrow.returned.to_string(),
+                    ])?;
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    writer.flush()?;
+    Ok(count)
+}
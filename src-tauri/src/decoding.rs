@@ -0,0 +1,875 @@
+//! Reading the codes the app prints from camera frames and photos, such as a photo of a list
+//! of donated books, without a decoder in the webview. For EAN-13, Code 128, Code 39 and ITF
+//! barcodes, rows and columns of the picture are read one by one, each in both directions,
+//! and a code counts once it has been read on more than one line. QR codes are found by
+//! their three finder patterns and Data Matrix symbols by their solid L, at any angle, and
+//! their error correction is what tells them from misreads.
+
+use image::{DynamicImage, GrayImage};
+use serde::Serialize;
+
+use crate::barcode::{self, Symbology};
+use crate::matrix_codes::{self, Matrix, DATA_MATRIX_SIZES, MAX_QR_VERSION};
+use crate::SerializedResult;
+
+/// How many lines a code has to be read on, which leaves out misreads of stray lines.
+const MIN_HITS: usize = 2;
+/// Lines read across the picture in each direction, at most.
+const MAX_LINES: u32 = 600;
+/// Least difference between the darkest and lightest pixels around a bar.
+const MIN_CONTRAST: u8 = 48;
+/// Widest an element may be off its pattern, in modules.
+const MAX_ELEMENT_VARIANCE: f32 = 0.7;
+/// Most elements may be off their pattern on average, in modules.
+const MAX_AVERAGE_VARIANCE: f32 = 0.4;
+/// Light a code needs on each side, in modules; the standards ask for more, but photos are
+/// often cropped close.
+const QUIET_ZONE: f32 = 3.0;
+/// Finder patterns tried in threes for QR codes, the most often seen first.
+const MAX_FINDERS: usize = 9;
+/// Smallest dark patch, in pixels across, tried as the L of a Data Matrix symbol.
+const MIN_PATCH: usize = 10;
+/// Least share of the border modules of a Data Matrix symbol that have to be right.
+const MIN_BORDER_SCORE: f64 = 0.8;
+
+/// Bars and spaces of the digits of the left half of an EAN-13 with odd parity, from the
+/// first space; digits with even parity are these reversed, and right half digits start
+/// with a bar instead.
+const EAN_DIGITS: [[u8; 4]; 10] = [
+    [3, 2, 1, 1], [2, 2, 2, 1], [2, 1, 2, 2], [1, 4, 1, 1], [1, 1, 3, 2],
+    [1, 2, 3, 1], [1, 1, 1, 4], [1, 3, 1, 2], [1, 2, 1, 3], [3, 1, 1, 2],
+];
+/// Parities of the six left digits for each first digit, a bit set for even parity.
+const EAN_PARITIES: [u8; 10] = [0b000000, 0b001011, 0b001101, 0b001110, 0b010011, 0b011001, 0b011100, 0b010101, 0b010110, 0b011010];
+
+/// Bars and spaces of every Code 128 value, from 0 to the three start codes.
+const CODE128_VALUES: [[u8; 6]; 106] = [
+    [2, 1, 2, 2, 2, 2], [2, 2, 2, 1, 2, 2], [2, 2, 2, 2, 2, 1], [1, 2, 1, 2, 2, 3], [1, 2, 1, 3, 2, 2], [1, 3, 1, 2, 2, 2], [1, 2, 2, 2, 1, 3], [1, 2, 2, 3, 1, 2],
+    [1, 3, 2, 2, 1, 2], [2, 2, 1, 2, 1, 3], [2, 2, 1, 3, 1, 2], [2, 3, 1, 2, 1, 2], [1, 1, 2, 2, 3, 2], [1, 2, 2, 1, 3, 2], [1, 2, 2, 2, 3, 1], [1, 1, 3, 2, 2, 2],
+    [1, 2, 3, 1, 2, 2], [1, 2, 3, 2, 2, 1], [2, 2, 3, 2, 1, 1], [2, 2, 1, 1, 3, 2], [2, 2, 1, 2, 3, 1], [2, 1, 3, 2, 1, 2], [2, 2, 3, 1, 1, 2], [3, 1, 2, 1, 3, 1],
+    [3, 1, 1, 2, 2, 2], [3, 2, 1, 1, 2, 2], [3, 2, 1, 2, 2, 1], [3, 1, 2, 2, 1, 2], [3, 2, 2, 1, 1, 2], [3, 2, 2, 2, 1, 1], [2, 1, 2, 1, 2, 3], [2, 1, 2, 3, 2, 1],
+    [2, 3, 2, 1, 2, 1], [1, 1, 1, 3, 2, 3], [1, 3, 1, 1, 2, 3], [1, 3, 1, 3, 2, 1], [1, 1, 2, 3, 1, 3], [1, 3, 2, 1, 1, 3], [1, 3, 2, 3, 1, 1], [2, 1, 1, 3, 1, 3],
+    [2, 3, 1, 1, 1, 3], [2, 3, 1, 3, 1, 1], [1, 1, 2, 1, 3, 3], [1, 1, 2, 3, 3, 1], [1, 3, 2, 1, 3, 1], [1, 1, 3, 1, 2, 3], [1, 1, 3, 3, 2, 1], [1, 3, 3, 1, 2, 1],
+    [3, 1, 3, 1, 2, 1], [2, 1, 1, 3, 3, 1], [2, 3, 1, 1, 3, 1], [2, 1, 3, 1, 1, 3], [2, 1, 3, 3, 1, 1], [2, 1, 3, 1, 3, 1], [3, 1, 1, 1, 2, 3], [3, 1, 1, 3, 2, 1],
+    [3, 3, 1, 1, 2, 1], [3, 1, 2, 1, 1, 3], [3, 1, 2, 3, 1, 1], [3, 3, 2, 1, 1, 1], [3, 1, 4, 1, 1, 1], [2, 2, 1, 4, 1, 1], [4, 3, 1, 1, 1, 1], [1, 1, 1, 2, 2, 4],
+    [1, 1, 1, 4, 2, 2], [1, 2, 1, 1, 2, 4], [1, 2, 1, 4, 2, 1], [1, 4, 1, 1, 2, 2], [1, 4, 1, 2, 2, 1], [1, 1, 2, 2, 1, 4], [1, 1, 2, 4, 1, 2], [1, 2, 2, 1, 1, 4],
+    [1, 2, 2, 4, 1, 1], [1, 4, 2, 1, 1, 2], [1, 4, 2, 2, 1, 1], [2, 4, 1, 2, 1, 1], [2, 2, 1, 1, 1, 4], [4, 1, 3, 1, 1, 1], [2, 4, 1, 1, 1, 2], [1, 3, 4, 1, 1, 1],
+    [1, 1, 1, 2, 4, 2], [1, 2, 1, 1, 4, 2], [1, 2, 1, 2, 4, 1], [1, 1, 4, 2, 1, 2], [1, 2, 4, 1, 1, 2], [1, 2, 4, 2, 1, 1], [4, 1, 1, 2, 1, 2], [4, 2, 1, 1, 1, 2],
+    [4, 2, 1, 2, 1, 1], [2, 1, 2, 1, 4, 1], [2, 1, 4, 1, 2, 1], [4, 1, 2, 1, 2, 1], [1, 1, 1, 1, 4, 3], [1, 1, 1, 3, 4, 1], [1, 3, 1, 1, 4, 1], [1, 1, 4, 1, 1, 3],
+    [1, 1, 4, 3, 1, 1], [4, 1, 1, 1, 1, 3], [4, 1, 1, 3, 1, 1], [1, 1, 3, 1, 4, 1], [1, 1, 4, 1, 3, 1], [3, 1, 1, 1, 4, 1], [4, 1, 1, 1, 3, 1], [2, 1, 1, 4, 1, 2],
+    [2, 1, 1, 2, 1, 4], [2, 1, 1, 2, 3, 2]
+];
+const CODE128_STOP: [u8; 7] = [2, 3, 3, 1, 1, 1, 2];
+const CODE128_START_A: u8 = 103;
+const CODE128_START_C: u8 = 105;
+
+/// Least ratio between the narrowest wide element and the widest narrow one of a Code 39
+/// character or ITF digit, whose wide elements may be two to three times the narrow ones.
+const MIN_WIDE_RATIO: f32 = 1.4;
+const CODE39_CHARS: &[u8; 44] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-. $/+%*";
+/// The wide elements of each of [`CODE39_CHARS`], from its first bar, a bit set for each.
+const CODE39_WIDE: [u16; 44] = [
+    0b000110100, 0b100100001, 0b001100001, 0b101100000, 0b000110001, 0b100110000, 0b001110000, 0b000100101, 0b100100100, 0b001100100,
+    0b100001001, 0b001001001, 0b101001000, 0b000011001, 0b100011000, 0b001011000, 0b000001101, 0b100001100, 0b001001100, 0b000011100,
+    0b100000011, 0b001000011, 0b101000010, 0b000010011, 0b100010010, 0b001010010, 0b000000111, 0b100000110, 0b001000110, 0b000010110,
+    0b110000001, 0b011000001, 0b111000000, 0b010010001, 0b110010000, 0b011010000, 0b010000101, 0b110000100, 0b011000100, 0b010101000,
+    0b010100010, 0b010001010, 0b000101010, 0b010010100,
+];
+/// The wide elements of each ITF digit, a bit set for each.
+const ITF_WIDE: [u16; 10] = [0b00110, 0b10001, 0b01001, 0b11000, 0b00101, 0b10100, 0b01100, 0b00011, 0b10010, 0b01010];
+
+/// A code read from a picture.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScannedCode {
+    pub symbology: Symbology,
+    pub text: String,
+}
+
+/// How far `widths` are from `pattern` on average, in modules, or `None` when any of them
+/// is too far off.
+fn variance(widths: &[f32], pattern: &[u8]) -> Option<f32> {
+    let total: f32 = widths.iter().sum();
+    let modules: u32 = pattern.iter().map(|&width| width as u32).sum();
+    let unit = total / modules as f32;
+
+    let mut sum = 0.0;
+    for (&width, &expected) in widths.iter().zip(pattern) {
+        let off = (width / unit - expected as f32).abs();
+        if off > MAX_ELEMENT_VARIANCE {
+            return None;
+        }
+        sum += off;
+    }
+    Some(sum / widths.len() as f32).filter(|average| *average <= MAX_AVERAGE_VARIANCE)
+}
+
+/// The pattern `widths` are closest to, if close enough to one.
+fn closest<'a>(widths: &[f32], patterns: impl IntoIterator<Item = &'a [u8]>) -> Option<usize> {
+    patterns.into_iter().enumerate()
+        .filter_map(|(index, pattern)| variance(widths, pattern).map(|variance| (index, variance)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+/// Lengths of the light and dark runs of `line`, starting with a light one. A pixel is dark
+/// when it is below halfway between the darkest and lightest pixels around it, which copes
+/// with uneven light, and runs end where the line crosses that level between two pixels, so
+/// bars keep their widths when they are not a whole number of pixels wide.
+fn runs(line: &[u8]) -> Vec<f32> {
+    let radius = (line.len() / 32).max(10);
+    let level = |index: usize| {
+        let around = &line[index.saturating_sub(radius)..(index + radius + 1).min(line.len())];
+        let (darkest, lightest) = around.iter().fold((u8::MAX, 0), |(low, high), &value| (low.min(value), high.max(value)));
+        // paper and its noise only, nothing is dark there
+        if lightest - darkest < MIN_CONTRAST {
+            return 0.0;
+        }
+        (darkest as f32 + lightest as f32) / 2.0
+    };
+
+    let mut runs = Vec::new();
+    let mut edge = 0.0;
+    let mut dark = false;
+    for index in 0..line.len() {
+        let threshold = level(index);
+        if ((line[index] as f32) < threshold) == dark {
+            continue;
+        }
+        // pixel centers are at half pixels; the crossing is between this one and the last
+        let crossing = match index.checked_sub(1) {
+            Some(previous) if line[previous] != line[index] => {
+                let fraction = (line[previous] as f32 - threshold) / (line[previous] as f32 - line[index] as f32);
+                previous as f32 + 0.5 + fraction.clamp(0.0, 1.0)
+            }
+            _ => index as f32,
+        };
+        runs.push(crossing - edge);
+        edge = crossing;
+        dark = !dark;
+    }
+    runs.push(line.len() as f32 - edge);
+    runs
+}
+
+/// The same runs read the other way, again starting with a light one.
+fn reversed(runs: &[f32]) -> Vec<f32> {
+    let mut reversed: Vec<f32> = runs.iter().rev().copied().collect();
+    if runs.len().is_multiple_of(2) {
+        reversed.insert(0, 0.0);
+    }
+    reversed
+}
+
+/// Whether the light run at `index` is wide enough to stand before or after a code, or
+/// the line ends there.
+fn quiet(runs: &[f32], index: usize, module: f32) -> bool {
+    runs.get(index).is_none_or(|&width| width >= QUIET_ZONE * module)
+}
+
+fn ean13_at(runs: &[f32], start: usize) -> Option<(String, usize)> {
+    let runs = runs.get(start - 1..start + 60).or_else(|| runs.get(start - 1..start + 59))?;
+    let module = (runs[1] + runs[2] + runs[3]) / 3.0;
+    if !quiet(runs, 0, module) || !quiet(runs, 60, module) {
+        return None;
+    }
+    variance(&runs[1..4], &[1, 1, 1])?;
+    variance(&runs[28..33], &[1, 1, 1, 1, 1])?;
+    variance(&runs[57..60], &[1, 1, 1])?;
+
+    // odd parity patterns, then even parity ones
+    let left: Vec<[u8; 4]> = EAN_DIGITS.iter().copied()
+        .chain(EAN_DIGITS.iter().map(|pattern| [pattern[3], pattern[2], pattern[1], pattern[0]]))
+        .collect();
+    let mut digits = Vec::with_capacity(13);
+    let mut parities = 0u8;
+    for group in runs[4..28].chunks(4) {
+        let index = closest(group, left.iter().map(|pattern| &pattern[..]))?;
+        digits.push((index % 10) as u8);
+        parities = parities << 1 | (index / 10) as u8;
+    }
+    for group in runs[33..57].chunks(4) {
+        digits.push(closest(group, EAN_DIGITS.iter().map(|pattern| &pattern[..]))? as u8);
+    }
+
+    let first = EAN_PARITIES.iter().position(|&pattern| pattern == parities)? as u8;
+    digits.insert(0, first);
+    let sum: u32 = digits[..12].iter().enumerate().map(|(index, &digit)| digit as u32 * if index % 2 == 0 { 1 } else { 3 }).sum();
+    if (10 - sum % 10) % 10 != digits[12] as u32 {
+        return None;
+    }
+    Some((digits.iter().map(|digit| char::from(b'0' + digit)).collect(), start + 60))
+}
+
+/// What the Code 128 `values` after `start` stand for, with code set changes and shifts
+/// followed and function codes left out.
+fn code128_text(start: u8, values: &[u8]) -> Option<String> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Set { A, B, C }
+
+    let mut set = match start {
+        CODE128_START_A => Set::A,
+        CODE128_START_C => Set::C,
+        _ => Set::B,
+    };
+    let mut shifted = false;
+    let mut text = String::new();
+    for &value in values {
+        let current = match (shifted, set) {
+            (true, Set::A) => Set::B,
+            (true, Set::B) => Set::A,
+            _ => set,
+        };
+        shifted = false;
+        match (current, value) {
+            (Set::C, 0..=99) => text.push_str(&format!("{:02}", value)),
+            (Set::A, 0..=63) | (Set::B, 0..=95) => text.push(char::from(value + 32)),
+            (Set::A, 64..=95) => text.push(char::from(value - 64)),
+            (Set::A | Set::B, 98) => shifted = true,
+            (Set::A | Set::B, 99) => set = Set::C,
+            (Set::A, 100) | (Set::C, 100) => set = Set::B,
+            (Set::B, 101) | (Set::C, 101) => set = Set::A,
+            // FNC1 to FNC4
+            (Set::A | Set::B, 96 | 97) | (Set::A, 101) | (Set::B, 100) | (_, 102) => {}
+            _ => return None,
+        }
+    }
+    Some(text)
+}
+
+fn code128_at(runs: &[f32], start: usize) -> Option<(String, usize)> {
+    let module = runs.get(start..start + 6)?.iter().sum::<f32>() / 11.0;
+    if !quiet(runs, start - 1, module) {
+        return None;
+    }
+    let code = closest(&runs[start..start + 6], CODE128_VALUES[CODE128_START_A as usize..].iter().map(|pattern| &pattern[..]))? as u8 + CODE128_START_A;
+
+    let mut values = Vec::new();
+    let mut position = start + 6;
+    loop {
+        if let Some(stop) = runs.get(position..position + 7) {
+            if variance(stop, &CODE128_STOP).is_some() && quiet(runs, position + 7, module) {
+                break;
+            }
+        }
+        let group = runs.get(position..position + 6)?;
+        values.push(closest(group, CODE128_VALUES[..CODE128_START_A as usize].iter().map(|pattern| &pattern[..]))? as u8);
+        position += 6;
+    }
+
+    let (&checksum, values) = values.split_last()?;
+    let sum = values.iter().enumerate().fold(code as u32, |sum, (index, &value)| sum + (index as u32 + 1) * value as u32);
+    if values.is_empty() || sum % 103 != checksum as u32 {
+        return None;
+    }
+    Some((code128_text(code, values)?, position + 8))
+}
+
+/// Which of `widths` are wide, a bit set for each from the first, when exactly `wide` of
+/// them stand out and the narrow ones are about `module` wide.
+fn wide_elements(widths: &[f32], wide: usize, module: f32) -> Option<u16> {
+    let mut sorted = widths.to_vec();
+    sorted.sort_by(|a, b| b.total_cmp(a));
+    let narrow = &sorted[wide..];
+    let average = narrow.iter().sum::<f32>() / narrow.len() as f32;
+    if sorted[wide - 1] < MIN_WIDE_RATIO * narrow[0] || !(0.6 * module..1.6 * module).contains(&average) {
+        return None;
+    }
+    Some(widths.iter().fold(0, |bits, &width| bits << 1 | (width >= sorted[wide - 1]) as u16))
+}
+
+fn code39_char(widths: &[f32], module: f32) -> Option<u8> {
+    let wide = wide_elements(widths, 3, module)?;
+    CODE39_WIDE.iter().position(|&pattern| pattern == wide).map(|index| CODE39_CHARS[index])
+}
+
+/// A Code 39 code between its `*` guards, without a check character, as barcoders prints it.
+fn code39_at(runs: &[f32], start: usize) -> Option<(String, usize)> {
+    let first = runs.get(start..start + 9)?;
+    let mut sorted = first.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let mut module = sorted[..6].iter().sum::<f32>() / 6.0;
+    if code39_char(first, module)? != b'*' || !quiet(runs, start - 1, module) {
+        return None;
+    }
+
+    let mut text = String::new();
+    let mut position = start + 9;
+    loop {
+        // the gap between characters, which may be wider than a narrow space
+        if !(0.5 * module..3.5 * module).contains(runs.get(position)?) {
+            return None;
+        }
+        let widths = runs.get(position + 1..position + 10)?;
+        let character = code39_char(widths, module)?;
+        position += 10;
+        if character == b'*' {
+            break;
+        }
+        text.push(char::from(character));
+        module = widths.iter().filter(|&&width| width < MIN_WIDE_RATIO * module).sum::<f32>() / 6.0;
+    }
+    if text.is_empty() || !quiet(runs, position, module) {
+        return None;
+    }
+    Some((text, position + 1))
+}
+
+/// An Interleaved 2 of 5 code: a start of four narrow elements, pairs of digits with the
+/// first drawn in the bars and the second in the spaces, and a wide bar, a narrow space and
+/// a narrow bar to stop.
+fn itf_at(runs: &[f32], start: usize) -> Option<(String, usize)> {
+    let mut module = runs.get(start..start + 4)?.iter().sum::<f32>() / 4.0;
+    variance(&runs[start..start + 4], &[1, 1, 1, 1])?;
+    if !quiet(runs, start - 1, module) {
+        return None;
+    }
+
+    let mut text = String::new();
+    let mut widest = 0.0;
+    let mut position = start + 4;
+    loop {
+        if let Some(&[bar, space, last]) = runs.get(position..position + 3) {
+            let narrow = (0.5 * module..MIN_WIDE_RATIO * module).contains(&space) && (0.5 * module..MIN_WIDE_RATIO * module).contains(&last);
+            // wide spaces are as wide as a short quiet zone, so it has to be wider than any
+            let clear = runs.get(position + 3).is_none_or(|&light| light > MIN_WIDE_RATIO * widest);
+            if narrow && bar >= MIN_WIDE_RATIO * space.max(last) && clear && quiet(runs, position + 3, module) {
+                break;
+            }
+        }
+        let pair = runs.get(position..position + 10)?;
+        widest = pair.iter().copied().fold(widest, f32::max);
+        let (bars, spaces): (Vec<f32>, Vec<f32>) = (pair.iter().step_by(2).copied().collect(), pair.iter().skip(1).step_by(2).copied().collect());
+        for widths in [bars, spaces] {
+            let wide = wide_elements(&widths, 2, module)?;
+            text.push(char::from(b'0' + ITF_WIDE.iter().position(|&pattern| pattern == wide)? as u8));
+            module = 0.5 * module + 0.5 * widths.iter().filter(|&&width| width < MIN_WIDE_RATIO * module).sum::<f32>() / 3.0;
+        }
+        position += 10;
+    }
+    // a lone pair is too easily seen in other codes and text
+    if text.len() < 4 {
+        return None;
+    }
+    Some((barcode::itf_text(&text), position + 4))
+}
+
+/// Reads a code starting at a dark run, giving its text and the dark run after it.
+type Reader = fn(&[f32], usize) -> Option<(String, usize)>;
+
+/// Every code on one line of runs, read from left to right.
+fn decode_runs(runs: &[f32]) -> Vec<ScannedCode> {
+    let readers: [(Symbology, Reader); 4] = [
+        (Symbology::Ean13, ean13_at),
+        (Symbology::Code128, code128_at),
+        (Symbology::Code39, code39_at),
+        (Symbology::Itf, itf_at),
+    ];
+    let mut codes = Vec::new();
+    // dark runs are at the odd indices; a code read is skipped, so no other is seen in its bars
+    let mut start = 1;
+    while start < runs.len() {
+        match readers.iter().find_map(|&(symbology, read)| read(runs, start).map(|(text, end)| (symbology, text, end))) {
+            Some((symbology, text, end)) => {
+                codes.push(ScannedCode { symbology, text });
+                start = end;
+            }
+            None => start += 2,
+        }
+    }
+    codes
+}
+
+fn read_lines(image: &GrayImage, transposed: bool, hits: &mut Vec<(ScannedCode, usize)>) {
+    let (width, height) = if transposed { (image.height(), image.width()) } else { image.dimensions() };
+    let step = (height / MAX_LINES).max(1);
+
+    for y in (0..height).step_by(step as usize) {
+        let line: Vec<u8> = (0..width).map(|x| if transposed { image.get_pixel(y, x)[0] } else { image.get_pixel(x, y)[0] }).collect();
+        let forward = runs(&line);
+        let mut found = decode_runs(&forward);
+        for code in decode_runs(&reversed(&forward)) {
+            if !found.contains(&code) {
+                found.push(code);
+            }
+        }
+
+        for code in found {
+            match hits.iter_mut().find(|(seen, _)| *seen == code) {
+                Some((_, count)) => *count += 1,
+                None => hits.push((code, 1)),
+            }
+        }
+    }
+}
+
+/// `image` in dark and light pixels. A pixel is dark when it is below halfway between the
+/// darkest and lightest pixels of the blocks around it, like the runs of [`runs`]; the blocks
+/// are wide enough to take in the middle of a finder pattern.
+struct Binary {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+}
+
+impl Binary {
+    fn new(image: &GrayImage) -> Self {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let block = (width.min(height) / 24).max(8);
+        let (columns, rows) = (width.div_ceil(block), height.div_ceil(block));
+        let mut extremes = vec![(u8::MAX, 0u8); columns * rows];
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let extreme = &mut extremes[(y as usize / block) * columns + x as usize / block];
+            *extreme = (extreme.0.min(pixel[0]), extreme.1.max(pixel[0]));
+        }
+
+        let mut levels = vec![None; columns * rows];
+        for row in 0..rows {
+            for column in 0..columns {
+                let (mut darkest, mut lightest) = (u8::MAX, 0);
+                for around in row.saturating_sub(2)..(row + 3).min(rows) {
+                    for &(low, high) in &extremes[around * columns + column.saturating_sub(2)..around * columns + (column + 3).min(columns)] {
+                        (darkest, lightest) = (darkest.min(low), lightest.max(high));
+                    }
+                }
+                if lightest - darkest >= MIN_CONTRAST {
+                    levels[row * columns + column] = Some((darkest as u16 + lightest as u16) / 2);
+                }
+            }
+        }
+
+        let dark = image.enumerate_pixels()
+            .map(|(x, y, pixel)| levels[(y as usize / block) * columns + x as usize / block].is_some_and(|level| (pixel[0] as u16) < level))
+            .collect();
+        Binary { width, height, dark }
+    }
+
+    /// Whether the pixel at `x` and `y` is dark; everything outside the picture is light.
+    fn is_dark(&self, x: f64, y: f64) -> bool {
+        x >= 0.0 && y >= 0.0 && (x as usize) < self.width && (y as usize) < self.height && self.dark[y as usize * self.width + x as usize]
+    }
+}
+
+/// A map from the modules of a symbol to the picture, which may show it at an angle.
+struct Perspective([f64; 8]);
+
+impl Perspective {
+    /// The map taking each of four points of a symbol to where it is in the picture.
+    fn from_points(points: [((f64, f64), (f64, f64)); 4]) -> Option<Self> {
+        // x = (a u + b v + c) / (g u + h v + 1) and y = (d u + e v + f) / (g u + h v + 1)
+        let mut equations = [[0.0; 9]; 8];
+        for (index, ((u, v), (x, y))) in points.into_iter().enumerate() {
+            equations[2 * index] = [u, v, 1.0, 0.0, 0.0, 0.0, -u * x, -v * x, x];
+            equations[2 * index + 1] = [0.0, 0.0, 0.0, u, v, 1.0, -u * y, -v * y, y];
+        }
+        for column in 0..8 {
+            let pivot = (column..8).max_by(|&a, &b| equations[a][column].abs().total_cmp(&equations[b][column].abs()))?;
+            if equations[pivot][column].abs() < 1e-9 {
+                return None;
+            }
+            equations.swap(column, pivot);
+            let pivot = equations[column];
+            for (row, equation) in equations.iter_mut().enumerate() {
+                if row != column {
+                    let factor = equation[column] / pivot[column];
+                    for (value, &subtracted) in equation.iter_mut().zip(&pivot).skip(column) {
+                        *value -= factor * subtracted;
+                    }
+                }
+            }
+        }
+        Some(Perspective(std::array::from_fn(|index| equations[index][8] / equations[index][index])))
+    }
+
+    fn map(&self, u: f64, v: f64) -> (f64, f64) {
+        let [a, b, c, d, e, f, g, h] = self.0;
+        let scale = g * u + h * v + 1.0;
+        ((a * u + b * v + c) / scale, (d * u + e * v + f) / scale)
+    }
+
+    /// The modules of a `size` symbol, read at their centers.
+    fn sample(&self, binary: &Binary, size: usize) -> Matrix {
+        let mut matrix = Matrix::new(size, size);
+        for y in 0..size {
+            for x in 0..size {
+                let (px, py) = self.map(x as f64 + 0.5, y as f64 + 0.5);
+                matrix.set(x, y, binary.is_dark(px, py));
+            }
+        }
+        matrix
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// The center of a QR finder pattern and the width of its modules.
+#[derive(Clone, Copy)]
+struct Finder {
+    x: f64,
+    y: f64,
+    module: f64,
+    hits: usize,
+}
+
+/// The dark, light, dark, light and dark runs through (`x`, `y`) along (`dx`, `dy`), with
+/// where the middle one is centered, when they are those of a finder pattern.
+fn finder_along(binary: &Binary, (x, y): (f64, f64), (dx, dy): (f64, f64), limit: f64) -> Option<(f64, f64)> {
+    let mut widths = [0.0f32; 5];
+    let mut ends = [0.0; 2];
+    for (side, sign) in [-1.0, 1.0].into_iter().enumerate() {
+        // the middle run, then the light and the dark runs beyond it
+        let order = if side == 0 { [2, 1, 0] } else { [2, 3, 4] };
+        let mut step = 0.0;
+        for (index, run) in order.into_iter().enumerate() {
+            let start = step;
+            while binary.is_dark(x + sign * step * dx, y + sign * step * dy) == (index != 1) {
+                step += 1.0;
+                if step > limit {
+                    return None;
+                }
+            }
+            if step == start {
+                return None;
+            }
+            widths[run] += (step - start) as f32;
+            if run == 2 {
+                ends[side] = step;
+            }
+        }
+    }
+    // the pixel at (x, y) was counted from both sides
+    widths[2] -= 1.0;
+    variance(&widths, &[1, 1, 3, 1, 1])?;
+    Some(((ends[1] - ends[0]) / 2.0, widths.iter().sum::<f32>() as f64))
+}
+
+/// The finder patterns along the rows of `binary`, each checked across and merged with the
+/// others found on the rows next to it.
+fn finders(binary: &Binary) -> Vec<Finder> {
+    let mut finders: Vec<Finder> = Vec::new();
+    for y in 0..binary.height {
+        let row = &binary.dark[y * binary.width..(y + 1) * binary.width];
+        // starts of the runs of the row, and where it ends
+        let mut edges: Vec<usize> = (0..row.len()).filter(|&x| x == 0 || row[x] != row[x - 1]).collect();
+        edges.push(row.len());
+        let first_dark = if row.first() == Some(&true) { 0 } else { 1 };
+
+        for index in (first_dark..edges.len().saturating_sub(5)).step_by(2) {
+            let widths: Vec<f32> = edges[index..index + 6].windows(2).map(|pair| (pair[1] - pair[0]) as f32).collect();
+            if variance(&widths, &[1, 1, 3, 1, 1]).is_none() {
+                continue;
+            }
+            let total: f32 = widths.iter().sum();
+            let x = (edges[index + 2] + edges[index + 3]) as f64 / 2.0;
+            let limit = 2.0 * total as f64;
+            let Some((dy, down)) = finder_along(binary, (x, y as f64 + 0.5), (0.0, 1.0), limit) else { continue };
+            let y = y as f64 + 0.5 + dy;
+            let Some((dx, across)) = finder_along(binary, (x, y), (1.0, 0.0), limit) else { continue };
+            if !(0.6..1.6).contains(&(down / across)) {
+                continue;
+            }
+            let finder = Finder { x: x + dx, y, module: (down + across) / 14.0, hits: 1 };
+
+            match finders.iter_mut().find(|seen| distance((seen.x, seen.y), (finder.x, finder.y)) < 2.0 * seen.module) {
+                Some(seen) => {
+                    let hits = seen.hits as f64;
+                    seen.x = (seen.x * hits + finder.x) / (hits + 1.0);
+                    seen.y = (seen.y * hits + finder.y) / (hits + 1.0);
+                    seen.module = (seen.module * hits + finder.module) / (hits + 1.0);
+                    seen.hits += 1;
+                }
+                None => finders.push(finder),
+            }
+        }
+    }
+    finders.retain(|finder| finder.hits >= MIN_HITS);
+    finders.sort_by_key(|finder| std::cmp::Reverse(finder.hits));
+    finders.truncate(MAX_FINDERS);
+    finders
+}
+
+/// How many of the 25 modules around `center`, `right` and `down` apart, are those of an
+/// alignment pattern.
+fn alignment_score(binary: &Binary, center: (f64, f64), right: (f64, f64), down: (f64, f64)) -> usize {
+    let mut score = 0;
+    for j in -2i32..=2 {
+        for i in -2i32..=2 {
+            let (x, y) = (center.0 + i as f64 * right.0 + j as f64 * down.0, center.1 + i as f64 * right.1 + j as f64 * down.1);
+            if binary.is_dark(x, y) == (i.abs().max(j.abs()) != 1) {
+                score += 1;
+            }
+        }
+    }
+    score
+}
+
+/// The QR code whose finder patterns are the top left, top right and bottom left ones given.
+fn read_qr(binary: &Binary, [top_left, top_right, bottom_left]: [(f64, f64); 3], module: f64) -> Option<Vec<u8>> {
+    let span = (distance(top_left, top_right) + distance(top_left, bottom_left)) / 2.0 / module;
+    let estimate = ((span + 7.0 - 17.0) / 4.0).round() as i64;
+
+    for version in [estimate, estimate - 1, estimate + 1] {
+        let Some(version) = usize::try_from(version).ok().filter(|version| (1..=MAX_QR_VERSION).contains(version)) else { continue };
+        let size = 17 + 4 * version;
+        let apart = (size - 7) as f64;
+        let right = ((top_right.0 - top_left.0) / apart, (top_right.1 - top_left.1) / apart);
+        let down = ((bottom_left.0 - top_left.0) / apart, (bottom_left.1 - top_left.1) / apart);
+        let affine = |u: f64, v: f64| (top_left.0 + (u - 3.5) * right.0 + (v - 3.5) * down.0, top_left.1 + (u - 3.5) * right.1 + (v - 3.5) * down.1);
+
+        // the bottom right alignment pattern, where an angle may have moved it, or the
+        // corner that would make a parallelogram for codes without one
+        let corner = size as f64 - 6.5;
+        let mut fourth = ((size as f64 - 3.5, size as f64 - 3.5), affine(size as f64 - 3.5, size as f64 - 3.5));
+        if version > 1 {
+            let expected = affine(corner, corner);
+            let reach = (4.0 * module).ceil() as i32;
+            let mut best = (0, expected);
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let center = (expected.0 + dx as f64, expected.1 + dy as f64);
+                    let score = alignment_score(binary, center, right, down);
+                    if score > best.0 {
+                        best = (score, center);
+                    }
+                }
+            }
+            if best.0 >= 23 {
+                fourth = ((corner, corner), best.1);
+            }
+        }
+
+        let perspective = Perspective::from_points([((3.5, 3.5), top_left), ((apart + 3.5, 3.5), top_right), ((3.5, apart + 3.5), bottom_left), fourth])?;
+        if let Some(data) = matrix_codes::decode_qr(&perspective.sample(binary, size)) {
+            return Some(data);
+        }
+    }
+    None
+}
+
+/// The QR codes whose three finder patterns are among `finders`.
+fn qr_codes(binary: &Binary, finders: &[Finder]) -> Vec<Vec<u8>> {
+    let mut codes = Vec::new();
+    for (a, first) in finders.iter().enumerate() {
+        for (b, second) in finders.iter().enumerate().skip(a + 1) {
+            for third in &finders[b + 1..] {
+                let modules = [first.module, second.module, third.module];
+                let module = modules.iter().sum::<f64>() / 3.0;
+                if modules.iter().any(|&size| !(0.67..1.5).contains(&(size / module))) {
+                    continue;
+                }
+                // the top left pattern is across from the longest side, at a right angle
+                let mut points = [(first.x, first.y), (second.x, second.y), (third.x, third.y)];
+                let longest = (0..3).max_by(|&i, &j| {
+                    distance(points[(i + 1) % 3], points[(i + 2) % 3]).total_cmp(&distance(points[(j + 1) % 3], points[(j + 2) % 3]))
+                }).unwrap_or(0);
+                points.rotate_left(longest);
+                let [corner, mut top_right, mut bottom_left] = points;
+                let (sides, diagonal) = ([distance(corner, top_right), distance(corner, bottom_left)], distance(top_right, bottom_left));
+                if !(0.8..1.25).contains(&(sides[0] / sides[1])) || !(0.85..1.15).contains(&(diagonal / sides[0].hypot(sides[1]))) {
+                    continue;
+                }
+                // with y going down, the top right pattern is clockwise from the bottom left one
+                let cross = (top_right.0 - corner.0) * (bottom_left.1 - corner.1) - (top_right.1 - corner.1) * (bottom_left.0 - corner.0);
+                if cross < 0.0 {
+                    std::mem::swap(&mut top_right, &mut bottom_left);
+                }
+                if let Some(data) = read_qr(binary, [corner, top_right, bottom_left], module) {
+                    codes.push(data);
+                }
+            }
+        }
+    }
+    codes
+}
+
+/// The outline of each patch of connected dark pixels big enough to be a Data Matrix symbol,
+/// as the corners of the pixels on its left and right edges.
+fn dark_patches(binary: &Binary) -> Vec<Vec<(f64, f64)>> {
+    let mut seen = vec![false; binary.dark.len()];
+    let mut patches = Vec::new();
+    for start in 0..binary.dark.len() {
+        if !binary.dark[start] || seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        let mut stack = vec![start];
+        // the leftmost and rightmost pixel of each row the patch spans
+        let mut extents: Vec<(usize, usize, usize)> = Vec::new();
+        let (mut top, mut bottom) = (usize::MAX, 0);
+        while let Some(index) = stack.pop() {
+            let (x, y) = (index % binary.width, index / binary.width);
+            (top, bottom) = (top.min(y), bottom.max(y));
+            match extents.iter_mut().find(|(row, ..)| *row == y) {
+                Some((_, left, right)) => (*left, *right) = ((*left).min(x), (*right).max(x)),
+                None => extents.push((y, x, x)),
+            }
+            for ny in y.saturating_sub(1)..=(y + 1).min(binary.height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(binary.width - 1) {
+                    let next = ny * binary.width + nx;
+                    if binary.dark[next] && !seen[next] {
+                        seen[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        let (left, right) = extents.iter().fold((usize::MAX, 0), |(left, right), &(_, l, r)| (left.min(l), right.max(r)));
+        if bottom - top + 1 < MIN_PATCH || right - left + 1 < MIN_PATCH {
+            continue;
+        }
+        patches.push(extents.into_iter().flat_map(|(y, left, right)| {
+            let (y, left, right) = (y as f64, left as f64, right as f64 + 1.0);
+            [(left, y), (left, y + 1.0), (right, y), (right, y + 1.0)]
+        }).collect());
+    }
+    patches
+}
+
+/// The convex hull of `points`, counterclockwise as seen with y going up.
+fn hull(mut points: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    points.dedup();
+    let turn = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0);
+    let mut hull: Vec<(f64, f64)> = Vec::new();
+    for pass in 0..2 {
+        let floor = hull.len();
+        let ordered: Box<dyn Iterator<Item = &(f64, f64)>> = if pass == 0 { Box::new(points.iter()) } else { Box::new(points.iter().rev()) };
+        for &point in ordered {
+            while hull.len() >= floor + 2 && turn(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// How many of the modules on the border of a Data Matrix of `size` read through
+/// `perspective` are those of its solid L and its alternating top and right sides.
+fn border_score(binary: &Binary, perspective: &Perspective, size: usize) -> f64 {
+    let dark = |x: usize, y: usize| {
+        let (px, py) = perspective.map(x as f64 + 0.5, y as f64 + 0.5);
+        binary.is_dark(px, py)
+    };
+    let mut matches = 0;
+    for index in 0..size {
+        matches += dark(0, index) as usize
+            + dark(index, size - 1) as usize
+            + (dark(index, 0) == index.is_multiple_of(2)) as usize
+            + (dark(size - 1, index) != index.is_multiple_of(2)) as usize;
+    }
+    matches as f64 / (4 * size) as f64
+}
+
+/// The Data Matrix symbol whose L is the dark patch outlined by `outline`, if it is one.
+/// The corners of its L are the ends of the longest line across the patch and the point
+/// farthest from it; the fourth is where they make a parallelogram, moved until the
+/// alternating sides line up.
+fn read_data_matrix(binary: &Binary, outline: Vec<(f64, f64)>) -> Option<Vec<u8>> {
+    let hull = hull(outline);
+    let mut ends = (hull[0], hull[0]);
+    for (index, &a) in hull.iter().enumerate() {
+        for &b in &hull[index + 1..] {
+            if distance(a, b) > distance(ends.0, ends.1) {
+                ends = (a, b);
+            }
+        }
+    }
+    let (a, c) = ends;
+    let away = |p: (f64, f64)| ((c.0 - a.0) * (p.1 - a.1) - (c.1 - a.1) * (p.0 - a.0)).abs();
+    let b = hull.iter().copied().max_by(|p, q| away(*p).total_cmp(&away(*q)))?;
+    let d = (a.0 + c.0 - b.0, a.1 + c.1 - b.1);
+    if !(0.5..2.0).contains(&(distance(a, b) / distance(b, c))) {
+        return None;
+    }
+
+    // clockwise as seen with y going down, and which of them is the guessed one
+    let center = ((a.0 + c.0) / 2.0, (a.1 + c.1) / 2.0);
+    let mut corners = [(a, false), (b, false), (c, false), (d, true)];
+    corners.sort_by(|p, q| (p.0 .1 - center.1).atan2(p.0 .0 - center.0).total_cmp(&(q.0 .1 - center.1).atan2(q.0 .0 - center.0)));
+    let guessed = corners.iter().position(|&(_, guessed)| guessed)?;
+    let corners = corners.map(|(corner, _)| corner);
+
+    // the symbol's top left corner may be at any of them
+    let perspective = |size: usize, turn: usize, corners: [(f64, f64); 4]| {
+        let square = [(0.0, 0.0), (size as f64, 0.0), (size as f64, size as f64), (0.0, size as f64)];
+        Perspective::from_points(std::array::from_fn(|index| (square[index], corners[(index + turn) % 4])))
+    };
+    let mut candidates = Vec::new();
+    for &(size, ..) in &DATA_MATRIX_SIZES {
+        for turn in 0..4 {
+            if let Some(perspective) = perspective(size, turn, corners) {
+                candidates.push((border_score(binary, &perspective, size), size, turn));
+            }
+        }
+    }
+    candidates.sort_by(|x, y| y.0.total_cmp(&x.0));
+
+    let side = (distance(a, b) + distance(b, c)) / 2.0;
+    for &(_, size, turn) in candidates.iter().take(3) {
+        let step = side / size as f64 / 4.0;
+        let mut best = (0.0, corners);
+        for dy in -6..=6 {
+            for dx in -6..=6 {
+                let mut moved = corners;
+                moved[guessed] = (corners[guessed].0 + dx as f64 * step, corners[guessed].1 + dy as f64 * step);
+                let Some(perspective) = perspective(size, turn, moved) else { continue };
+                let score = border_score(binary, &perspective, size);
+                if score > best.0 {
+                    best = (score, moved);
+                }
+            }
+        }
+        if best.0 < MIN_BORDER_SCORE {
+            continue;
+        }
+        let perspective = perspective(size, turn, best.1)?;
+        if let Some(data) = matrix_codes::decode_data_matrix(&perspective.sample(binary, size)) {
+            return Some(data);
+        }
+    }
+    None
+}
+
+/// What a QR code or Data Matrix symbol carries, as UTF-8 or else as Latin-1.
+fn symbol_text(data: Vec<u8>) -> String {
+    String::from_utf8(data).unwrap_or_else(|error| error.into_bytes().into_iter().map(char::from).collect())
+}
+
+/// The QR codes and Data Matrix symbols in `image`.
+fn decode_symbols(image: &GrayImage) -> Vec<ScannedCode> {
+    let binary = Binary::new(image);
+    let mut codes: Vec<ScannedCode> = qr_codes(&binary, &finders(&binary)).into_iter()
+        .map(|data| ScannedCode { symbology: Symbology::Qr, text: symbol_text(data) })
+        .collect();
+    for outline in dark_patches(&binary) {
+        if let Some(data) = read_data_matrix(&binary, outline) {
+            codes.push(ScannedCode { symbology: Symbology::DataMatrix, text: symbol_text(data) });
+        }
+    }
+    codes
+}
+
+/// The codes in `image`, rows first and then columns, in the order they were first read.
+pub fn decode(image: &DynamicImage) -> Vec<ScannedCode> {
+    let gray = image.to_luma8();
+    let mut hits = Vec::new();
+    read_lines(&gray, false, &mut hits);
+    read_lines(&gray, true, &mut hits);
+    let mut codes: Vec<ScannedCode> = hits.into_iter().filter(|(_, count)| *count >= MIN_HITS).map(|(code, _)| code).collect();
+    // error correction already tells these from misreads
+    for code in decode_symbols(&gray) {
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+    codes
+}
+
+/// The codes in a PNG, JPEG or other picture file.
+pub fn decode_file(data: &[u8]) -> SerializedResult<Vec<ScannedCode>> {
+    Ok(decode(&image::load_from_memory(data)?))
+}
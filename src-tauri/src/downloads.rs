@@ -0,0 +1,95 @@
+//! Where downloaded labels, badges and sheets are saved: a path picked for the download, or
+//! the folder set in the settings, under a name filled in from a template such as
+//! `{id}_{lastName}`. A file of the same name is never overwritten; the new one is numbered.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::validation::ValidationError;
+use crate::{Error, SerializedResult};
+
+/// Most copies of one name there can be, `name (2)` up to `name (999)`.
+const MAX_COPIES: u32 = 999;
+
+fn invalid(field: &str, message: String) -> Error {
+    Error::Validation(vec![ValidationError { field: field.to_string(), message }])
+}
+
+/// Replaces what cannot be in a file name on Windows, macOS or Linux.
+pub fn sanitize(name: &str) -> String {
+    let replaced: String = name.chars()
+        .map(|c| if c.is_control() || "<>:\"/\\|?*".contains(c) { '_' } else { c })
+        .collect();
+    // Windows drops dots and spaces at the end of names
+    replaced.trim().trim_end_matches('.').to_string()
+}
+
+/// Fills the `{placeholder}`s of `template` from `values`; placeholders it does not know,
+/// and names left empty, are reported on `field`.
+pub fn file_name(template: &str, values: &[(&str, &str)], field: &str) -> SerializedResult<String> {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err(invalid(field, format!("'{}' has a {{ that is never closed", template)));
+        };
+        let placeholder = &rest[start + 1..start + end];
+        let Some((_, value)) = values.iter().find(|(key, _)| *key == placeholder) else {
+            let known = values.iter().map(|(key, _)| format!("{{{}}}", key)).collect::<Vec<_>>().join(", ");
+            return Err(invalid(field, format!("{{{}}} is not one of {}", placeholder, known)));
+        };
+        name.push_str(value);
+        rest = &rest[start + end + 1..];
+    }
+    name.push_str(rest);
+
+    let name = sanitize(&name);
+    if name.is_empty() {
+        return Err(invalid(field, format!("'{}' gives an empty file name", template)));
+    }
+    Ok(name)
+}
+
+/// Where a file named `name` with `extension` goes: into `target` when it is a folder, to
+/// `target` itself when it is a file path, and into `directory` when there is no target.
+pub fn target_path(target: Option<&Path>, directory: &Path, name: &str, extension: &str) -> PathBuf {
+    let file = format!("{}.{}", name, extension);
+    match target {
+        Some(target) if target.is_dir() => target.join(file),
+        Some(target) if target.extension().is_some_and(|given| given.eq_ignore_ascii_case(extension)) => target.to_path_buf(),
+        Some(target) => {
+            let mut path = target.as_os_str().to_owned();
+            path.push(format!(".{}", extension));
+            PathBuf::from(path)
+        }
+        None => directory.join(file),
+    }
+}
+
+/// Writes `data` to `path`, or beside it as `name (2).ext`, `name (3).ext` and so on when
+/// a file already has that name, and returns the path written.
+pub fn write_new(path: &Path, data: &[u8]) -> SerializedResult<PathBuf> {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+
+    for copy in 1..=MAX_COPIES {
+        let candidate = if copy == 1 { path.to_path_buf() } else { path.with_file_name(format!("{} ({}){}", stem, copy, extension)) };
+        // create_new fails instead of truncating, also when another download takes the name first
+        match OpenOptions::new().write(true).create_new(true).open(&candidate) {
+            Ok(mut file) => {
+                file.write_all(data)?;
+                return Ok(candidate);
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Err(invalid("target", format!("There are already {} files named {}{}", MAX_COPIES, stem, extension)))
+}
+
+/// Saves `data` as [`target_path`] places it without overwriting anything, see [`write_new`].
+pub fn save(target: Option<&Path>, directory: &Path, name: &str, extension: &str, data: &[u8]) -> SerializedResult<PathBuf> {
+    write_new(&target_path(target, directory, name, extension), data)
+}
@@ -0,0 +1,219 @@
+//! Labels and badges kept as the shapes they are drawn with instead of pixels, so the same
+//! layout gives a PNG, or an SVG or PDF that stays sharp at any size. Text is written out
+//! as the outlines of its glyphs, which needs no fonts where the file is opened.
+
+use std::fmt::Write;
+use std::io::Cursor;
+
+use ab_glyph::{Font, FontArc, GlyphId, OutlineCurve, Point, PxScale, ScaleFont};
+use image::{ImageFormat, Rgba, RgbaImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_text_mut};
+use imageproc::rect::Rect;
+use serde::{Deserialize, Serialize};
+
+use crate::pdf::{PdfWriter, Placement};
+use crate::SerializedResult;
+
+/// The files labels and badges can be downloaded as.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    Svg,
+    Pdf,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Svg => "svg",
+            OutputFormat::Pdf => "pdf",
+        }
+    }
+}
+
+pub enum Shape {
+    Rect { x: i32, y: i32, width: u32, height: u32, color: Rgba<u8> },
+    /// A line of text whose top is at `y`, as `imageproc` draws it.
+    Text { x: i32, y: i32, scale: PxScale, font: FontArc, text: String, color: Rgba<u8> },
+    /// Photos and logos, which stay pixels in every format.
+    Image { x: i64, y: i64, image: RgbaImage },
+}
+
+/// A step of a path filled with the nonzero rule, in pixels from the top left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    MoveTo(Point),
+    LineTo(Point),
+    /// Control point and end.
+    QuadTo(Point, Point),
+    /// Both control points and end.
+    CubicTo(Point, Point, Point),
+    Close,
+}
+
+/// The outlines of `text` drawn at `x`, `y` with `font`, glyph by glyph as `imageproc`
+/// places them.
+pub fn outline_text(x: f32, y: f32, scale: PxScale, font: &FontArc, text: &str) -> Vec<Segment> {
+    let scaled = font.as_scaled(scale);
+    let (h_factor, v_factor) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+    let mut segments = Vec::new();
+    let mut caret = 0.0;
+    let mut last: Option<GlyphId> = None;
+
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        let origin = caret;
+        caret += scaled.h_advance(id);
+        let Some(outline) = font.outline(id) else { continue };
+        if let Some(last) = last {
+            caret += scaled.kern(id, last);
+        }
+        last = Some(id);
+
+        // font units count up from the baseline
+        let place = |point: Point| ab_glyph::point(x + origin + point.x * h_factor, y + scaled.ascent() - point.y * v_factor);
+        let mut end: Option<Point> = None;
+        for curve in outline.curves {
+            let start = match curve {
+                OutlineCurve::Line(start, _) | OutlineCurve::Quad(start, _, _) | OutlineCurve::Cubic(start, _, _, _) => start,
+            };
+            if end != Some(start) {
+                if end.is_some() {
+                    segments.push(Segment::Close);
+                }
+                segments.push(Segment::MoveTo(place(start)));
+            }
+            end = Some(match curve {
+                OutlineCurve::Line(_, to) => {
+                    segments.push(Segment::LineTo(place(to)));
+                    to
+                }
+                OutlineCurve::Quad(_, control, to) => {
+                    segments.push(Segment::QuadTo(place(control), place(to)));
+                    to
+                }
+                OutlineCurve::Cubic(_, first, second, to) => {
+                    segments.push(Segment::CubicTo(place(first), place(second), place(to)));
+                    to
+                }
+            });
+        }
+        if end.is_some() {
+            segments.push(Segment::Close);
+        }
+    }
+    segments
+}
+
+fn hex(color: Rgba<u8>) -> String {
+    format!("#{:02X}{:02X}{:02X}", color[0], color[1], color[2])
+}
+
+fn fill(color: Rgba<u8>) -> String {
+    if color[3] == 255 {
+        format!("fill=\"{}\"", hex(color))
+    } else {
+        format!("fill=\"{}\" fill-opacity=\"{:.3}\"", hex(color), color[3] as f32 / 255.0)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+pub fn encode_png(image: &RgbaImage) -> SerializedResult<Vec<u8>> {
+    let mut data = Vec::new();
+    image.write_to(&mut Cursor::new(&mut data), ImageFormat::Png)?;
+    Ok(data)
+}
+
+/// A label or badge of `width` x `height` pixels, drawn shape over shape.
+pub struct Drawing {
+    pub width: u32,
+    pub height: u32,
+    pub shapes: Vec<Shape>,
+}
+
+impl Drawing {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height, shapes: Vec::new() }
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: Rgba<u8>) {
+        self.shapes.push(Shape::Rect { x, y, width, height, color });
+    }
+
+    pub fn draw_text(&mut self, x: i32, y: i32, scale: PxScale, font: &FontArc, text: &str, color: Rgba<u8>) {
+        self.shapes.push(Shape::Text { x, y, scale, font: font.clone(), text: text.to_string(), color });
+    }
+
+    /// Draws `image` over what is under it, blending by its alpha.
+    pub fn draw_image(&mut self, x: i64, y: i64, image: RgbaImage) {
+        self.shapes.push(Shape::Image { x, y, image });
+    }
+
+    pub fn to_image(&self) -> RgbaImage {
+        let mut image = RgbaImage::new(self.width, self.height);
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect { x, y, width, height, color } => {
+                    draw_filled_rect_mut(&mut image, Rect::at(*x, *y).of_size(*width, *height), *color);
+                }
+                Shape::Text { x, y, scale, font, text, color } => draw_text_mut(&mut image, *color, *x, *y, *scale, font, text),
+                Shape::Image { x, y, image: picture } => image::imageops::overlay(&mut image, picture, *x, *y),
+            }
+        }
+        image
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+            self.width, self.height
+        );
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect { x, y, width, height, color } => {
+                    writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>", x, y, width, height, fill(*color)).unwrap();
+                }
+                Shape::Text { x, y, scale, font, text, color } => {
+                    let path: String = outline_text(*x as f32, *y as f32, *scale, font, text).iter().map(|segment| match segment {
+                        Segment::MoveTo(to) => format!("M{:.2} {:.2}", to.x, to.y),
+                        Segment::LineTo(to) => format!("L{:.2} {:.2}", to.x, to.y),
+                        Segment::QuadTo(control, to) => format!("Q{:.2} {:.2} {:.2} {:.2}", control.x, control.y, to.x, to.y),
+                        Segment::CubicTo(first, second, to) => format!("C{:.2} {:.2} {:.2} {:.2} {:.2} {:.2}", first.x, first.y, second.x, second.y, to.x, to.y),
+                        Segment::Close => "Z".to_string(),
+                    }).collect();
+                    if !path.is_empty() {
+                        writeln!(svg, "<path aria-label=\"{}\" d=\"{}\" {}/>", escape(text), path, fill(*color)).unwrap();
+                    }
+                }
+                Shape::Image { x, y, image } => {
+                    let data = crate::photos::encode_base64(&encode_png(image).unwrap());
+                    writeln!(svg, "<image x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" href=\"data:image/png;base64,{}\"/>", x, y, image.width(), image.height(), data).unwrap();
+                }
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// The drawing as a file in `format`. A PDF is a single page of `page` millimetres, width
+    /// and height, with the drawing as large as fits in it.
+    pub fn encode(&self, format: OutputFormat, page: (f32, f32)) -> SerializedResult<Vec<u8>> {
+        Ok(match format {
+            OutputFormat::Png => encode_png(&self.to_image())?,
+            OutputFormat::Svg => self.to_svg().into_bytes(),
+            OutputFormat::Pdf => {
+                let mut writer = PdfWriter::new();
+                writer.add_page(page.0, page.1);
+                let whole_page = Placement { x: 0.0, y: 0.0, width: page.0, height: page.1 };
+                writer.add_drawing(self, whole_page.fit_size(self.width, self.height));
+                writer.finish()
+            }
+        })
+    }
+}
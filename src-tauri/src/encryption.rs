@@ -0,0 +1,128 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use diesel::{Connection, QueryableByName, RunQueryDsl, SqliteConnection};
+use diesel::sql_types::Text;
+
+use crate::{Error, SerializedResult};
+
+/// Every unencrypted SQLite file starts with this header; SQLCipher files start with random salt.
+const PLAIN_HEADER: &[u8; 16] = b"SQLite format 3\0";
+/// SQLCipher only ever writes whole pages of this size.
+const CIPHER_PAGE_SIZE: u64 = 4096;
+
+#[derive(QueryableByName)]
+struct CipherVersion {
+    #[diesel(sql_type = Text)]
+    cipher_version: String,
+}
+
+/// Quotes `value` as an SQL string literal.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Whether the file at `path` is an encrypted database. A file which is neither plain SQLite
+/// nor whole pages SQLCipher refuses to read without a key is reported as damaged instead,
+/// so it reaches the recovery window rather than the passphrase prompt.
+pub fn is_encrypted(path: &Path) -> SerializedResult<bool> {
+    let length = match std::fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return Ok(false)
+    };
+    if length == 0 {
+        return Ok(false);
+    }
+
+    let mut header = [0u8; 16];
+    if std::fs::File::open(path).and_then(|mut file| file.read_exact(&mut header)).is_ok() && &header == PLAIN_HEADER {
+        return Ok(false);
+    }
+
+    if length % CIPHER_PAGE_SIZE != 0 {
+        return Err(Error::Damaged(format!("{} is not a database", path.display())));
+    }
+
+    let mut connection = SqliteConnection::establish(&path.to_string_lossy())?;
+    if !is_supported(&mut connection) {
+        return Err(Error::EncryptionUnavailable);
+    }
+
+    // without a key SQLCipher reads the file as plain SQLite, which an encrypted file is not
+    Ok(diesel::sql_query("SELECT count(*) FROM sqlite_master").execute(&mut connection).is_err())
+}
+
+/// Whether this build links SQLCipher; a plain SQLite build silently ignores `PRAGMA key`.
+pub fn is_supported(connection: &mut SqliteConnection) -> bool {
+    diesel::sql_query("PRAGMA cipher_version")
+        .load::<CipherVersion>(connection)
+        .map(|versions| versions.iter().any(|version| !version.cipher_version.is_empty()))
+        .unwrap_or(false)
+}
+
+/// Hands the passphrase to SQLCipher, which derives the page key from it. Has to be the
+/// first statement run on a freshly opened connection.
+pub fn apply_key(connection: &mut SqliteConnection, passphrase: &str) -> diesel::QueryResult<()> {
+    diesel::sql_query(format!("PRAGMA key = {}", quote(passphrase))).execute(connection)?;
+    Ok(())
+}
+
+/// Opens an encrypted database, failing with [`Error::AuthError`] when the passphrase is wrong.
+/// Pending migrations are left to the caller, which saves a copy before applying them.
+pub fn establish_encrypted_connection(database_url: &str, passphrase: &str) -> SerializedResult<SqliteConnection> {
+    let mut connection = SqliteConnection::establish(database_url)?;
+
+    if !is_supported(&mut connection) {
+        return Err(Error::EncryptionUnavailable);
+    }
+
+    apply_key(&mut connection, passphrase)?;
+
+    // the key is only checked once a page is read
+    diesel::sql_query("SELECT count(*) FROM sqlite_master").execute(&mut connection).map_err(|_| Error::AuthError)?;
+    diesel::sql_query("PRAGMA foreign_keys = ON").execute(&mut connection)?;
+
+    Ok(connection)
+}
+
+fn pending_path(path: &Path) -> PathBuf {
+    path.with_extension("encrypting")
+}
+
+/// Writes an encrypted copy of the database behind `connection` next to `path` and returns
+/// where it was written. [`finish_pending`] moves it over `path` on the next start.
+pub fn export_encrypted(connection: &mut SqliteConnection, path: &Path, passphrase: &str) -> SerializedResult<PathBuf> {
+    if !is_supported(connection) {
+        return Err(Error::EncryptionUnavailable);
+    }
+
+    let target = pending_path(path);
+    if target.exists() {
+        std::fs::remove_file(&target)?;
+    }
+
+    let target_url = target.to_string_lossy();
+
+    diesel::sql_query(format!("ATTACH DATABASE {} AS encrypted KEY {}", quote(&target_url), quote(passphrase))).execute(connection)?;
+    let exported = diesel::sql_query("SELECT sqlcipher_export('encrypted')").execute(connection);
+    diesel::sql_query("DETACH DATABASE encrypted").execute(connection)?;
+    exported?;
+
+    Ok(target)
+}
+
+/// Replaces the database at `path` with an encrypted copy left by [`export_encrypted`].
+/// Must run before any connection to `path` is opened.
+pub fn finish_pending(path: &Path) -> SerializedResult<()> {
+    let pending = pending_path(path);
+    if !pending.exists() {
+        return Ok(());
+    }
+
+    // the copy already holds everything from the old write-ahead log, which must not be
+    // replayed on top of the new file
+    crate::database::remove_journal_files(path)?;
+
+    std::fs::rename(pending, path)?;
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use crate::models::database::Borrow;
+use crate::SerializedResult;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    /// Borrows pointing at a client or a book which no longer exists.
+    pub orphaned_borrows: Vec<Borrow>,
+    pub removed: usize,
+}
+
+/// Borrows left behind by deletes made while foreign keys were not enforced.
+pub fn find_orphaned_borrows(connection: &mut SqliteConnection) -> SerializedResult<Vec<Borrow>> {
+    use crate::schema::{books, borrows, clients};
+
+    let orphans = borrows::table
+        .filter(borrows::clientID.ne_all(clients::table.select(clients::id))
+            .or(borrows::bookISBN.ne_all(books::table.select(books::isbn))))
+        .select(Borrow::as_select())
+        .load(connection)?;
+
+    Ok(orphans)
+}
+
+/// Reports orphaned borrows and, unless `dry_run` is set, deletes them in one transaction.
+pub fn repair(connection: &mut SqliteConnection, dry_run: bool) -> SerializedResult<IntegrityReport> {
+    use crate::schema::borrows;
+
+    connection.transaction::<_, crate::Error, _>(|connection| {
+        let orphaned_borrows = find_orphaned_borrows(connection)?;
+
+        let removed = if dry_run {
+            0
+        } else {
+            let ids = orphaned_borrows.iter().map(|borrow| borrow.id).collect::<Vec<i32>>();
+            diesel::delete(borrows::table.filter(borrows::id.eq_any(ids))).execute(connection)?
+        };
+
+        Ok(IntegrityReport { orphaned_borrows, removed })
+    })
+}
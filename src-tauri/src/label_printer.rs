@@ -0,0 +1,360 @@
+//! Labels printed straight on thermal label printers in their own language, ZPL or EPL,
+//! sent to the raw TCP port of the printer or to its device file. The printer draws the
+//! barcodes itself, so they come out sharper than any image at its resolution would.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::barcode::{self, LabelSymbologies, Symbology};
+use crate::validation::ValidationError;
+use crate::{Error, SerializedResult};
+
+/// Port label printers take raw print jobs on.
+pub const RAW_PORT: u16 = 9100;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PrinterLanguage {
+    /// Zebra Programming Language, spoken by Zebra printers and most compatible ones.
+    #[default]
+    Zpl,
+    /// Eltron Programming Language, of older Zebra desktop printers such as the LP 2844.
+    Epl,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LabelPrinter {
+    pub language: PrinterLanguage,
+    /// `host` or `host:port` of a network printer, port 9100 when left out, or the path of
+    /// its device file such as `/dev/usb/lp0`; empty when there is no label printer.
+    pub address: String,
+    /// Dots per inch of the print head, 203 or 300 on most printers.
+    pub dpi: u32,
+    pub label_width: f32,
+    pub label_height: f32,
+}
+
+impl Default for LabelPrinter {
+    fn default() -> Self {
+        Self {
+            language: PrinterLanguage::Zpl,
+            address: "".to_string(),
+            dpi: 203,
+            label_width: 50.0,
+            label_height: 25.0,
+        }
+    }
+}
+
+/// Where print jobs are sent.
+#[derive(Debug, PartialEq)]
+pub enum PrinterTarget {
+    Network(String),
+    Device(String),
+}
+
+impl PrinterTarget {
+    /// Paths of device files start with `/`, `.` or `\`, anything else is a host.
+    pub fn parse(address: &str) -> SerializedResult<Self> {
+        let address = address.trim();
+        if address.is_empty() {
+            return Err(invalid("labelPrinter", "No label printer is set".to_string()));
+        }
+
+        if address.starts_with(['/', '.', '\\']) {
+            Ok(PrinterTarget::Device(address.to_string()))
+        } else if address.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) {
+            Ok(PrinterTarget::Network(address.to_string()))
+        } else {
+            Ok(PrinterTarget::Network(format!("{}:{}", address, RAW_PORT)))
+        }
+    }
+
+    pub fn send(&self, job: &[u8]) -> SerializedResult<()> {
+        match self {
+            PrinterTarget::Network(address) => {
+                let address = address.to_socket_addrs()?.next()
+                    .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} has no address", address)))?;
+                let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                stream.write_all(job)?;
+                stream.flush()?;
+                stream.shutdown(std::net::Shutdown::Write)?;
+            }
+            PrinterTarget::Device(path) => {
+                let mut device = OpenOptions::new().write(true).open(path)?;
+                device.write_all(job)?;
+                device.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a label is printed for.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum PrinterLabel {
+    /// The barcode of an ISBN-13.
+    Isbn { isbn: String },
+    /// The barcode of one copy of a book, with its title.
+    Copy { isbn: String, title: String, copy: u32 },
+    /// A badge of a client, printed on a label.
+    #[serde(rename_all = "camelCase")]
+    Badge {
+        client_id: String,
+        client_name: String,
+        issued: NaiveDate,
+        /// The signed payload QR codes and Data Matrix symbols carry, filled in by the
+        /// desk before printing and never taken from the frontend.
+        #[serde(skip_deserializing)]
+        signed_id: Option<String>,
+    },
+}
+
+/// Sizes of a label in dots, as the printer counts them.
+struct Layout {
+    width: u32,
+    height: u32,
+    margin: u32,
+    /// Width of a narrow bar.
+    module: u32,
+}
+
+impl Layout {
+    fn of(printer: &LabelPrinter) -> Self {
+        let dots = |mm: f32| (mm * printer.dpi as f32 / 25.4).round() as u32;
+        Self {
+            width: dots(printer.label_width),
+            height: dots(printer.label_height),
+            margin: dots(2.0),
+            module: (printer.dpi / 100).max(1),
+        }
+    }
+
+    fn inner_width(&self) -> u32 {
+        self.width.saturating_sub(2 * self.margin)
+    }
+
+    fn inner_height(&self) -> u32 {
+        self.height.saturating_sub(2 * self.margin)
+    }
+
+    /// Height left for a barcode under `used` dots of text, keeping room for the digits under it.
+    fn barcode_height(&self, used: u32, digits: u32) -> u32 {
+        self.height.saturating_sub(2 * self.margin + used + digits).max(self.module * 10)
+    }
+}
+
+fn invalid(field: &str, message: String) -> Error {
+    Error::Validation(vec![ValidationError { field: field.to_string(), message }])
+}
+
+/// The ISBN-13 of `isbn`, which is what ISBN labels carry.
+fn isbn13(isbn: &str) -> SerializedResult<String> {
+    crate::validation::to_isbn13(isbn).ok_or_else(|| invalid("isbn", format!("'{}' is not a valid ISBN", isbn)))
+}
+
+/// Dots per module and side in dots of a QR code or Data Matrix symbol of `data` at most
+/// `side` dots wide.
+fn matrix_size(symbology: Symbology, data: &str, side: u32) -> SerializedResult<(u32, u32)> {
+    let (modules, largest) = match symbology {
+        Symbology::Qr => (crate::matrix_codes::qr_code(data.as_bytes())?.width as u32, 10),
+        _ => (crate::matrix_codes::data_matrix(data.as_bytes())?.width as u32, u32::MAX),
+    };
+    let module = (side / modules).clamp(1, largest);
+    Ok((module, module * modules))
+}
+
+/// Text of a ZPL field; `^`, `~` and `_` are written as hex since they start commands.
+fn zpl_text(text: &str) -> String {
+    let escaped = text.replace('_', "_5F").replace('^', "_5E").replace('~', "_7E");
+    format!("^FH^FD{}^FS", escaped)
+}
+
+/// A barcode at `x`, `y` with bars `height` dots high and the text under them, or a QR code
+/// or Data Matrix symbol `height` dots wide at most, which the printer draws itself.
+fn zpl_barcode(symbology: Symbology, data: &str, x: u32, y: u32, module: u32, height: u32) -> SerializedResult<String> {
+    if !symbology.is_2d() {
+        // refuses what the symbology cannot carry, which printers would skip without a word
+        barcode::encode_bars(symbology, data)?;
+    }
+    let origin = format!("^FO{},{}^BY{}", x, y, module);
+    Ok(match symbology {
+        // the printer works out the check digit itself
+        Symbology::Ean13 => format!("{}^BEN,{},Y,N^FD{}^FS\n", origin, height, &data[..12]),
+        Symbology::Code128 => format!("{}^BCN,{},Y,N,N{}\n", origin, height, zpl_text(data)),
+        Symbology::Code39 => format!("{}^B3N,N,{},Y,N{}\n", origin, height, zpl_text(data)),
+        Symbology::Itf => format!("{}^B2N,{},Y,N,N^FD{}^FS\n", origin, height, barcode::itf_digits(data)),
+        Symbology::Qr => {
+            let (magnification, _) = matrix_size(symbology, data, height)?;
+            format!("^FO{},{}^BQN,2,{}{}\n", x, y, magnification, zpl_text(&format!("MA,{}", data)))
+        }
+        Symbology::DataMatrix => {
+            let (module, _) = matrix_size(symbology, data, height)?;
+            format!("^FO{},{}^BXN,{},200{}\n", x, y, module, zpl_text(data))
+        }
+    })
+}
+
+fn zpl(label: &PrinterLabel, layout: &Layout, symbologies: &LabelSymbologies, library_name: &str) -> SerializedResult<String> {
+    let Layout { width, height, margin, module } = *layout;
+    let font = (height / 8).max(20);
+    let digits = font;
+    let mut job = format!("^XA^CI28^PW{}^LL{}\n", width, height);
+    let text = |x: u32, y: u32, size: u32, width: u32, align: char, value: &str| {
+        format!("^FO{},{}^A0N,{},{}^FB{},1,0,{}{}\n", x, y, size, size, width, align, zpl_text(value))
+    };
+
+    match label {
+        PrinterLabel::Isbn { isbn } => {
+            let isbn = isbn13(isbn)?;
+            let symbology = symbologies.isbn;
+            if symbology.is_2d() {
+                let side = layout.inner_height().saturating_sub(digits + margin);
+                let (_, drawn) = matrix_size(symbology, &isbn, side)?;
+                job += &zpl_barcode(symbology, &isbn, width.saturating_sub(drawn) / 2, margin, module, side)?;
+                job += &text(margin, margin + drawn + margin, font, layout.inner_width(), 'C', &isbn);
+            } else {
+                let bars = barcode::encode_bars(symbology, &isbn)?.len() as u32;
+                let x = width.saturating_sub(bars * module) / 2;
+                job += &zpl_barcode(symbology, &isbn, x, margin, module, layout.barcode_height(0, digits))?;
+            }
+        }
+        PrinterLabel::Copy { isbn, title, copy } => {
+            let code = barcode::copy_code(isbn, *copy);
+            let symbology = symbologies.copy;
+            if symbology.is_2d() {
+                // the symbol on the left, the title and the code next to it
+                let (_, side) = matrix_size(symbology, &code, layout.inner_height())?;
+                let left = margin + side + margin;
+                let room = width.saturating_sub(left + margin);
+                job += &zpl_barcode(symbology, &code, margin, margin, module, layout.inner_height())?;
+                job += &format!("^FO{},{}^A0N,{},{}^FB{},3,0,L{}\n", left, margin, font, font, room, zpl_text(title));
+                job += &text(left, height.saturating_sub(margin + font), font * 3 / 4, room, 'L', &code);
+            } else {
+                job += &text(margin, margin, font, layout.inner_width(), 'L', title);
+                job += &zpl_barcode(symbology, &code, margin, margin + font + margin, module, layout.barcode_height(font + margin, digits))?;
+            }
+        }
+        PrinterLabel::Badge { client_id, client_name, issued, signed_id } => {
+            let symbology = symbologies.badge;
+            let (block, symbol) = if symbology.is_2d() {
+                let code = signed_id.as_deref().unwrap_or(client_id);
+                let (_, side) = matrix_size(symbology, code, layout.inner_height())?;
+                let symbol = zpl_barcode(symbology, code, width.saturating_sub(margin + side), margin, module, layout.inner_height())?;
+                (layout.inner_width().saturating_sub(side + margin), Some(symbol))
+            } else {
+                (layout.inner_width(), None)
+            };
+
+            job += &text(margin, margin, font + font / 2, block, 'C', &format!("Biblioteca {}", library_name));
+            job += &text(margin, margin + 2 * font, font, block, 'C', client_name);
+            job += &text(margin, margin + 3 * font + font / 2, font * 3 / 4, block, 'C', &format!("Emis pe {}", issued.format("%d.%m.%Y")));
+            match symbol {
+                Some(symbol) => job += &symbol,
+                None => {
+                    let used = 5 * font;
+                    job += &zpl_barcode(symbology, client_id, margin, margin + used, module, layout.barcode_height(used, digits))?;
+                }
+            }
+        }
+    }
+
+    job += "^XZ\n";
+    Ok(job)
+}
+
+/// EPL only prints single byte code pages, so Romanian letters lose their diacritics.
+fn epl_text(text: &str) -> String {
+    let folded: String = text.chars().map(|c| match c {
+        'ă' | 'â' => 'a',
+        'Ă' | 'Â' => 'A',
+        'î' => 'i',
+        'Î' => 'I',
+        'ș' | 'ş' => 's',
+        'Ș' | 'Ş' => 'S',
+        'ț' | 'ţ' => 't',
+        'Ț' | 'Ţ' => 'T',
+        c if c.is_ascii() => c,
+        _ => '?',
+    }).collect();
+    format!("\"{}\"", folded.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A barcode in EPL, which has no two dimensional symbologies.
+fn epl_barcode(symbology: Symbology, data: &str, x: u32, y: u32, module: u32, height: u32) -> SerializedResult<String> {
+    if !symbology.is_2d() {
+        barcode::encode_bars(symbology, data)?;
+    }
+    let (kind, payload) = match symbology {
+        Symbology::Ean13 => ("E30", data[..12].to_string()),
+        Symbology::Code128 => ("1", data.to_string()),
+        Symbology::Code39 => ("3", data.to_string()),
+        Symbology::Itf => ("2", barcode::itf_digits(data)),
+        Symbology::Qr | Symbology::DataMatrix => {
+            return Err(invalid("symbologies", "EPL printers cannot print QR codes or Data Matrix symbols".to_string()));
+        }
+    };
+    // wide bars of Code 39 and ITF are two and a half narrow ones
+    Ok(format!("B{},{},0,{},{},{},{},B,{}\n", x, y, kind, module, (module * 5 / 2).max(2), height, epl_text(&payload)))
+}
+
+fn epl(label: &PrinterLabel, layout: &Layout, symbologies: &LabelSymbologies, library_name: &str) -> SerializedResult<String> {
+    let Layout { width, height, margin, module } = *layout;
+    // the fonts are bigger on 300 dpi printers, font 4 is 24 dots high at 203 dpi and 44 at 300
+    let line = if module >= 3 { 48 } else { 28 };
+    let digits = line;
+    let mut job = format!("\nN\nq{}\nQ{},24\n", width, height);
+
+    match label {
+        PrinterLabel::Isbn { isbn } => {
+            let isbn = isbn13(isbn)?;
+            let bars = barcode::encode_bars(symbologies.isbn, &isbn).map(|bars| bars.len() as u32).unwrap_or(0);
+            let x = width.saturating_sub(bars * module) / 2;
+            job += &epl_barcode(symbologies.isbn, &isbn, x, margin, module, layout.barcode_height(0, digits))?;
+        }
+        PrinterLabel::Copy { isbn, title, copy } => {
+            job += &format!("A{},{},0,3,1,1,N,{}\n", margin, margin, epl_text(title));
+            job += &epl_barcode(symbologies.copy, &barcode::copy_code(isbn, *copy), margin, margin + line, module, layout.barcode_height(line, digits))?;
+        }
+        PrinterLabel::Badge { client_id, client_name, issued, .. } => {
+            job += &format!("A{},{},0,4,1,1,N,{}\n", margin, margin, epl_text(&format!("Biblioteca {}", library_name)));
+            job += &format!("A{},{},0,3,1,1,N,{}\n", margin, margin + line + line / 2, epl_text(client_name));
+            job += &format!("A{},{},0,2,1,1,N,{}\n", margin, margin + 2 * line + line / 2, epl_text(&format!("Emis pe {}", issued.format("%d.%m.%Y"))));
+            let used = 4 * line;
+            job += &epl_barcode(symbologies.badge, client_id, margin, margin + used, module, layout.barcode_height(used, digits))?;
+        }
+    }
+
+    job += "P1\n";
+    Ok(job)
+}
+
+/// The print job of `labels`, one after the other, in the language of `printer` and the
+/// symbology set for each kind of label.
+pub fn render(printer: &LabelPrinter, symbologies: &LabelSymbologies, labels: &[PrinterLabel], library_name: &str) -> SerializedResult<String> {
+    let layout = Layout::of(printer);
+    let mut job = String::new();
+    for label in labels {
+        job += &match printer.language {
+            PrinterLanguage::Zpl => zpl(label, &layout, symbologies, library_name)?,
+            PrinterLanguage::Epl => epl(label, &layout, symbologies, library_name)?,
+        };
+    }
+    Ok(job)
+}
+
+/// Prints `labels` on the label printer set in the settings.
+pub fn print(printer: &LabelPrinter, symbologies: &LabelSymbologies, labels: &[PrinterLabel], library_name: &str) -> SerializedResult<()> {
+    let target = PrinterTarget::parse(&printer.address)?;
+    let job = render(printer, symbologies, labels, library_name)?;
+    target.send(job.as_bytes())
+}
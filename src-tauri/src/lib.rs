@@ -1,5 +1,27 @@
 pub mod schema;
 pub mod models;
+pub mod csv_io;
+pub mod marc;
+pub mod backup;
+pub mod encryption;
+pub mod integrity;
+pub mod branches;
+pub mod server;
+pub mod replication;
+pub mod services;
+pub mod validation;
+pub mod client_ids;
+pub mod photos;
+pub mod badges;
+pub mod pdf;
+pub mod sheets;
+pub mod label_printer;
+pub mod matrix_codes;
+pub mod signing;
+pub mod drawing;
+pub mod downloads;
+pub mod decoding;
+pub mod scans;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -8,49 +30,259 @@ pub enum Error {
     #[error(transparent)]
     Diesel(#[from] diesel::result::Error),
     #[error(transparent)]
+    Connection(#[from] diesel::ConnectionError),
+    #[error(transparent)]
+    Pool(#[from] diesel::r2d2::PoolError),
+    #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
     #[error(transparent)]
     Request(#[from] reqwest::Error),
     #[error(transparent)]
     Image(#[from] image::ImageError),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Xml(#[from] quick_xml::Error),
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
     #[error("Authentication error")]
     AuthError,
+    #[error("Only administrators may run {0}")]
+    Forbidden(String),
+    #[error("Invalid MARC record: {0}")]
+    Marc(String),
+    #[error("Incompatible database: {0}")]
+    IncompatibleSchema(String),
+    #[error("Migration error: {0}")]
+    Migration(String),
+    #[error("This build of LibraManager does not support database encryption")]
+    EncryptionUnavailable,
+    #[error("Damaged database: {0}")]
+    Damaged(String),
+    #[error("Client {0} still has books on loan")]
+    ActiveLoans(String),
+    #[error("{0} is archived")]
+    Archived(String),
+    #[error("Only {available} copies of {isbn} are available at this branch")]
+    InsufficientStock { isbn: String, available: i64 },
+    #[error("Unknown command {0}")]
+    UnknownCommand(String),
+    #[error("{0}")]
+    Remote(String),
+    #[error("Sync error: {0}")]
+    Replication(String),
+    #[error("Invalid {}", .0.iter().map(|error| format!("{}: {}", error.field, error.message)).collect::<Vec<_>>().join(", "))]
+    Validation(Vec<validation::ValidationError>),
+    #[error("Client ID {0} has an invalid check digit")]
+    InvalidClientId(String),
+    #[error("No book, copy or client has the code {0}")]
+    UnknownCode(String),
 }
 
 impl serde::Serialize for Error {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: serde::ser::Serializer, {
-        serializer.serialize_str(self.to_string().as_ref())
+        match self {
+            // the forms show these next to the fields they are about
+            Error::Validation(errors) => errors.serialize(serializer),
+            _ => serializer.serialize_str(self.to_string().as_ref())
+        }
     }
 }
 
 pub type SerializedResult<T> = Result<T, Error>;
 
 pub mod database {
+    use std::path::{Path, PathBuf};
     use std::sync::Mutex;
+    use std::time::Duration;
 
     use diesel::{Connection, SqliteConnection};
+    use diesel::connection::SimpleConnection;
+    use diesel::migration::MigrationSource;
+    use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool, PooledConnection};
+    use diesel::sqlite::Sqlite;
     use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 
+    use crate::SerializedResult;
+
     const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
-    pub fn establish_connection(database_url: &str) -> SqliteConnection {
-        let mut connection = SqliteConnection::establish(database_url)
-            .unwrap_or_else(|_| panic!("Error connecting to {}", database_url));
+    const POOL_SIZE: u32 = 8;
+    const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Applied to every pooled connection. WAL lets readers carry on while a command writes,
+    /// and the busy timeout makes a writer wait for the lock instead of failing right away.
+    const CONNECTION_PRAGMAS: &str = "PRAGMA journal_mode = WAL; PRAGMA busy_timeout = 5000; PRAGMA foreign_keys = ON;";
+    const READ_ONLY_PRAGMAS: &str = "PRAGMA query_only = ON; PRAGMA busy_timeout = 5000; PRAGMA foreign_keys = ON;";
 
-        connection.run_pending_migrations(MIGRATIONS).unwrap();
+    pub type PooledSqliteConnection = PooledConnection<ConnectionManager<SqliteConnection>>;
 
-        return connection;
+    /// Versions of every migration compiled into this build.
+    pub fn embedded_versions() -> Vec<String> {
+        MigrationSource::<Sqlite>::migrations(&MIGRATIONS)
+            .map(|migrations| migrations.iter().map(|migration| migration.name().version().to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Versions of the migrations already applied to the database behind `connection`.
+    pub fn applied_versions(connection: &mut SqliteConnection) -> SerializedResult<Vec<String>> {
+        let applied = connection.applied_migrations().map_err(|e| crate::Error::Migration(e.to_string()))?;
+        Ok(applied.iter().map(|version| version.to_string()).collect())
+    }
+
+    pub fn run_migrations(connection: &mut SqliteConnection) -> SerializedResult<()> {
+        connection.run_pending_migrations(MIGRATIONS).map_err(|e| crate::Error::Migration(e.to_string()))?;
+        Ok(())
+    }
+
+    pub fn establish_connection(database_url: &str) -> SerializedResult<SqliteConnection> {
+        let mut connection = SqliteConnection::establish(database_url)?;
+
+        connection.batch_execute("PRAGMA foreign_keys = ON;")?;
+
+        run_migrations(&mut connection)?;
+
+        Ok(connection)
+    }
+
+    /// Deletes the write-ahead log files of the database at `path`. Only safe while no
+    /// connection has it open and the log holds nothing that is still needed.
+    pub fn remove_journal_files(path: &Path) -> SerializedResult<()> {
+        for suffix in ["-wal", "-shm"] {
+            let mut journal = path.as_os_str().to_owned();
+            journal.push(suffix);
+            let journal = PathBuf::from(journal);
+            if journal.exists() {
+                std::fs::remove_file(journal)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Why the database could not be opened at startup, shown by the recovery window.
+    #[derive(Default)]
+    pub struct StartupFailure(Mutex<Option<String>>);
+
+    impl StartupFailure {
+        pub fn get(&self) -> Option<String> {
+            self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+        }
+
+        pub fn set(&self, error: Option<String>) {
+            *self.0.lock().unwrap_or_else(|e| e.into_inner()) = error;
+        }
+    }
+
+    #[derive(Debug)]
+    struct ConnectionOptions {
+        key: Option<String>,
+        read_only: bool,
+    }
+
+    impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for ConnectionOptions {
+        fn on_acquire(&self, connection: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+            if let Some(key) = &self.key {
+                crate::encryption::apply_key(connection, key).map_err(diesel::r2d2::Error::QueryError)?;
+            }
+            let pragmas = if self.read_only { READ_ONLY_PRAGMAS } else { CONNECTION_PRAGMAS };
+            connection.batch_execute(pragmas).map_err(diesel::r2d2::Error::QueryError)
+        }
     }
 
     pub struct DatabaseConnection {
-        pub client: Mutex<SqliteConnection>,
+        pub pool: Pool<ConnectionManager<SqliteConnection>>,
+        pub path: PathBuf,
+        /// Passphrase of an encrypted database, needed again to open copies of it.
+        pub key: Option<String>,
+        pub read_only: bool,
     }
 
     impl DatabaseConnection {
-        pub fn from(url: &str) -> DatabaseConnection {
-            DatabaseConnection {
-                client: Mutex::from(establish_connection(url))
+        pub fn from(url: &str) -> SerializedResult<DatabaseConnection> {
+            let database = DatabaseConnection::open(url, None, false)?;
+            database.migrate()?;
+            Ok(database)
+        }
+
+        /// Opens the database without migrating it and rejects every write, so a database
+        /// the current build cannot migrate can still be looked at.
+        pub fn read_only(url: &str) -> SerializedResult<DatabaseConnection> {
+            DatabaseConnection::open(url, None, true)
+        }
+
+        pub fn encrypted(url: &str, passphrase: &str) -> SerializedResult<DatabaseConnection> {
+            let database = DatabaseConnection::open(url, Some(passphrase.to_string()), false)?;
+            database.migrate()?;
+            Ok(database)
+        }
+
+        /// Opens the database, encrypted with `key` if given, without migrating it, for tools
+        /// which must not change the schema unasked. Unless `read_only`, a database with
+        /// pending migrations is refused, since writes would not fit its schema.
+        pub fn unmigrated(url: &str, key: Option<&str>, read_only: bool) -> SerializedResult<DatabaseConnection> {
+            let database = DatabaseConnection::open(url, key.map(str::to_string), read_only)?;
+
+            if !read_only {
+                let pending = database.get()?.pending_migrations(MIGRATIONS).map_err(|e| crate::Error::Migration(e.to_string()))?;
+                if !pending.is_empty() {
+                    return Err(crate::Error::Migration(format!("{} migrations have to be applied first", pending.len())));
+                }
+            }
+
+            Ok(database)
+        }
+
+        fn open(url: &str, key: Option<String>, read_only: bool) -> SerializedResult<DatabaseConnection> {
+            if let Some(key) = &key {
+                // checks the passphrase once, so a wrong one fails fast instead of timing out the pool
+                crate::encryption::establish_encrypted_connection(url, key)?;
+            }
+
+            // connections are opened as they are needed, each one of an encrypted database
+            // derives its key again
+            let pool = Pool::builder()
+                .max_size(POOL_SIZE)
+                .min_idle(Some(1))
+                .connection_timeout(CONNECTION_TIMEOUT)
+                .connection_customizer(Box::new(ConnectionOptions { key: key.clone(), read_only }))
+                .build(ConnectionManager::<SqliteConnection>::new(url))?;
+
+            Ok(DatabaseConnection { pool, path: PathBuf::from(url), key, read_only })
+        }
+
+        /// Applies pending migrations, after saving a copy of the database next to it.
+        fn migrate(&self) -> SerializedResult<()> {
+            let connection = &mut *self.get()?;
+            let migration_error = |e: Box<dyn std::error::Error + Send + Sync>| crate::Error::Migration(e.to_string());
+
+            let pending = connection.pending_migrations(MIGRATIONS).map_err(migration_error)?;
+            let applied = connection.applied_migrations().map_err(migration_error)?;
+
+            // a fresh database has nothing worth saving
+            if !pending.is_empty() && !applied.is_empty() {
+                crate::backup::pre_migration_backup(&self.path, self.key.as_deref())?;
             }
+
+            run_migrations(connection)
+        }
+
+        pub fn get(&self) -> SerializedResult<PooledSqliteConnection> {
+            Ok(self.pool.get()?)
+        }
+
+        /// Replaces the database with the backup at `source`. Every pooled connection is taken
+        /// first, waiting for commands and server requests still using one, so nothing else
+        /// reads or writes while the pages are copied over.
+        pub fn restore(&self, source: &Path) -> SerializedResult<()> {
+            let mut connections = (0..self.pool.max_size()).map(|_| self.get()).collect::<SerializedResult<Vec<_>>>()?;
+            crate::backup::restore_database(&mut connections[0], &self.path, source, self.key.as_deref())
+        }
+
+        /// Writes an encrypted copy of the database which replaces the plain file the next
+        /// time the app starts, once no connection has it open.
+        pub fn encrypt(&self, passphrase: &str) -> SerializedResult<()> {
+            crate::encryption::export_encrypted(&mut *self.get()?, &self.path, passphrase)?;
+            Ok(())
         }
     }
 }
@@ -62,15 +294,69 @@ pub mod settings {
     use serde::{Deserialize, Serialize};
 
     #[derive(Serialize, Deserialize)]
-    #[serde(rename_all = "camelCase")]
+    #[serde(rename_all = "camelCase", default)]
     pub struct Settings {
         pub library_name: String,
         pub camera_device_id: String,
+        /// Folder for automatic backups, empty for the `backups` folder in the app data directory.
+        pub backup_directory: String,
+        /// Hours between automatic backups, 0 turns them off.
+        pub backup_interval_hours: u32,
+        /// How many automatic backups are kept before the oldest ones are deleted.
+        pub backup_retention: u32,
+        /// Database file chosen in the recovery window, empty for the one in the app data directory.
+        pub database_path: String,
+        /// Port the database is shared on with other desks, 0 keeps it private.
+        pub server_port: u16,
+        /// Address of the network interface the database is shared on, such as `192.168.1.10`;
+        /// `0.0.0.0` listens on all of them. Traffic is plain HTTP.
+        pub server_address: String,
+        /// Address of the desk sharing its database, such as `http://192.168.1.10:7878`;
+        /// when set this instance works as a thin client of it.
+        pub server_url: String,
+        /// Regular expression new client IDs have to match in full.
+        pub client_id_pattern: String,
+        /// How IDs are generated for new clients.
+        pub client_id_format: crate::client_ids::ClientIdFormat,
+        /// Label sheets ISBN labels, spine labels and badges are printed on.
+        pub isbn_sheet: crate::sheets::SheetLayout,
+        pub spine_sheet: crate::sheets::SheetLayout,
+        pub badge_sheet: crate::sheets::SheetLayout,
+        /// Thermal printer labels can be printed on directly.
+        pub label_printer: crate::label_printer::LabelPrinter,
+        /// Barcode symbology of each kind of label.
+        pub symbologies: crate::barcode::LabelSymbologies,
+        /// Folder labels, badges and sheets are saved in, empty for Documents.
+        pub download_directory: String,
+        /// Name badges are saved under, from `{id}`, `{firstName}`, `{lastName}` and `{date}`.
+        pub badge_file_name: String,
+        /// Name ISBN labels are saved under, from `{isbn}`.
+        pub isbn_file_name: String,
     }
 
     impl Default for Settings {
         fn default() -> Self {
-            Self { library_name: "Librarie".to_string(), camera_device_id: "".to_string() }
+            Self {
+                library_name: "Librarie".to_string(),
+                camera_device_id: "".to_string(),
+                backup_directory: "".to_string(),
+                backup_interval_hours: 24,
+                backup_retention: 7,
+                database_path: "".to_string(),
+                server_port: 0,
+                server_address: "127.0.0.1".to_string(),
+                server_url: "".to_string(),
+                client_id_pattern: crate::validation::DEFAULT_CLIENT_ID_PATTERN.to_string(),
+                client_id_format: Default::default(),
+                isbn_sheet: crate::sheets::SheetLayout::avery_l7160(),
+                spine_sheet: crate::sheets::SheetLayout::avery_l7651(),
+                badge_sheet: crate::sheets::SheetLayout::cr80_a4(),
+                label_printer: Default::default(),
+                symbologies: Default::default(),
+                download_directory: "".to_string(),
+                badge_file_name: "Legitimatie {firstName} {lastName}".to_string(),
+                isbn_file_name: "{isbn}".to_string(),
+            }
         }
     }
 
@@ -94,7 +380,7 @@ pub mod settings {
                     let mut contents: String = String::new();
                     config.read_to_string(&mut contents)?;
 
-                    Ok(toml::from_str(&*contents).unwrap())
+                    toml::from_str(&contents).map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
                 }
                 Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
                     self.store(Settings::default())?;
@@ -114,107 +400,296 @@ pub mod settings {
 }
 
 pub mod barcode {
-    use barcoders::generators::image::{Image, Color, Rotation};
+    use image::{Rgba, RgbaImage};
+    use ab_glyph::{FontArc, PxScale};
     use barcoders::sym::code128::Code128;
-    use image::{Rgba, RgbaImage, GenericImage, ImageBuffer};
-    use ab_glyph::{FontRef, PxScale};
+    use barcoders::sym::code39::Code39;
     use barcoders::sym::ean13::EAN13;
-    use imageproc::drawing::{draw_text_mut, draw_filled_rect_mut, text_size};
-    use imageproc::rect::Rect;
-    use chrono::NaiveDate;
+    use barcoders::sym::tf::TF;
+    use imageproc::drawing::text_size;
+    use serde::{Deserialize, Serialize};
+
+    use crate::drawing::Drawing;
+    use crate::matrix_codes::Matrix;
+    use crate::validation::ValidationError;
+    use crate::{Error, SerializedResult};
 
     const BLACK: Rgba<u8> = Rgba::<u8>([0, 0, 0, 255]);
     const WHITE: Rgba<u8> = Rgba::<u8>([255, 255, 255, 255]);
-    const BADGE_WIDTH: u32 = 450u32;
-    const BADGE_HEIGHT: u32 = 250u32;
-    const BADGE_PADDING: u32 = 40u32;
-
     const ISBN_WIDTH: u32 = 220u32;
     const ISBN_HEIGHT: u32 = 100u32;
     const ISBN_PADDING: u32 = 1u32;
 
-    const TITLE_SCALE: PxScale = PxScale {
-        x: 24.0,
-        y: 24.0,
+    const ISBN_SCALE: PxScale = PxScale {
+        x: 25.0,
+        y: 25.0,
+    };
+
+    const SPINE_WIDTH: u32 = 220u32;
+    const SPINE_HEIGHT: u32 = 120u32;
+    const SPINE_PADDING: u32 = 8u32;
+    /// Where bars start on spine labels, under the title.
+    const SPINE_BARS_TOP: u32 = 86u32;
+
+    const SPINE_CODE_SCALE: PxScale = PxScale {
+        x: 44.0,
+        y: 44.0,
     };
-    const HEADING_SCALE: PxScale = PxScale {
+    const SPINE_TEXT_SCALE: PxScale = PxScale {
         x: 18.0,
         y: 18.0,
     };
-    const BODY_SCALE: PxScale = PxScale {
-        x: 16.0,
-        y: 16.0,
-    };
 
-    const ISBN_SCALE: PxScale = PxScale {
-        x: 25.0,
-        y: 25.0,
-    };
+    #[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Symbology {
+        /// Only for ISBNs, which are EAN-13 numbers.
+        Ean13,
+        #[default]
+        Code128,
+        /// Upper case letters, digits and `-. $/+%`, read by the oldest scanners.
+        Code39,
+        /// Interleaved 2 of 5, digits only.
+        Itf,
+        Qr,
+        DataMatrix,
+    }
 
-    macro_rules! create_buffer {
-        ($height:expr) => {
-            Image::ImageBuffer {
-                height: $height,
-                xdim: 2,
-                rotation: Rotation::Zero,
-                foreground: Color::new([0, 0, 0, 255]),
-                background: Color::new([255, 255, 255, 255]),
+    impl Symbology {
+        /// Whether this is a two dimensional symbol, drawn as a square instead of bars.
+        pub fn is_2d(self) -> bool {
+            matches!(self, Symbology::Qr | Symbology::DataMatrix)
+        }
+    }
+
+    /// The symbology of each kind of label.
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[serde(rename_all = "camelCase", default)]
+    pub struct LabelSymbologies {
+        pub isbn: Symbology,
+        /// Labels of single copies, printed on the label printer.
+        pub copy: Symbology,
+        /// Spine labels carry no barcode unless one is set.
+        pub spine: Option<Symbology>,
+        pub badge: Symbology,
+    }
+
+    impl Default for LabelSymbologies {
+        fn default() -> Self {
+            Self {
+                isbn: Symbology::Ean13,
+                copy: Symbology::Code128,
+                spine: None,
+                badge: Symbology::Code128,
             }
         }
     }
 
-    pub fn create_badge(client_id: &str, client_name: &str, library_name: &str, date: NaiveDate) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
-        let bold_font = FontRef::try_from_slice(include_bytes!("assets/bold_font.otf")).unwrap();
-        let regular_font = FontRef::try_from_slice(include_bytes!("assets/regular_font.otf")).unwrap();
+    fn invalid(message: String) -> Error {
+        Error::Validation(vec![ValidationError { field: "barcode".to_string(), message }])
+    }
 
-        let barcode = Code128::new(format!("\u{0181}{}", client_id)).unwrap();
+    /// What an ITF barcode of `data` carries; its digits go in pairs, so an odd count gets
+    /// a leading zero, which keeps an EAN-13 a valid GTIN-14.
+    pub fn itf_digits(data: &str) -> String {
+        if data.len() % 2 == 1 { format!("0{}", data) } else { data.to_string() }
+    }
 
-        let buffer = create_buffer!(75);
-        let encoded = barcode.encode();
-        let barcode_image = buffer.generate_buffer(&encoded[..]).unwrap();
+    /// What an ITF barcode read back stands for: the EAN-13 of a GTIN-14 that [`itf_digits`]
+    /// padded, or its digits as they are.
+    pub fn itf_text(digits: &str) -> String {
+        match digits.strip_prefix('0') {
+            Some(ean) if ean.len() == 13 && crate::validation::normalize_isbn(ean).is_some() => ean.to_string(),
+            _ => digits.to_string(),
+        }
+    }
 
-        let mut image = RgbaImage::new(BADGE_WIDTH, BADGE_HEIGHT);
+    /// Bars of a linear barcode, as barcoders encodes them, one entry per module.
+    pub fn encode_bars(symbology: Symbology, data: &str) -> SerializedResult<Vec<u8>> {
+        let refused = |error: barcoders::error::Error| invalid(format!("'{}' cannot be written in {:?}: {}", data, symbology, error));
+        Ok(match symbology {
+            Symbology::Ean13 => EAN13::new(data).map_err(refused)?.encode(),
+            // character set B holds every printable ASCII character
+            Symbology::Code128 => Code128::new(format!("\u{0181}{}", data)).map_err(refused)?.encode(),
+            Symbology::Code39 => Code39::new(data).map_err(refused)?.encode(),
+            Symbology::Itf => TF::interleaved(itf_digits(data)).map_err(refused)?.encode(),
+            Symbology::Qr | Symbology::DataMatrix => return Err(invalid(format!("{:?} is not a linear barcode", symbology))),
+        })
+    }
 
-        //border and background (black border and white background)
-        draw_filled_rect_mut(&mut image, Rect::at(0, 0).of_size(BADGE_WIDTH, BADGE_HEIGHT), BLACK);
-        draw_filled_rect_mut(&mut image, Rect::at(2, 2).of_size(BADGE_WIDTH - 4, BADGE_HEIGHT - 4), WHITE);
+    enum Modules {
+        Bars { bars: Vec<u8>, xdim: u32 },
+        Matrix { matrix: Matrix, quiet_zone: usize, module: u32 },
+    }
 
-        //copying barcode into image
-        image.copy_from(&barcode_image, (BADGE_WIDTH - barcode_image.width()) / 2, BADGE_HEIGHT - barcode_image.height() - BADGE_PADDING / 2).unwrap();
+    /// `data` encoded in a symbology and sized, ready to be drawn. Linear barcodes get bars
+    /// `xdim` pixels wide and `height` high; QR codes and Data Matrix symbols are squares of
+    /// at most `height` pixels with their quiet zone, whose modules are as many whole pixels
+    /// as fit.
+    pub struct Barcode {
+        pub width: u32,
+        pub height: u32,
+        modules: Modules,
+    }
 
-        let title = format!("Biblioteca {}", library_name);
-        let (w, _) = text_size(TITLE_SCALE, &bold_font, &title);
+    impl Barcode {
+        pub fn new(symbology: Symbology, data: &str, xdim: u32, height: u32) -> SerializedResult<Self> {
+            let (matrix, quiet_zone) = match symbology {
+                Symbology::Qr => (crate::matrix_codes::qr_code(data.as_bytes())?, 4),
+                Symbology::DataMatrix => (crate::matrix_codes::data_matrix(data.as_bytes())?, 1),
+                _ => {
+                    let bars = encode_bars(symbology, data)?;
+                    return Ok(Self { width: bars.len() as u32 * xdim, height, modules: Modules::Bars { bars, xdim } });
+                }
+            };
 
-        draw_text_mut(&mut image, BLACK, ((BADGE_WIDTH - w) / 2) as i32, 12, TITLE_SCALE, &bold_font, &title);
-        draw_text_mut(&mut image, BLACK, BADGE_PADDING as i32, 80, HEADING_SCALE, &bold_font, "Nume complet");
-        draw_text_mut(&mut image, BLACK, BADGE_PADDING as i32, 100, BODY_SCALE, &regular_font, client_name);
+            let modules = matrix.width + 2 * quiet_zone;
+            let module = (height as usize / modules).max(1) as u32;
+            let side = modules as u32 * module;
+            Ok(Self { width: side, height: side, modules: Modules::Matrix { matrix, quiet_zone, module } })
+        }
 
-        let date_text = format!("{}", date.format("%d.%m.%Y"));
+        /// Draws the barcode with its top left corner at `x`, `y`, runs of dark modules as
+        /// single rectangles.
+        pub fn draw(&self, drawing: &mut Drawing, x: i32, y: i32, foreground: Rgba<u8>, background: Rgba<u8>) {
+            drawing.fill_rect(x, y, self.width, self.height, background);
+            let mut runs = |row: &mut dyn Iterator<Item = bool>, module: u32, top: i32, height: u32| {
+                let mut start = None;
+                for (index, dark) in row.chain([false]).enumerate() {
+                    match (dark, start) {
+                        (true, None) => start = Some(index as u32),
+                        (false, Some(first)) => {
+                            drawing.fill_rect(x + (first * module) as i32, top, (index as u32 - first) * module, height, foreground);
+                            start = None;
+                        }
+                        _ => {}
+                    }
+                }
+            };
+
+            match &self.modules {
+                Modules::Bars { bars, xdim } => runs(&mut bars.iter().map(|bar| *bar != 0), *xdim, y, self.height),
+                Modules::Matrix { matrix, quiet_zone, module } => {
+                    for row in 0..matrix.height {
+                        let top = y + ((quiet_zone + row) as u32 * module) as i32;
+                        let mut columns = (0..quiet_zone + matrix.width).map(|column| column >= *quiet_zone && matrix.is_dark(column - quiet_zone, row));
+                        runs(&mut columns, *module, top, *module);
+                    }
+                }
+            }
+        }
+    }
 
-        let (w, _) = text_size(HEADING_SCALE, &bold_font, "Emis pe");
-        draw_text_mut(&mut image, BLACK, (BADGE_WIDTH - BADGE_PADDING - w) as i32, 80, HEADING_SCALE, &bold_font, "Emis pe");
-        let (w, _) = text_size(BODY_SCALE, &regular_font, &date_text);
-        draw_text_mut(&mut image, BLACK, (BADGE_WIDTH - BADGE_PADDING - w) as i32, 100, BODY_SCALE, &regular_font, &date_text);
-        return image;
+    /// Draws `data` in `symbology`, sized as [`Barcode::new`] does.
+    pub fn draw(symbology: Symbology, data: &str, xdim: u32, height: u32, foreground: Rgba<u8>, background: Rgba<u8>) -> SerializedResult<RgbaImage> {
+        let barcode = Barcode::new(symbology, data, xdim, height)?;
+        let mut drawing = Drawing::new(barcode.width, barcode.height);
+        barcode.draw(&mut drawing, 0, 0, foreground, background);
+        Ok(drawing.to_image())
     }
 
-    pub fn create_isbn(isbn: &str) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
-        let bold_font = FontRef::try_from_slice(include_bytes!("assets/bold_font.otf")).unwrap();
+    fn bold_font() -> FontArc {
+        FontArc::try_from_slice(include_bytes!("assets/bold_font.otf")).unwrap()
+    }
 
-        let barcode = EAN13::new(isbn).unwrap();
-        let buffer = create_buffer!(75);
+    fn regular_font() -> FontArc {
+        FontArc::try_from_slice(include_bytes!("assets/regular_font.otf")).unwrap()
+    }
 
-        let encoded = barcode.encode();
-        let barcode_image = buffer.generate_buffer(&encoded[..]).unwrap();
+    /// The label of an ISBN-13: its barcode, or symbol, with the number under it.
+    pub fn isbn_label(isbn: &str, symbology: Symbology) -> SerializedResult<Drawing> {
+        let barcode = Barcode::new(symbology, isbn, 2, 75)?;
+        if barcode.width > ISBN_WIDTH {
+            return Err(invalid(format!("{} is too long for an ISBN label in {:?}", isbn, symbology)));
+        }
 
-        let mut image = RgbaImage::new(ISBN_WIDTH, ISBN_HEIGHT);
+        let mut drawing = Drawing::new(ISBN_WIDTH, ISBN_HEIGHT);
+        drawing.fill_rect(0, 0, ISBN_WIDTH, ISBN_HEIGHT, WHITE);
+        barcode.draw(&mut drawing, ((ISBN_WIDTH - barcode.width) / 2) as i32, 0, BLACK, WHITE);
 
-        draw_filled_rect_mut(&mut image, Rect::at(0, 0).of_size(ISBN_WIDTH, ISBN_HEIGHT), WHITE);
+        let bold_font = bold_font();
+        let (w, _) = text_size(ISBN_SCALE, &bold_font, isbn);
+        drawing.draw_text(((ISBN_WIDTH - w) / 2) as i32, (barcode.height + ISBN_PADDING) as i32, ISBN_SCALE, &bold_font, isbn, BLACK);
+        Ok(drawing)
+    }
 
-        image.copy_from(&barcode_image, (ISBN_WIDTH - barcode_image.width()) / 2, 0).unwrap();
+    /// [`isbn_label`] in pixels.
+    pub fn create_isbn(isbn: &str, symbology: Symbology) -> SerializedResult<RgbaImage> {
+        Ok(isbn_label(isbn, symbology)?.to_image())
+    }
 
-        let (w, _) = text_size(ISBN_SCALE, &bold_font, isbn);
-        draw_text_mut(&mut image, BLACK, ((ISBN_WIDTH - w) / 2) as i32, (barcode_image.height() + ISBN_PADDING) as i32, ISBN_SCALE, &bold_font, isbn);
-        return image;
+    /// What the barcode of one copy of a book carries: its ISBN-13 and the number of the copy,
+    /// such as `9789734633456-003`.
+    pub fn copy_code(isbn: &str, copy: u32) -> String {
+        let isbn = crate::validation::to_isbn13(isbn).unwrap_or_else(|| isbn.to_string());
+        format!("{}-{:03}", isbn, copy)
+    }
+
+    /// The first three letters of the last name of the author, as shelves are ordered by.
+    pub fn author_code(author: &str) -> String {
+        let last_name = author.split_whitespace().last().unwrap_or("");
+        last_name.chars().filter(|c| c.is_alphanumeric()).take(3).collect::<String>().to_uppercase()
     }
-}
\ No newline at end of file
+
+    /// Shortens `text` with an ellipsis until it is at most `width` pixels wide.
+    fn fit_text(text: &str, width: u32, scale: PxScale, font: &FontArc) -> String {
+        let mut fitted = text.to_string();
+        let mut chars: Vec<char> = text.chars().collect();
+        while text_size(scale, font, &fitted).0 > width && !chars.is_empty() {
+            chars.pop();
+            fitted = format!("{}…", chars.iter().collect::<String>().trim_end());
+        }
+        fitted
+    }
+
+    /// The label of the spine: the author code over the title and the ISBN. With a
+    /// symbology the ISBN-13 is also encoded, as a symbol to the left of the text or as
+    /// bars in place of the printed ISBN.
+    pub fn spine_label(isbn: &str, title: &str, author: &str, symbology: Option<Symbology>) -> SerializedResult<Drawing> {
+        let bold_font = bold_font();
+        let regular_font = regular_font();
+
+        let mut drawing = Drawing::new(SPINE_WIDTH, SPINE_HEIGHT);
+        drawing.fill_rect(0, 0, SPINE_WIDTH, SPINE_HEIGHT, WHITE);
+
+        let code = crate::validation::to_isbn13(isbn).unwrap_or_else(|| isbn.to_string());
+        let mut left = 0;
+        let mut isbn_line = Some(isbn.to_string());
+        match symbology {
+            Some(symbology) if symbology.is_2d() => {
+                let symbol = Barcode::new(symbology, &code, 1, SPINE_HEIGHT - 2 * SPINE_PADDING)?;
+                symbol.draw(&mut drawing, SPINE_PADDING as i32, ((SPINE_HEIGHT - symbol.height) / 2) as i32, BLACK, WHITE);
+                left = SPINE_PADDING + symbol.width;
+                isbn_line = None;
+            }
+            Some(symbology) => {
+                let bars = Barcode::new(symbology, &code, 1, SPINE_HEIGHT - SPINE_BARS_TOP - SPINE_PADDING)?;
+                if bars.width > SPINE_WIDTH - 2 * SPINE_PADDING {
+                    return Err(invalid(format!("{} is too long for a spine label in {:?}", code, symbology)));
+                }
+                bars.draw(&mut drawing, ((SPINE_WIDTH - bars.width) / 2) as i32, SPINE_BARS_TOP as i32, BLACK, WHITE);
+                isbn_line = None;
+            }
+            None => {}
+        }
+
+        let width = SPINE_WIDTH - left;
+        let mut lines = vec![
+            (author_code(author), SPINE_CODE_SCALE, &bold_font, SPINE_PADDING),
+            (fit_text(title, width - 2 * SPINE_PADDING, SPINE_TEXT_SCALE, &regular_font), SPINE_TEXT_SCALE, &regular_font, 62),
+        ];
+        if let Some(isbn) = isbn_line {
+            lines.push((isbn, SPINE_TEXT_SCALE, &regular_font, 88));
+        }
+        for (text, scale, font, y) in lines {
+            let (w, _) = text_size(scale, font, &text);
+            drawing.draw_text((left + width.saturating_sub(w) / 2) as i32, y as i32, scale, font, &text, BLACK);
+        }
+        Ok(drawing)
+    }
+
+    /// [`spine_label`] in pixels.
+    pub fn create_spine(isbn: &str, title: &str, author: &str, symbology: Option<Symbology>) -> SerializedResult<RgbaImage> {
+        Ok(spine_label(isbn, title, author, symbology)?.to_image())
+    }
+}
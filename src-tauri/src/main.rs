@@ -3,347 +3,890 @@
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 
-use chrono::{Days, Local, NaiveDate};
-use tauri::{Manager, State};
-
-use libra_manager::database::DatabaseConnection;
+use chrono::{Local, NaiveDate};
+use tauri::{AppHandle, Manager, State};
+
+use libra_manager::badges::{BadgeData, TemplateLoader};
+use libra_manager::branches::DEFAULT_BRANCH;
+use libra_manager::csv_io::{ColumnMapping, Entity, ExportFilter, ImportReport};
+use libra_manager::database::{DatabaseConnection, StartupFailure};
+use libra_manager::decoding::ScannedCode;
+use libra_manager::drawing::OutputFormat;
+use libra_manager::integrity::IntegrityReport;
+use libra_manager::label_printer::PrinterLabel;
+use libra_manager::marc::{CatalogRecord, MarcFormat};
+use libra_manager::photos::Crop;
+use libra_manager::replication::{SyncConflict, SyncReport};
+use libra_manager::scans::ScanTarget;
 use libra_manager::Error::AuthError;
-use libra_manager::models::book_api::BookData;
-use libra_manager::models::database::{Book, Borrow, Client, NewBorrow, UpdateUser, User};
+use libra_manager::models::book_api::{BookData, LOOKUP_INTERVAL, LookupProgress};
+use libra_manager::models::database::{Book, Branch, Client, Holding, NewBranch, StagedBook, Transfer, UpdateUser, User};
 use libra_manager::models::database::joined_data::{BookBorrow, ClientBorrow};
 use libra_manager::SerializedResult;
+use libra_manager::server::Session;
+use libra_manager::sheets::BookLabel;
+use libra_manager::services::{book_service, borrow_service, client_service, user_service};
 use libra_manager::settings::{Settings, SettingsLoader};
 
 #[tauri::command]
-fn get_settings(settings_loader: State<SettingsLoader>) -> Settings {
-    let settings = settings_loader.load().unwrap();
-    settings
+fn get_settings(settings_loader: State<SettingsLoader>) -> SerializedResult<Settings> {
+    Ok(settings_loader.load()?)
 }
 
 #[tauri::command]
-fn save_settings(settings_loader: State<SettingsLoader>, settings: Settings) -> () {
-    settings_loader.store(settings).unwrap();
+fn save_settings(settings_loader: State<SettingsLoader>, settings: Settings) -> SerializedResult<()> {
+    Ok(settings_loader.store(settings)?)
 }
 
 #[tauri::command]
-fn login(database: State<DatabaseConnection>, username: String, password: String) -> SerializedResult<User> {
-    use libra_manager::schema::users::dsl::users;
-    use diesel::{QueryDsl, RunQueryDsl};
-
-    let client = &mut *database.client.lock().unwrap();
+fn login(database: State<DatabaseConnection>, username: String, password: String, branch_id: Option<i32>) -> SerializedResult<User> {
+    user_service::login(&mut *database.get()?, &username, &password, branch_id)
+}
 
-    let result: User = users.find(username).first(client)?;
-    if result.password.eq(&password) {
-        return Ok(result);
-    }
+#[tauri::command]
+fn select_branch(database: State<DatabaseConnection>, username: String, branch_id: i32) -> SerializedResult<()> {
+    user_service::select_branch(&mut *database.get()?, &username, branch_id)
+}
 
-    Err(AuthError)
+#[tauri::command]
+fn fetch_books(database: State<DatabaseConnection>, include_archived: Option<bool>) -> SerializedResult<Vec<Book>> {
+    book_service::fetch_books(&mut *database.get()?, include_archived.unwrap_or(false))
 }
 
 #[tauri::command]
-fn fetch_books(database: State<DatabaseConnection>) -> SerializedResult<Vec<Book>> {
-    use libra_manager::schema::books::dsl::*;
-    use diesel::{QueryDsl, RunQueryDsl, SelectableHelper};
+fn fetch_book(database: State<DatabaseConnection>, isbn: String, include_archived: Option<bool>) -> SerializedResult<Option<Book>> {
+    book_service::fetch_book(&mut *database.get()?, &isbn, include_archived.unwrap_or(false))
+}
 
-    let client = &mut *database.client.lock().unwrap();
-    let result = books.select(Book::as_select()).load(client)?;
-    Ok(result)
+#[tauri::command]
+fn create_book(database: State<DatabaseConnection>, book: Book, branch_id: Option<i32>) -> SerializedResult<()> {
+    book_service::create_book(&mut *database.get()?, &book, branch_id.unwrap_or(DEFAULT_BRANCH))
 }
 
 #[tauri::command]
-fn fetch_book(database: State<DatabaseConnection>, isbn: String) -> SerializedResult<Option<Book>> {
-    use libra_manager::schema::books::dsl::books;
-    use diesel::{OptionalExtension, QueryDsl, RunQueryDsl};
+fn delete_book(database: State<DatabaseConnection>, isbn: String) -> SerializedResult<()> {
+    book_service::archive_book(&mut *database.get()?, &isbn)
+}
 
-    let client = &mut *database.client.lock().unwrap();
-    let result = books.find(isbn).get_result(client).optional()?;
-    Ok(result)
+#[tauri::command]
+fn restore_book(database: State<DatabaseConnection>, isbn: String) -> SerializedResult<()> {
+    book_service::restore_book(&mut *database.get()?, &isbn)
 }
 
 #[tauri::command]
-fn create_book(database: State<DatabaseConnection>, book: Book) -> SerializedResult<()> {
-    use libra_manager::schema::books::dsl::*;
-    use diesel::RunQueryDsl;
-    use diesel::associations::HasTable;
+fn purge_book(database: State<DatabaseConnection>, username: String, password: String, isbn: String) -> SerializedResult<()> {
+    let client = &mut *database.get()?;
 
-    let client = &mut *database.client.lock().unwrap();
-    diesel::insert_into(books::table()).values(&book).execute(client)?;
-    Ok(())
+    user_service::authorize_admin(client, &username, &password)?;
+    book_service::purge_book(client, &isbn)
 }
 
 #[tauri::command]
-fn delete_book(database: State<DatabaseConnection>, isbn: String) -> SerializedResult<()> {
-    use libra_manager::schema::books::dsl::books;
-    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
-    let client = &mut *database.client.lock().unwrap();
+fn update_book(database: State<DatabaseConnection>, book: Book, branch_id: Option<i32>) -> SerializedResult<()> {
+    book_service::update_book(&mut *database.get()?, &book, branch_id.unwrap_or(DEFAULT_BRANCH))
+}
 
-    diesel::delete(books.filter(libra_manager::schema::books::isbn.eq(isbn))).execute(client)?;
-    Ok(())
+#[tauri::command]
+fn fetch_clients(database: State<DatabaseConnection>, include_archived: Option<bool>) -> SerializedResult<Vec<Client>> {
+    client_service::fetch_clients(&mut *database.get()?, include_archived.unwrap_or(false))
 }
 
 #[tauri::command]
-fn update_book(database: State<DatabaseConnection>, book: Book) -> SerializedResult<()> {
-    use diesel::RunQueryDsl;
-    let client = &mut *database.client.lock().unwrap();
+fn fetch_client(database: State<DatabaseConnection>, settings_loader: State<SettingsLoader>, id: String, include_archived: Option<bool>) -> SerializedResult<Option<Client>> {
+    let format = settings_loader.load()?.client_id_format;
+    client_service::lookup_client(&mut *database.get()?, &id, &format, include_archived.unwrap_or(false))
+}
 
-    diesel::update(&book).set(&book).execute(client)?;
-    Ok(())
+#[tauri::command]
+fn create_client(database: State<DatabaseConnection>, settings_loader: State<SettingsLoader>, client: Client, branch_id: Option<i32>) -> SerializedResult<Client> {
+    let settings = settings_loader.load()?;
+    let id_pattern = libra_manager::validation::client_id_pattern(&settings.client_id_pattern)?;
+    client_service::register_client(&mut *database.get()?, &client, &settings.client_id_format, branch_id.unwrap_or(DEFAULT_BRANCH), &id_pattern)
 }
 
 #[tauri::command]
-fn fetch_clients(database: State<DatabaseConnection>) -> SerializedResult<Vec<Client>> {
-    use libra_manager::schema::clients::dsl::*;
-    use diesel::{QueryDsl, RunQueryDsl, SelectableHelper};
+fn delete_client(database: State<DatabaseConnection>, id: String) -> SerializedResult<()> {
+    client_service::archive_client(&mut *database.get()?, &id)
+}
 
-    let client = &mut *database.client.lock().unwrap();
-    let result = clients.select(Client::as_select()).load(client)?;
-    Ok(result)
+#[tauri::command]
+fn restore_client(database: State<DatabaseConnection>, id: String) -> SerializedResult<()> {
+    client_service::restore_client(&mut *database.get()?, &id)
 }
 
 #[tauri::command]
-fn fetch_client(database: State<DatabaseConnection>, id: String) -> SerializedResult<Option<Client>> {
-    use libra_manager::schema::clients::dsl::clients;
-    use diesel::{OptionalExtension, QueryDsl, RunQueryDsl};
+fn purge_client(database: State<DatabaseConnection>, username: String, password: String, id: String) -> SerializedResult<()> {
+    let client = &mut *database.get()?;
 
-    let client = &mut *database.client.lock().unwrap();
-    let result = clients.find(id).get_result(client).optional()?;
-    Ok(result)
+    user_service::authorize_admin(client, &username, &password)?;
+    client_service::purge_client(client, &id)
 }
 
 #[tauri::command]
-fn create_client(database: State<DatabaseConnection>, client: Client) -> SerializedResult<()> {
-    use libra_manager::schema::clients::dsl::*;
-    use diesel::RunQueryDsl;
-    use diesel::associations::HasTable;
+fn update_client(database: State<DatabaseConnection>, client: Client) -> SerializedResult<()> {
+    client_service::update_client(&mut *database.get()?, &client)
+}
 
-    let db_client = &mut *database.client.lock().unwrap();
+#[tauri::command]
+fn fetch_borrowers(database: State<DatabaseConnection>, isbn: String) -> SerializedResult<Vec<ClientBorrow>> {
+    borrow_service::fetch_borrowers(&mut *database.get()?, &isbn)
+}
 
-    diesel::insert_into(clients::table()).values(&client).execute(db_client)?;
-    Ok(())
+#[tauri::command]
+fn fetch_borrowed_books(database: State<DatabaseConnection>, id: String) -> SerializedResult<Vec<BookBorrow>> {
+    borrow_service::fetch_borrowed_books(&mut *database.get()?, &id)
 }
 
 #[tauri::command]
-fn delete_client(database: State<DatabaseConnection>, id: String) -> SerializedResult<()> {
-    use libra_manager::schema::clients::dsl::clients;
-    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
-    let client = &mut *database.client.lock().unwrap();
+fn is_book_available(database: State<DatabaseConnection>, isbn: String, client_id: String, branch_id: Option<i32>) -> SerializedResult<Option<bool>> {
+    borrow_service::is_book_available(&mut *database.get()?, &isbn, &client_id, branch_id)
+}
 
-    let client_id = id;
+#[tauri::command]
+fn add_borrow(database: State<DatabaseConnection>, isbn: String, client_id: String, branch_id: Option<i32>) -> SerializedResult<()> {
+    borrow_service::add_borrow(&mut *database.get()?, &isbn, &client_id, branch_id.unwrap_or(DEFAULT_BRANCH), Local::now().date_naive())
+}
 
-    diesel::delete(clients.filter(libra_manager::schema::clients::id.eq(client_id))).execute(client)?;
-    Ok(())
+#[tauri::command]
+fn delete_borrow(database: State<DatabaseConnection>, id: i32) -> SerializedResult<()> {
+    borrow_service::delete_borrow(&mut *database.get()?, id)
 }
 
 #[tauri::command]
-fn update_client(database: State<DatabaseConnection>, client: Client) -> SerializedResult<()> {
-    use diesel::RunQueryDsl;
-    let db_client = &mut *database.client.lock().unwrap();
+fn update_borrow(database: State<DatabaseConnection>, id: i32, returned: bool, end_date: NaiveDate) -> SerializedResult<()> {
+    borrow_service::update_borrow(&mut *database.get()?, id, returned, end_date)
+}
 
-    diesel::update(&client).set(&client).execute(db_client)?;
-    Ok(())
+#[tauri::command]
+fn update_user(database: State<DatabaseConnection>, user: UpdateUser, password: String) -> SerializedResult<()> {
+    user_service::update_user(&mut *database.get()?, &user, &password)
 }
 
 #[tauri::command]
-fn fetch_borrowers(database: State<DatabaseConnection>, isbn: String) -> SerializedResult<Vec<ClientBorrow>> {
-    use libra_manager::schema::clients::dsl::clients;
-    use libra_manager::schema::books::dsl::books;
-    use diesel::{BelongingToDsl, QueryDsl, RunQueryDsl, SelectableHelper};
-    use diesel::associations::HasTable;
+fn fetch_user(database: State<DatabaseConnection>, username: String) -> SerializedResult<User> {
+    user_service::fetch_user(&mut *database.get()?, &username)
+}
 
-    let client = &mut *database.client.lock().unwrap();
+#[tauri::command]
+fn fetch_users(database: State<DatabaseConnection>) -> SerializedResult<Vec<User>> {
+    user_service::fetch_users(&mut *database.get()?)
+}
 
-    let book: Book = books.find(isbn).get_result(client).unwrap();
-    let result = Borrow::belonging_to(&book)
-        .inner_join(clients::table())
-        .select((Borrow::as_select(), Client::as_select()))
-        .load::<(Borrow, Client)>(client)?;
+#[tauri::command]
+fn create_user(database: State<DatabaseConnection>, user: User) -> SerializedResult<()> {
+    user_service::create_user(&mut *database.get()?, &user)
+}
 
-    let collected = result.into_iter().map(|(borrow, client)| ClientBorrow { borrow, client }).collect::<Vec<ClientBorrow>>();
-    Ok(collected)
+#[tauri::command]
+fn delete_user(database: State<DatabaseConnection>, username: String) -> SerializedResult<()> {
+    user_service::delete_user(&mut *database.get()?, &username)
 }
 
 #[tauri::command]
-fn fetch_borrowed_books(database: State<DatabaseConnection>, id: String) -> SerializedResult<Vec<BookBorrow>> {
-    use libra_manager::schema::clients::dsl::clients;
-    use libra_manager::schema::books::dsl::books;
-    use diesel::{BelongingToDsl, QueryDsl, RunQueryDsl, SelectableHelper};
-    use diesel::associations::HasTable;
-    let db_client = &mut *database.client.lock().unwrap();
+fn fetch_branches(database: State<DatabaseConnection>) -> SerializedResult<Vec<Branch>> {
+    let client = &mut *database.get()?;
+    libra_manager::branches::fetch_branches(client)
+}
 
-    let client: Client = clients.find(id).get_result(db_client).unwrap();
+#[tauri::command]
+fn create_branch(database: State<DatabaseConnection>, branch: NewBranch) -> SerializedResult<Branch> {
+    let client = &mut *database.get()?;
+    libra_manager::branches::create_branch(client, &branch)
+}
 
-    let result = Borrow::belonging_to(&client)
-        .inner_join(books::table())
-        .select((Borrow::as_select(), Book::as_select()))
-        .load::<(Borrow, Book)>(db_client)?;
+#[tauri::command]
+fn update_branch(database: State<DatabaseConnection>, branch: Branch) -> SerializedResult<()> {
+    let client = &mut *database.get()?;
+    libra_manager::branches::update_branch(client, &branch)
+}
 
-    let collected = result.into_iter().map(|(borrow, book)| BookBorrow { borrow, book }).collect::<Vec<BookBorrow>>();
+#[tauri::command]
+fn delete_branch(database: State<DatabaseConnection>, id: i32) -> SerializedResult<()> {
+    let client = &mut *database.get()?;
+    libra_manager::branches::delete_branch(client, id)
+}
 
-    Ok(collected)
+#[tauri::command]
+fn fetch_holdings(database: State<DatabaseConnection>, isbn: String) -> SerializedResult<Vec<Holding>> {
+    let client = &mut *database.get()?;
+    libra_manager::branches::fetch_holdings(client, &isbn)
 }
 
 #[tauri::command]
-fn is_book_available(database: State<DatabaseConnection>, isbn: String, client_id: String) -> SerializedResult<Option<bool>> {
-    use libra_manager::schema::books::dsl::books;
-    use libra_manager::schema::clients::dsl::clients;
-    use libra_manager::schema::borrows::returned;
-    use diesel::{BelongingToDsl, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
-    use diesel::associations::HasTable;
+fn transfer_copies(database: State<DatabaseConnection>, isbn: String, from_branch: i32, to_branch: i32, items: i32, username: String) -> SerializedResult<Transfer> {
+    let client = &mut *database.get()?;
+    libra_manager::branches::transfer(client, &isbn, from_branch, to_branch, items, &username)
+}
 
-    let client = &mut *database.client.lock().unwrap();
+#[tauri::command]
+fn fetch_transfers(database: State<DatabaseConnection>, isbn: Option<String>) -> SerializedResult<Vec<Transfer>> {
+    let client = &mut *database.get()?;
+    libra_manager::branches::fetch_transfers(client, isbn.as_deref())
+}
 
-    let book_optional: Option<Book> = books.find(isbn).get_result(client).optional()?;
+/// Commands other desks may run through the server, listed with their arguments after
+/// the `State<DatabaseConnection>` each of them takes first. Any other managed state a
+/// command takes follows its name in brackets, and commands not every user may run are
+/// marked `#[own]` or `#[admin]` (see [`Access`](libra_manager::server::Access)).
+macro_rules! remote_commands {
+    ($($(#[$access:ident])? $name:ident $([$($state:ty),+])? ($($arg:ident: $kind:ty),*)),* $(,)?) => {
+        fn dispatch(app: &AppHandle, user: &User, command: &str, args: serde_json::Value) -> SerializedResult<serde_json::Value> {
+            match command {
+                $(stringify!($name) => {
+                    #[derive(serde::Deserialize)]
+                    #[serde(rename_all = "camelCase")]
+                    struct Args { $($arg: $kind),* }
+
+                    libra_manager::server::authorize(user, access!($($access)?), command, &args)?;
+                    let Args { $($arg),* } = serde_json::from_value(args)?;
+                    Ok(serde_json::to_value($name(app.state(), $($(app.state::<$state>(),)+)? $($arg),*)?)?)
+                })*
+                _ => Err(libra_manager::Error::UnknownCommand(command.to_string()))
+            }
+        }
+    };
+}
 
-    return if let Some(book) = book_optional {
-        let result = Borrow::belonging_to(&book)
-            .inner_join(clients::table())
-            .select((Client::as_select(), Borrow::as_select()))
-            .filter(returned.eq(false))
-            .load::<(Client, Borrow)>(client)?;
+macro_rules! access {
+    () => { libra_manager::server::Access::User };
+    (own) => { libra_manager::server::Access::Own };
+    (admin) => { libra_manager::server::Access::Admin };
+}
 
-        let check = result.iter().any(|(client, _borrow)| client.id == client_id);
+remote_commands! {
+    login(username: String, password: String, branch_id: Option<i32>),
+    #[own] select_branch(username: String, branch_id: i32),
+    fetch_books(include_archived: Option<bool>),
+    fetch_book(isbn: String, include_archived: Option<bool>),
+    create_book(book: Book, branch_id: Option<i32>),
+    delete_book(isbn: String),
+    restore_book(isbn: String),
+    purge_book(username: String, password: String, isbn: String),
+    update_book(book: Book, branch_id: Option<i32>),
+    fetch_clients(include_archived: Option<bool>),
+    fetch_client[SettingsLoader](id: String, include_archived: Option<bool>),
+    create_client[SettingsLoader](client: Client, branch_id: Option<i32>),
+    delete_client(id: String),
+    restore_client(id: String),
+    purge_client(username: String, password: String, id: String),
+    update_client(client: Client),
+    fetch_borrowers(isbn: String),
+    fetch_borrowed_books(id: String),
+    is_book_available(isbn: String, client_id: String, branch_id: Option<i32>),
+    add_borrow(isbn: String, client_id: String, branch_id: Option<i32>),
+    delete_borrow(id: i32),
+    update_borrow(id: i32, returned: bool, end_date: NaiveDate),
+    update_user(user: UpdateUser, password: String),
+    #[own] fetch_user(username: String),
+    #[admin] fetch_users(),
+    #[admin] create_user(user: User),
+    #[admin] delete_user(username: String),
+    fetch_branches(),
+    #[admin] create_branch(branch: NewBranch),
+    #[admin] update_branch(branch: Branch),
+    #[admin] delete_branch(id: i32),
+    fetch_holdings(isbn: String),
+    #[own] transfer_copies(isbn: String, from_branch: i32, to_branch: i32, items: i32, username: String),
+    fetch_transfers(isbn: Option<String>),
+    fetch_staged_books(),
+    update_staged_book(book: StagedBook),
+    delete_staged_book(isbn: String),
+    commit_staged_books(isbns: Vec<String>, branch_id: Option<i32>),
+    upload_client_photo(client_id: String, photo: String),
+    fetch_client_photo(client_id: String),
+    crop_client_photo(client_id: String, crop: Crop),
+    resize_client_photo(client_id: String, width: u32, height: u32),
+    delete_client_photo(client_id: String),
+    sign_client_badge(client_id: String, library_name: String),
+    resolve_scan[SettingsLoader](code: String),
+    fetch_counts(),
+}
 
-        Ok(Some((result.len() as i32) < book.items && !check))
-    } else {
-        Ok(None)
+/// Shares the database with other desks when a server port is configured.
+fn start_server(app: &AppHandle) {
+    let settings = match app.state::<SettingsLoader>().load() {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("LibraManager server not started: the settings could not be read: {}", error);
+            return;
+        }
     };
+    if settings.server_port == 0 {
+        return;
+    }
+
+    let pool = app.state::<DatabaseConnection>().pool.clone();
+    let handle = app.clone();
+    let dispatch: libra_manager::server::Dispatch = std::sync::Arc::new(move |user, command, args| dispatch(&handle, user, command, args));
+    let address = match settings.server_address.parse::<std::net::IpAddr>() {
+        Ok(ip) => std::net::SocketAddr::new(ip, settings.server_port),
+        Err(_) => {
+            eprintln!("LibraManager server not started: {} is not an IP address", settings.server_address);
+            return;
+        }
+    };
+
+    tauri::async_runtime::spawn(async move {
+        if let Err(error) = libra_manager::server::serve(address, pool, dispatch).await {
+            eprintln!("LibraManager server stopped: {}", error);
+        }
+    });
+}
+
+/// Logs in on the desk at `url` and sends the database commands there from now on.
+#[tauri::command]
+async fn connect_server(session: State<'_, Session>, url: String, username: String, password: String, branch_id: Option<i32>) -> SerializedResult<serde_json::Value> {
+    let client = libra_manager::server::Client::new(&url, &username, &password);
+    let user = client.call("login", &serde_json::json!({ "username": username, "password": password, "branchId": branch_id })).await?;
+    session.set(Some(client));
+    Ok(user)
+}
+
+#[tauri::command]
+fn disconnect_server(session: State<Session>) {
+    session.set(None);
 }
 
 #[tauri::command]
-fn add_borrow(database: State<DatabaseConnection>, isbn: String, client_id: String) -> SerializedResult<()> {
-    let current_date: NaiveDate = Local::now().date_naive();
-    let next_date = Local::now().date_naive() + Days::new(14);
-    let client = &mut *database.client.lock().unwrap();
+async fn remote_invoke(session: State<'_, Session>, command: String, args: serde_json::Value) -> SerializedResult<serde_json::Value> {
+    let client = session.get().ok_or(AuthError)?;
+    client.call(&command, &args).await
+}
 
-    let borrow = NewBorrow { client_id: &client_id, book_isbn: &isbn, returned: false, start_date: current_date, end_date: next_date };
+#[tauri::command]
+async fn lookup_book(isbn: String) -> SerializedResult<Option<BookData>> {
+    Ok(libra_manager::models::book_api::fetch_book(isbn).await?)
+}
 
-    use libra_manager::schema::borrows::dsl::*;
+#[tauri::command]
+fn bulk_lookup(app: AppHandle, database: State<DatabaseConnection>, isbns: Vec<String>) -> SerializedResult<()> {
+    use libra_manager::schema::staged_books::dsl::*;
     use diesel::RunQueryDsl;
     use diesel::associations::HasTable;
 
-    diesel::insert_into(borrows::table()).values(&borrow).execute(client)?;
+    let client = &mut *database.get()?;
+
+    // ISBNs already staged keep what the librarian edited, only new ones are looked up
+    let mut pending = Vec::new();
+    for code in &isbns {
+        let staged = StagedBook {
+            isbn: code.clone(),
+            title: String::new(),
+            author: String::new(),
+            items: 1,
+            status: "pending".to_string(),
+            edition: None,
+            publication: None,
+            extent: None,
+            subjects: String::new(),
+        };
+        if diesel::insert_or_ignore_into(staged_books::table()).values(&staged).execute(client)? > 0 {
+            pending.push(staged);
+        }
+    }
+
+    std::thread::spawn(move || {
+        let total = pending.len();
+
+        for (index, mut staged) in pending.into_iter().enumerate() {
+            if index > 0 {
+                std::thread::sleep(LOOKUP_INTERVAL);
+            }
+
+            match tauri::async_runtime::block_on(libra_manager::models::book_api::fetch_book(staged.isbn.clone())) {
+                Ok(Some(data)) => {
+                    staged.author = data.author_names();
+                    staged.title = data.title;
+                    staged.status = "found".to_string();
+                }
+                Ok(None) => staged.status = "not_found".to_string(),
+                Err(_) => staged.status = "failed".to_string(),
+            }
+
+            let database = app.state::<DatabaseConnection>();
+            let Ok(mut client) = database.get() else {
+                continue;
+            };
+
+            if diesel::update(&staged).set(&staged).execute(&mut client).is_err() {
+                continue;
+            }
+
+            let _ = app.emit_all("bulk-lookup-progress", LookupProgress {
+                isbn: staged.isbn,
+                status: staged.status,
+                completed: index + 1,
+                total,
+            });
+        }
+    });
+
     Ok(())
 }
 
 #[tauri::command]
-fn delete_borrow(database: State<DatabaseConnection>, id: i32) -> SerializedResult<()> {
-    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
-    use libra_manager::schema::borrows::dsl::borrows;
-    let client = &mut *database.client.lock().unwrap();
+fn fetch_staged_books(database: State<DatabaseConnection>) -> SerializedResult<Vec<StagedBook>> {
+    book_service::fetch_staged_books(&mut *database.get()?)
+}
 
-    diesel::delete(borrows.filter(libra_manager::schema::borrows::dsl::id.eq(&id))).execute(client)?;
-    Ok(())
+#[tauri::command]
+fn update_staged_book(database: State<DatabaseConnection>, book: StagedBook) -> SerializedResult<()> {
+    book_service::update_staged_book(&mut *database.get()?, &book)
 }
 
 #[tauri::command]
-fn update_borrow(database: State<DatabaseConnection>, id: i32, returned: bool, end_date: NaiveDate) -> SerializedResult<()> {
-    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
-    use libra_manager::schema::borrows::dsl::borrows;
+fn delete_staged_book(database: State<DatabaseConnection>, isbn: String) -> SerializedResult<()> {
+    book_service::delete_staged_book(&mut *database.get()?, &isbn)
+}
 
-    let client = &mut *database.client.lock().unwrap();
+#[tauri::command]
+fn commit_staged_books(database: State<DatabaseConnection>, isbns: Vec<String>, branch_id: Option<i32>) -> SerializedResult<usize> {
+    book_service::commit_staged_books(&mut *database.get()?, &isbns, branch_id.unwrap_or(DEFAULT_BRANCH))
+}
 
-    diesel::update(borrows.filter(libra_manager::schema::borrows::dsl::id.eq(&id)))
-        .set((
-            libra_manager::schema::borrows::dsl::returned.eq(&returned),
-            libra_manager::schema::borrows::dsl::endDate.eq(&end_date)
-        )).execute(client)?;
-    Ok(())
+#[tauri::command]
+fn import_csv(database: State<DatabaseConnection>, entity: Entity, path: String, mapping: Option<ColumnMapping>, dry_run: bool) -> SerializedResult<ImportReport> {
+    let client = &mut *database.get()?;
+    libra_manager::csv_io::import_csv(client, entity, std::path::Path::new(&path), &mapping.unwrap_or_default(), dry_run)
 }
 
 #[tauri::command]
-fn update_user(database: State<DatabaseConnection>, user: UpdateUser, password: String) -> SerializedResult<()> {
-    use diesel::{QueryDsl, RunQueryDsl};
-    use libra_manager::schema::users::dsl::users;
-    let client = &mut *database.client.lock().unwrap();
+fn export_csv(database: State<DatabaseConnection>, entity: Entity, path: String, filter: Option<ExportFilter>) -> SerializedResult<usize> {
+    let client = &mut *database.get()?;
+    libra_manager::csv_io::export_csv(client, entity, std::path::Path::new(&path), &filter.unwrap_or_default())
+}
+
+#[tauri::command]
+fn import_marc(database: State<DatabaseConnection>, path: String) -> SerializedResult<Vec<CatalogRecord>> {
+    let records = libra_manager::marc::parse(&std::fs::read(path)?)?
+        .iter()
+        .map(|record| record.to_catalog_record())
+        .filter(|record| !record.isbn.is_empty())
+        .collect::<Vec<CatalogRecord>>();
+
+    let staged = records.iter().map(CatalogRecord::to_staged_book).collect::<Vec<StagedBook>>();
+
+    let client = &mut *database.get()?;
+    book_service::stage_books(client, &staged)?;
+
+    Ok(records)
+}
+
+#[tauri::command]
+fn export_marc(database: State<DatabaseConnection>, path: String, format: MarcFormat) -> SerializedResult<usize> {
+    let client = &mut *database.get()?;
+    let records = libra_manager::marc::catalog(client)?;
 
-    let user_password = users.find(&user.username).get_result::<User>(client).unwrap().password;
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    libra_manager::marc::write(&mut file, &records, format)?;
 
-    if user_password != password {
-        return Err(AuthError);
+    Ok(records.len())
+}
+
+#[tauri::command]
+fn export_sync_bundle(database: State<DatabaseConnection>, path: String) -> SerializedResult<usize> {
+    let client = &mut *database.get()?;
+    libra_manager::replication::export_bundle(client, std::path::Path::new(&path))
+}
+
+#[tauri::command]
+fn import_sync_bundle(database: State<DatabaseConnection>, path: String) -> SerializedResult<SyncReport> {
+    let client = &mut *database.get()?;
+    libra_manager::replication::import_bundle(client, std::path::Path::new(&path))
+}
+
+#[tauri::command]
+fn fetch_sync_conflicts(database: State<DatabaseConnection>) -> SerializedResult<Vec<SyncConflict>> {
+    let client = &mut *database.get()?;
+    libra_manager::replication::fetch_conflicts(client)
+}
+
+#[tauri::command]
+fn resolve_sync_conflict(database: State<DatabaseConnection>, id: i32, items: i32) -> SerializedResult<()> {
+    let client = &mut *database.get()?;
+    libra_manager::replication::resolve_conflict(client, id, items)
+}
+
+#[tauri::command]
+fn reset_replica_id(database: State<DatabaseConnection>) -> SerializedResult<String> {
+    let client = &mut *database.get()?;
+    libra_manager::replication::reset_site(client)
+}
+
+#[tauri::command]
+fn backup_database(database: State<DatabaseConnection>, path: String) -> SerializedResult<()> {
+    libra_manager::backup::backup_database(&database.path, std::path::Path::new(&path), database.key.as_deref())
+}
+
+#[tauri::command]
+fn restore_database(database: State<DatabaseConnection>, path: String) -> SerializedResult<()> {
+    database.restore(std::path::Path::new(&path))
+}
+
+#[tauri::command]
+fn repair_integrity(database: State<DatabaseConnection>, dry_run: bool) -> SerializedResult<IntegrityReport> {
+    let client = &mut *database.get()?;
+    libra_manager::integrity::repair(client, dry_run)
+}
+
+/// Whether the database is encrypted and waits in the recovery window for its passphrase.
+#[tauri::command]
+fn is_database_locked(app: AppHandle) -> bool {
+    app.try_state::<DatabaseConnection>().is_none() && database_path(&app).and_then(|path| libra_manager::encryption::is_encrypted(&path)).unwrap_or(false)
+}
+
+#[tauri::command]
+fn unlock_database(app: AppHandle, passphrase: String) -> SerializedResult<()> {
+    if app.try_state::<DatabaseConnection>().is_some() {
+        return Ok(());
+    }
+
+    let path = database_path(&app)?;
+    finish_recovery(&app, DatabaseConnection::encrypted(path.to_str().unwrap(), &passphrase)?)
+}
+
+/// Opens the database at `path`, with `passphrase` when it is encrypted.
+fn open_database(path: &std::path::Path, passphrase: Option<&str>) -> SerializedResult<DatabaseConnection> {
+    let url = path.to_str().unwrap();
+    match passphrase {
+        Some(passphrase) if libra_manager::encryption::is_encrypted(path)? => DatabaseConnection::encrypted(url, passphrase),
+        _ => DatabaseConnection::from(url),
     }
-    diesel::update(&user).set(&user).execute(client)?;
-    Ok(())
 }
 
 #[tauri::command]
-fn fetch_user(database: State<DatabaseConnection>, username: String) -> SerializedResult<User> {
-    use diesel::{QueryDsl, RunQueryDsl};
-    use libra_manager::schema::users::dsl::users;
-    let client = &mut *database.client.lock().unwrap();
-    let user: User = users.find(&username).get_result(client).unwrap();
-    Ok(user)
+fn startup_error(startup_failure: State<StartupFailure>) -> Option<String> {
+    startup_failure.get()
 }
 
+/// Copies a backup over the database; backups of an encrypted database need its `passphrase`.
 #[tauri::command]
-fn fetch_users(database: State<DatabaseConnection>) -> SerializedResult<Vec<User>> {
-    use libra_manager::schema::users::dsl::*;
-    use diesel::{QueryDsl, RunQueryDsl, SelectableHelper};
+fn recover_from_backup(app: AppHandle, path: String, passphrase: Option<String>) -> SerializedResult<()> {
+    let backup = std::path::Path::new(&path);
+    let passphrase = passphrase.filter(|passphrase| !passphrase.is_empty());
+    libra_manager::backup::validate_backup(backup, passphrase.as_deref())?;
 
-    let client = &mut *database.client.lock().unwrap();
-    let result = users.select(User::as_select()).load(client)?;
-    Ok(result)
+    let database = database_path(&app)?;
+    libra_manager::database::remove_journal_files(&database)?;
+    std::fs::copy(backup, &database)?;
+
+    finish_recovery(&app, open_database(&database, passphrase.as_deref())?)
 }
 
 #[tauri::command]
-fn create_user(database: State<DatabaseConnection>, user: User) -> SerializedResult<()> {
-    use libra_manager::schema::users::dsl::*;
-    use diesel::RunQueryDsl;
-    use diesel::associations::HasTable;
+fn open_database_file(app: AppHandle, settings_loader: State<SettingsLoader>, path: String, passphrase: Option<String>) -> SerializedResult<()> {
+    let database = open_database(std::path::Path::new(&path), passphrase.as_deref().filter(|passphrase| !passphrase.is_empty()))?;
+
+    let mut settings = settings_loader.load()?;
+    settings.database_path = path;
+    settings_loader.store(settings)?;
+
+    finish_recovery(&app, database)
+}
+
+#[tauri::command]
+fn open_read_only(app: AppHandle) -> SerializedResult<()> {
+    let database = DatabaseConnection::read_only(database_path(&app)?.to_str().unwrap())?;
+    finish_recovery(&app, database)
+}
+
+/// Hands the recovered database to the commands and swaps the recovery window for the main one.
+fn finish_recovery(app: &AppHandle, database: DatabaseConnection) -> SerializedResult<()> {
+    app.manage(database);
+    app.state::<StartupFailure>().set(None);
+    start_server(app);
+
+    if let Some(window) = app.get_window("main") {
+        let _ = window.eval("window.location.replace('/')");
+        let _ = window.show();
+    }
+    if let Some(window) = app.get_window("recovery") {
+        let _ = window.close();
+    }
+    Ok(())
+}
 
-    let client = &mut *database.client.lock().unwrap();
-    diesel::insert_into(users::table()).values(&user).execute(client)?;
+fn open_recovery_window(app: &AppHandle) -> tauri::Result<()> {
+    if let Some(window) = app.get_window("main") {
+        window.hide()?;
+    }
+
+    tauri::WindowBuilder::new(app, "recovery", tauri::WindowUrl::App("recovery".into()))
+        .title("LibraManager")
+        .inner_size(600.0, 460.0)
+        .build()?;
     Ok(())
 }
 
+/// Encrypts the database with `passphrase`, asked for in the recovery window on every start.
 #[tauri::command]
-fn delete_user(database: State<DatabaseConnection>, username: String) -> SerializedResult<()> {
-    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
-    use libra_manager::schema::users::dsl::users;
-    let client = &mut *database.client.lock().unwrap();
-    diesel::delete(users.filter(libra_manager::schema::users::username.eq(&username))).execute(client)?;
+fn encrypt_database(app: AppHandle, database: State<DatabaseConnection>, username: String, password: String, passphrase: String) -> SerializedResult<()> {
+    user_service::authorize_admin(&mut *database.get()?, &username, &password)?;
+    if passphrase.is_empty() {
+        return Err(libra_manager::Error::Validation(vec![libra_manager::validation::ValidationError {
+            field: "passphrase".to_string(),
+            message: "Must not be empty".to_string(),
+        }]));
+    }
+    database.encrypt(&passphrase)?;
+
+    // the encrypted copy replaces the database file while the app starts up again
+    app.restart();
     Ok(())
 }
 
+/// The photo printed on the badge of a client; a thin client prints the one kept on the server.
+async fn badge_photo(database: &DatabaseConnection, session: &Session, client_id: &str) -> SerializedResult<Option<image::DynamicImage>> {
+    let photo = match session.get() {
+        Some(remote) => {
+            let data: Option<String> = remote.call("fetch_client_photo", &serde_json::json!({ "clientId": client_id })).await?;
+            data.map(|data| libra_manager::photos::decode_base64(&data)).transpose()?
+        }
+        None => libra_manager::photos::fetch_photo(&mut *database.get()?, client_id)?
+    };
+    Ok(photo.map(|photo| image::load_from_memory(&photo)).transpose()?)
+}
+
+/// What the QR code of a badge carries, signed with the key of the database the desk works on.
+async fn badge_signature(database: &DatabaseConnection, session: &Session, client_id: &str, library_name: &str) -> SerializedResult<String> {
+    match session.get() {
+        Some(remote) => remote.call("sign_client_badge", &serde_json::json!({ "clientId": client_id, "libraryName": library_name })).await,
+        None => libra_manager::signing::sign_badge(&mut *database.get()?, client_id, library_name)
+    }
+}
+
 #[tauri::command]
-async fn lookup_book(isbn: String) -> SerializedResult<Option<BookData>> {
-    Ok(libra_manager::models::book_api::fetch_book(isbn).await?)
+fn sign_client_badge(database: State<DatabaseConnection>, client_id: String, library_name: String) -> SerializedResult<String> {
+    libra_manager::signing::sign_badge(&mut *database.get()?, &client_id, &library_name)
+}
+
+/// The folder downloads are saved in when none is picked: the one in the settings, or Documents.
+fn download_directory(settings: &Settings) -> SerializedResult<std::path::PathBuf> {
+    if !settings.download_directory.is_empty() {
+        return Ok(std::path::PathBuf::from(&settings.download_directory));
+    }
+    tauri::api::path::document_dir().ok_or_else(|| libra_manager::Error::Validation(vec![libra_manager::validation::ValidationError {
+        field: "downloadDirectory".to_string(),
+        message: "There is no Documents folder to save in".to_string(),
+    }]))
+}
+
+/// Saves `data` under `name` in `target`, or the download folder, and returns the path written.
+fn save_download(settings: &Settings, target: Option<String>, name: &str, extension: &str, data: &[u8]) -> SerializedResult<String> {
+    let directory = download_directory(settings)?;
+    let path = libra_manager::downloads::save(target.as_deref().map(std::path::Path::new), &directory, name, extension, data)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// The badge of a client in `format`, PNG unless given; a PDF is one card of the badge sheet.
+/// Returns where it was saved.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn download_client_badge(database: State<'_, DatabaseConnection>, session: State<'_, Session>, settings_loader: State<'_, SettingsLoader>, template_loader: State<'_, TemplateLoader>, client_id: String, first_name: String, last_name: String, date: NaiveDate, format: Option<OutputFormat>, target: Option<String>) -> SerializedResult<String> {
+    let settings = settings_loader.load()?;
+    let library_name = &settings.library_name;
+    let client_name = format!("{} {}", first_name, last_name);
+    let issued = date.format("%Y-%m-%d").to_string();
+    let values = [("id", client_id.as_str()), ("firstName", &first_name), ("lastName", &last_name), ("date", &issued)];
+    let name = libra_manager::downloads::file_name(&settings.badge_file_name, &values, "badgeFileName")?;
+
+    let photo = badge_photo(&database, &session, &client_id).await?;
+    let signed_id = badge_signature(&database, &session, &client_id, library_name).await?;
+
+    let data = BadgeData { client_id: &client_id, client_name: &client_name, library_name, issued: date, photo: photo.as_ref(), symbology: settings.symbologies.badge, signed_id: &signed_id };
+    let drawing = libra_manager::badges::layout(&template_loader.load()?, &data, template_loader.directory())?;
+    let format = format.unwrap_or_default();
+    let file = drawing.encode(format, (settings.badge_sheet.label_width, settings.badge_sheet.label_height))?;
+
+    save_download(&settings, target, &name, format.extension(), &file)
 }
 
 #[tauri::command]
-fn download_client_badge(settings_loader: State<SettingsLoader>, client_id: String, client_name: String, date: NaiveDate) -> SerializedResult<()> {
-    let library_name = settings_loader.load().unwrap().library_name;
-    let buffer = libra_manager::barcode::create_badge(&client_id, &client_name, &library_name, date);
+fn fetch_badge_template(template_loader: State<TemplateLoader>) -> SerializedResult<String> {
+    template_loader.source()
+}
 
-    let mut documents_path = tauri::api::path::document_dir().unwrap_or(std::path::PathBuf::new());
+#[tauri::command]
+fn save_badge_template(template_loader: State<TemplateLoader>, source: String) -> SerializedResult<()> {
+    template_loader.store(&source)
+}
 
-    documents_path.push(format!("Legitimatie {}.png", client_name));
+/// A badge drawn with `source`, or the saved template when not given, as a base64 PNG.
+#[tauri::command]
+fn preview_badge(settings_loader: State<SettingsLoader>, template_loader: State<TemplateLoader>, source: Option<String>) -> SerializedResult<String> {
+    let template = match source {
+        Some(source) => template_loader.parse(&source)?,
+        None => template_loader.load()?,
+    };
+    let settings = settings_loader.load()?;
+    let client_id = settings.client_id_format.format("CEN", 421);
 
-    buffer.save(&documents_path)?;
+    let image = libra_manager::badges::render_sample(&template, &settings.library_name, &client_id, settings.symbologies.badge, template_loader.directory())?;
+    Ok(libra_manager::photos::encode_base64(&libra_manager::drawing::encode_png(&image)?))
+}
 
-    Ok(())
+#[tauri::command]
+fn upload_client_photo(database: State<DatabaseConnection>, client_id: String, photo: String) -> SerializedResult<()> {
+    let data = libra_manager::photos::decode_base64(&photo)?;
+    libra_manager::photos::upload_photo(&mut *database.get()?, &client_id, &data)
+}
+
+#[tauri::command]
+fn fetch_client_photo(database: State<DatabaseConnection>, client_id: String) -> SerializedResult<Option<String>> {
+    let photo = libra_manager::photos::fetch_photo(&mut *database.get()?, &client_id)?;
+    Ok(photo.as_deref().map(libra_manager::photos::encode_base64))
 }
 
 #[tauri::command]
-fn download_book_isbn(isbn: String) -> SerializedResult<()> {
-    let buffer = libra_manager::barcode::create_isbn(&isbn);
+fn crop_client_photo(database: State<DatabaseConnection>, client_id: String, crop: Crop) -> SerializedResult<()> {
+    libra_manager::photos::crop_photo(&mut *database.get()?, &client_id, crop)
+}
 
-    let mut documents_path = tauri::api::path::document_dir().unwrap_or(std::path::PathBuf::new());
+#[tauri::command]
+fn resize_client_photo(database: State<DatabaseConnection>, client_id: String, width: u32, height: u32) -> SerializedResult<()> {
+    libra_manager::photos::resize_photo(&mut *database.get()?, &client_id, width, height)
+}
 
-    documents_path.push(format!("{}.png", isbn));
-    buffer.save(&documents_path)?;
-    Ok(())
+#[tauri::command]
+fn delete_client_photo(database: State<DatabaseConnection>, client_id: String) -> SerializedResult<()> {
+    libra_manager::photos::delete_photo(&mut *database.get()?, &client_id)
+}
+
+/// The ISBN label of a book in `format`, PNG unless given; a PDF is one label of the ISBN sheet.
+/// Returns where it was saved.
+#[tauri::command]
+fn download_book_isbn(settings_loader: State<SettingsLoader>, isbn: String, format: Option<OutputFormat>, target: Option<String>) -> SerializedResult<String> {
+    let settings = settings_loader.load()?;
+    let isbn = libra_manager::validation::to_isbn13(&isbn).unwrap_or(isbn);
+    let name = libra_manager::downloads::file_name(&settings.isbn_file_name, &[("isbn", &isbn)], "isbnFileName")?;
+    let drawing = libra_manager::barcode::isbn_label(&isbn, settings.symbologies.isbn)?;
+    let format = format.unwrap_or_default();
+    let file = drawing.encode(format, (settings.isbn_sheet.label_width, settings.isbn_sheet.label_height))?;
+
+    save_download(&settings, target, &name, format.extension(), &file)
+}
+
+/// One label of `label` for every book in `books`, tiled on the sheet set for them,
+/// leaving out the first `start` labels of the first sheet.
+#[tauri::command]
+fn download_label_sheet(settings_loader: State<SettingsLoader>, books: Vec<Book>, label: BookLabel, start: Option<usize>, target: Option<String>) -> SerializedResult<String> {
+    let settings = settings_loader.load()?;
+    let labels = books.iter().map(|book| label.render(book, &settings.symbologies)).collect::<SerializedResult<Vec<_>>>()?;
+    let (layout, field) = label.layout(&settings);
+    let pdf = libra_manager::sheets::sheet_pdf(layout, &labels, start.unwrap_or(0), field)?;
+
+    let name = match label {
+        BookLabel::Isbn => "Etichete ISBN",
+        BookLabel::Spine => "Etichete cotor",
+    };
+    save_download(&settings, target, name, "pdf", &pdf)
+}
+
+/// The badges of `clients`, issued today, tiled on the badge sheet.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn download_badge_sheet(database: State<'_, DatabaseConnection>, session: State<'_, Session>, settings_loader: State<'_, SettingsLoader>, template_loader: State<'_, TemplateLoader>, clients: Vec<Client>, start: Option<usize>, target: Option<String>) -> SerializedResult<String> {
+    let settings = settings_loader.load()?;
+    let template = template_loader.load()?;
+    let today = Local::now().date_naive();
+
+    let mut badges = Vec::with_capacity(clients.len());
+    for client in &clients {
+        let photo = badge_photo(&database, &session, &client.id).await?;
+        let signed_id = badge_signature(&database, &session, &client.id, &settings.library_name).await?;
+        let client_name = format!("{} {}", client.first_name, client.last_name);
+        let data = BadgeData { client_id: &client.id, client_name: &client_name, library_name: &settings.library_name, issued: today, photo: photo.as_ref(), symbology: settings.symbologies.badge, signed_id: &signed_id };
+        badges.push(libra_manager::badges::layout(&template, &data, template_loader.directory())?);
+    }
+    let pdf = libra_manager::sheets::sheet_pdf(&settings.badge_sheet, &badges, start.unwrap_or(0), "badgeSheet")?;
+
+    save_download(&settings, target, "Legitimatii", "pdf", &pdf)
+}
+
+#[tauri::command]
+async fn print_labels(database: State<'_, DatabaseConnection>, session: State<'_, Session>, settings_loader: State<'_, SettingsLoader>, mut labels: Vec<PrinterLabel>) -> SerializedResult<()> {
+    let settings = settings_loader.load()?;
+    if settings.symbologies.badge.is_2d() {
+        for label in &mut labels {
+            if let PrinterLabel::Badge { client_id, signed_id, .. } = label {
+                *signed_id = Some(badge_signature(&database, &session, client_id, &settings.library_name).await?);
+            }
+        }
+    }
+    libra_manager::label_printer::print(&settings.label_printer, &settings.symbologies, &labels, &settings.library_name)
+}
+
+/// The book, copy or client a code typed by a keyboard-wedge scanner refers to.
+#[tauri::command]
+fn resolve_scan(database: State<DatabaseConnection>, settings_loader: State<SettingsLoader>, code: String) -> SerializedResult<ScanTarget> {
+    let format = settings_loader.load()?.client_id_format;
+    libra_manager::scans::resolve(&mut *database.get()?, &code, &format)
+}
+
+/// The codes in a camera frame, sent as base64 or as a data URL.
+#[tauri::command]
+fn decode_frame(frame: String) -> SerializedResult<Vec<ScannedCode>> {
+    libra_manager::decoding::decode_file(&libra_manager::photos::decode_base64(&frame)?)
+}
+
+/// The codes in a picture on disk, e.g. a photo of a list of donated books.
+#[tauri::command]
+fn decode_image_file(path: String) -> SerializedResult<Vec<ScannedCode>> {
+    libra_manager::decoding::decode_file(&std::fs::read(path)?)
 }
 
 #[tauri::command]
 fn fetch_counts(database: State<DatabaseConnection>) -> SerializedResult<(i64, i64)> {
-    use libra_manager::schema::books::dsl::*;
-    use libra_manager::schema::clients::dsl::*;
-    use diesel::{QueryDsl, RunQueryDsl};
+    let client = &mut *database.get()?;
+    Ok((book_service::count_books(client)?, client_service::count_clients(client)?))
+}
+
+/// The app data directory, unless `LIBRA_MANAGER_DATA_DIR` points elsewhere so a second
+/// instance can run on the same machine, e.g. to try the server and a thin client locally.
+fn app_data_dir(app: &AppHandle) -> std::path::PathBuf {
+    std::env::var_os("LIBRA_MANAGER_DATA_DIR")
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| app.path_resolver().app_data_dir().unwrap())
+}
+
+fn database_path(app: &AppHandle) -> SerializedResult<std::path::PathBuf> {
+    let settings = app.state::<SettingsLoader>().load()?;
+    if !settings.database_path.is_empty() {
+        return Ok(std::path::PathBuf::from(settings.database_path));
+    }
 
-    let client = &mut *database.client.lock().unwrap();
-    let result_books = books.count().get_result(client)?;
-    let result_clients = clients.count().get_result(client)?;
-    Ok((result_books, result_clients))
+    let mut path = app_data_dir(app);
+    path.push("database");
+    Ok(path)
+}
+
+fn schedule_backups(app: AppHandle, default_directory: std::path::PathBuf) {
+    loop {
+        let settings = match app.state::<SettingsLoader>().load() {
+            Ok(settings) => settings,
+            Err(error) => {
+                eprintln!("Automatic backup skipped: the settings could not be read: {}", error);
+                std::thread::sleep(libra_manager::backup::SCHEDULE_CHECK_INTERVAL);
+                continue;
+            }
+        };
+
+        if settings.backup_interval_hours > 0 {
+            let directory = if settings.backup_directory.is_empty() {
+                default_directory.clone()
+            } else {
+                std::path::PathBuf::from(&settings.backup_directory)
+            };
+            let interval = std::time::Duration::from_secs(settings.backup_interval_hours as u64 * 3600);
+
+            // an encrypted database stays locked until the passphrase is entered
+            if let Some(database) = app.try_state::<DatabaseConnection>() {
+                let _ = libra_manager::backup::scheduled_backup(&database.path, &directory, interval, settings.backup_retention as usize, database.key.as_deref());
+            }
+        }
+
+        std::thread::sleep(libra_manager::backup::SCHEDULE_CHECK_INTERVAL);
+    }
 }
 
 fn main() {
@@ -352,16 +895,21 @@ fn main() {
             get_settings,
             save_settings,
             login,
+            select_branch,
             fetch_books,
             fetch_book,
             create_book,
             delete_book,
+            restore_book,
+            purge_book,
             update_book,
             fetch_borrowers,
             fetch_clients,
             fetch_client,
             create_client,
             delete_client,
+            restore_client,
+            purge_client,
             update_client,
             fetch_borrowed_books,
             is_book_available,
@@ -373,23 +921,97 @@ fn main() {
             fetch_users,
             create_user,
             delete_user,
+            fetch_branches,
+            create_branch,
+            update_branch,
+            delete_branch,
+            fetch_holdings,
+            transfer_copies,
+            fetch_transfers,
+            connect_server,
+            disconnect_server,
+            remote_invoke,
             lookup_book,
+            bulk_lookup,
+            fetch_staged_books,
+            update_staged_book,
+            delete_staged_book,
+            commit_staged_books,
+            import_csv,
+            export_csv,
+            import_marc,
+            export_marc,
+            export_sync_bundle,
+            import_sync_bundle,
+            fetch_sync_conflicts,
+            resolve_sync_conflict,
+            reset_replica_id,
+            backup_database,
+            restore_database,
+            repair_integrity,
+            is_database_locked,
+            unlock_database,
+            startup_error,
+            recover_from_backup,
+            open_database_file,
+            open_read_only,
+            encrypt_database,
             download_client_badge,
+            sign_client_badge,
+            fetch_badge_template,
+            save_badge_template,
+            preview_badge,
+            upload_client_photo,
+            fetch_client_photo,
+            crop_client_photo,
+            resize_client_photo,
+            delete_client_photo,
             download_book_isbn,
+            download_label_sheet,
+            download_badge_sheet,
+            print_labels,
+            resolve_scan,
+            decode_frame,
+            decode_image_file,
             fetch_counts
         ]).
         setup(|app| {
-            let mut app_data_path = app.path_resolver().app_data_dir().unwrap();
+            let app_data_path = app_data_dir(&app.handle());
 
             if !app_data_path.exists() {
                 std::fs::create_dir_all(&app_data_path).unwrap();
             }
 
             app.manage(SettingsLoader::from(&app_data_path));
+            app.manage(TemplateLoader::from(&app_data_path));
+            app.manage(StartupFailure::default());
+            app.manage(Session::default());
+
+            let backups_path = app_data_path.join("backups");
+            let handle = app.handle();
+            let opened = database_path(&handle).and_then(|database_path| {
+                libra_manager::encryption::finish_pending(&database_path)?;
+
+                // encrypted databases wait in the recovery window for `unlock_database`
+                if libra_manager::encryption::is_encrypted(&database_path)? {
+                    return Ok(false);
+                }
+                app.manage(DatabaseConnection::from(database_path.to_str().unwrap())?);
+                start_server(&handle);
+                Ok(true)
+            });
+
+            match opened {
+                Ok(true) => {}
+                Ok(false) => open_recovery_window(&handle)?,
+                Err(error) => {
+                    eprintln!("LibraManager could not open the database: {}", error);
+                    app.state::<StartupFailure>().set(Some(error.to_string()));
+                    open_recovery_window(&handle)?;
+                }
+            }
 
-            app_data_path.push("database");
-
-            app.manage(DatabaseConnection::from(app_data_path.to_str().unwrap()));
+            std::thread::spawn(move || schedule_backups(handle, backups_path));
 
             Ok(())
         })
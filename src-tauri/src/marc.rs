@@ -0,0 +1,415 @@
+use std::io::Write;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+use crate::models::database::{Book, BookDetails, StagedBook};
+use crate::{validation, Error, SerializedResult};
+
+const FIELD_TERMINATOR: u8 = 0x1E;
+const RECORD_TERMINATOR: u8 = 0x1D;
+const SUBFIELD_DELIMITER: u8 = 0x1F;
+const LEADER_LENGTH: usize = 24;
+const DIRECTORY_ENTRY_LENGTH: usize = 12;
+/// Largest field length and offset the four and five digits of a directory entry can hold.
+const MAX_FIELD_LENGTH: usize = 9999;
+const MAX_FIELD_START: usize = 99999;
+const MARCXML_NAMESPACE: &str = "http://www.loc.gov/MARC21/slim";
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum MarcFormat {
+    Iso2709,
+    MarcXml,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    Control { tag: String, value: String },
+    Data { tag: String, indicators: [char; 2], subfields: Vec<(char, String)> },
+}
+
+impl Field {
+    pub fn tag(&self) -> &str {
+        match self {
+            Field::Control { tag, .. } | Field::Data { tag, .. } => tag,
+        }
+    }
+
+    fn subfield(&self, code: char) -> Option<&str> {
+        match self {
+            Field::Data { subfields, .. } => subfields.iter().find(|(c, _)| *c == code).map(|(_, value)| value.as_str()),
+            Field::Control { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub leader: String,
+    pub fields: Vec<Field>,
+}
+
+/// The parts of a bibliographic record the catalog cares about.
+#[derive(Serialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogRecord {
+    pub isbn: String,
+    pub title: String,
+    pub author: String,
+    pub edition: Option<String>,
+    pub publication: Option<String>,
+    pub extent: Option<String>,
+    pub subjects: Vec<String>,
+}
+
+impl CatalogRecord {
+    /// The record staged for review before it joins the catalog, one copy of it.
+    pub fn to_staged_book(&self) -> StagedBook {
+        StagedBook {
+            isbn: self.isbn.clone(),
+            title: self.title.clone(),
+            author: self.author.clone(),
+            items: 1,
+            status: "found".to_string(),
+            edition: self.edition.clone(),
+            publication: self.publication.clone(),
+            extent: self.extent.clone(),
+            subjects: self.subjects.join("\n"),
+        }
+    }
+}
+
+/// Strips the ISBD punctuation cataloguers leave at the end of subfields ("Title /", "Author,").
+fn trim_punctuation(value: &str) -> String {
+    value.trim().trim_end_matches([' ', '/', ':', ';', ',', '=', '.']).trim().to_string()
+}
+
+impl Record {
+    fn first(&self, tags: &[&str]) -> Option<&Field> {
+        tags.iter().find_map(|tag| self.fields.iter().find(|field| field.tag() == *tag))
+    }
+
+    pub fn to_catalog_record(&self) -> CatalogRecord {
+        // the first valid one, records often list the ISBNs of other bindings too
+        let isbn = self.fields.iter()
+            .filter(|field| field.tag() == "020")
+            .filter_map(|field| field.subfield('a')?.split_whitespace().next())
+            .find_map(validation::normalize_isbn)
+            .unwrap_or_default();
+
+        let title = self.first(&["245"]).map(|field| {
+            let main = field.subfield('a').map(trim_punctuation).unwrap_or_default();
+            match field.subfield('b').map(trim_punctuation) {
+                Some(remainder) if !remainder.is_empty() => format!("{}: {}", main, remainder),
+                _ => main
+            }
+        }).unwrap_or_default();
+
+        let author = self.first(&["100"]).and_then(|field| field.subfield('a')).map(trim_punctuation).unwrap_or_default();
+
+        let edition = self.first(&["250"]).and_then(|field| field.subfield('a')).map(trim_punctuation);
+
+        let publication = self.first(&["264", "260"]).map(|field| {
+            ['a', 'b', 'c'].iter()
+                .filter_map(|code| field.subfield(*code).map(trim_punctuation))
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<String>>()
+                .join(", ")
+        }).filter(|value| !value.is_empty());
+
+        let extent = self.first(&["300"]).and_then(|field| field.subfield('a')).map(trim_punctuation);
+
+        let subjects = self.fields.iter()
+            .filter(|field| field.tag() == "650")
+            .filter_map(|field| field.subfield('a').map(trim_punctuation))
+            .collect();
+
+        CatalogRecord { isbn, title, author, edition, publication, extent, subjects }
+    }
+
+    pub fn from_book(book: &Book, details: Option<&BookDetails>) -> Self {
+        let data = |tag: &str, indicators: [char; 2], code: char, value: &str| Field::Data {
+            tag: tag.to_string(),
+            indicators,
+            subfields: vec![(code, value.to_string())],
+        };
+
+        let mut fields = vec![
+            Field::Control { tag: "001".to_string(), value: book.isbn.clone() },
+            data("020", [' ', ' '], 'a', &book.isbn),
+            data("100", ['1', ' '], 'a', &book.author),
+            data("245", ['1', '0'], 'a', &book.title),
+        ];
+        if let Some(details) = details {
+            fields.extend(details.edition.as_deref().map(|edition| data("250", [' ', ' '], 'a', edition)));
+            // kept as one string, so it goes back out as the place of publication alone
+            fields.extend(details.publication.as_deref().map(|publication| data("260", [' ', ' '], 'a', publication)));
+            fields.extend(details.extent.as_deref().map(|extent| data("300", [' ', ' '], 'a', extent)));
+            fields.extend(details.subjects.lines().filter(|subject| !subject.is_empty()).map(|subject| data("650", [' ', '4'], 'a', subject)));
+        }
+
+        Self {
+            // status "n"ew, "a" language material, "m"onograph, "a" for UTF-8
+            leader: "00000nam a2200000 i 4500".to_string(),
+            fields,
+        }
+    }
+}
+
+/// The catalog as MARC records, without archived books.
+pub fn catalog(connection: &mut SqliteConnection) -> SerializedResult<Vec<Record>> {
+    use crate::schema::{book_details, books};
+
+    let rows = books::table
+        .left_join(book_details::table)
+        .filter(books::deletedAt.is_null())
+        .order(books::isbn)
+        .select((Book::as_select(), Option::<BookDetails>::as_select()))
+        .load::<(Book, Option<BookDetails>)>(connection)?;
+    Ok(rows.iter().map(|(book, details)| Record::from_book(book, details.as_ref())).collect())
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Marc(message.to_string())
+}
+
+fn parse_number(bytes: &[u8]) -> SerializedResult<usize> {
+    std::str::from_utf8(bytes).ok()
+        .and_then(|value| value.parse::<usize>().ok())
+        .ok_or_else(|| invalid("non-numeric length in leader or directory"))
+}
+
+fn parse_iso2709_record(bytes: &[u8]) -> SerializedResult<Record> {
+    if bytes.len() < LEADER_LENGTH {
+        return Err(invalid("record shorter than its leader"));
+    }
+
+    let leader = String::from_utf8_lossy(&bytes[..LEADER_LENGTH]).to_string();
+    let base_address = parse_number(&bytes[12..17])?;
+
+    if base_address > bytes.len() || base_address <= LEADER_LENGTH {
+        return Err(invalid("base address of data is out of range"));
+    }
+
+    let directory = &bytes[LEADER_LENGTH..base_address - 1];
+    let mut fields = Vec::new();
+
+    for entry in directory.chunks(DIRECTORY_ENTRY_LENGTH) {
+        if entry.len() != DIRECTORY_ENTRY_LENGTH {
+            return Err(invalid("truncated directory entry"));
+        }
+
+        let tag = String::from_utf8_lossy(&entry[0..3]).to_string();
+        let length = parse_number(&entry[3..7])?;
+        let start = base_address + parse_number(&entry[7..12])?;
+
+        let raw = bytes.get(start..start + length).ok_or_else(|| invalid("field points outside of the record"))?;
+        let raw = raw.strip_suffix(&[FIELD_TERMINATOR]).unwrap_or(raw);
+
+        if tag.starts_with("00") {
+            fields.push(Field::Control { tag, value: String::from_utf8_lossy(raw).to_string() });
+            continue;
+        }
+
+        if raw.len() < 2 {
+            return Err(invalid("data field without indicators"));
+        }
+
+        let indicators = [raw[0] as char, raw[1] as char];
+        let subfields = raw[2..].split(|byte| *byte == SUBFIELD_DELIMITER)
+            .filter(|subfield| !subfield.is_empty())
+            .map(|subfield| (subfield[0] as char, String::from_utf8_lossy(&subfield[1..]).to_string()))
+            .collect();
+
+        fields.push(Field::Data { tag, indicators, subfields });
+    }
+
+    Ok(Record { leader, fields })
+}
+
+/// Parses a file of ISO 2709 records, each one ended by a record terminator.
+pub fn parse_iso2709(bytes: &[u8]) -> SerializedResult<Vec<Record>> {
+    bytes.split(|byte| *byte == RECORD_TERMINATOR)
+        .map(|record| record.trim_ascii_start())
+        .filter(|record| !record.is_empty())
+        .map(parse_iso2709_record)
+        .collect()
+}
+
+fn attribute(element: &quick_xml::events::BytesStart, name: &[u8]) -> SerializedResult<String> {
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        if attribute.key.local_name().as_ref() == name {
+            return Ok(attribute.unescape_value()?.to_string());
+        }
+    }
+    Ok(String::new())
+}
+
+pub fn parse_marcxml(text: &str) -> SerializedResult<Vec<Record>> {
+    let mut reader = Reader::from_str(text);
+    let mut records = Vec::new();
+
+    let mut record: Option<Record> = None;
+    let mut current_tag = String::new();
+    let mut current_text = String::new();
+    let mut in_control = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                current_text.clear();
+                match element.local_name().as_ref() {
+                    b"record" => record = Some(Record { leader: String::new(), fields: Vec::new() }),
+                    b"controlfield" => {
+                        current_tag = attribute(&element, b"tag")?;
+                        in_control = true;
+                    }
+                    b"datafield" => {
+                        let indicator = |value: String| value.chars().next().unwrap_or(' ');
+                        let indicators = [indicator(attribute(&element, b"ind1")?), indicator(attribute(&element, b"ind2")?)];
+                        if let Some(record) = record.as_mut() {
+                            record.fields.push(Field::Data { tag: attribute(&element, b"tag")?, indicators, subfields: Vec::new() });
+                        }
+                    }
+                    b"subfield" => current_tag = attribute(&element, b"code")?,
+                    _ => {}
+                }
+            }
+            Event::Text(text) => current_text.push_str(&text.unescape()?),
+            Event::End(element) => {
+                let Some(record) = record.as_mut() else {
+                    continue;
+                };
+
+                match element.local_name().as_ref() {
+                    b"leader" => record.leader = current_text.clone(),
+                    b"controlfield" if in_control => {
+                        record.fields.push(Field::Control { tag: current_tag.clone(), value: current_text.clone() });
+                        in_control = false;
+                    }
+                    b"subfield" => {
+                        if let Some(Field::Data { subfields, .. }) = record.fields.last_mut() {
+                            subfields.push((current_tag.chars().next().unwrap_or(' '), current_text.clone()));
+                        }
+                    }
+                    b"record" => records.push(record.clone()),
+                    _ => {}
+                }
+                current_text.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(records)
+}
+
+/// Reads either format, telling them apart by whether the content looks like XML.
+pub fn parse(bytes: &[u8]) -> SerializedResult<Vec<Record>> {
+    if bytes.trim_ascii_start().starts_with(b"<") {
+        parse_marcxml(&String::from_utf8_lossy(bytes))
+    } else {
+        parse_iso2709(bytes)
+    }
+}
+
+pub fn write_iso2709<W: Write>(writer: &mut W, record: &Record) -> SerializedResult<()> {
+    let mut directory = Vec::new();
+    let mut data = Vec::new();
+
+    for field in &record.fields {
+        let start = data.len();
+
+        match field {
+            Field::Control { value, .. } => data.extend_from_slice(value.as_bytes()),
+            Field::Data { indicators, subfields, .. } => {
+                data.extend(indicators.iter().map(|indicator| *indicator as u8));
+                for (code, value) in subfields {
+                    data.push(SUBFIELD_DELIMITER);
+                    data.push(*code as u8);
+                    data.extend_from_slice(value.as_bytes());
+                }
+            }
+        }
+        data.push(FIELD_TERMINATOR);
+
+        if data.len() - start > MAX_FIELD_LENGTH {
+            return Err(invalid(&format!("field {} is longer than {} bytes", field.tag(), MAX_FIELD_LENGTH)));
+        }
+        if start > MAX_FIELD_START {
+            return Err(invalid(&format!("field {} starts past byte {}", field.tag(), MAX_FIELD_START)));
+        }
+        directory.extend_from_slice(format!("{:0>3.3}{:04}{:05}", field.tag(), data.len() - start, start).as_bytes());
+    }
+    directory.push(FIELD_TERMINATOR);
+    data.push(RECORD_TERMINATOR);
+
+    let base_address = LEADER_LENGTH + directory.len();
+    let record_length = base_address + data.len();
+
+    if record_length > 99999 {
+        return Err(invalid("record is longer than 99999 bytes"));
+    }
+
+    let mut leader = format!("{:<24.24}", record.leader).into_bytes();
+    leader[0..5].copy_from_slice(format!("{:05}", record_length).as_bytes());
+    leader[12..17].copy_from_slice(format!("{:05}", base_address).as_bytes());
+
+    writer.write_all(&leader)?;
+    writer.write_all(&directory)?;
+    writer.write_all(&data)?;
+    Ok(())
+}
+
+fn escape(value: &str) -> String {
+    quick_xml::escape::escape(value).to_string()
+}
+
+pub fn write_marcxml<W: Write>(writer: &mut W, records: &[Record]) -> SerializedResult<()> {
+    writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(writer, "<collection xmlns=\"{}\">", MARCXML_NAMESPACE)?;
+
+    for record in records {
+        writeln!(writer, "  <record>")?;
+        writeln!(writer, "    <leader>{}</leader>", escape(&record.leader))?;
+
+        for field in &record.fields {
+            match field {
+                Field::Control { tag, value } => {
+                    writeln!(writer, "    <controlfield tag=\"{}\">{}</controlfield>", escape(tag), escape(value))?;
+                }
+                Field::Data { tag, indicators, subfields } => {
+                    writeln!(writer, "    <datafield tag=\"{}\" ind1=\"{}\" ind2=\"{}\">", escape(tag), indicators[0], indicators[1])?;
+                    for (code, value) in subfields {
+                        writeln!(writer, "      <subfield code=\"{}\">{}</subfield>", code, escape(value))?;
+                    }
+                    writeln!(writer, "    </datafield>")?;
+                }
+            }
+        }
+
+        writeln!(writer, "  </record>")?;
+    }
+
+    writeln!(writer, "</collection>")?;
+    Ok(())
+}
+
+pub fn write<W: Write>(writer: &mut W, records: &[Record], format: MarcFormat) -> SerializedResult<()> {
+    match format {
+        MarcFormat::Iso2709 => {
+            for record in records {
+                write_iso2709(writer, record)?;
+            }
+            Ok(())
+        }
+        MarcFormat::MarcXml => write_marcxml(writer, records),
+    }
+}
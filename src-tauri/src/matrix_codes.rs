@@ -0,0 +1,886 @@
+//! QR codes and Data Matrix symbols, the two dimensional barcodes printed on badges and on
+//! spine labels too small for a linear barcode. Both carry bytes, protected by Reed-Solomon
+//! codes so a scratched label still scans.
+
+use crate::validation::ValidationError;
+use crate::{Error, SerializedResult};
+
+/// A grid of dark and light modules, row by row, without the quiet zone around it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    pub width: usize,
+    pub height: usize,
+    modules: Vec<bool>,
+}
+
+impl Matrix {
+    /// A matrix of light modules.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, modules: vec![false; width * height] }
+    }
+
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.width + x] = dark;
+    }
+}
+
+fn too_long(symbol: &str, length: usize) -> Error {
+    Error::Validation(vec![ValidationError {
+        field: "barcode".to_string(),
+        message: format!("{} bytes do not fit in a {}", length, symbol),
+    }])
+}
+
+/// Product in GF(256) built on the reducing polynomial `poly`.
+fn gf_multiply(x: u8, y: u8, poly: u16) -> u8 {
+    let mut product: u16 = 0;
+    for bit in (0..8).rev() {
+        product = (product << 1) ^ ((product >> 7) * poly);
+        product ^= ((y as u16 >> bit) & 1) * x as u16;
+    }
+    product as u8
+}
+
+/// The `degree` error correction codewords of `data`, for a generator polynomial whose
+/// roots are consecutive powers of 2 from `2^first_root`.
+pub fn reed_solomon(data: &[u8], degree: usize, poly: u16, first_root: u32) -> Vec<u8> {
+    let mut root = 1u8;
+    for _ in 0..first_root {
+        root = gf_multiply(root, 2, poly);
+    }
+
+    // coefficients of the generator, highest power first, leaving out the leading 1
+    let mut generator = vec![0u8; degree];
+    generator[degree - 1] = 1;
+    for _ in 0..degree {
+        for index in 0..degree {
+            generator[index] = gf_multiply(generator[index], root, poly);
+            if index + 1 < degree {
+                generator[index] ^= generator[index + 1];
+            }
+        }
+        root = gf_multiply(root, 2, poly);
+    }
+
+    let mut remainder = vec![0u8; degree];
+    for &byte in data {
+        let factor = byte ^ remainder.remove(0);
+        remainder.push(0);
+        for (coefficient, &term) in remainder.iter_mut().zip(&generator) {
+            *coefficient ^= gf_multiply(term, factor, poly);
+        }
+    }
+    remainder
+}
+
+/// Corrects `codewords`, data followed by the `degree` error correction codewords
+/// [`reed_solomon`] gives for them, in place. Up to `degree / 2` wrong codewords are found
+/// and put right; returns whether the codewords are valid afterwards.
+pub fn correct_errors(codewords: &mut [u8], degree: usize, poly: u16, first_root: u32) -> bool {
+    // powers of 2 and their logarithms, twice over so sums of logarithms need no reducing
+    let mut exp = [0u8; 510];
+    let mut log = [0usize; 256];
+    let mut value = 1u8;
+    for power in 0..255 {
+        exp[power] = value;
+        exp[power + 255] = value;
+        log[value as usize] = power;
+        value = gf_multiply(value, 2, poly);
+    }
+    let multiply = |x: u8, y: u8| if x == 0 || y == 0 { 0 } else { exp[log[x as usize] + log[y as usize]] };
+    let divide = |x: u8, y: u8| if x == 0 { 0 } else { exp[log[x as usize] + 255 - log[y as usize]] };
+    let evaluate = |polynomial: &[u8], x: u8| polynomial.iter().rev().fold(0, |sum, &coefficient| multiply(sum, x) ^ coefficient);
+
+    // the codewords are a polynomial with the first as the highest power
+    let syndromes = |codewords: &[u8]| -> Vec<u8> {
+        (0..degree)
+            .map(|index| {
+                let root = exp[(first_root as usize + index) % 255];
+                codewords.iter().fold(0, |sum, &coefficient| multiply(sum, root) ^ coefficient)
+            })
+            .collect()
+    };
+    let found = syndromes(codewords);
+    if found.iter().all(|&syndrome| syndrome == 0) {
+        return true;
+    }
+
+    // Berlekamp-Massey: the shortest error locator polynomial, lowest power first
+    let (mut locator, mut previous) = (vec![1u8], vec![1u8]);
+    let (mut errors, mut shift, mut last) = (0, 1, 1u8);
+    for step in 0..degree {
+        let discrepancy = (1..=errors).fold(found[step], |sum, index| sum ^ multiply(locator.get(index).copied().unwrap_or(0), found[step - index]));
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let factor = divide(discrepancy, last);
+        let mut next = locator.clone();
+        next.resize(next.len().max(previous.len() + shift), 0);
+        for (index, &coefficient) in previous.iter().enumerate() {
+            next[index + shift] ^= multiply(factor, coefficient);
+        }
+        if 2 * errors <= step {
+            errors = step + 1 - errors;
+            previous = std::mem::replace(&mut locator, next);
+            last = discrepancy;
+            shift = 1;
+        } else {
+            locator = next;
+            shift += 1;
+        }
+    }
+    locator.truncate(errors + 1);
+    if errors > degree / 2 {
+        return false;
+    }
+
+    // Forney: the evaluator is the syndromes times the locator, up to the degree
+    let evaluator: Vec<u8> = (0..degree)
+        .map(|power| (0..=power).fold(0, |sum, index| sum ^ multiply(found[index], locator.get(power - index).copied().unwrap_or(0))))
+        .collect();
+    let derivative: Vec<u8> = locator.iter().enumerate().skip(1).map(|(index, &coefficient)| if index % 2 == 1 { coefficient } else { 0 }).collect();
+
+    let length = codewords.len();
+    let mut corrected = 0;
+    for (position, codeword) in codewords.iter_mut().enumerate() {
+        // Chien search: the codeword at `position` is wrong when the locator has a root there
+        let power = length - 1 - position;
+        let inverse = exp[(255 - power % 255) % 255];
+        if evaluate(&locator, inverse) != 0 {
+            continue;
+        }
+        let denominator = evaluate(&derivative, inverse);
+        if denominator == 0 {
+            return false;
+        }
+        let scale = exp[(power as i64 * (1 - first_root as i64)).rem_euclid(255) as usize];
+        *codeword ^= multiply(scale, divide(evaluate(&evaluator, inverse), denominator));
+        corrected += 1;
+    }
+
+    corrected == errors && syndromes(codewords).iter().all(|&syndrome| syndrome == 0)
+}
+
+/// Error correction codewords of a QR code.
+pub fn qr_error_correction(data: &[u8], degree: usize) -> Vec<u8> {
+    reed_solomon(data, degree, 0x11D, 0)
+}
+
+/// Error correction codewords of a Data Matrix symbol.
+pub fn data_matrix_error_correction(data: &[u8], degree: usize) -> Vec<u8> {
+    reed_solomon(data, degree, 0x12D, 1)
+}
+
+/// Total codewords, error correction codewords per block and the number of blocks with
+/// each count of data codewords.
+type QrVersion = (usize, usize, &'static [(usize, usize)]);
+
+/// Codewords of the QR versions 1 to 10 at error correction level M, which still reads with
+/// 15% of the symbol damaged.
+/// The largest QR version [`qr_code`] makes, and [`decode_qr`] reads.
+pub const MAX_QR_VERSION: usize = 10;
+
+const QR_VERSIONS: [QrVersion; MAX_QR_VERSION] = [
+    (26, 10, &[(1, 16)]),
+    (44, 16, &[(1, 28)]),
+    (70, 26, &[(1, 44)]),
+    (100, 18, &[(2, 32)]),
+    (134, 24, &[(2, 43)]),
+    (172, 16, &[(4, 27)]),
+    (196, 18, &[(4, 31)]),
+    (242, 22, &[(2, 38), (2, 39)]),
+    (292, 22, &[(3, 36), (2, 37)]),
+    (346, 26, &[(4, 43), (1, 44)]),
+];
+
+const QR_ALIGNMENT: [&[usize]; 10] = [
+    &[],
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+];
+
+/// The 15 bits of the format information for `data`, the error correction level and the
+/// mask, as a BCH code.
+fn format_bits(data: u32) -> u32 {
+    let mut remainder = data;
+    for _ in 0..10 {
+        remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+    }
+    ((data << 10) | remainder) ^ 0x5412
+}
+
+/// Where each bit of the two copies of the format information goes, lowest bit first: one
+/// around the top left finder, the other split between the other two.
+fn format_positions(size: usize) -> [[(usize, usize); 15]; 2] {
+    let around = std::array::from_fn(|index| match index {
+        0..=5 => (8, index),
+        6 => (8, 7),
+        7 => (8, 8),
+        8 => (7, 8),
+        _ => (14 - index, 8),
+    });
+    let split = std::array::from_fn(|index| if index < 8 { (size - 1 - index, 8) } else { (8, size - 15 + index) });
+    [around, split]
+}
+
+/// Whether data module `x`, `y` is inverted by `mask`.
+fn is_masked(mask: u32, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+struct QrBuilder {
+    matrix: Matrix,
+    /// Modules of the finder, timing and alignment patterns and of the format and version
+    /// information, which carry no data and are never masked.
+    function: Vec<bool>,
+}
+
+impl QrBuilder {
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.matrix.set(x, y, dark);
+        self.function[y * self.matrix.width + x] = true;
+    }
+
+    fn draw_finder(&mut self, center_x: i32, center_y: i32) {
+        let size = self.matrix.width as i32;
+        for dy in -4..=4 {
+            for dx in -4..=4 {
+                let (x, y) = (center_x + dx, center_y + dy);
+                if (0..size).contains(&x) && (0..size).contains(&y) {
+                    let distance = dx.abs().max(dy.abs());
+                    self.set_function(x as usize, y as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, center_x: usize, center_y: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                let (x, y) = ((center_x as i32 + dx) as usize, (center_y as i32 + dy) as usize);
+                self.set_function(x, y, dx.abs().max(dy.abs()) != 1);
+            }
+        }
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.matrix.width;
+        for index in 0..size {
+            self.set_function(6, index, index % 2 == 0);
+            self.set_function(index, 6, index % 2 == 0);
+        }
+
+        self.draw_finder(3, 3);
+        self.draw_finder(size as i32 - 4, 3);
+        self.draw_finder(3, size as i32 - 4);
+
+        let positions = QR_ALIGNMENT[version - 1];
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // the corners taken by the finders
+                let corner = [(0, 0), (0, last), (last, 0)].contains(&(i, j));
+                if !corner {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+
+        // reserves the format information, drawn once the mask is chosen
+        self.draw_format(0);
+        if version >= 7 {
+            self.draw_version(version);
+        }
+    }
+
+    /// Format information of level M with `mask`, a BCH code written twice around the finders.
+    fn draw_format(&mut self, mask: u32) {
+        let bits = format_bits(mask); // level M is 00
+        for positions in format_positions(self.matrix.width) {
+            for (index, (x, y)) in positions.into_iter().enumerate() {
+                self.set_function(x, y, (bits >> index) & 1 != 0);
+            }
+        }
+        let size = self.matrix.width;
+        self.set_function(8, size - 8, true);
+    }
+
+    /// Version information of versions 7 and up, a BCH code next to two of the finders.
+    fn draw_version(&mut self, version: usize) {
+        let mut remainder = version as u32;
+        for _ in 0..12 {
+            remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1F25);
+        }
+        let bits = ((version as u32) << 12) | remainder;
+        let size = self.matrix.width;
+
+        for index in 0..18 {
+            let dark = (bits >> index) & 1 != 0;
+            let (a, b) = (size - 11 + index % 3, index / 3);
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// The data modules in the order codewords fill them: in two module wide columns,
+    /// zigzagging up and down from the right.
+    fn data_positions(&self) -> Vec<(usize, usize)> {
+        let size = self.matrix.width;
+        let mut positions = Vec::new();
+        let mut right = size as i32 - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..size {
+                for offset in 0..2 {
+                    let x = right as usize - offset;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { size - 1 - vertical } else { vertical };
+                    if !self.function[y * size + x] {
+                        positions.push((x, y));
+                    }
+                }
+            }
+            right -= 2;
+        }
+        positions
+    }
+
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        // the remainder bits past the codewords stay light
+        for (index, (x, y)) in self.data_positions().into_iter().take(codewords.len() * 8).enumerate() {
+            self.matrix.set(x, y, (codewords[index >> 3] >> (7 - (index & 7))) & 1 != 0);
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        let size = self.matrix.width;
+        for y in 0..size {
+            for x in 0..size {
+                if is_masked(mask, x, y) && !self.function[y * size + x] {
+                    let dark = self.matrix.is_dark(x, y);
+                    self.matrix.set(x, y, !dark);
+                }
+            }
+        }
+    }
+
+    /// How hard the symbol is to scan: long runs, 2x2 blocks, lookalikes of the finder
+    /// and an uneven share of dark modules all count against it.
+    fn penalty(&self) -> usize {
+        let size = self.matrix.width;
+        let mut penalty = 0;
+        let lines = |transpose: bool| (0..size).map(move |line| {
+            (0..size).map(move |index| if transpose { (line, index) } else { (index, line) })
+        });
+
+        for transpose in [false, true] {
+            for line in lines(transpose) {
+                let colors: Vec<bool> = line.map(|(x, y)| self.matrix.is_dark(x, y)).collect();
+                let mut run = 1;
+                for index in 1..=size {
+                    if index < size && colors[index] == colors[index - 1] {
+                        run += 1;
+                    } else {
+                        if run >= 5 {
+                            penalty += run - 2;
+                        }
+                        run = 1;
+                    }
+                }
+                for window in colors.windows(11) {
+                    let finder = [true, false, true, true, true, false, true];
+                    if (window[..7] == finder && window[7..].iter().all(|dark| !dark))
+                        || (window[4..] == finder && window[..4].iter().all(|dark| !dark)) {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = self.matrix.is_dark(x, y);
+                if color == self.matrix.is_dark(x + 1, y) && color == self.matrix.is_dark(x, y + 1) && color == self.matrix.is_dark(x + 1, y + 1) {
+                    penalty += 3;
+                }
+            }
+        }
+
+        let dark = self.matrix.modules.iter().filter(|&&dark| dark).count();
+        let total = size * size;
+        penalty + (dark * 20).abs_diff(total * 10) / total * 10
+    }
+}
+
+/// Splits `data` in the blocks of `version`, adds their error correction and interleaves them.
+fn qr_codewords(data: &[u8], version: usize) -> Vec<u8> {
+    let (_, ecc_per_block, groups) = QR_VERSIONS[version - 1];
+    let mut blocks = Vec::new();
+    let mut rest = data;
+    for &(count, length) in groups {
+        for _ in 0..count {
+            let (block, remaining) = rest.split_at(length);
+            blocks.push((block, qr_error_correction(block, ecc_per_block)));
+            rest = remaining;
+        }
+    }
+
+    let longest = blocks.iter().map(|(block, _)| block.len()).max().unwrap_or(0);
+    let mut codewords = Vec::new();
+    for index in 0..longest {
+        codewords.extend(blocks.iter().filter_map(|(block, _)| block.get(index)));
+    }
+    for index in 0..ecc_per_block {
+        codewords.extend(blocks.iter().map(|(_, ecc)| ecc[index]));
+    }
+    codewords
+}
+
+/// Data codewords of `data` in byte mode, filling `capacity` codewords.
+fn qr_data(data: &[u8], version: usize, capacity: usize) -> Vec<u8> {
+    fn push(bits: &mut Vec<bool>, value: usize, length: usize) {
+        for bit in (0..length).rev() {
+            bits.push((value >> bit) & 1 != 0);
+        }
+    }
+
+    let mut bits: Vec<bool> = Vec::with_capacity(capacity * 8);
+    push(&mut bits, 0b0100, 4);
+    push(&mut bits, data.len(), if version < 10 { 8 } else { 16 });
+    for &byte in data {
+        push(&mut bits, byte as usize, 8);
+    }
+    let terminator = (capacity * 8 - bits.len()).min(4);
+    push(&mut bits, 0, terminator);
+    let padding = (8 - bits.len() % 8) % 8;
+    push(&mut bits, 0, padding);
+
+    let mut codewords: Vec<u8> = bits.chunks(8)
+        .map(|byte| byte.iter().fold(0u8, |value, &bit| (value << 1) | bit as u8))
+        .collect();
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if codewords.len() >= capacity {
+            break;
+        }
+        codewords.push(pad);
+    }
+    codewords
+}
+
+/// The smallest QR code, up to version 10 (57x57 modules), holding `data` at level M.
+pub fn qr_code(data: &[u8]) -> SerializedResult<Matrix> {
+    let version = (1..=QR_VERSIONS.len())
+        .find(|&version| {
+            let capacity: usize = QR_VERSIONS[version - 1].2.iter().map(|(count, length)| count * length).sum();
+            let header = if version < 10 { 12 } else { 20 };
+            header + data.len() * 8 <= capacity * 8
+        })
+        .ok_or_else(|| too_long("QR code", data.len()))?;
+
+    let capacity: usize = QR_VERSIONS[version - 1].2.iter().map(|(count, length)| count * length).sum();
+    let codewords = qr_codewords(&qr_data(data, version, capacity), version);
+    debug_assert_eq!(codewords.len(), QR_VERSIONS[version - 1].0);
+
+    let size = version * 4 + 17;
+    let mut builder = QrBuilder { matrix: Matrix::new(size, size), function: vec![false; size * size] };
+    builder.draw_function_patterns(version);
+    builder.draw_codewords(&codewords);
+
+    let mut best = (usize::MAX, 0);
+    for mask in 0..8 {
+        builder.apply_mask(mask);
+        builder.draw_format(mask);
+        best = best.min((builder.penalty(), mask));
+        builder.apply_mask(mask);
+    }
+    builder.apply_mask(best.1);
+    builder.draw_format(best.1);
+
+    Ok(builder.matrix)
+}
+
+/// Characters of the alphanumeric mode, by their value.
+const QR_ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// The bytes of the segments in the data codewords of a QR code of `version`, in byte,
+/// numeric or alphanumeric mode.
+fn qr_segments(data: &[u8], version: usize) -> Option<Vec<u8>> {
+    let mut bits = data.iter().flat_map(|&byte| (0..8).rev().map(move |bit| (byte >> bit) & 1 != 0));
+    let mut read = |length: usize| (0..length).try_fold(0usize, |value, _| bits.next().map(|bit| value << 1 | bit as usize));
+
+    let mut text = Vec::new();
+    loop {
+        match read(4) {
+            // the terminator, or codewords too short to hold another segment
+            None | Some(0) => return Some(text),
+            Some(0b0100) => {
+                let count = read(if version < 10 { 8 } else { 16 })?;
+                for _ in 0..count {
+                    text.push(read(8)? as u8);
+                }
+            }
+            Some(0b0001) => {
+                let mut count = read(if version < 10 { 10 } else { 12 })?;
+                while count > 0 {
+                    let digits = count.min(3);
+                    let value = read([4, 7, 10][digits - 1])?;
+                    if value >= 10usize.pow(digits as u32) {
+                        return None;
+                    }
+                    text.extend(format!("{:0width$}", value, width = digits).bytes());
+                    count -= digits;
+                }
+            }
+            Some(0b0010) => {
+                let mut count = read(if version < 10 { 9 } else { 11 })?;
+                while count > 0 {
+                    if count == 1 {
+                        text.push(*QR_ALPHANUMERIC.get(read(6)?)?);
+                        break;
+                    }
+                    let value = read(11)?;
+                    text.push(*QR_ALPHANUMERIC.get(value / 45)?);
+                    text.push(QR_ALPHANUMERIC[value % 45]);
+                    count -= 2;
+                }
+            }
+            // an ECI designator; the bytes are taken as UTF-8 whatever it says
+            Some(0b0111) => {
+                read(8)?;
+            }
+            Some(_) => return None,
+        }
+    }
+}
+
+/// The bytes a QR code carries, read off its modules with errors corrected, or `None`
+/// when `matrix` is not a QR code of the versions and level [`qr_code`] makes or is too
+/// damaged to read.
+pub fn decode_qr(matrix: &Matrix) -> Option<Vec<u8>> {
+    let size = matrix.width;
+    if matrix.height != size || size < 21 || !(size - 17).is_multiple_of(4) {
+        return None;
+    }
+    let version = (size - 17) / 4;
+    let &(total, ecc_per_block, groups) = QR_VERSIONS.get(version - 1)?;
+
+    // the format information closest to either copy, which has to be of level M
+    let copies = format_positions(size).map(|positions| {
+        positions.iter().enumerate().fold(0u32, |bits, (index, &(x, y))| bits | (matrix.is_dark(x, y) as u32) << index)
+    });
+    let distance = |data: u32| copies.iter().map(|bits| (bits ^ format_bits(data)).count_ones()).min().unwrap_or(u32::MAX);
+    let data = (0..32).min_by_key(|&data| distance(data))?;
+    if distance(data) > 3 || data >> 3 != 0 {
+        return None;
+    }
+    let mask = data & 7;
+
+    let mut builder = QrBuilder { matrix: Matrix::new(size, size), function: vec![false; size * size] };
+    builder.draw_function_patterns(version);
+    let bits: Vec<bool> = builder.data_positions().into_iter().map(|(x, y)| matrix.is_dark(x, y) != is_masked(mask, x, y)).collect();
+    let mut codewords = bits.chunks_exact(8).take(total).map(|byte| byte.iter().fold(0u8, |value, &bit| (value << 1) | bit as u8));
+
+    // the blocks were interleaved codeword by codeword
+    let lengths: Vec<usize> = groups.iter().flat_map(|&(count, length)| std::iter::repeat_n(length, count)).collect();
+    let mut blocks: Vec<Vec<u8>> = lengths.iter().map(|&length| Vec::with_capacity(length + ecc_per_block)).collect();
+    let longest = lengths.iter().copied().max()?;
+    for index in 0..longest {
+        for (block, &length) in blocks.iter_mut().zip(&lengths) {
+            if index < length {
+                block.push(codewords.next()?);
+            }
+        }
+    }
+    for _ in 0..ecc_per_block {
+        for block in blocks.iter_mut() {
+            block.push(codewords.next()?);
+        }
+    }
+
+    let mut data = Vec::with_capacity(total);
+    for (mut block, length) in blocks.into_iter().zip(lengths) {
+        if !correct_errors(&mut block, ecc_per_block, 0x11D, 0) {
+            return None;
+        }
+        data.extend_from_slice(&block[..length]);
+    }
+    qr_segments(&data, version)
+}
+
+/// Square Data Matrix ECC 200 sizes: modules on a side, data and error correction codewords.
+pub const DATA_MATRIX_SIZES: [(usize, usize, usize); 13] = [
+    (10, 3, 5),
+    (12, 5, 7),
+    (14, 8, 10),
+    (16, 12, 12),
+    (18, 18, 14),
+    (20, 22, 18),
+    (22, 30, 20),
+    (24, 36, 24),
+    (26, 44, 28),
+    (32, 62, 36),
+    (36, 86, 42),
+    (40, 114, 48),
+    (44, 144, 56),
+];
+
+/// Data codewords in ASCII encodation: pairs of digits take one codeword, bytes above 127
+/// two.
+fn data_matrix_ascii(data: &[u8]) -> Vec<u8> {
+    let mut codewords = Vec::with_capacity(data.len());
+    let mut index = 0;
+    while index < data.len() {
+        let byte = data[index];
+        match data.get(index + 1) {
+            Some(&next) if byte.is_ascii_digit() && next.is_ascii_digit() => {
+                codewords.push(130 + (byte - b'0') * 10 + (next - b'0'));
+                index += 2;
+                continue;
+            }
+            _ => {}
+        }
+        if byte > 127 {
+            // upper shift
+            codewords.push(235);
+            codewords.push(byte - 127);
+        } else {
+            codewords.push(byte + 1);
+        }
+        index += 1;
+    }
+    codewords
+}
+
+/// Places the codewords in the mapping matrix following annex F of ISO/IEC 16022, each
+/// entry `10 * codeword + bit` counted from 1, or 1 for the fixed dark corner module.
+struct Placement {
+    rows: i32,
+    columns: i32,
+    entries: Vec<usize>,
+}
+
+impl Placement {
+    fn module(&mut self, mut row: i32, mut column: i32, codeword: usize, bit: usize) {
+        if row < 0 {
+            row += self.rows;
+            column += 4 - ((self.rows + 4) % 8);
+        }
+        if column < 0 {
+            column += self.columns;
+            row += 4 - ((self.columns + 4) % 8);
+        }
+        self.entries[(row * self.columns + column) as usize] = 10 * codeword + bit;
+    }
+
+    fn utah(&mut self, row: i32, column: i32, codeword: usize) {
+        let modules = [(-2, -2), (-2, -1), (-1, -2), (-1, -1), (-1, 0), (0, -2), (0, -1), (0, 0)];
+        for (bit, (dr, dc)) in modules.into_iter().enumerate() {
+            self.module(row + dr, column + dc, codeword, bit + 1);
+        }
+    }
+
+    fn corner(&mut self, modules: [(i32, i32); 8], codeword: usize) {
+        for (bit, (row, column)) in modules.into_iter().enumerate() {
+            let row = if row < 0 { self.rows + row } else { row };
+            let column = if column < 0 { self.columns + column } else { column };
+            self.module(row, column, codeword, bit + 1);
+        }
+    }
+
+    fn is_free(&self, row: i32, column: i32) -> bool {
+        self.entries[(row * self.columns + column) as usize] == 0
+    }
+
+    fn fill(rows: usize, columns: usize) -> Self {
+        let mut placement = Self { rows: rows as i32, columns: columns as i32, entries: vec![0; rows * columns] };
+        let (rows, columns) = (rows as i32, columns as i32);
+        let (mut codeword, mut row, mut column) = (1, 4, 0);
+
+        loop {
+            if row == rows && column == 0 {
+                placement.corner([(-1, 0), (-1, 1), (-1, 2), (0, -2), (0, -1), (1, -1), (2, -1), (3, -1)], codeword);
+                codeword += 1;
+            }
+            if row == rows - 2 && column == 0 && columns % 4 != 0 {
+                placement.corner([(-3, 0), (-2, 0), (-1, 0), (0, -4), (0, -3), (0, -2), (0, -1), (1, -1)], codeword);
+                codeword += 1;
+            }
+            if row == rows - 2 && column == 0 && columns % 8 == 4 {
+                placement.corner([(-3, 0), (-2, 0), (-1, 0), (0, -2), (0, -1), (1, -1), (2, -1), (3, -1)], codeword);
+                codeword += 1;
+            }
+            if row == rows + 4 && column == 2 && columns % 8 == 0 {
+                placement.corner([(-1, 0), (-1, -1), (0, -3), (0, -2), (0, -1), (1, -3), (1, -2), (1, -1)], codeword);
+                codeword += 1;
+            }
+
+            // up and to the right
+            loop {
+                if row < rows && column >= 0 && placement.is_free(row, column) {
+                    placement.utah(row, column, codeword);
+                    codeword += 1;
+                }
+                row -= 2;
+                column += 2;
+                if !(row >= 0 && column < columns) {
+                    break;
+                }
+            }
+            row += 1;
+            column += 3;
+
+            // down and to the left
+            loop {
+                if row >= 0 && column < columns && placement.is_free(row, column) {
+                    placement.utah(row, column, codeword);
+                    codeword += 1;
+                }
+                row += 2;
+                column -= 2;
+                if !(row < rows && column >= 0) {
+                    break;
+                }
+            }
+            row += 3;
+            column += 1;
+
+            if !(row < rows || column < columns) {
+                break;
+            }
+        }
+
+        let last = (rows * columns - 1) as usize;
+        if placement.entries[last] == 0 {
+            placement.entries[last] = 1;
+            placement.entries[last - columns as usize - 1] = 1;
+        }
+        placement
+    }
+}
+
+/// The smallest square Data Matrix ECC 200 symbol, up to 44x44 modules, holding `data`.
+pub fn data_matrix(data: &[u8]) -> SerializedResult<Matrix> {
+    let mut codewords = data_matrix_ascii(data);
+    let &(size, capacity, ecc) = DATA_MATRIX_SIZES.iter()
+        .find(|(_, capacity, _)| *capacity >= codewords.len())
+        .ok_or_else(|| too_long("Data Matrix", data.len()))?;
+
+    if codewords.len() < capacity {
+        codewords.push(129);
+    }
+    while codewords.len() < capacity {
+        // pads after the first are scrambled by their position
+        let position = codewords.len() + 1;
+        let pad = 129 + (149 * position) % 253 + 1;
+        codewords.push(if pad > 254 { pad - 254 } else { pad } as u8);
+    }
+    let ecc = data_matrix_error_correction(&codewords, ecc);
+    codewords.extend(ecc);
+
+    // symbols from 32x32 up are split in 2x2 regions, each with its own finder and timing
+    let regions = if size >= 32 { 2 } else { 1 };
+    let region = (size - 2 * regions) / regions;
+    let placement = Placement::fill(region * regions, region * regions);
+
+    let mut matrix = Matrix::new(size, size);
+    for region_row in 0..regions {
+        for region_column in 0..regions {
+            let (top, left) = (region_row * (region + 2), region_column * (region + 2));
+            for index in 0..region + 2 {
+                // solid L on the left and the bottom, alternating on the top and the right
+                matrix.set(left, top + index, true);
+                matrix.set(left + index, top + region + 1, true);
+                matrix.set(left + index, top, index % 2 == 0);
+                matrix.set(left + region + 1, top + index, index % 2 == 1);
+            }
+        }
+    }
+
+    let columns = region * regions;
+    for (index, &entry) in placement.entries.iter().enumerate() {
+        let (row, column) = (index / columns, index % columns);
+        let dark = match entry {
+            1 => true,
+            0 => false,
+            entry => (codewords[entry / 10 - 1] >> (8 - entry % 10)) & 1 != 0,
+        };
+        let y = 1 + row + 2 * (row / region);
+        let x = 1 + column + 2 * (column / region);
+        matrix.set(x, y, dark);
+    }
+
+    Ok(matrix)
+}
+
+/// The bytes of data codewords in ASCII encodation, up to the first pad.
+fn data_matrix_text(codewords: &[u8]) -> Option<Vec<u8>> {
+    let mut text = Vec::with_capacity(codewords.len());
+    let mut upper = false;
+    for &codeword in codewords {
+        match codeword {
+            129 => break,
+            1..=128 => {
+                text.push(codeword - 1 + if upper { 128 } else { 0 });
+                upper = false;
+            }
+            130..=229 => {
+                let pair = codeword - 130;
+                text.extend([b'0' + pair / 10, b'0' + pair % 10]);
+            }
+            235 => upper = true,
+            // other encodations, which [`data_matrix`] never uses
+            _ => return None,
+        }
+    }
+    Some(text)
+}
+
+/// The bytes a Data Matrix symbol carries, read off its modules with errors corrected, or
+/// `None` when `matrix` is not one of the sizes [`data_matrix`] makes or is too damaged to read.
+pub fn decode_data_matrix(matrix: &Matrix) -> Option<Vec<u8>> {
+    let size = matrix.width;
+    let &(_, capacity, ecc) = DATA_MATRIX_SIZES.iter().find(|(side, ..)| *side == size && matrix.height == size)?;
+    let regions = if size >= 32 { 2 } else { 1 };
+    let region = (size - 2 * regions) / regions;
+    let columns = region * regions;
+
+    let mut codewords = vec![0u8; capacity + ecc];
+    for (index, &entry) in Placement::fill(columns, columns).entries.iter().enumerate() {
+        // free modules and the fixed dark corner carry nothing
+        if entry < 10 {
+            continue;
+        }
+        let (row, column) = (index / columns, index % columns);
+        if matrix.is_dark(1 + column + 2 * (column / region), 1 + row + 2 * (row / region)) {
+            *codewords.get_mut(entry / 10 - 1)? |= 1 << (8 - entry % 10);
+        }
+    }
+
+    if !correct_errors(&mut codewords, ecc, 0x12D, 1) {
+        return None;
+    }
+    data_matrix_text(&codewords[..capacity])
+}
@@ -16,7 +16,10 @@ pub mod database {
         #[diesel(column_name = lastName)]
         pub last_name: String,
         pub role: String,
-
+        /// Branch the user last logged in at.
+        #[diesel(column_name = branchID)]
+        #[serde(default)]
+        pub branch_id: Option<i32>,
     }
 
     #[derive(Identifiable, AsChangeset, Deserialize)]
@@ -42,9 +45,13 @@ pub mod database {
         pub title: String,
         pub author: String,
         pub items: i32,
+        /// Set when the book is archived instead of deleted.
+        #[diesel(column_name = deletedAt)]
+        #[serde(default, rename = "deletedAt")]
+        pub deleted_at: Option<chrono::NaiveDateTime>,
     }
 
-    #[derive(Queryable, Selectable, Serialize, Insertable, Deserialize, Identifiable, AsChangeset)]
+    #[derive(Queryable, Selectable, Serialize, Insertable, Deserialize, Identifiable, AsChangeset, Clone)]
     #[diesel(table_name = crate::schema::clients)]
     #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
     #[serde(rename_all = "camelCase")]
@@ -56,6 +63,10 @@ pub mod database {
         pub last_name: String,
         pub email: String,
         pub phone: String,
+        /// Set when the client is archived instead of deleted.
+        #[diesel(column_name = deletedAt)]
+        #[serde(default)]
+        pub deleted_at: Option<chrono::NaiveDateTime>,
     }
 
     #[derive(Queryable, Selectable, Associations, Serialize, Identifiable)]
@@ -75,6 +86,8 @@ pub mod database {
         #[diesel(column_name = endDate)]
         pub end_date: chrono::NaiveDate,
         pub returned: bool,
+        #[diesel(column_name = branchID)]
+        pub branch_id: i32,
     }
 
     #[derive(Insertable, Deserialize)]
@@ -90,6 +103,107 @@ pub mod database {
         #[diesel(column_name = endDate)]
         pub end_date: chrono::NaiveDate,
         pub returned: bool,
+        #[diesel(column_name = branchID)]
+        pub branch_id: i32,
+    }
+
+    #[derive(Queryable, Selectable, Serialize, Insertable, Deserialize, Identifiable, AsChangeset, Clone)]
+    #[diesel(table_name = crate::schema::staged_books)]
+    #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+    #[diesel(primary_key(isbn))]
+    pub struct StagedBook {
+        pub isbn: String,
+        pub title: String,
+        pub author: String,
+        pub items: i32,
+        pub status: String,
+        /// What a MARC record gave besides the title and author, kept as [`BookDetails`].
+        #[serde(default)]
+        pub edition: Option<String>,
+        #[serde(default)]
+        pub publication: Option<String>,
+        #[serde(default)]
+        pub extent: Option<String>,
+        #[serde(default)]
+        pub subjects: String,
+    }
+
+    /// What a MARC record says about a book besides its title and author.
+    #[derive(Queryable, Selectable, Serialize, Insertable, Clone, Debug, Default, PartialEq)]
+    #[diesel(table_name = crate::schema::book_details)]
+    #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+    pub struct BookDetails {
+        pub isbn: String,
+        pub edition: Option<String>,
+        /// Place, publisher and date.
+        pub publication: Option<String>,
+        /// Pages and illustrations.
+        pub extent: Option<String>,
+        /// One subject heading per line.
+        pub subjects: String,
+    }
+
+    #[derive(Queryable, Selectable, Serialize, Deserialize, Identifiable, AsChangeset)]
+    #[diesel(table_name = crate::schema::branches)]
+    #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+    pub struct Branch {
+        pub id: i32,
+        /// Short code printed on client IDs and labels.
+        pub code: String,
+        pub name: String,
+    }
+
+    #[derive(Insertable, Deserialize)]
+    #[diesel(table_name = crate::schema::branches)]
+    #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+    pub struct NewBranch {
+        pub code: String,
+        pub name: String,
+    }
+
+    /// Copies of a book shelved at one branch.
+    #[derive(Queryable, Selectable, Serialize, Insertable)]
+    #[diesel(table_name = crate::schema::holdings)]
+    #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+    #[serde(rename_all = "camelCase")]
+    pub struct Holding {
+        #[diesel(column_name = branchID)]
+        pub branch_id: i32,
+        #[diesel(column_name = bookISBN)]
+        pub book_isbn: String,
+        pub items: i32,
+    }
+
+    #[derive(Queryable, Selectable, Serialize)]
+    #[diesel(table_name = crate::schema::transfers)]
+    #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+    #[serde(rename_all = "camelCase")]
+    pub struct Transfer {
+        pub id: i32,
+        #[diesel(column_name = bookISBN)]
+        pub book_isbn: String,
+        #[diesel(column_name = fromBranch)]
+        pub from_branch: i32,
+        #[diesel(column_name = toBranch)]
+        pub to_branch: i32,
+        pub items: i32,
+        pub date: chrono::NaiveDateTime,
+        pub username: String,
+    }
+
+    #[derive(Insertable)]
+    #[diesel(table_name = crate::schema::transfers)]
+    #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+    pub struct NewTransfer<'a> {
+        #[diesel(column_name = bookISBN)]
+        pub book_isbn: &'a str,
+        #[diesel(column_name = fromBranch)]
+        pub from_branch: i32,
+        #[diesel(column_name = toBranch)]
+        pub to_branch: i32,
+        pub items: i32,
+        pub date: chrono::NaiveDateTime,
+        pub username: &'a str,
     }
 
     pub mod joined_data {
@@ -112,12 +226,17 @@ pub mod database {
 }
 
 pub mod book_api {
+    use std::time::Duration;
+
     use reqwest::StatusCode;
     use serde::{Deserialize, Serialize};
     use serde::de::DeserializeOwned;
 
     use crate::SerializedResult;
 
+    /// Delay between two consecutive OpenLibrary requests made by a bulk lookup.
+    pub const LOOKUP_INTERVAL: Duration = Duration::from_millis(1000);
+
     #[derive(Deserialize, Debug)]
     struct OpenLibraryKey {
         pub key: String,
@@ -216,7 +335,23 @@ pub mod book_api {
         pub isbn_13: Vec<String>,
     }
 
+    #[derive(Serialize, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct LookupProgress {
+        pub isbn: String,
+        pub status: String,
+        pub completed: usize,
+        pub total: usize,
+    }
+
     impl BookData {
+        pub fn author_names(&self) -> String {
+            match &self.authors {
+                Some(authors) => authors.iter().map(|author| author.name.as_str()).collect::<Vec<&str>>().join(", "),
+                None => String::new()
+            }
+        }
+
         async fn from(open_library_book_data: OpenLibraryBookData) -> SerializedResult<Self> {
             let authors = open_library_book_data.get_authors().await?;
 
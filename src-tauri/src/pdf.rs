@@ -0,0 +1,241 @@
+//! Just enough of PDF to print labels at exact sizes: pages holding RGB images or
+//! [`Drawing`]s placed in millimetres, compressed with Flate. Text in drawings comes as the
+//! outlines of its glyphs, so nothing here deals with fonts.
+
+use std::fmt::Write as _;
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use image::{Rgba, RgbaImage};
+
+use crate::drawing::{outline_text, Drawing, Segment, Shape};
+
+/// Points in a millimetre; PDF measures everything in points.
+const POINTS_PER_MM: f32 = 72.0 / 25.4;
+
+pub const A4_WIDTH_MM: f32 = 210.0;
+pub const A4_HEIGHT_MM: f32 = 297.0;
+
+/// Where an image goes on a page, in millimetres from the top left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Placement {
+    /// The largest placement with the proportions of `image` that fits within this one,
+    /// centered in it.
+    pub fn fit(&self, image: &RgbaImage) -> Placement {
+        self.fit_size(image.width(), image.height())
+    }
+
+    /// As [`Placement::fit`], for something of `width` x `height` pixels.
+    pub fn fit_size(&self, width: u32, height: u32) -> Placement {
+        let scale = (self.width / width as f32).min(self.height / height as f32);
+        let (width, height) = (width as f32 * scale, height as f32 * scale);
+        Placement { x: self.x + (self.width - width) / 2.0, y: self.y + (self.height - height) / 2.0, width, height }
+    }
+}
+
+struct Page {
+    width: f32,
+    height: f32,
+    /// Images the page draws, by their index.
+    images: Vec<usize>,
+    contents: String,
+}
+
+/// A document being put together; [`PdfWriter::finish`] gives the bytes of the file.
+#[derive(Default)]
+pub struct PdfWriter {
+    /// Body of every image object, numbered from 3 on in the finished file.
+    images: Vec<Vec<u8>>,
+    pages: Vec<Page>,
+}
+
+fn mm(value: f32) -> f32 {
+    value * POINTS_PER_MM
+}
+
+/// A color for the `rg` operator, flattened onto white as images are.
+fn rgb(color: Rgba<u8>) -> String {
+    let [r, g, b, a] = color.0;
+    let blend = |channel: u8| (channel as f32 * a as f32 + 255.0 * (255 - a) as f32) / 255.0 / 255.0;
+    format!("{:.3} {:.3} {:.3} rg", blend(r), blend(g), blend(b))
+}
+
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn image_object(image: &RgbaImage, channels: &[u8], color_space: &str, mask: Option<usize>) -> Vec<u8> {
+    let data = compress(channels);
+    let mask = mask.map(|mask| format!("/SMask {} 0 R ", 3 + mask)).unwrap_or_default();
+    let mut object = format!(
+        "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} /BitsPerComponent 8 {}/Filter /FlateDecode /Length {} >>\nstream\n",
+        image.width(), image.height(), color_space, mask, data.len()
+    ).into_bytes();
+    object.extend(data);
+    object.extend(b"\nendstream");
+    object
+}
+
+impl PdfWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Starts a new page of `width` x `height` millimetres, which further images go on.
+    pub fn add_page(&mut self, width: f32, height: f32) {
+        self.pages.push(Page { width, height, images: Vec::new(), contents: String::new() });
+    }
+
+    fn last_page(&mut self) -> &mut Page {
+        if self.pages.is_empty() {
+            self.add_page(A4_WIDTH_MM, A4_HEIGHT_MM);
+        }
+        self.pages.last_mut().unwrap()
+    }
+
+    /// Draws `image` on the last page, flattened onto white.
+    pub fn add_image(&mut self, image: &RgbaImage, placement: Placement) {
+        let mut pixels = Vec::with_capacity((image.width() * image.height() * 3) as usize);
+        for pixel in image.pixels() {
+            let [r, g, b, a] = pixel.0;
+            let blend = |channel: u8| ((channel as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+            pixels.extend([blend(r), blend(g), blend(b)]);
+        }
+        self.images.push(image_object(image, &pixels, "DeviceRGB", None));
+        let index = self.images.len() - 1;
+
+        let page = self.last_page();
+        page.images.push(index);
+        // images are unit squares scaled into place, with y counted from the bottom
+        writeln!(
+            page.contents, "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do Q",
+            mm(placement.width), mm(placement.height), mm(placement.x), mm(page.height - placement.y - placement.height), index
+        ).unwrap();
+    }
+
+    /// Draws `drawing` on the last page as paths, stretched over `placement`. Its images keep
+    /// their transparency.
+    pub fn add_drawing(&mut self, drawing: &Drawing, placement: Placement) {
+        let mut contents = String::new();
+        let mut images = Vec::new();
+        // pixels of the drawing, y counted from the top, mapped onto the placement
+        writeln!(
+            contents, "q {:.4} 0 0 {:.4} {:.2} {:.2} cm",
+            mm(placement.width) / drawing.width as f32, -mm(placement.height) / drawing.height as f32,
+            mm(placement.x), mm(self.last_page().height - placement.y)
+        ).unwrap();
+
+        for shape in &drawing.shapes {
+            match shape {
+                Shape::Rect { x, y, width, height, color } => {
+                    writeln!(contents, "{} {} {} {} {} re f", rgb(*color), x, y, width, height).unwrap();
+                }
+                Shape::Text { x, y, scale, font, text, color } => {
+                    let segments = outline_text(*x as f32, *y as f32, *scale, font, text);
+                    if segments.is_empty() {
+                        continue;
+                    }
+                    let mut current = ab_glyph::point(0.0, 0.0);
+                    let mut path = String::new();
+                    for segment in segments {
+                        match segment {
+                            Segment::MoveTo(to) => write!(path, "{:.2} {:.2} m ", to.x, to.y),
+                            Segment::LineTo(to) => write!(path, "{:.2} {:.2} l ", to.x, to.y),
+                            Segment::QuadTo(control, to) => {
+                                // PDF only has cubic curves, which hold every quadratic one
+                                let toward = |from: ab_glyph::Point| ab_glyph::point(from.x + (control.x - from.x) * 2.0 / 3.0, from.y + (control.y - from.y) * 2.0 / 3.0);
+                                let (first, second) = (toward(current), toward(to));
+                                write!(path, "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c ", first.x, first.y, second.x, second.y, to.x, to.y)
+                            }
+                            Segment::CubicTo(first, second, to) => {
+                                write!(path, "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c ", first.x, first.y, second.x, second.y, to.x, to.y)
+                            }
+                            Segment::Close => write!(path, "h "),
+                        }.unwrap();
+                        if let Segment::MoveTo(to) | Segment::LineTo(to) | Segment::QuadTo(_, to) | Segment::CubicTo(_, _, to) = segment {
+                            current = to;
+                        }
+                    }
+                    writeln!(contents, "{} {}f", rgb(*color), path).unwrap();
+                }
+                Shape::Image { x, y, image } => {
+                    let colors: Vec<u8> = image.pixels().flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect();
+                    let mask = image.pixels().any(|pixel| pixel[3] < 255).then(|| {
+                        let alpha: Vec<u8> = image.pixels().map(|pixel| pixel[3]).collect();
+                        self.images.push(image_object(image, &alpha, "DeviceGray", None));
+                        self.images.len() - 1
+                    });
+                    self.images.push(image_object(image, &colors, "DeviceRGB", mask));
+                    let index = self.images.len() - 1;
+                    images.push(index);
+                    // the first row of an image is the top of its unit square
+                    writeln!(contents, "q {} 0 0 {} {} {} cm /Im{} Do Q", image.width(), -(image.height() as i64), x, y + image.height() as i64, index).unwrap();
+                }
+            }
+        }
+        contents.push_str("Q\n");
+
+        let page = self.last_page();
+        page.images.extend(images);
+        page.contents.push_str(&contents);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        // 1 is the catalog, 2 the page tree, then the images, then a page and its contents for every page
+        let first_page = 3 + self.images.len();
+        let mut objects: Vec<Vec<u8>> = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                (0..self.pages.len()).map(|page| format!("{} 0 R", first_page + 2 * page)).collect::<Vec<_>>().join(" "),
+                self.pages.len()
+            ).into_bytes(),
+        ];
+        objects.extend(self.images);
+
+        for (number, page) in self.pages.iter().enumerate() {
+            let contents_id = first_page + 2 * number + 1;
+            let resources: String = page.images.iter().map(|index| format!("/Im{} {} 0 R ", index, 3 + index)).collect();
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /XObject << {}>> >> /Contents {} 0 R >>",
+                mm(page.width), mm(page.height), resources, contents_id
+            ).into_bytes());
+
+            let mut stream = format!("<< /Length {} >>\nstream\n", page.contents.len()).into_bytes();
+            stream.extend(page.contents.as_bytes());
+            stream.extend(b"endstream");
+            objects.push(stream);
+        }
+
+        let mut file = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(file.len());
+            file.extend(format!("{} 0 obj\n", index + 1).as_bytes());
+            file.extend(object);
+            file.extend(b"\nendobj\n");
+        }
+
+        let xref = file.len();
+        file.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+        for offset in offsets {
+            file.extend(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        file.extend(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).as_bytes());
+        file
+    }
+}
@@ -0,0 +1,127 @@
+//! Photos of clients, kept in the database as JPEG so they travel with backups and the
+//! server, and printed on the left side of their badges.
+
+use std::io::Cursor;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::Local;
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::Deserialize;
+
+use crate::validation::ValidationError;
+use crate::{Error, SerializedResult};
+
+/// Longest side a photo is stored with; larger uploads are scaled down.
+pub const MAX_SIDE: u32 = 1024;
+const JPEG_QUALITY: u8 = 90;
+
+/// Rectangle to keep of a photo, in pixels of the stored photo.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+fn invalid(field: &str, message: String) -> Error {
+    Error::Validation(vec![ValidationError { field: field.to_string(), message }])
+}
+
+/// Decodes a photo sent by the frontend as base64.
+pub fn decode_base64(data: &str) -> SerializedResult<Vec<u8>> {
+    // data URLs from a file input carry the type in front
+    let data = data.split_once(',').map_or(data, |(_, data)| data);
+    STANDARD.decode(data.trim()).map_err(|error| invalid("photo", error.to_string()))
+}
+
+pub fn encode_base64(photo: &[u8]) -> String {
+    STANDARD.encode(photo)
+}
+
+fn store(connection: &mut SqliteConnection, client_id: &str, image: &DynamicImage) -> SerializedResult<()> {
+    use crate::schema::client_photos;
+
+    let mut photo = Vec::new();
+    JpegEncoder::new_with_quality(Cursor::new(&mut photo), JPEG_QUALITY).encode_image(&image.to_rgb8())?;
+
+    diesel::replace_into(client_photos::table)
+        .values((
+            client_photos::clientID.eq(client_id),
+            client_photos::photo.eq(photo),
+            client_photos::updatedAt.eq(Local::now().naive_local()),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+/// The stored JPEG of a client, if they have a photo.
+pub fn fetch_photo(connection: &mut SqliteConnection, client_id: &str) -> SerializedResult<Option<Vec<u8>>> {
+    use crate::schema::client_photos;
+
+    let photo = client_photos::table.find(client_id)
+        .select(client_photos::photo)
+        .first(connection)
+        .optional()?;
+    Ok(photo)
+}
+
+pub fn load_photo(connection: &mut SqliteConnection, client_id: &str) -> SerializedResult<Option<DynamicImage>> {
+    match fetch_photo(connection, client_id)? {
+        Some(photo) => Ok(Some(image::load_from_memory(&photo)?)),
+        None => Ok(None),
+    }
+}
+
+fn require_photo(connection: &mut SqliteConnection, client_id: &str) -> SerializedResult<DynamicImage> {
+    load_photo(connection, client_id)?.ok_or_else(|| invalid("photo", format!("Client {} has no photo", client_id)))
+}
+
+/// Stores an image file of any format the `image` crate reads as the photo of a client,
+/// replacing the previous one. Photos larger than [`MAX_SIDE`] are scaled down.
+pub fn upload_photo(connection: &mut SqliteConnection, client_id: &str, data: &[u8]) -> SerializedResult<()> {
+    let image = image::load_from_memory(data).map_err(|error| invalid("photo", error.to_string()))?;
+
+    let image = if image.width() > MAX_SIDE || image.height() > MAX_SIDE {
+        image.resize(MAX_SIDE, MAX_SIDE, FilterType::Lanczos3)
+    } else {
+        image
+    };
+    store(connection, client_id, &image)
+}
+
+/// Keeps only `crop` of the stored photo.
+pub fn crop_photo(connection: &mut SqliteConnection, client_id: &str, crop: Crop) -> SerializedResult<()> {
+    let image = require_photo(connection, client_id)?;
+
+    let inside = crop.width > 0 && crop.height > 0
+        && crop.x.checked_add(crop.width).is_some_and(|right| right <= image.width())
+        && crop.y.checked_add(crop.height).is_some_and(|bottom| bottom <= image.height());
+    if !inside {
+        return Err(invalid("crop", format!("The photo is only {}x{} pixels", image.width(), image.height())));
+    }
+
+    store(connection, client_id, &image.crop_imm(crop.x, crop.y, crop.width, crop.height))
+}
+
+/// Scales the stored photo to fit within `width` x `height`, keeping its proportions.
+pub fn resize_photo(connection: &mut SqliteConnection, client_id: &str, width: u32, height: u32) -> SerializedResult<()> {
+    if width == 0 || height == 0 || width > MAX_SIDE || height > MAX_SIDE {
+        return Err(invalid("size", format!("Photos are between 1 and {} pixels on each side", MAX_SIDE)));
+    }
+
+    let image = require_photo(connection, client_id)?;
+    store(connection, client_id, &image.resize(width, height, FilterType::Lanczos3))
+}
+
+pub fn delete_photo(connection: &mut SqliteConnection, client_id: &str) -> SerializedResult<()> {
+    use crate::schema::client_photos;
+
+    diesel::delete(client_photos::table.find(client_id)).execute(connection)?;
+    Ok(())
+}
@@ -0,0 +1,423 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Nullable, Text};
+use diesel::sqlite::Sqlite;
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::branches::DEFAULT_BRANCH;
+use crate::models::database::Book;
+use crate::{photos, signing, validation, Error, SerializedResult};
+
+/// A table whose rows are merged field by field; `key` identifies a row on every installation.
+struct Replicated {
+    table: &'static str,
+    key: &'static str,
+    fields: &'static [&'static str],
+}
+
+/// In merge order, so books and clients exist before the borrows pointing at them.
+const REPLICATED: [Replicated; 3] = [
+    Replicated { table: "books", key: "isbn", fields: &["title", "author", "items", "deletedAt"] },
+    Replicated { table: "clients", key: "id", fields: &["firstName", "lastName", "email", "phone", "deletedAt"] },
+    Replicated { table: "borrows", key: "syncKey", fields: &["clientID", "bookISBN", "startDate", "endDate", "returned", "branchID"] },
+];
+
+/// Stock counts changed on both sides are never merged automatically.
+const REVIEWED: (&str, &str) = ("books", "items");
+
+/// Field of the change logged when a row is deleted.
+const DELETED: &str = "*";
+
+/// One field of one row written by an installation, as logged by the database triggers.
+#[derive(Queryable, Selectable, Insertable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = crate::schema::changes)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    #[diesel(column_name = tableName)]
+    pub table_name: String,
+    #[diesel(column_name = rowKey)]
+    pub row_key: String,
+    pub field: String,
+    pub value: Option<String>,
+    #[diesel(column_name = changedAt)]
+    pub changed_at: String,
+    pub origin: String,
+}
+
+impl Change {
+    /// Orders writes to the same field: the later one wins, ties go to the greater site id.
+    fn stamp(&self) -> (&str, &str) {
+        (&self.changed_at, &self.origin)
+    }
+}
+
+/// The file carried between installations.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bundle {
+    pub site: String,
+    pub created_at: String,
+    /// Newest change the exporting installation held from each site, including its own.
+    pub seen: HashMap<String, String>,
+    pub changes: Vec<Change>,
+    /// Secrets badges are signed with, by installation, so every one accepts the badges of
+    /// the others; bundles have to be carried as carefully as the database itself.
+    #[serde(default)]
+    pub badge_keys: BTreeMap<String, String>,
+    /// Photos are not in the change log, each client's newest is carried whole instead. A
+    /// photo deleted here stays at the other installations until replaced there.
+    #[serde(default)]
+    pub photos: Vec<SyncedPhoto>,
+}
+
+/// The photo of a client, as JPEG in base64.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncedPhoto {
+    pub client_id: String,
+    pub photo: String,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Serialize)]
+#[diesel(table_name = crate::schema::sync_conflicts)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    pub id: i32,
+    #[diesel(column_name = tableName)]
+    pub table_name: String,
+    #[diesel(column_name = rowKey)]
+    pub row_key: String,
+    pub field: String,
+    #[diesel(column_name = localValue)]
+    pub local_value: Option<String>,
+    #[diesel(column_name = remoteValue)]
+    pub remote_value: Option<String>,
+    #[diesel(column_name = remoteChangedAt)]
+    pub remote_changed_at: String,
+    pub origin: String,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    /// Changes in the bundle which this installation had not merged before.
+    pub received: usize,
+    /// Fields updated and rows inserted.
+    pub applied: usize,
+    pub deleted: usize,
+    /// Stock counts left for review.
+    pub conflicts: usize,
+    /// Client photos newer than the ones here, or of clients without one.
+    pub photos: usize,
+    /// Rows which could not be inserted, e.g. borrows of a book the bundle does not contain.
+    pub skipped: Vec<String>,
+}
+
+#[derive(QueryableByName)]
+struct Current {
+    #[diesel(sql_type = Nullable<Text>)]
+    value: Option<String>,
+}
+
+/// The id of this installation in the change log.
+pub fn site(connection: &mut SqliteConnection) -> SerializedResult<String> {
+    use crate::schema::replica;
+
+    Ok(replica::table.select(replica::site).first(connection)?)
+}
+
+/// Gives a copied database its own id, so it can be reconciled with the one it was copied from.
+pub fn reset_site(connection: &mut SqliteConnection) -> SerializedResult<String> {
+    diesel::sql_query("UPDATE replica SET site = lower(hex(randomblob(8)))").execute(connection)?;
+    site(connection)
+}
+
+fn set_applying(connection: &mut SqliteConnection, applying: bool) -> SerializedResult<()> {
+    use crate::schema::replica;
+
+    diesel::update(replica::table).set(replica::applying.eq(applying as i32)).execute(connection)?;
+    Ok(())
+}
+
+/// Writes the whole change log to `path`. Importing a bundle twice is harmless,
+/// so there is no need to remember what was sent where.
+pub fn export_bundle(connection: &mut SqliteConnection, path: &Path) -> SerializedResult<usize> {
+    use crate::schema::{changes, client_photos};
+
+    let changes = changes::table.order(changes::seq).select(Change::as_select()).load(connection)?;
+    let seen = changes::table
+        .group_by(changes::origin)
+        .select((changes::origin, diesel::dsl::max(changes::changedAt)))
+        .load::<(String, Option<String>)>(connection)?
+        .into_iter()
+        .filter_map(|(origin, newest)| newest.map(|newest| (origin, newest)))
+        .collect();
+
+    let site = site(connection)?;
+    let badge_keys = signing::badge_keys(connection, &site)?;
+    let photos = client_photos::table
+        .select((client_photos::clientID, client_photos::photo, client_photos::updatedAt))
+        .load::<(String, Vec<u8>, NaiveDateTime)>(connection)?
+        .into_iter()
+        .map(|(client_id, photo, updated_at)| SyncedPhoto { client_id, photo: photos::encode_base64(&photo), updated_at })
+        .collect();
+    let bundle = Bundle { site, created_at: Utc::now().to_rfc3339(), seen, changes, badge_keys, photos };
+    serde_json::to_writer(BufWriter::new(File::create(path)?), &bundle)?;
+    Ok(bundle.changes.len())
+}
+
+/// The current value of `column` in a row, as text; `None` when the row does not exist.
+fn current(connection: &mut SqliteConnection, spec: &Replicated, column: &str, key: &str) -> SerializedResult<Option<Option<String>>> {
+    let row = diesel::sql_query(format!("SELECT CAST({} AS TEXT) AS value FROM {} WHERE {} = ?", column, spec.table, spec.key))
+        .bind::<Text, _>(key)
+        .get_result::<Current>(connection)
+        .optional()?;
+    Ok(row.map(|row| row.value))
+}
+
+fn latest<'a>(changes: impl Iterator<Item=&'a Change>) -> Option<&'a Change> {
+    changes.max_by(|a, b| a.stamp().cmp(&b.stamp()))
+}
+
+fn record_conflict(connection: &mut SqliteConnection, remote: &Change, local: Option<String>) -> SerializedResult<()> {
+    use crate::schema::sync_conflicts;
+
+    diesel::insert_into(sync_conflicts::table)
+        .values((
+            sync_conflicts::tableName.eq(&remote.table_name),
+            sync_conflicts::rowKey.eq(&remote.row_key),
+            sync_conflicts::field.eq(&remote.field),
+            sync_conflicts::localValue.eq(local),
+            sync_conflicts::remoteValue.eq(&remote.value),
+            sync_conflicts::remoteChangedAt.eq(&remote.changed_at),
+            sync_conflicts::origin.eq(&remote.origin),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Copies of `isbn` on the shelves of the default branch, where merged stock changes land.
+fn default_holding(connection: &mut SqliteConnection, isbn: &str) -> SerializedResult<i32> {
+    use crate::schema::holdings;
+
+    let items = holdings::table.find((DEFAULT_BRANCH, isbn))
+        .select(holdings::items)
+        .first::<i32>(connection)
+        .optional()?;
+    Ok(items.unwrap_or(0))
+}
+
+/// Whether the merged fields of a book, in the order of [`REPLICATED`], make one the catalog
+/// would accept from a form.
+fn is_valid_book(isbn: &str, merged: &[Option<String>]) -> bool {
+    let text = |index: usize| merged[index].clone().unwrap_or_default();
+    let book = Book {
+        isbn: isbn.to_string(),
+        title: text(0),
+        author: text(1),
+        items: text(2).parse().unwrap_or(0),
+        deleted_at: None,
+    };
+    validation::validate_book(&book).is_ok_and(|valid| valid.isbn == isbn)
+}
+
+/// Merges the `incoming` changes of one row into the table, given the row's full history
+/// which already contains them.
+fn merge_row(connection: &mut SqliteConnection, spec: &Replicated, key: &str, incoming: &[&Change], seen: &HashMap<String, String>, report: &mut SyncReport) -> SerializedResult<()> {
+    use crate::schema::changes;
+
+    let history = changes::table
+        .filter(changes::tableName.eq(spec.table))
+        .filter(changes::rowKey.eq(key))
+        .select(Change::as_select())
+        .load(connection)?;
+    let exists = current(connection, spec, spec.key, key)?.is_some();
+
+    // a row deleted on any installation stays deleted
+    if history.iter().any(|change| change.field == DELETED) {
+        if exists {
+            diesel::sql_query(format!("DELETE FROM {} WHERE {} = ?", spec.table, spec.key)).bind::<Text, _>(key).execute(connection)?;
+            report.deleted += 1;
+        }
+        return Ok(());
+    }
+
+    let mut merged = Vec::new();
+    let mut updates = Vec::new();
+
+    for &field in spec.fields {
+        let Some(winner) = latest(history.iter().filter(|change| change.field == field)) else { continue };
+        merged.push(winner.value.clone());
+
+        let Some(remote) = latest(incoming.iter().copied().filter(|change| change.field == field)) else { continue };
+
+        if exists && (spec.table, field) == REVIEWED {
+            let local = current(connection, spec, field, key)?.flatten();
+
+            // a count written here which the other installation had not seen when exporting
+            let concurrent = history.iter().any(|change| {
+                change.field == field
+                    && !incoming.iter().any(|other| other.stamp() == change.stamp())
+                    && seen.get(&change.origin).is_none_or(|newest| change.changed_at > *newest)
+            });
+
+            let delta = remote.value.as_deref().and_then(|value| value.parse::<i32>().ok()).unwrap_or(0)
+                - local.as_deref().and_then(|value| value.parse::<i32>().ok()).unwrap_or(0);
+
+            if local != remote.value && (concurrent || default_holding(connection, key)? + delta < 0) {
+                record_conflict(connection, remote, local)?;
+                report.conflicts += 1;
+                continue;
+            }
+
+            if winner.stamp() == remote.stamp() && delta != 0 {
+                crate::branches::adjust_holding(connection, key, DEFAULT_BRANCH, delta)?;
+            }
+        }
+
+        if winner.stamp() == remote.stamp() {
+            updates.push((field, remote.value.clone()));
+        }
+    }
+
+    if exists {
+        for (field, value) in updates {
+            diesel::sql_query(format!("UPDATE {} SET {} = ? WHERE {} = ?", spec.table, field, spec.key))
+                .bind::<Nullable<Text>, _>(value)
+                .bind::<Text, _>(key)
+                .execute(connection)?;
+            report.applied += 1;
+        }
+        return Ok(());
+    }
+
+    if merged.len() < spec.fields.len() || (spec.table == "books" && !is_valid_book(key, &merged)) {
+        report.skipped.push(format!("{} {}", spec.table, key));
+        return Ok(());
+    }
+
+    let sql = format!(
+        "INSERT INTO {} ({}, {}) VALUES (?{})",
+        spec.table,
+        spec.key,
+        spec.fields.join(", "),
+        ", ?".repeat(spec.fields.len())
+    );
+    let mut query = diesel::sql_query(sql).into_boxed::<Sqlite>().bind::<Text, _>(key.to_string());
+    for value in merged {
+        query = query.bind::<Nullable<Text>, _>(value);
+    }
+
+    // a borrow of a book or client missing here is skipped, not fatal
+    match connection.transaction::<_, diesel::result::Error, _>(|connection| query.execute(connection)) {
+        Ok(_) => report.applied += 1,
+        Err(_) => report.skipped.push(format!("{} {}", spec.table, key)),
+    }
+    Ok(())
+}
+
+/// Keeps the photos of `bundle` newer than the ones here, of clients this database has.
+fn merge_photos(connection: &mut SqliteConnection, photos: &[SyncedPhoto], report: &mut SyncReport) -> SerializedResult<()> {
+    use crate::schema::{client_photos, clients};
+
+    for synced in photos {
+        let known = clients::table.find(&synced.client_id).count().get_result::<i64>(connection)? > 0;
+        let local = client_photos::table.find(&synced.client_id)
+            .select(client_photos::updatedAt)
+            .first::<NaiveDateTime>(connection)
+            .optional()?;
+        if !known || local.is_some_and(|local| local >= synced.updated_at) {
+            continue;
+        }
+        diesel::replace_into(client_photos::table)
+            .values((
+                client_photos::clientID.eq(&synced.client_id),
+                client_photos::photo.eq(photos::decode_base64(&synced.photo)?),
+                client_photos::updatedAt.eq(synced.updated_at),
+            ))
+            .execute(connection)?;
+        report.photos += 1;
+    }
+    Ok(())
+}
+
+/// Merges the bundle at `path` into the database in one transaction. Every field takes the
+/// value of its latest write, deletes win over edits, and stock counts changed on both sides
+/// are kept as they are here and listed in `sync_conflicts` for a librarian to settle.
+pub fn import_bundle(connection: &mut SqliteConnection, path: &Path) -> SerializedResult<SyncReport> {
+    use crate::schema::changes;
+
+    let bundle: Bundle = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+
+    let own = site(connection)?;
+    if bundle.site == own {
+        return Err(Error::Replication("the bundle comes from this database or a copy of it, give the copy a new replica id first".to_string()));
+    }
+
+    connection.transaction::<_, Error, _>(|connection| {
+        set_applying(connection, true)?;
+        signing::add_badge_keys(connection, &own, &bundle.badge_keys)?;
+
+        let mut report = SyncReport::default();
+        let mut fresh = Vec::new();
+
+        // changes already in the log were merged by an earlier import
+        for change in &bundle.changes {
+            if diesel::insert_or_ignore_into(changes::table).values(change).execute(connection)? > 0 {
+                fresh.push(change);
+            }
+        }
+        report.received = fresh.len();
+
+        for spec in &REPLICATED {
+            let mut rows: BTreeMap<&str, Vec<&Change>> = BTreeMap::new();
+            for change in fresh.iter().filter(|change| change.table_name == spec.table) {
+                rows.entry(change.row_key.as_str()).or_default().push(change);
+            }
+
+            for (key, incoming) in rows {
+                merge_row(connection, spec, key, &incoming, &bundle.seen, &mut report)?;
+            }
+        }
+        merge_photos(connection, &bundle.photos, &mut report)?;
+
+        set_applying(connection, false)?;
+        Ok(report)
+    })
+}
+
+pub fn fetch_conflicts(connection: &mut SqliteConnection) -> SerializedResult<Vec<SyncConflict>> {
+    use crate::schema::sync_conflicts;
+
+    Ok(sync_conflicts::table.order(sync_conflicts::id).select(SyncConflict::as_select()).load(connection)?)
+}
+
+/// Settles the stock count of a conflict; the new count is logged as a local change,
+/// so it wins on the other installation at the next sync.
+pub fn resolve_conflict(connection: &mut SqliteConnection, id: i32, items: i32) -> SerializedResult<()> {
+    use crate::schema::{books, sync_conflicts};
+
+    connection.transaction::<_, Error, _>(|connection| {
+        let conflict: SyncConflict = sync_conflicts::table.find(id).select(SyncConflict::as_select()).first(connection)?;
+
+        let previous: i32 = books::table.find(&conflict.row_key).select(books::items).first(connection)?;
+        diesel::update(books::table.find(&conflict.row_key)).set(books::items.eq(items)).execute(connection)?;
+        crate::branches::adjust_holding(connection, &conflict.row_key, DEFAULT_BRANCH, items - previous)?;
+
+        diesel::delete(sync_conflicts::table
+            .filter(sync_conflicts::tableName.eq(&conflict.table_name))
+            .filter(sync_conflicts::rowKey.eq(&conflict.row_key))
+            .filter(sync_conflicts::field.eq(&conflict.field)))
+            .execute(connection)?;
+        Ok(())
+    })
+}
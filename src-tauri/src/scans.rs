@@ -0,0 +1,116 @@
+//! What a code from a keyboard-wedge scanner stands for. Such scanners type the code and an
+//! Enter like a keyboard, so a desk gets a string and no symbology: it may be an ISBN-13 or
+//! ISBN-10, the barcode of one copy (`{isbn13}-{copy:03}`), a client ID or the signed
+//! payload of a badge.
+
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use crate::client_ids::ClientIdFormat;
+use crate::models::database::{Book, Client};
+use crate::services::{book_service, client_service};
+use crate::{barcode, signing, validation, Error, SerializedResult};
+
+/// Kinds of code a scan can be, told apart by their form alone.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanKind {
+    /// An ISBN, normalized.
+    Isbn(String),
+    /// The ISBN-13 of a book and the number of one of its copies.
+    Copy(String, u32),
+    /// A client ID, as Code 128 badges carry it.
+    Client(String),
+    /// The payload of a QR code or Data Matrix badge, still to be verified.
+    SignedBadge(String),
+}
+
+/// The book or client a scan refers to.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ScanTarget {
+    /// A book, with the copy scanned when the code was that of a copy.
+    Book { book: Book, copy: Option<u32> },
+    Client { client: Client },
+}
+
+/// The barcode of a copy, as [`crate::barcode::copy_code`] writes it.
+fn parse_copy(code: &str) -> Option<(String, u32)> {
+    let (isbn, copy) = code.rsplit_once('-')?;
+    if copy.len() < 3 || !copy.chars().all(|c| c.is_ascii_digit()) || isbn.len() != 13 {
+        return None;
+    }
+    let isbn = validation::normalize_isbn(isbn)?;
+    Some((isbn, copy.parse().ok().filter(|&copy| copy > 0)?))
+}
+
+/// What `code` is by its form: signed badges carry `|`, copies end in `-` and at least three
+/// digits, ISBNs pass their check digit, also with the zero ITF labels put before them, and
+/// anything else is taken for a client ID.
+pub fn classify(code: &str) -> Option<ScanKind> {
+    let code = code.trim();
+    if code.is_empty() {
+        return None;
+    }
+    let code = &barcode::itf_text(code);
+    if code.contains('|') {
+        return Some(ScanKind::SignedBadge(code.to_string()));
+    }
+    if let Some((isbn, copy)) = parse_copy(code) {
+        return Some(ScanKind::Copy(isbn, copy));
+    }
+    if let Some(isbn) = validation::normalize_isbn(code) {
+        return Some(ScanKind::Isbn(isbn));
+    }
+    Some(ScanKind::Client(code.to_string()))
+}
+
+/// The book catalogued under `isbn`, which may have been added as an ISBN-10 or ISBN-13.
+fn find_book(connection: &mut SqliteConnection, isbn: &str) -> SerializedResult<Option<Book>> {
+    for candidate in [Some(isbn.to_string()), validation::to_isbn13(isbn), validation::to_isbn10(isbn)].into_iter().flatten() {
+        if let Some(book) = book_service::fetch_book(connection, &candidate, false)? {
+            return Ok(Some(book));
+        }
+    }
+    Ok(None)
+}
+
+fn find_client(connection: &mut SqliteConnection, id: &str, format: &ClientIdFormat) -> SerializedResult<Option<ScanTarget>> {
+    let client = client_service::lookup_client(connection, id, format, false)?;
+    Ok(client.map(|client| ScanTarget::Client { client }))
+}
+
+/// `code` without the zero an ITF label puts before an odd number of digits, when it could
+/// have one.
+fn itf_unpadded(code: &str) -> Option<&str> {
+    code.strip_prefix('0').filter(|digits| !digits.len().is_multiple_of(2) && digits.bytes().all(|digit| digit.is_ascii_digit()))
+}
+
+/// The book, copy or client `code` refers to. Generated client IDs with a wrong check digit
+/// are refused as [`Error::InvalidClientId`]; codes nothing has, and badges not signed here,
+/// as [`Error::UnknownCode`].
+pub fn resolve(connection: &mut SqliteConnection, code: &str, format: &ClientIdFormat) -> SerializedResult<ScanTarget> {
+    let unknown = || Error::UnknownCode(code.trim().to_string());
+    let target = match classify(code).ok_or_else(unknown)? {
+        ScanKind::SignedBadge(payload) => {
+            let badge = signing::verify_badge(connection, &payload)?.ok_or_else(unknown)?;
+            find_client(connection, &badge.client_id, format)?
+        }
+        ScanKind::Copy(isbn, copy) => find_book(connection, &isbn)?
+            .filter(|book| copy <= book.items as u32)
+            .map(|book| ScanTarget::Book { book, copy: Some(copy) }),
+        ScanKind::Isbn(isbn) => match find_book(connection, &isbn)? {
+            Some(book) => Some(ScanTarget::Book { book, copy: None }),
+            // typed client IDs made only of digits may pass as an ISBN-10
+            None => find_client(connection, code.trim(), format)?,
+        },
+        ScanKind::Client(id) => match itf_unpadded(&id) {
+            // the ID may have had an odd number of digits, or really start with a zero
+            Some(unpadded) => match find_client(connection, unpadded, format).ok().flatten() {
+                Some(target) => Some(target),
+                None => find_client(connection, &id, format)?,
+            },
+            None => find_client(connection, &id, format)?,
+        },
+    };
+    target.ok_or_else(unknown)
+}
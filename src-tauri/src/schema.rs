@@ -6,6 +6,17 @@ diesel::table! {
         title -> Text,
         author -> Text,
         items -> Integer,
+        deletedAt -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    book_details (isbn) {
+        isbn -> Text,
+        edition -> Nullable<Text>,
+        publication -> Nullable<Text>,
+        extent -> Nullable<Text>,
+        subjects -> Text,
     }
 }
 
@@ -17,6 +28,43 @@ diesel::table! {
         startDate -> Date,
         endDate -> Date,
         returned -> Bool,
+        branchID -> Integer,
+        syncKey -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    branches (id) {
+        id -> Integer,
+        code -> Text,
+        name -> Text,
+    }
+}
+
+diesel::table! {
+    changes (seq) {
+        seq -> Integer,
+        tableName -> Text,
+        rowKey -> Text,
+        field -> Text,
+        value -> Nullable<Text>,
+        changedAt -> Text,
+        origin -> Text,
+    }
+}
+
+diesel::table! {
+    client_id_sequences (scope) {
+        scope -> Text,
+        next -> Integer,
+    }
+}
+
+diesel::table! {
+    client_photos (clientID) {
+        clientID -> Text,
+        photo -> Binary,
+        updatedAt -> Timestamp,
     }
 }
 
@@ -27,6 +75,52 @@ diesel::table! {
         lastName -> Text,
         email -> Text,
         phone -> Text,
+        deletedAt -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    holdings (branchID, bookISBN) {
+        branchID -> Integer,
+        bookISBN -> Text,
+        items -> Integer,
+    }
+}
+
+diesel::table! {
+    replica (id) {
+        id -> Integer,
+        site -> Text,
+        applying -> Integer,
+    }
+}
+
+diesel::table! {
+    row_versions (tableName, rowKey) {
+        tableName -> Text,
+        rowKey -> Text,
+        version -> Integer,
+    }
+}
+
+diesel::table! {
+    signing_keys (name) {
+        name -> Text,
+        key -> Text,
+    }
+}
+
+diesel::table! {
+    staged_books (isbn) {
+        isbn -> Text,
+        title -> Text,
+        author -> Text,
+        items -> Integer,
+        status -> Text,
+        edition -> Nullable<Text>,
+        publication -> Nullable<Text>,
+        extent -> Nullable<Text>,
+        subjects -> Text,
     }
 }
 
@@ -37,15 +131,60 @@ diesel::table! {
         firstName -> Text,
         lastName -> Text,
         role -> Text,
+        branchID -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    sync_conflicts (id) {
+        id -> Integer,
+        tableName -> Text,
+        rowKey -> Text,
+        field -> Text,
+        localValue -> Nullable<Text>,
+        remoteValue -> Nullable<Text>,
+        remoteChangedAt -> Text,
+        origin -> Text,
+    }
+}
+
+diesel::table! {
+    transfers (id) {
+        id -> Integer,
+        bookISBN -> Text,
+        fromBranch -> Integer,
+        toBranch -> Integer,
+        items -> Integer,
+        date -> Timestamp,
+        username -> Text,
     }
 }
 
+diesel::joinable!(book_details -> books (isbn));
 diesel::joinable!(borrows -> books (bookISBN));
+diesel::joinable!(borrows -> branches (branchID));
 diesel::joinable!(borrows -> clients (clientID));
+diesel::joinable!(client_photos -> clients (clientID));
+diesel::joinable!(holdings -> books (bookISBN));
+diesel::joinable!(holdings -> branches (branchID));
+diesel::joinable!(transfers -> books (bookISBN));
+diesel::joinable!(users -> branches (branchID));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    book_details,
     books,
     borrows,
+    branches,
+    changes,
+    client_id_sequences,
+    client_photos,
     clients,
+    holdings,
+    replica,
+    row_versions,
+    signing_keys,
+    staged_books,
+    sync_conflicts,
+    transfers,
     users,
 );
@@ -0,0 +1,160 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::SqliteConnection;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::models::database::User;
+use crate::services::user_service;
+use crate::{Error, SerializedResult};
+
+/// Runs one command by name on behalf of an authenticated user, with the same JSON
+/// arguments the frontend passes to `invoke`. Called on a blocking thread.
+pub type Dispatch = Arc<dyn Fn(&User, &str, Value) -> SerializedResult<Value> + Send + Sync>;
+
+/// Who may run a command through the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Any user with an account.
+    User,
+    /// Administrators, and users acting on themselves: the `username` argument is theirs.
+    Own,
+    /// Administrators only.
+    Admin,
+}
+
+/// Checks that `user` may run `command` with `args` before it is dispatched. The pages
+/// of the app are hidden by role, but the server is reachable without them.
+pub fn authorize(user: &User, access: Access, command: &str, args: &Value) -> SerializedResult<()> {
+    let allowed = user.role == "admin" || match access {
+        Access::User => true,
+        Access::Own => args.get("username").and_then(Value::as_str) == Some(user.username.as_str()),
+        Access::Admin => false,
+    };
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(Error::Forbidden(command.to_string()))
+    }
+}
+
+#[derive(Clone)]
+struct ServerState {
+    pool: Pool<ConnectionManager<SqliteConnection>>,
+    dispatch: Dispatch,
+}
+
+/// Reads the username and password of an `Authorization: Basic` header.
+fn credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let decoded = STANDARD.decode(value.strip_prefix("Basic ")?).ok()?;
+    let (username, password) = std::str::from_utf8(&decoded).ok()?.split_once(':')?;
+    Some((username.to_string(), password.to_string()))
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Error::AuthError => StatusCode::UNAUTHORIZED,
+            Error::Forbidden(_) => StatusCode::FORBIDDEN,
+            Error::UnknownCommand(_) => StatusCode::NOT_FOUND,
+            Error::SerdeJson(_) | Error::Validation(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR
+        };
+        (status, Json(self)).into_response()
+    }
+}
+
+async fn invoke(State(state): State<ServerState>, Path(command): Path<String>, headers: HeaderMap, Json(args): Json<Value>) -> SerializedResult<Json<Value>> {
+    let (username, password) = credentials(&headers).ok_or(Error::AuthError)?;
+
+    // diesel blocks, so neither the check nor the command may run on the async workers
+    let result = tokio::task::spawn_blocking(move || {
+        let user = user_service::authenticate(&mut *state.pool.get()?, &username, &password)?;
+        (state.dispatch)(&user, &command, args)
+    }).await.map_err(|error| Error::Io(error.into()))??;
+
+    Ok(Json(result))
+}
+
+/// Serves the commands of this instance as `POST /api/<command>` on `address` until the
+/// runtime shuts down. Every request carries the credentials of a user in Basic auth.
+///
+/// The server speaks plain HTTP, so passwords and data cross the network unencrypted: bind
+/// it to the library's own network, or put it behind a TLS proxy, never on a public one.
+pub async fn serve(address: SocketAddr, pool: Pool<ConnectionManager<SqliteConnection>>, dispatch: Dispatch) -> SerializedResult<()> {
+    let router = Router::new()
+        .route("/api/:command", post(invoke))
+        .with_state(ServerState { pool, dispatch });
+
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+/// Connection of a thin client to the instance running [`serve`].
+#[derive(Clone)]
+pub struct Client {
+    url: String,
+    username: String,
+    password: String,
+    http: reqwest::Client,
+}
+
+impl Client {
+    pub fn new(url: &str, username: &str, password: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Runs `command` on the server; errors raised there come back as [`Error::Remote`].
+    pub async fn call<A: Serialize, T: DeserializeOwned>(&self, command: &str, args: &A) -> SerializedResult<T> {
+        let response = self.http
+            .post(format!("{}/api/{}", self.url, command))
+            .basic_auth(&self.username, Some(&self.password))
+            .json(args)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(response.json::<T>().await?)
+        } else if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            Err(Error::AuthError)
+        } else {
+            // validation errors keep their fields, anything else arrives as its message
+            match response.json::<Value>().await? {
+                Value::String(message) => Err(Error::Remote(message)),
+                errors => Err(Error::Validation(serde_json::from_value(errors)?)),
+            }
+        }
+    }
+}
+
+/// The server this desk works against as a thin client, if any.
+#[derive(Default)]
+pub struct Session(Mutex<Option<Client>>);
+
+impl Session {
+    pub fn get(&self) -> Option<Client> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn set(&self, client: Option<Client>) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = client;
+    }
+}
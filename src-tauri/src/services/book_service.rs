@@ -0,0 +1,176 @@
+use chrono::Local;
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+use crate::branches;
+use crate::models::database::{Book, BookDetails, StagedBook};
+use crate::validation::{self, ValidationError};
+use crate::{Error, SerializedResult};
+
+pub fn fetch_books(connection: &mut SqliteConnection, include_archived: bool) -> SerializedResult<Vec<Book>> {
+    use crate::schema::books;
+
+    let mut query = books::table.select(Book::as_select()).into_boxed();
+    if !include_archived {
+        query = query.filter(books::deletedAt.is_null());
+    }
+    Ok(query.load(connection)?)
+}
+
+pub fn fetch_book(connection: &mut SqliteConnection, isbn: &str, include_archived: bool) -> SerializedResult<Option<Book>> {
+    use crate::schema::books;
+
+    let result: Option<Book> = books::table.find(isbn).select(Book::as_select()).get_result(connection).optional()?;
+    Ok(result.filter(|book| book.deleted_at.is_none() || include_archived))
+}
+
+/// Adds a book to the catalog with all of its copies on the shelves of `branch`.
+pub fn create_book(connection: &mut SqliteConnection, book: &Book, branch: i32) -> SerializedResult<()> {
+    use crate::schema::books;
+
+    let book = &validation::validate_book(book).map_err(Error::Validation)?;
+
+    connection.transaction::<_, Error, _>(|connection| {
+        diesel::insert_into(books::table).values(book).execute(connection)?;
+        branches::shelve_new_book(connection, &book.isbn, branch)
+    })
+}
+
+/// Saves `book`; copies added or removed while editing are counted at `branch`.
+pub fn update_book(connection: &mut SqliteConnection, book: &Book, branch: i32) -> SerializedResult<()> {
+    use crate::schema::books;
+
+    let book = &validation::validate_book_changes(book).map_err(Error::Validation)?;
+
+    connection.transaction::<_, Error, _>(|connection| {
+        let previous: Book = books::table.find(&book.isbn).select(Book::as_select()).get_result(connection)?;
+        diesel::update(book).set(book).execute(connection)?;
+
+        if book.items != previous.items {
+            branches::adjust_holding(connection, &book.isbn, branch, book.items - previous.items)?;
+        }
+        Ok(())
+    })
+}
+
+/// Hides a book from the catalog; its borrows stay in the history.
+pub fn archive_book(connection: &mut SqliteConnection, isbn: &str) -> SerializedResult<()> {
+    use crate::schema::books;
+
+    diesel::update(books::table.find(isbn))
+        .set(books::deletedAt.eq(Local::now().naive_local()))
+        .execute(connection)?;
+    Ok(())
+}
+
+pub fn restore_book(connection: &mut SqliteConnection, isbn: &str) -> SerializedResult<()> {
+    use crate::schema::books;
+
+    diesel::update(books::table.find(isbn))
+        .set(books::deletedAt.eq(None::<chrono::NaiveDateTime>))
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Deletes an archived book for good, together with its borrows. Books still in the
+/// catalog are left alone.
+pub fn purge_book(connection: &mut SqliteConnection, isbn: &str) -> SerializedResult<()> {
+    use crate::schema::books;
+
+    diesel::delete(books::table.find(isbn).filter(books::deletedAt.is_not_null())).execute(connection)?;
+    Ok(())
+}
+
+/// Number of books in the catalog, archived ones excluded.
+pub fn count_books(connection: &mut SqliteConnection) -> SerializedResult<i64> {
+    use crate::schema::books;
+
+    Ok(books::table.filter(books::deletedAt.is_null()).count().get_result(connection)?)
+}
+
+pub fn fetch_staged_books(connection: &mut SqliteConnection) -> SerializedResult<Vec<StagedBook>> {
+    use crate::schema::staged_books;
+
+    Ok(staged_books::table.select(StagedBook::as_select()).load(connection)?)
+}
+
+/// Stages `books` for review, leaving alone those already staged so edits made to them are
+/// kept. Returns how many were new.
+pub fn stage_books(connection: &mut SqliteConnection, books: &[StagedBook]) -> SerializedResult<usize> {
+    use crate::schema::staged_books;
+
+    Ok(diesel::insert_or_ignore_into(staged_books::table).values(books).execute(connection)?)
+}
+
+pub fn update_staged_book(connection: &mut SqliteConnection, book: &StagedBook) -> SerializedResult<()> {
+    diesel::update(book).set(book).execute(connection)?;
+    Ok(())
+}
+
+pub fn delete_staged_book(connection: &mut SqliteConnection, isbn: &str) -> SerializedResult<()> {
+    use crate::schema::staged_books;
+
+    diesel::delete(staged_books::table.find(isbn)).execute(connection)?;
+    Ok(())
+}
+
+/// Moves the staged books `isbns` into the catalog, shelved at `branch`, all or none.
+/// Each is checked like a book added by hand, and books already in the catalog are
+/// restocked. Returns how many were committed.
+pub fn commit_staged_books(connection: &mut SqliteConnection, isbns: &[String], branch: i32) -> SerializedResult<usize> {
+    use crate::schema::{book_details, books, staged_books};
+
+    connection.transaction::<_, Error, _>(|connection| {
+        let staged = staged_books::table
+            .filter(staged_books::isbn.eq_any(isbns))
+            .select(StagedBook::as_select())
+            .load(connection)?;
+
+        // every invalid book is reported at once, each message starting with its ISBN
+        let mut errors = Vec::new();
+        let mut valid = Vec::new();
+        for entry in &staged {
+            let book = Book { isbn: entry.isbn.clone(), title: entry.title.clone(), author: entry.author.clone(), items: entry.items, deleted_at: None };
+            match validation::validate_book(&book) {
+                Ok(book) => valid.push((entry, book)),
+                Err(invalid) => errors.extend(invalid.into_iter().map(|error| ValidationError { message: format!("{}: {}", entry.isbn, error.message), ..error })),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(Error::Validation(errors));
+        }
+
+        for (entry, book) in valid {
+            let existing: Option<Book> = books::table.find(&book.isbn).select(Book::as_select()).get_result(connection).optional()?;
+
+            if let Some(existing) = existing {
+                // restocking an archived book brings it back into the catalog
+                diesel::update(&existing)
+                    .set((
+                        books::items.eq(existing.items + book.items),
+                        books::deletedAt.eq(None::<chrono::NaiveDateTime>)
+                    ))
+                    .execute(connection)?;
+                branches::adjust_holding(connection, &book.isbn, branch, book.items)?;
+            } else {
+                diesel::insert_into(books::table).values(&book).execute(connection)?;
+                branches::shelve_new_book(connection, &book.isbn, branch)?;
+            }
+
+            // what a MARC record said about the book, unless the catalog already has it
+            let details = BookDetails {
+                isbn: book.isbn.clone(),
+                edition: entry.edition.clone(),
+                publication: entry.publication.clone(),
+                extent: entry.extent.clone(),
+                subjects: entry.subjects.clone(),
+            };
+            if details != (BookDetails { isbn: book.isbn.clone(), ..BookDetails::default() }) {
+                diesel::insert_or_ignore_into(book_details::table).values(&details).execute(connection)?;
+            }
+        }
+
+        diesel::delete(staged_books::table.filter(staged_books::isbn.eq_any(isbns))).execute(connection)?;
+        Ok(staged.len())
+    })
+}
@@ -0,0 +1,114 @@
+use chrono::{Days, NaiveDate};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+use crate::branches;
+use crate::models::database::{Book, Borrow, Client, NewBorrow};
+use crate::models::database::joined_data::{BookBorrow, ClientBorrow};
+use crate::{Error, SerializedResult};
+
+/// How long a book may be kept, in days.
+pub const LOAN_PERIOD: u64 = 14;
+
+/// Every borrow of the book `isbn`, with the client who made it.
+pub fn fetch_borrowers(connection: &mut SqliteConnection, isbn: &str) -> SerializedResult<Vec<ClientBorrow>> {
+    use crate::schema::{books, clients};
+
+    let book: Book = books::table.find(isbn).select(Book::as_select()).get_result(connection)?;
+    let result = Borrow::belonging_to(&book)
+        .inner_join(clients::table)
+        .select((Borrow::as_select(), Client::as_select()))
+        .load::<(Borrow, Client)>(connection)?;
+
+    Ok(result.into_iter().map(|(borrow, client)| ClientBorrow { borrow, client }).collect())
+}
+
+/// Every borrow of the client `id`, with the book borrowed.
+pub fn fetch_borrowed_books(connection: &mut SqliteConnection, id: &str) -> SerializedResult<Vec<BookBorrow>> {
+    use crate::schema::{books, clients};
+
+    let client: Client = clients::table.find(id).select(Client::as_select()).get_result(connection)?;
+    let result = Borrow::belonging_to(&client)
+        .inner_join(books::table)
+        .select((Borrow::as_select(), Book::as_select()))
+        .load::<(Borrow, Book)>(connection)?;
+
+    Ok(result.into_iter().map(|(borrow, book)| BookBorrow { borrow, book }).collect())
+}
+
+/// Whether `client_id` may borrow `isbn`: a copy must be free, at `branch` when given or
+/// in the whole library otherwise, and the client must not have one on loan already.
+/// `None` when the book is not in the catalog.
+pub fn is_book_available(connection: &mut SqliteConnection, isbn: &str, client_id: &str, branch: Option<i32>) -> SerializedResult<Option<bool>> {
+    use crate::schema::{books, borrows};
+
+    let book: Option<Book> = books::table.find(isbn).select(Book::as_select()).get_result(connection).optional()?;
+    let Some(book) = book.filter(|book| book.deleted_at.is_none()) else {
+        return Ok(None);
+    };
+
+    let active = Borrow::belonging_to(&book)
+        .filter(borrows::returned.eq(false))
+        .select(Borrow::as_select())
+        .load::<Borrow>(connection)?;
+
+    let already_borrowed = active.iter().any(|borrow| borrow.client_id == client_id);
+
+    let in_stock = match branch {
+        Some(branch) => branches::available_copies(connection, &book.isbn, branch)? > 0,
+        None => (active.len() as i32) < book.items
+    };
+
+    Ok(Some(in_stock && !already_borrowed))
+}
+
+/// Lends `isbn` to `client_id` from `branch`, starting on `start_date` for [`LOAN_PERIOD`] days.
+/// Archived books and clients are refused; availability is for the caller to check
+/// with [`is_book_available`].
+pub fn add_borrow(connection: &mut SqliteConnection, isbn: &str, client_id: &str, branch: i32, start_date: NaiveDate) -> SerializedResult<()> {
+    use crate::schema::{books, borrows, clients};
+
+    let book_archived = books::table.find(isbn)
+        .select(books::deletedAt.is_not_null())
+        .first::<bool>(connection)
+        .optional()?;
+    if book_archived == Some(true) {
+        return Err(Error::Archived(format!("Book {}", isbn)));
+    }
+
+    let client_archived = clients::table.find(client_id)
+        .select(clients::deletedAt.is_not_null())
+        .first::<bool>(connection)
+        .optional()?;
+    if client_archived == Some(true) {
+        return Err(Error::Archived(format!("Client {}", client_id)));
+    }
+
+    let borrow = NewBorrow {
+        client_id,
+        book_isbn: isbn,
+        returned: false,
+        start_date,
+        end_date: start_date + Days::new(LOAN_PERIOD),
+        branch_id: branch,
+    };
+    diesel::insert_into(borrows::table).values(&borrow).execute(connection)?;
+    Ok(())
+}
+
+pub fn delete_borrow(connection: &mut SqliteConnection, id: i32) -> SerializedResult<()> {
+    use crate::schema::borrows;
+
+    diesel::delete(borrows::table.find(id)).execute(connection)?;
+    Ok(())
+}
+
+/// Marks a borrow as returned or not, or extends it to `end_date`.
+pub fn update_borrow(connection: &mut SqliteConnection, id: i32, returned: bool, end_date: NaiveDate) -> SerializedResult<()> {
+    use crate::schema::borrows;
+
+    diesel::update(borrows::table.find(id))
+        .set((borrows::returned.eq(returned), borrows::endDate.eq(end_date)))
+        .execute(connection)?;
+    Ok(())
+}
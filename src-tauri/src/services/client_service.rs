@@ -0,0 +1,117 @@
+use chrono::Local;
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+use regex::Regex;
+
+use crate::client_ids::{self, ClientIdFormat};
+use crate::models::database::Client;
+use crate::validation;
+use crate::{Error, SerializedResult};
+
+pub fn fetch_clients(connection: &mut SqliteConnection, include_archived: bool) -> SerializedResult<Vec<Client>> {
+    use crate::schema::clients;
+
+    let mut query = clients::table.select(Client::as_select()).into_boxed();
+    if !include_archived {
+        query = query.filter(clients::deletedAt.is_null());
+    }
+    Ok(query.load(connection)?)
+}
+
+pub fn fetch_client(connection: &mut SqliteConnection, id: &str, include_archived: bool) -> SerializedResult<Option<Client>> {
+    use crate::schema::clients;
+
+    let result: Option<Client> = clients::table.find(id).select(Client::as_select()).get_result(connection).optional()?;
+    Ok(result.filter(|client| client.deleted_at.is_none() || include_archived))
+}
+
+/// Adds a client whose ID matches `id_pattern`, with their phone number stored in E.164,
+/// and returns them as stored.
+pub fn create_client(connection: &mut SqliteConnection, client: &Client, id_pattern: &Regex) -> SerializedResult<Client> {
+    use crate::schema::clients;
+
+    let client = validation::validate_client(client, Some(id_pattern)).map_err(Error::Validation)?;
+    diesel::insert_into(clients::table).values(&client).execute(connection)?;
+    Ok(client)
+}
+
+/// Like [`create_client`], but a client without an ID gets the next one of `format`
+/// for `branch`.
+pub fn register_client(connection: &mut SqliteConnection, client: &Client, format: &ClientIdFormat, branch: i32, id_pattern: &Regex) -> SerializedResult<Client> {
+    connection.transaction::<_, Error, _>(|connection| {
+        if !client.id.trim().is_empty() {
+            return create_client(connection, client, id_pattern);
+        }
+
+        let id = client_ids::generate(connection, format, branch)?;
+        create_client(connection, &Client { id, ..client.clone() }, id_pattern)
+    })
+}
+
+/// Looks up a scanned or typed client ID, refusing generated IDs with a wrong check digit.
+/// IDs on record are found whatever format they were issued in.
+pub fn lookup_client(connection: &mut SqliteConnection, id: &str, format: &ClientIdFormat, include_archived: bool) -> SerializedResult<Option<Client>> {
+    let client = fetch_client(connection, id, include_archived)?;
+    if client.is_none() {
+        format.verify(id)?;
+    }
+    Ok(client)
+}
+
+pub fn update_client(connection: &mut SqliteConnection, client: &Client) -> SerializedResult<()> {
+    let client = validation::validate_client(client, None).map_err(Error::Validation)?;
+    diesel::update(&client).set(&client).execute(connection)?;
+    Ok(())
+}
+
+/// Books `id` has borrowed and not returned yet.
+pub fn active_loans(connection: &mut SqliteConnection, id: &str) -> SerializedResult<i64> {
+    use crate::schema::borrows;
+
+    let count = borrows::table
+        .filter(borrows::clientID.eq(id))
+        .filter(borrows::returned.eq(false))
+        .count()
+        .get_result(connection)?;
+    Ok(count)
+}
+
+/// Hides a client from the list; refused while they still have books on loan.
+pub fn archive_client(connection: &mut SqliteConnection, id: &str) -> SerializedResult<()> {
+    use crate::schema::clients;
+
+    if active_loans(connection, id)? > 0 {
+        return Err(Error::ActiveLoans(id.to_string()));
+    }
+
+    diesel::update(clients::table.find(id))
+        .set(clients::deletedAt.eq(Local::now().naive_local()))
+        .execute(connection)?;
+    Ok(())
+}
+
+pub fn restore_client(connection: &mut SqliteConnection, id: &str) -> SerializedResult<()> {
+    use crate::schema::clients;
+
+    diesel::update(clients::table.find(id))
+        .set(clients::deletedAt.eq(None::<chrono::NaiveDateTime>))
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Deletes an archived client for good, together with their borrows. Clients who
+/// are not archived are left alone.
+pub fn purge_client(connection: &mut SqliteConnection, id: &str) -> SerializedResult<()> {
+    use crate::schema::clients;
+
+    diesel::delete(clients::table.find(id).filter(clients::deletedAt.is_not_null())).execute(connection)?;
+    Ok(())
+}
+
+/// Number of clients in the list, archived ones excluded.
+pub fn count_clients(connection: &mut SqliteConnection) -> SerializedResult<i64> {
+    use crate::schema::clients;
+
+    Ok(clients::table.filter(clients::deletedAt.is_null()).count().get_result(connection)?)
+}
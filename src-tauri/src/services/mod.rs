@@ -0,0 +1,7 @@
+//! What the commands do to the database, kept apart from Tauri so it can run on any
+//! `SqliteConnection`: the app's pool, the HTTP server, `libra-cli` or a test.
+
+pub mod book_service;
+pub mod borrow_service;
+pub mod client_service;
+pub mod user_service;
@@ -0,0 +1,97 @@
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+
+use crate::models::database::{UpdateUser, User};
+use crate::{Error, SerializedResult};
+
+/// Checks a username and password against the `users` table.
+pub fn authenticate(connection: &mut SqliteConnection, username: &str, password: &str) -> SerializedResult<User> {
+    use crate::schema::users;
+
+    let user: Option<User> = users::table.find(username).first(connection).optional()?;
+
+    match user {
+        Some(user) if user.password == password => Ok(user),
+        _ => Err(Error::AuthError)
+    }
+}
+
+/// Like [`authenticate`], but the user must also be an administrator.
+pub fn authorize_admin(connection: &mut SqliteConnection, username: &str, password: &str) -> SerializedResult<()> {
+    match authenticate(connection, username, password) {
+        Ok(user) if user.role == "admin" => Ok(()),
+        _ => Err(Error::AuthError)
+    }
+}
+
+/// Authenticates a user and, when `branch` is given, remembers it as the branch they work at.
+///
+/// Desks keep the branch they last chose, so a branch deleted since then is ignored instead of
+/// locking everyone out of that desk.
+pub fn login(connection: &mut SqliteConnection, username: &str, password: &str, branch: Option<i32>) -> SerializedResult<User> {
+    use crate::schema::branches;
+
+    let mut user = authenticate(connection, username, password)?;
+
+    let branch = match branch {
+        Some(branch) => branches::table.find(branch).select(branches::id).first::<i32>(connection).optional()?,
+        None => None,
+    };
+    if let Some(branch) = branch {
+        select_branch(connection, username, branch)?;
+        user.branch_id = Some(branch);
+    }
+    Ok(user)
+}
+
+pub fn select_branch(connection: &mut SqliteConnection, username: &str, branch: i32) -> SerializedResult<()> {
+    use crate::schema::users;
+
+    diesel::update(users::table.find(username)).set(users::branchID.eq(branch)).execute(connection)?;
+    Ok(())
+}
+
+/// Changes the details of a user after checking their current `password`.
+pub fn update_user(connection: &mut SqliteConnection, user: &UpdateUser, password: &str) -> SerializedResult<()> {
+    authenticate(connection, &user.username, password)?;
+
+    diesel::update(user).set(user).execute(connection)?;
+    Ok(())
+}
+
+/// Sets a new password without knowing the old one, for administrators.
+pub fn reset_password(connection: &mut SqliteConnection, username: &str, password: &str) -> SerializedResult<()> {
+    use crate::schema::users;
+
+    let updated = diesel::update(users::table.find(username)).set(users::password.eq(password)).execute(connection)?;
+    if updated == 0 {
+        return Err(Error::Diesel(diesel::result::Error::NotFound));
+    }
+    Ok(())
+}
+
+pub fn fetch_user(connection: &mut SqliteConnection, username: &str) -> SerializedResult<User> {
+    use crate::schema::users;
+
+    Ok(users::table.find(username).select(User::as_select()).get_result(connection)?)
+}
+
+pub fn fetch_users(connection: &mut SqliteConnection) -> SerializedResult<Vec<User>> {
+    use crate::schema::users;
+
+    Ok(users::table.select(User::as_select()).load(connection)?)
+}
+
+pub fn create_user(connection: &mut SqliteConnection, user: &User) -> SerializedResult<()> {
+    use crate::schema::users;
+
+    diesel::insert_into(users::table).values(user).execute(connection)?;
+    Ok(())
+}
+
+pub fn delete_user(connection: &mut SqliteConnection, username: &str) -> SerializedResult<()> {
+    use crate::schema::users;
+
+    diesel::delete(users::table.find(username)).execute(connection)?;
+    Ok(())
+}
@@ -0,0 +1,189 @@
+//! Labels and badges tiled onto sheets for printing, such as Avery labels on A4 or CR80
+//! cards, with a start offset so a partly used sheet can go through the printer again.
+
+use serde::{Deserialize, Serialize};
+
+use crate::barcode::LabelSymbologies;
+use crate::drawing::Drawing;
+use crate::models::database::Book;
+use crate::pdf::{PdfWriter, Placement, A4_HEIGHT_MM, A4_WIDTH_MM};
+use crate::settings::Settings;
+use crate::validation::ValidationError;
+use crate::{Error, SerializedResult};
+
+/// Size of an ID-1 card, the size of payment cards, in millimetres.
+pub const CR80_WIDTH_MM: f32 = 85.6;
+pub const CR80_HEIGHT_MM: f32 = 53.98;
+
+/// The grid of a sheet of labels, all in millimetres.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SheetLayout {
+    pub page_width: f32,
+    pub page_height: f32,
+    pub columns: u32,
+    pub rows: u32,
+    pub label_width: f32,
+    pub label_height: f32,
+    /// From the top of the page to the top of the first row.
+    pub margin_top: f32,
+    /// From the left of the page to the left of the first column.
+    pub margin_left: f32,
+    pub column_gap: f32,
+    pub row_gap: f32,
+}
+
+impl Default for SheetLayout {
+    fn default() -> Self {
+        Self::avery_l7160()
+    }
+}
+
+impl SheetLayout {
+    /// Avery L7160: 21 labels of 63.5 x 38.1 mm on A4, the usual size for ISBN labels.
+    pub fn avery_l7160() -> Self {
+        Self {
+            page_width: A4_WIDTH_MM,
+            page_height: A4_HEIGHT_MM,
+            columns: 3,
+            rows: 7,
+            label_width: 63.5,
+            label_height: 38.1,
+            margin_top: 15.15,
+            margin_left: 7.25,
+            column_gap: 2.5,
+            row_gap: 0.0,
+        }
+    }
+
+    /// Avery L7651: 65 labels of 38.1 x 21.2 mm on A4, small enough for spines.
+    pub fn avery_l7651() -> Self {
+        Self {
+            page_width: A4_WIDTH_MM,
+            page_height: A4_HEIGHT_MM,
+            columns: 5,
+            rows: 13,
+            label_width: 38.1,
+            label_height: 21.2,
+            margin_top: 10.7,
+            margin_left: 4.75,
+            column_gap: 2.5,
+            row_gap: 0.0,
+        }
+    }
+
+    /// Ten CR80 cards on A4, two columns of five, centered on the page.
+    pub fn cr80_a4() -> Self {
+        let column_gap = 5.0;
+        Self {
+            page_width: A4_WIDTH_MM,
+            page_height: A4_HEIGHT_MM,
+            columns: 2,
+            rows: 5,
+            label_width: CR80_WIDTH_MM,
+            label_height: CR80_HEIGHT_MM,
+            margin_top: (A4_HEIGHT_MM - 5.0 * CR80_HEIGHT_MM) / 2.0,
+            margin_left: (A4_WIDTH_MM - 2.0 * CR80_WIDTH_MM - column_gap) / 2.0,
+            column_gap,
+            row_gap: 0.0,
+        }
+    }
+
+    pub fn per_page(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    /// Where the label in cell `index` of a sheet goes, counted row by row.
+    pub fn cell(&self, index: usize) -> Placement {
+        let column = (index % self.columns as usize) as f32;
+        let row = (index / self.columns as usize) as f32;
+        Placement {
+            x: self.margin_left + column * (self.label_width + self.column_gap),
+            y: self.margin_top + row * (self.label_height + self.row_gap),
+            width: self.label_width,
+            height: self.label_height,
+        }
+    }
+
+    /// Refuses grids that are empty or run off the page; errors are reported on `field`.
+    pub fn validate(&self, field: &str) -> SerializedResult<()> {
+        let right = self.margin_left + self.columns as f32 * self.label_width + self.columns.saturating_sub(1) as f32 * self.column_gap;
+        let bottom = self.margin_top + self.rows as f32 * self.label_height + self.rows.saturating_sub(1) as f32 * self.row_gap;
+
+        let message = if self.columns == 0 || self.rows == 0 {
+            Some("The sheet needs at least one row and one column".to_string())
+        } else if self.label_width <= 0.0 || self.label_height <= 0.0 {
+            Some("Labels need a width and a height".to_string())
+        } else if [self.margin_top, self.margin_left, self.column_gap, self.row_gap].iter().any(|value| *value < 0.0) {
+            Some("Margins and gaps cannot be negative".to_string())
+        } else if right > self.page_width + 0.01 || bottom > self.page_height + 0.01 {
+            Some(format!("The labels need {:.1} x {:.1} mm but the page is {:.1} x {:.1} mm", right, bottom, self.page_width, self.page_height))
+        } else {
+            None
+        };
+
+        match message {
+            Some(message) => Err(Error::Validation(vec![ValidationError { field: field.to_string(), message }])),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Tiles `labels` onto as many sheets as they need, skipping the first `start` cells of
+/// the first sheet, and returns the PDF. Each label is scaled to fit its cell, its bars and
+/// text as paths so they print sharp at any size.
+pub fn sheet_pdf(layout: &SheetLayout, labels: &[Drawing], start: usize, field: &str) -> SerializedResult<Vec<u8>> {
+    layout.validate(field)?;
+    if labels.is_empty() {
+        return Err(Error::Validation(vec![ValidationError { field: "labels".to_string(), message: "Nothing to print".to_string() }]));
+    }
+    if start >= layout.per_page() {
+        return Err(Error::Validation(vec![ValidationError {
+            field: "start".to_string(),
+            message: format!("A sheet only has {} labels", layout.per_page()),
+        }]));
+    }
+
+    let mut writer = PdfWriter::new();
+    for (index, label) in labels.iter().enumerate() {
+        let cell = (start + index) % layout.per_page();
+        if index == 0 || cell == 0 {
+            writer.add_page(layout.page_width, layout.page_height);
+        }
+        writer.add_drawing(label, layout.cell(cell).fit_size(label.width, label.height));
+    }
+    Ok(writer.finish())
+}
+
+/// The labels printed for books.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum BookLabel {
+    /// The EAN-13 barcode of the ISBN, for the back cover.
+    Isbn,
+    /// The first letters of the author with the title, for the spine.
+    Spine,
+}
+
+impl BookLabel {
+    pub fn render(self, book: &Book, symbologies: &LabelSymbologies) -> SerializedResult<Drawing> {
+        match self {
+            BookLabel::Isbn => {
+                let isbn = crate::validation::to_isbn13(&book.isbn).ok_or_else(|| Error::Validation(vec![ValidationError {
+                    field: "isbn".to_string(),
+                    message: format!("'{}' is not a valid ISBN", book.isbn),
+                }]))?;
+                crate::barcode::isbn_label(&isbn, symbologies.isbn)
+            }
+            BookLabel::Spine => crate::barcode::spine_label(&book.isbn, &book.title, &book.author, symbologies.spine),
+        }
+    }
+
+    /// The sheet these labels are printed on, and the setting it comes from.
+    pub fn layout(self, settings: &Settings) -> (&SheetLayout, &'static str) {
+        match self {
+            BookLabel::Isbn => (&settings.isbn_sheet, "isbnSheet"),
+            BookLabel::Spine => (&settings.spine_sheet, "spineSheet"),
+        }
+    }
+}
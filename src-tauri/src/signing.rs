@@ -0,0 +1,128 @@
+//! Signatures of what the QR codes and Data Matrix symbols of badges carry: the client ID
+//! and the name of the library, with an HMAC-SHA256 over both keyed by a secret kept in the
+//! database, so a badge made up outside the library does not scan as a client. Each
+//! installation signs with its own secret and sync bundles carry it to the others, which
+//! then accept the badges it printed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::SerializedResult;
+
+const BADGE_KEY: &str = "badges";
+/// Keys of other installations are kept as `badges:{site}`.
+const OTHER_BADGE_KEYS: &str = "badges:";
+/// Hex digits of the MAC kept on the badge, 64 bits, which keeps the QR code small.
+const SIGNATURE_LENGTH: usize = 16;
+
+fn mac(key: &[u8], message: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(message);
+    mac
+}
+
+/// HMAC-SHA256 of `message`, as in RFC 2104.
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    mac(key, message).finalize().into_bytes().into()
+}
+
+/// The secret badges are signed with, made the first time one is asked for.
+fn badge_key(connection: &mut SqliteConnection) -> SerializedResult<String> {
+    use crate::schema::signing_keys;
+
+    diesel::sql_query("INSERT OR IGNORE INTO signing_keys (name, key) VALUES (?, lower(hex(randomblob(32))))")
+        .bind::<diesel::sql_types::Text, _>(BADGE_KEY)
+        .execute(connection)?;
+    Ok(signing_keys::table.find(BADGE_KEY).select(signing_keys::key).first(connection)?)
+}
+
+/// The secrets badges are signed with here and at every installation synced with, by the
+/// replica id of each; this one's is `site`.
+pub fn badge_keys(connection: &mut SqliteConnection, site: &str) -> SerializedResult<BTreeMap<String, String>> {
+    use crate::schema::signing_keys;
+
+    badge_key(connection)?;
+    let keys = signing_keys::table
+        .filter(signing_keys::name.eq(BADGE_KEY).or(signing_keys::name.like(format!("{}%", OTHER_BADGE_KEYS))))
+        .load::<(String, String)>(connection)?;
+    Ok(keys.into_iter().map(|(name, key)| match name.strip_prefix(OTHER_BADGE_KEYS) {
+        Some(other) => (other.to_string(), key),
+        None => (site.to_string(), key),
+    }).collect())
+}
+
+/// Keeps the secrets of the other installations in `keys`, so the badges they sign scan
+/// here too. A key already known for a site is left as it is.
+pub fn add_badge_keys(connection: &mut SqliteConnection, site: &str, keys: &BTreeMap<String, String>) -> SerializedResult<()> {
+    use crate::schema::signing_keys;
+
+    for (other, key) in keys.iter().filter(|(other, _)| *other != site) {
+        diesel::insert_or_ignore_into(signing_keys::table)
+            .values((signing_keys::name.eq(format!("{}{}", OTHER_BADGE_KEYS, other)), signing_keys::key.eq(key)))
+            .execute(connection)?;
+    }
+    Ok(())
+}
+
+/// What a signed badge carries, written as `{clientId}|{libraryName}|{signature}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedBadge {
+    pub client_id: String,
+    pub library_name: String,
+    pub signature: String,
+}
+
+impl SignedBadge {
+    /// Splits a scanned payload; the library name may hold `|` itself, client IDs never do.
+    pub fn parse(payload: &str) -> Option<Self> {
+        let (client_id, rest) = payload.split_once('|')?;
+        let (library_name, signature) = rest.rsplit_once('|')?;
+        if client_id.is_empty() || signature.len() != SIGNATURE_LENGTH || !signature.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(Self { client_id: client_id.to_string(), library_name: library_name.to_string(), signature: signature.to_lowercase() })
+    }
+
+    fn message(client_id: &str, library_name: &str) -> String {
+        format!("{}|{}", client_id, library_name)
+    }
+
+    fn compute(key: &str, client_id: &str, library_name: &str) -> String {
+        let mac = hmac_sha256(key.as_bytes(), Self::message(client_id, library_name).as_bytes());
+        mac.iter().map(|byte| format!("{:02x}", byte)).collect::<String>()[..SIGNATURE_LENGTH].to_string()
+    }
+
+    /// Whether this badge was signed with `key`, compared in constant time.
+    fn signed_with(&self, key: &str) -> bool {
+        let signature: Vec<u8> = (0..SIGNATURE_LENGTH).step_by(2).filter_map(|index| u8::from_str_radix(&self.signature[index..index + 2], 16).ok()).collect();
+        mac(key.as_bytes(), Self::message(&self.client_id, &self.library_name).as_bytes()).verify_truncated_left(&signature).is_ok()
+    }
+}
+
+impl fmt::Display for SignedBadge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}|{}|{}", self.client_id, self.library_name, self.signature)
+    }
+}
+
+/// The payload of the badge of `client_id` at `library_name`.
+pub fn sign_badge(connection: &mut SqliteConnection, client_id: &str, library_name: &str) -> SerializedResult<String> {
+    let key = badge_key(connection)?;
+    let signature = SignedBadge::compute(&key, client_id, library_name);
+    Ok(SignedBadge { client_id: client_id.to_string(), library_name: library_name.to_string(), signature }.to_string())
+}
+
+/// The badge `payload` stands for, `None` when it is no signed badge or was not signed here
+/// or at an installation synced with.
+pub fn verify_badge(connection: &mut SqliteConnection, payload: &str) -> SerializedResult<Option<SignedBadge>> {
+    let Some(badge) = SignedBadge::parse(payload) else {
+        return Ok(None);
+    };
+    let keys = badge_keys(connection, "")?;
+    Ok(keys.values().any(|key| badge.signed_with(key)).then_some(badge))
+}
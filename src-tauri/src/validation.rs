@@ -0,0 +1,228 @@
+//! Checks on books and clients before they reach the database, reported per field so
+//! the forms can point at what to fix instead of showing a constraint failure.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::models::database::{Book, Client};
+use crate::{Error, SerializedResult};
+
+/// Client IDs accepted when the settings do not say otherwise: the six character IDs
+/// the app has always generated, as well as longer ones with dashes.
+pub const DEFAULT_CLIENT_ID_PATTERN: &str = "[A-Za-z0-9][A-Za-z0-9-]{3,31}";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// Name of the invalid field as the frontend knows it, e.g. `email`.
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self { field: field.to_string(), message: message.into() }
+    }
+}
+
+/// Compiles the client ID pattern from the settings; it has to match the whole ID.
+pub fn client_id_pattern(pattern: &str) -> SerializedResult<Regex> {
+    Regex::new(&format!("^(?:{})$", pattern))
+        .map_err(|error| Error::Validation(vec![ValidationError::new("clientIdPattern", error.to_string())]))
+}
+
+pub fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return false;
+    };
+
+    let local_valid = !local.is_empty()
+        && local.len() <= 64
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~.-".contains(c));
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    let domain_valid = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && labels.last().is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()));
+
+    local_valid && domain_valid
+}
+
+/// Brings a phone number to E.164. Romanian numbers may be written nationally
+/// (`0722 123 456`) or with the country code (`+40 722 123 456`, `0040722123456`);
+/// numbers of other countries have to start with `+`. `None` when it is not a number.
+pub fn normalize_phone(phone: &str) -> Option<String> {
+    let compact: String = phone.chars().filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')' | '/')).collect();
+
+    let (international, digits) = match compact.strip_prefix('+') {
+        Some(rest) => (true, rest.to_string()),
+        None => match compact.strip_prefix("00") {
+            Some(rest) => (true, rest.to_string()),
+            None => (false, compact),
+        }
+    };
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let national = match (international, digits.strip_prefix("40")) {
+        (true, Some(national)) => national.to_string(),
+        (true, None) => {
+            // a foreign number, which we can only check for its length
+            let valid = (8..=15).contains(&digits.len()) && !digits.starts_with('0');
+            return valid.then(|| format!("+{}", digits));
+        }
+        (false, _) => digits.strip_prefix('0')?.to_string(),
+    };
+
+    // fixed lines start with 2 or 3, mobiles with 7, all with nine digits after the 0
+    let valid = national.len() == 9 && matches!(national.as_bytes()[0], b'2' | b'3' | b'7');
+    valid.then(|| format!("+40{}", national))
+}
+
+/// Strips the dashes and spaces of an ISBN-10 or ISBN-13 and checks its check digit.
+pub fn normalize_isbn(isbn: &str) -> Option<String> {
+    let compact: String = isbn.chars().filter(|c| !matches!(c, ' ' | '-')).collect::<String>().to_uppercase();
+    if !compact.is_ascii() {
+        return None;
+    }
+
+    let valid = match compact.len() {
+        13 if compact.chars().all(|c| c.is_ascii_digit()) => {
+            let sum: u32 = compact.bytes().enumerate()
+                .map(|(index, digit)| (digit - b'0') as u32 * if index % 2 == 0 { 1 } else { 3 })
+                .sum();
+            sum.is_multiple_of(10)
+        }
+        10 if compact[..9].chars().all(|c| c.is_ascii_digit()) => {
+            let check = match compact.as_bytes()[9] {
+                b'X' => 10,
+                digit @ b'0'..=b'9' => (digit - b'0') as u32,
+                _ => return None,
+            };
+            let sum: u32 = compact[..9].bytes().enumerate()
+                .map(|(index, digit)| (digit - b'0') as u32 * (10 - index as u32))
+                .sum();
+            (sum + check).is_multiple_of(11)
+        }
+        _ => false,
+    };
+
+    valid.then_some(compact)
+}
+
+/// The ISBN-13 of a valid ISBN-10 or ISBN-13, the form printed as an EAN-13 barcode.
+pub fn to_isbn13(isbn: &str) -> Option<String> {
+    let isbn = normalize_isbn(isbn)?;
+    if isbn.len() == 13 {
+        return Some(isbn);
+    }
+
+    let payload = format!("978{}", &isbn[..9]);
+    let sum: u32 = payload.bytes().enumerate()
+        .map(|(index, digit)| (digit - b'0') as u32 * if index % 2 == 0 { 1 } else { 3 })
+        .sum();
+    Some(format!("{}{}", payload, (10 - sum % 10) % 10))
+}
+
+/// The ISBN-10 of a valid ISBN, for ISBN-13s starting with 978, which older books are
+/// catalogued under.
+pub fn to_isbn10(isbn: &str) -> Option<String> {
+    let isbn = normalize_isbn(isbn)?;
+    if isbn.len() == 10 {
+        return Some(isbn);
+    }
+
+    let payload = isbn.strip_prefix("978")?[..9].to_string();
+    let sum: u32 = payload.bytes().enumerate()
+        .map(|(index, digit)| (digit - b'0') as u32 * (10 - index as u32))
+        .sum();
+    let check = match (11 - sum % 11) % 11 {
+        10 => 'X',
+        check => char::from_digit(check, 10)?,
+    };
+    Some(format!("{}{}", payload, check))
+}
+
+fn required(errors: &mut Vec<ValidationError>, field: &str, value: &str) -> String {
+    let value = value.trim();
+    if value.is_empty() {
+        errors.push(ValidationError::new(field, "Must not be empty"));
+    }
+    value.to_string()
+}
+
+fn check_book(book: &Book, errors: &mut Vec<ValidationError>) -> Book {
+    if book.items <= 0 {
+        errors.push(ValidationError::new("items", "Must be at least 1"));
+    }
+
+    Book {
+        isbn: book.isbn.clone(),
+        title: required(errors, "title", &book.title),
+        author: required(errors, "author", &book.author),
+        items: book.items,
+        deleted_at: book.deleted_at,
+    }
+}
+
+/// Validates a book about to be added and returns it with a normalized ISBN and trimmed text.
+pub fn validate_book(book: &Book) -> Result<Book, Vec<ValidationError>> {
+    let mut errors = Vec::new();
+    let mut checked = check_book(book, &mut errors);
+
+    match normalize_isbn(&book.isbn) {
+        Some(isbn) => checked.isbn = isbn,
+        None => errors.push(ValidationError::new("isbn", format!("'{}' is not a valid ISBN", book.isbn))),
+    }
+
+    if errors.is_empty() { Ok(checked) } else { Err(errors) }
+}
+
+/// Like [`validate_book`] for a book already in the catalog, whose ISBN is its key and stays as it is.
+pub fn validate_book_changes(book: &Book) -> Result<Book, Vec<ValidationError>> {
+    let mut errors = Vec::new();
+    let checked = check_book(book, &mut errors);
+
+    if errors.is_empty() { Ok(checked) } else { Err(errors) }
+}
+
+/// Validates a client and returns it with its phone number in E.164 and trimmed text.
+/// The ID is only checked against `id_pattern` when one is given, since it cannot
+/// change once the client exists.
+pub fn validate_client(client: &Client, id_pattern: Option<&Regex>) -> Result<Client, Vec<ValidationError>> {
+    let mut errors = Vec::new();
+
+    if let Some(pattern) = id_pattern {
+        if !pattern.is_match(&client.id) {
+            errors.push(ValidationError::new("id", format!("'{}' does not match the client ID pattern", client.id)));
+        }
+    }
+
+    let first_name = required(&mut errors, "firstName", &client.first_name);
+    let last_name = required(&mut errors, "lastName", &client.last_name);
+
+    let email = client.email.trim().to_lowercase();
+    if !is_valid_email(&email) {
+        errors.push(ValidationError::new("email", format!("'{}' is not a valid email address", client.email)));
+    }
+
+    let phone = normalize_phone(&client.phone).unwrap_or_else(|| {
+        errors.push(ValidationError::new("phone", format!("'{}' is not a valid phone number", client.phone)));
+        client.phone.clone()
+    });
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(Client { id: client.id.clone(), first_name, last_name, email, phone, deleted_at: client.deleted_at })
+}
@@ -0,0 +1,124 @@
+//! Backups taken while the database is open, checked before they are restored over it.
+
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use diesel::SqliteConnection;
+
+use libra_manager::backup;
+use libra_manager::branches::DEFAULT_BRANCH;
+use libra_manager::database::{establish_connection, DatabaseConnection};
+use libra_manager::models::database::Book;
+use libra_manager::services::book_service;
+use libra_manager::Error;
+
+/// A folder of its own for each test, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("libra-{}-{}-{:?}", name, std::process::id(), thread::current().id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn book(isbn: &str, title: &str) -> Book {
+    Book { isbn: isbn.to_string(), title: title.to_string(), author: "Mircea Eliade".to_string(), items: 1, deleted_at: None }
+}
+
+fn open(path: &Path) -> SqliteConnection {
+    establish_connection(path.to_str().unwrap()).unwrap()
+}
+
+fn titles(connection: &mut SqliteConnection) -> Vec<String> {
+    book_service::fetch_books(connection, false).unwrap().into_iter().map(|book| book.title).collect()
+}
+
+#[test]
+fn a_backup_restores_the_database_as_it_was() {
+    let directory = TempDir::new("backup");
+    let (live, copy) = (directory.join("libra.sqlite"), directory.join("copy.sqlite"));
+    let mut connection = open(&live);
+    book_service::create_book(&mut connection, &book("9789734633456", "Maitreyi"), DEFAULT_BRANCH).unwrap();
+
+    // taken while the connection is open
+    backup::backup_database(&live, &copy, None).unwrap();
+    backup::validate_backup(&copy, None).unwrap();
+
+    book_service::create_book(&mut connection, &book("9780306406157", "Nuntă în cer"), DEFAULT_BRANCH).unwrap();
+    assert_eq!(titles(&mut connection).len(), 2);
+
+    backup::restore_database(&mut connection, &live, &copy, None).unwrap();
+    assert_eq!(titles(&mut connection), ["Maitreyi"]);
+}
+
+#[test]
+fn restores_wait_until_no_connection_is_in_use() {
+    let directory = TempDir::new("backup-pool");
+    let (live, copy) = (directory.join("libra.sqlite"), directory.join("copy.sqlite"));
+    let database = DatabaseConnection::from(live.to_str().unwrap()).unwrap();
+    book_service::create_book(&mut database.get().unwrap(), &book("9789734633456", "Maitreyi"), DEFAULT_BRANCH).unwrap();
+    backup::backup_database(&live, &copy, None).unwrap();
+    book_service::create_book(&mut database.get().unwrap(), &book("9780306406157", "Nuntă în cer"), DEFAULT_BRANCH).unwrap();
+
+    // as a server request would, while the restore starts
+    let busy = database.get().unwrap();
+    thread::scope(|scope| {
+        let restore = scope.spawn(|| database.restore(&copy));
+        thread::sleep(Duration::from_millis(200));
+        assert!(!restore.is_finished());
+
+        drop(busy);
+        restore.join().unwrap().unwrap();
+    });
+
+    assert_eq!(titles(&mut database.get().unwrap()), ["Maitreyi"]);
+}
+
+#[test]
+fn files_which_are_not_backups_are_refused() {
+    let directory = TempDir::new("backup-invalid");
+    let (live, other) = (directory.join("libra.sqlite"), directory.join("other.sqlite"));
+    let mut connection = open(&live);
+    book_service::create_book(&mut connection, &book("9789734633456", "Maitreyi"), DEFAULT_BRANCH).unwrap();
+
+    // a database of something else
+    rusqlite::Connection::open(&other).unwrap().execute_batch("CREATE TABLE notes (text TEXT)").unwrap();
+    assert!(matches!(backup::validate_backup(&other, None), Err(Error::IncompatibleSchema(_))));
+    assert!(matches!(backup::restore_database(&mut connection, &live, &other, None), Err(Error::IncompatibleSchema(_))));
+
+    // a backup made by a newer version
+    let newer = directory.join("newer.sqlite");
+    backup::backup_database(&live, &newer, None).unwrap();
+    rusqlite::Connection::open(&newer).unwrap()
+        .execute_batch("INSERT INTO __diesel_schema_migrations (version) VALUES ('29991231000000')").unwrap();
+    assert!(matches!(backup::validate_backup(&newer, None), Err(Error::IncompatibleSchema(_))));
+
+    assert_eq!(titles(&mut connection), ["Maitreyi"]);
+}
+
+#[test]
+fn scheduled_backups_wait_for_their_interval() {
+    let directory = TempDir::new("backup-schedule");
+    let live = directory.join("libra.sqlite");
+    let backups = directory.join("backups");
+    open(&live);
+
+    let interval = Duration::from_secs(3600);
+    let first = backup::scheduled_backup(&live, &backups, interval, 3, None).unwrap().unwrap();
+    backup::validate_backup(&first, None).unwrap();
+    assert!(backup::scheduled_backup(&live, &backups, interval, 3, None).unwrap().is_none());
+}
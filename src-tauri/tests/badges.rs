@@ -0,0 +1,186 @@
+//! Badge templates: read from TOML or JSON next to the settings and drawn as laid out.
+
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use image::{Rgba, RgbaImage};
+
+use libra_manager::badges::{self, Align, BadgeData, BadgeTemplate, Color, Logo, TemplateLoader, TextField};
+use libra_manager::barcode::Symbology;
+use libra_manager::Error;
+
+const RED: Color = Color(Rgba([220, 0, 0, 255]));
+
+fn data() -> BadgeData<'static> {
+    BadgeData {
+        client_id: "LM-CEN-0004215",
+        client_name: "Ana Popescu",
+        library_name: "Centrală",
+        issued: NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(),
+        photo: None,
+        symbology: Symbology::Code128,
+        signed_id: "LM-CEN-0004215|Centrală|0000000000000000",
+    }
+}
+
+/// A folder of its own for each test, emptied first.
+fn directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("libra-badges-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn has_color(image: &RgbaImage, area: (u32, u32, u32, u32), color: Color) -> bool {
+    let (x, y, width, height) = area;
+    (x..x + width).any(|x| (y..y + height).any(|y| image.get_pixel(x, y) == &color.0))
+}
+
+#[test]
+fn default_template_round_trips_through_toml_and_json() {
+    let template = BadgeTemplate::default();
+
+    for json in [false, true] {
+        let parsed = BadgeTemplate::parse(&template.to_source(json), json).unwrap();
+        assert_eq!(parsed.width, 450);
+        assert_eq!(parsed.fields.len(), template.fields.len());
+        assert_eq!(parsed.fields[0].text, "Biblioteca {libraryName}");
+        assert_eq!(parsed.barcode.x, None);
+    }
+}
+
+#[test]
+fn colors_are_written_in_hex() {
+    let source = r##"
+        width = 300
+        height = 200
+        background = "#fafafa"
+        borderColor = "#00000080"
+
+        [barcode]
+        y = 100
+        height = 50
+        xdim = 1
+    "##;
+    let template = BadgeTemplate::parse(source, false).unwrap();
+    assert_eq!(template.background, Color(Rgba([250, 250, 250, 255])));
+    assert_eq!(template.border_color, Color(Rgba([0, 0, 0, 128])));
+    assert_eq!(String::from(template.border_color), "#00000080");
+
+    let Err(Error::Validation(errors)) = BadgeTemplate::parse(&source.replace("#fafafa", "white"), false) else {
+        panic!("a color name is not a color");
+    };
+    assert_eq!(errors[0].field, "template");
+}
+
+#[test]
+fn fields_are_filled_in_and_aligned() {
+    let template = BadgeTemplate {
+        fields: vec![TextField {
+            text: "{clientName}".to_string(),
+            x: 440,
+            y: 20,
+            font: "bold".to_string(),
+            size: 20.0,
+            color: RED,
+            align: Align::Right,
+        }],
+        ..Default::default()
+    };
+
+    let badge = badges::render(&template, &data(), Path::new("")).unwrap();
+
+    assert!(has_color(&badge, (300, 20, 140, 25), RED));
+    assert!(!has_color(&badge, (0, 0, 300, 250), RED));
+}
+
+#[test]
+fn barcode_goes_where_the_template_puts_it() {
+    let mut template = BadgeTemplate::default();
+    template.fields.clear();
+    template.photo = None;
+    template.barcode.x = Some(10);
+    template.barcode.y = 10;
+    template.barcode.color = RED;
+
+    let badge = badges::render(&template, &data(), Path::new("")).unwrap();
+
+    assert!(has_color(&badge, (10, 10, 50, 75), RED));
+    assert!(!has_color(&badge, (0, 150, 450, 100), RED));
+}
+
+#[test]
+fn logo_is_read_next_to_the_template() {
+    let directory = directory("logo");
+    RgbaImage::from_pixel(40, 40, RED.0).save(directory.join("logo.png")).unwrap();
+
+    let mut template = BadgeTemplate {
+        logo: Some(Logo { path: "logo.png".to_string(), x: 380, y: 8, width: 32, height: 32 }),
+        ..Default::default()
+    };
+    let badge = badges::render(&template, &data(), &directory).unwrap();
+    assert_eq!(badge.get_pixel(390, 20), &RED.0);
+
+    template.logo.as_mut().unwrap().path = "missing.png".to_string();
+    let Err(Error::Validation(errors)) = badges::render(&template, &data(), &directory) else {
+        panic!("a missing logo has to be reported");
+    };
+    assert_eq!(errors[0].field, "logo");
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn templates_that_cannot_be_drawn_are_refused() {
+    let mut template = BadgeTemplate { border_width: 200, ..Default::default() };
+    template.barcode.xdim = 0;
+
+    let Err(Error::Validation(errors)) = template.validate() else {
+        panic!("the template has to be refused");
+    };
+    let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+    assert_eq!(fields, ["borderWidth", "barcode"]);
+}
+
+#[test]
+fn sizes_too_large_to_add_up_are_refused() {
+    let mut template = BadgeTemplate { border_width: u32::MAX / 2 + 1, ..Default::default() };
+    template.barcode.xdim = u32::MAX;
+    let Err(Error::Validation(errors)) = template.validate() else {
+        panic!("the template has to be refused");
+    };
+    let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+    assert_eq!(fields, ["borderWidth", "barcode"]);
+
+    // a barcode placed at the far end of the range is reported instead of wrapping around
+    let mut template = BadgeTemplate::default();
+    template.barcode.x = Some(u32::MAX - 10);
+    let Err(Error::Validation(errors)) = badges::render(&template, &data(), Path::new("")) else {
+        panic!("the barcode does not fit");
+    };
+    assert_eq!(errors[0].field, "barcode");
+
+    template.barcode.x = Some(10);
+    template.barcode.y = u32::MAX;
+    assert!(matches!(badges::render(&template, &data(), Path::new("")), Err(Error::Validation(_))));
+}
+
+#[test]
+fn loader_writes_the_default_template_and_keeps_it_when_a_broken_one_is_saved() {
+    let directory = directory("loader");
+    let loader = TemplateLoader::from(&directory);
+
+    let source = loader.source().unwrap();
+    assert!(directory.join("badge.toml").exists());
+    assert_eq!(loader.load().unwrap().fields.len(), BadgeTemplate::default().fields.len());
+
+    assert!(loader.store("width = \"wide\"").is_err());
+    assert_eq!(loader.source().unwrap(), source);
+
+    // a JSON template takes over once there is one
+    std::fs::write(directory.join("badge.json"), BadgeTemplate::default().to_source(true)).unwrap();
+    let loader = TemplateLoader::from(&directory);
+    assert!(loader.source().unwrap().trim_start().starts_with('{'));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,248 @@
+//! Circulation rules, checked against a fresh in-memory database with every migration applied.
+
+use chrono::{Days, NaiveDate};
+use diesel::SqliteConnection;
+
+use libra_manager::branches::{self, DEFAULT_BRANCH};
+use libra_manager::database::establish_connection;
+use libra_manager::models::database::{Book, Client, NewBranch, User};
+use libra_manager::services::borrow_service::LOAN_PERIOD;
+use libra_manager::services::{book_service, borrow_service, client_service, user_service};
+use libra_manager::{validation, Error};
+
+const ISBN: &str = "9789734633456";
+
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, 19).unwrap()
+}
+
+fn database() -> SqliteConnection {
+    establish_connection(":memory:").expect("in-memory database")
+}
+
+fn book(isbn: &str, items: i32) -> Book {
+    Book { isbn: isbn.to_string(), title: "Maitreyi".to_string(), author: "Mircea Eliade".to_string(), items, deleted_at: None }
+}
+
+fn client(id: &str, phone: &str) -> Client {
+    Client {
+        id: id.to_string(),
+        first_name: "Ana".to_string(),
+        last_name: "Popescu".to_string(),
+        email: format!("{}@example.com", id.to_lowercase()),
+        phone: phone.to_string(),
+        deleted_at: None,
+    }
+}
+
+/// A library with `items` copies of [`ISBN`] at the default branch and clients `C0001`, `C0002` and `C0003`.
+fn library(items: i32) -> SqliteConnection {
+    let mut connection = database();
+    book_service::create_book(&mut connection, &book(ISBN, items), DEFAULT_BRANCH).unwrap();
+    let id_pattern = validation::client_id_pattern(validation::DEFAULT_CLIENT_ID_PATTERN).unwrap();
+    for (id, phone) in [("C0001", "0722000001"), ("C0002", "0722000002"), ("C0003", "0722000003")] {
+        client_service::create_client(&mut connection, &client(id, phone), &id_pattern).unwrap();
+    }
+    connection
+}
+
+fn second_branch(connection: &mut SqliteConnection) -> i32 {
+    branches::create_branch(connection, &NewBranch { code: "NRD".to_string(), name: "Filiala Nord".to_string() }).unwrap().id
+}
+
+fn available(connection: &mut SqliteConnection, client_id: &str, branch: Option<i32>) -> Option<bool> {
+    borrow_service::is_book_available(connection, ISBN, client_id, branch).unwrap()
+}
+
+#[test]
+fn lends_until_every_copy_is_out() {
+    let mut connection = library(2);
+
+    borrow_service::add_borrow(&mut connection, ISBN, "C0001", DEFAULT_BRANCH, today()).unwrap();
+    assert_eq!(available(&mut connection, "C0002", None), Some(true));
+
+    borrow_service::add_borrow(&mut connection, ISBN, "C0002", DEFAULT_BRANCH, today()).unwrap();
+    assert_eq!(available(&mut connection, "C0003", None), Some(false));
+    assert_eq!(available(&mut connection, "C0003", Some(DEFAULT_BRANCH)), Some(false));
+}
+
+#[test]
+fn a_client_cannot_borrow_the_same_book_twice() {
+    let mut connection = library(3);
+
+    borrow_service::add_borrow(&mut connection, ISBN, "C0001", DEFAULT_BRANCH, today()).unwrap();
+
+    assert_eq!(available(&mut connection, "C0001", None), Some(false));
+    assert_eq!(available(&mut connection, "C0002", None), Some(true));
+}
+
+#[test]
+fn returning_a_book_frees_the_copy() {
+    let mut connection = library(1);
+
+    borrow_service::add_borrow(&mut connection, ISBN, "C0001", DEFAULT_BRANCH, today()).unwrap();
+    assert_eq!(available(&mut connection, "C0002", None), Some(false));
+
+    let borrow = &borrow_service::fetch_borrowed_books(&mut connection, "C0001").unwrap()[0].borrow;
+    borrow_service::update_borrow(&mut connection, borrow.id, true, borrow.end_date).unwrap();
+
+    assert_eq!(available(&mut connection, "C0002", None), Some(true));
+    assert_eq!(available(&mut connection, "C0001", None), Some(true));
+}
+
+#[test]
+fn loans_last_the_loan_period() {
+    let mut connection = library(1);
+
+    borrow_service::add_borrow(&mut connection, ISBN, "C0001", DEFAULT_BRANCH, today()).unwrap();
+
+    let borrowers = borrow_service::fetch_borrowers(&mut connection, ISBN).unwrap();
+    assert_eq!(borrowers.len(), 1);
+    assert_eq!(borrowers[0].client.id, "C0001");
+    assert_eq!(borrowers[0].borrow.start_date, today());
+    assert_eq!(borrowers[0].borrow.end_date, today() + Days::new(LOAN_PERIOD));
+    assert!(!borrowers[0].borrow.returned);
+}
+
+#[test]
+fn archived_books_cannot_be_lent() {
+    let mut connection = library(1);
+
+    book_service::archive_book(&mut connection, ISBN).unwrap();
+
+    assert_eq!(available(&mut connection, "C0001", None), None);
+    assert!(matches!(
+        borrow_service::add_borrow(&mut connection, ISBN, "C0001", DEFAULT_BRANCH, today()),
+        Err(Error::Archived(_))
+    ));
+
+    book_service::restore_book(&mut connection, ISBN).unwrap();
+    assert_eq!(available(&mut connection, "C0001", None), Some(true));
+}
+
+#[test]
+fn unknown_books_are_not_available() {
+    let mut connection = library(1);
+
+    assert_eq!(borrow_service::is_book_available(&mut connection, "9780000000002", "C0001", None).unwrap(), None);
+}
+
+#[test]
+fn archived_clients_cannot_borrow() {
+    let mut connection = library(1);
+
+    client_service::archive_client(&mut connection, "C0001").unwrap();
+
+    assert!(matches!(
+        borrow_service::add_borrow(&mut connection, ISBN, "C0001", DEFAULT_BRANCH, today()),
+        Err(Error::Archived(_))
+    ));
+    assert!(client_service::fetch_client(&mut connection, "C0001", false).unwrap().is_none());
+    assert!(client_service::fetch_client(&mut connection, "C0001", true).unwrap().is_some());
+}
+
+#[test]
+fn clients_with_books_on_loan_cannot_be_archived() {
+    let mut connection = library(1);
+
+    borrow_service::add_borrow(&mut connection, ISBN, "C0001", DEFAULT_BRANCH, today()).unwrap();
+    assert!(matches!(client_service::archive_client(&mut connection, "C0001"), Err(Error::ActiveLoans(_))));
+
+    let borrow = &borrow_service::fetch_borrowed_books(&mut connection, "C0001").unwrap()[0].borrow;
+    borrow_service::update_borrow(&mut connection, borrow.id, true, borrow.end_date).unwrap();
+
+    client_service::archive_client(&mut connection, "C0001").unwrap();
+    assert_eq!(client_service::count_clients(&mut connection).unwrap(), 2);
+}
+
+#[test]
+fn only_archived_records_are_purged() {
+    let mut connection = library(1);
+    borrow_service::add_borrow(&mut connection, ISBN, "C0001", DEFAULT_BRANCH, today()).unwrap();
+
+    book_service::purge_book(&mut connection, ISBN).unwrap();
+    assert!(book_service::fetch_book(&mut connection, ISBN, true).unwrap().is_some());
+
+    book_service::archive_book(&mut connection, ISBN).unwrap();
+    book_service::purge_book(&mut connection, ISBN).unwrap();
+    assert!(book_service::fetch_book(&mut connection, ISBN, true).unwrap().is_none());
+    assert!(borrow_service::fetch_borrowed_books(&mut connection, "C0001").unwrap().is_empty());
+}
+
+#[test]
+fn copies_are_lent_from_the_branch_that_holds_them() {
+    let mut connection = library(1);
+    let north = second_branch(&mut connection);
+
+    assert_eq!(available(&mut connection, "C0001", Some(DEFAULT_BRANCH)), Some(true));
+    assert_eq!(available(&mut connection, "C0001", Some(north)), Some(false));
+
+    branches::transfer(&mut connection, ISBN, DEFAULT_BRANCH, north, 1, "admin").unwrap();
+
+    assert_eq!(available(&mut connection, "C0001", Some(DEFAULT_BRANCH)), Some(false));
+    assert_eq!(available(&mut connection, "C0001", Some(north)), Some(true));
+    // the library as a whole still has its copy
+    assert_eq!(available(&mut connection, "C0001", None), Some(true));
+}
+
+#[test]
+fn copies_on_loan_cannot_be_transferred() {
+    let mut connection = library(2);
+    let north = second_branch(&mut connection);
+
+    borrow_service::add_borrow(&mut connection, ISBN, "C0001", DEFAULT_BRANCH, today()).unwrap();
+
+    assert!(matches!(
+        branches::transfer(&mut connection, ISBN, DEFAULT_BRANCH, north, 2, "admin"),
+        Err(Error::InsufficientStock { available: 1, .. })
+    ));
+    branches::transfer(&mut connection, ISBN, DEFAULT_BRANCH, north, 1, "admin").unwrap();
+    assert_eq!(branches::available_copies(&mut connection, ISBN, DEFAULT_BRANCH).unwrap(), 0);
+}
+
+#[test]
+fn new_copies_are_shelved_at_the_editing_branch() {
+    let mut connection = library(1);
+    let north = second_branch(&mut connection);
+
+    book_service::update_book(&mut connection, &book(ISBN, 3), north).unwrap();
+
+    assert_eq!(branches::available_copies(&mut connection, ISBN, DEFAULT_BRANCH).unwrap(), 1);
+    assert_eq!(branches::available_copies(&mut connection, ISBN, north).unwrap(), 2);
+}
+
+#[test]
+fn branches_are_deleted_once_nothing_refers_to_them() {
+    let mut connection = library(1);
+    let user = User { username: "ion".to_string(), password: "parola".to_string(), first_name: "A".to_string(), last_name: "B".to_string(), role: "user".to_string(), branch_id: None };
+    user_service::create_user(&mut connection, &user).unwrap();
+
+    // a branch a desk works at, but which never held anything
+    let north = second_branch(&mut connection);
+    user_service::select_branch(&mut connection, "ion", north).unwrap();
+    branches::delete_branch(&mut connection, north).unwrap();
+    assert_eq!(user_service::fetch_user(&mut connection, "ion").unwrap().branch_id, None);
+
+    let north = second_branch(&mut connection);
+    assert!(matches!(branches::transfer(&mut connection, ISBN, north, north, 1, "admin"), Err(Error::Validation(_))));
+    branches::transfer(&mut connection, ISBN, DEFAULT_BRANCH, north, 1, "admin").unwrap();
+
+    let Err(Error::Validation(errors)) = branches::delete_branch(&mut connection, north) else { panic!("the branch holds a copy") };
+    assert_eq!(errors.iter().map(|error| error.field.as_str()).collect::<Vec<_>>(), ["holdings", "transfers"]);
+}
+
+#[test]
+fn only_admins_may_purge() {
+    let mut connection = database();
+    for (username, role) in [("ana", "admin"), ("ion", "user")] {
+        let user = User { username: username.to_string(), password: "parola".to_string(), first_name: "A".to_string(), last_name: "B".to_string(), role: role.to_string(), branch_id: None };
+        user_service::create_user(&mut connection, &user).unwrap();
+    }
+
+    assert!(user_service::authorize_admin(&mut connection, "ana", "parola").is_ok());
+    assert!(matches!(user_service::authorize_admin(&mut connection, "ana", "gresit"), Err(Error::AuthError)));
+    assert!(matches!(user_service::authorize_admin(&mut connection, "ion", "parola"), Err(Error::AuthError)));
+    assert_eq!(user_service::login(&mut connection, "ion", "parola", Some(DEFAULT_BRANCH)).unwrap().branch_id, Some(DEFAULT_BRANCH));
+    // a desk still set to a deleted branch keeps the one the user last worked at
+    assert_eq!(user_service::login(&mut connection, "ion", "parola", Some(42)).unwrap().branch_id, Some(DEFAULT_BRANCH));
+}
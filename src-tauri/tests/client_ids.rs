@@ -0,0 +1,142 @@
+//! Generated client IDs and the check digit that guards scans of them.
+
+use diesel::SqliteConnection;
+
+use libra_manager::branches::{self, DEFAULT_BRANCH};
+use libra_manager::client_ids::{self, CheckDigit, ClientIdFormat};
+use libra_manager::database::establish_connection;
+use libra_manager::models::database::{Client, NewBranch};
+use libra_manager::services::client_service;
+use libra_manager::validation::{self, DEFAULT_CLIENT_ID_PATTERN};
+use libra_manager::Error;
+
+fn client(id: &str, phone: &str) -> Client {
+    Client {
+        id: id.to_string(),
+        first_name: "Ana".to_string(),
+        last_name: "Popescu".to_string(),
+        email: format!("ana{}@example.ro", phone),
+        phone: phone.to_string(),
+        deleted_at: None,
+    }
+}
+
+fn register(connection: &mut SqliteConnection, format: &ClientIdFormat, branch: i32, phone: &str) -> String {
+    let pattern = validation::client_id_pattern(DEFAULT_CLIENT_ID_PATTERN).unwrap();
+    client_service::register_client(connection, &client("", phone), format, branch, &pattern).unwrap().id
+}
+
+#[test]
+fn check_digits_match_the_usual_algorithms() {
+    assert_eq!(CheckDigit::Luhn.compute("7992739871"), Some('3'));
+    assert_eq!(CheckDigit::Mod11.compute("036532"), Some('7'));
+    assert_eq!(CheckDigit::None.compute("7992739871"), None);
+
+    assert!(CheckDigit::Luhn.verify("79927398713"));
+    assert!(!CheckDigit::Luhn.verify("79927398731"));
+    assert!(!CheckDigit::Luhn.verify(""));
+}
+
+#[test]
+fn letters_are_part_of_the_check() {
+    let format = ClientIdFormat::default();
+    let id = format.format("CEN", 421);
+
+    assert!(id.starts_with("LM-CEN-000421"));
+    assert!(format.verify(&id).is_ok());
+    assert!(format.verify(&id.to_lowercase()).is_ok());
+    assert!(matches!(format.verify(&id.replace("CEN", "CEM")), Err(Error::InvalidClientId(_))));
+    assert!(matches!(format.verify(&id.replace("421", "412")), Err(Error::InvalidClientId(_))));
+}
+
+#[test]
+fn mod11_uses_x_for_ten() {
+    let format = ClientIdFormat { check_digit: CheckDigit::Mod11, ..ClientIdFormat::default() };
+
+    let ids: Vec<String> = (1..200).map(|sequence| format.format("CEN", sequence)).collect();
+    assert!(ids.iter().any(|id| id.ends_with('X')));
+    assert!(ids.iter().all(|id| format.verify(id).is_ok()));
+}
+
+#[test]
+fn ids_typed_in_by_hand_are_not_checked() {
+    let format = ClientIdFormat::default();
+
+    assert!(!format.is_generated("aB3xYz"));
+    assert!(format.verify("aB3xYz").is_ok());
+}
+
+#[test]
+fn every_branch_counts_on_its_own() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let north = branches::create_branch(&mut connection, &NewBranch { code: "NRD".to_string(), name: "Filiala Nord".to_string() }).unwrap().id;
+    let format = ClientIdFormat::default();
+
+    let first = register(&mut connection, &format, DEFAULT_BRANCH, "0722000001");
+    let second = register(&mut connection, &format, DEFAULT_BRANCH, "0722000002");
+    let elsewhere = register(&mut connection, &format, north, "0722000003");
+
+    assert_eq!(first, format.format("CEN", 1));
+    assert_eq!(second, format.format("CEN", 2));
+    assert_eq!(elsewhere, format.format("NRD", 1));
+}
+
+#[test]
+fn taken_ids_are_skipped() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let format = ClientIdFormat { branch_code: false, check_digit: CheckDigit::None, ..ClientIdFormat::default() };
+    let pattern = validation::client_id_pattern(DEFAULT_CLIENT_ID_PATTERN).unwrap();
+
+    client_service::create_client(&mut connection, &client("LM-000001", "0722000001"), &pattern).unwrap();
+
+    assert_eq!(client_ids::generate(&mut connection, &format, DEFAULT_BRANCH).unwrap(), "LM-000002");
+}
+
+#[test]
+fn scans_with_a_wrong_check_digit_are_refused() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let format = ClientIdFormat::default();
+    let id = register(&mut connection, &format, DEFAULT_BRANCH, "0722000001");
+
+    assert!(client_service::lookup_client(&mut connection, &id, &format, false).unwrap().is_some());
+
+    let mut misread = id.clone();
+    let last = misread.pop().unwrap();
+    misread.push(if last == '0' { '1' } else { '0' });
+    assert!(matches!(client_service::lookup_client(&mut connection, &misread, &format, false), Err(Error::InvalidClientId(_))));
+}
+
+#[test]
+fn synced_installations_take_turns_at_numbers() {
+    let formats = [1, 2].map(|installation| ClientIdFormat { installations: 2, installation, ..ClientIdFormat::default() });
+    let ids: Vec<Vec<String>> = formats.iter().map(|format| {
+        let mut connection = establish_connection(":memory:").unwrap();
+        (1..=3).map(|n| register(&mut connection, format, DEFAULT_BRANCH, &format!("072200000{}", n))).collect()
+    }).collect();
+
+    assert_eq!(ids[0], [1, 3, 5].map(|sequence| formats[0].format("CEN", sequence)));
+    assert_eq!(ids[1], [2, 4, 6].map(|sequence| formats[1].format("CEN", sequence)));
+
+    let mut connection = establish_connection(":memory:").unwrap();
+    let invalid = ClientIdFormat { installations: 2, installation: 3, ..ClientIdFormat::default() };
+    assert!(matches!(client_ids::generate(&mut connection, &invalid, DEFAULT_BRANCH), Err(Error::Validation(_))));
+}
+
+#[test]
+fn badges_outlive_the_format_they_were_printed_with() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let id = register(&mut connection, &ClientIdFormat::default(), DEFAULT_BRANCH, "0722000001");
+
+    for format in [
+        ClientIdFormat { check_digit: CheckDigit::Mod11, ..ClientIdFormat::default() },
+        ClientIdFormat { separator: "/".to_string(), ..ClientIdFormat::default() },
+    ] {
+        assert!(client_service::lookup_client(&mut connection, &id, &format, false).unwrap().is_some());
+    }
+
+    // only the configured algorithm is trusted for IDs not on record
+    let mod11 = ClientIdFormat { check_digit: CheckDigit::Mod11, ..ClientIdFormat::default() };
+    let luhn = ClientIdFormat::default().format("CEN", 77);
+    assert_ne!(luhn, mod11.format("CEN", 77));
+    assert!(matches!(mod11.verify(&luhn), Err(Error::InvalidClientId(_))));
+}
@@ -0,0 +1,140 @@
+//! CSV files imported all or nothing, and exports which import back into an empty database.
+
+use std::path::PathBuf;
+use std::thread;
+
+use chrono::NaiveDate;
+use diesel::{RunQueryDsl, SqliteConnection};
+
+use libra_manager::branches::DEFAULT_BRANCH;
+use libra_manager::csv_io::{self, ColumnMapping, Entity, ExportFilter};
+use libra_manager::database::establish_connection;
+use libra_manager::models::database::{Book, Client};
+use libra_manager::schema::clients;
+use libra_manager::services::{book_service, borrow_service, client_service};
+use libra_manager::validation;
+
+/// A file of its own for each test, removed when dropped.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str, contents: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("libra-{}-{}-{:?}.csv", name, std::process::id(), thread::current().id()));
+        std::fs::write(&path, contents).unwrap();
+        TempFile(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn book(isbn: &str, title: &str) -> Book {
+    Book { isbn: isbn.to_string(), title: title.to_string(), author: "Mircea Eliade".to_string(), items: 2, deleted_at: None }
+}
+
+fn client(id: &str, email: &str, phone: &str) -> Client {
+    Client { id: id.to_string(), first_name: "Ana".to_string(), last_name: "Popescu".to_string(), email: email.to_string(), phone: phone.to_string(), deleted_at: None }
+}
+
+fn count(connection: &mut SqliteConnection) -> (i64, i64) {
+    (book_service::count_books(connection).unwrap(), client_service::count_clients(connection).unwrap())
+}
+
+#[test]
+fn a_dry_run_reports_every_row_and_writes_nothing() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let file = TempFile::new("dry-run", "Cod,title,author,items\n978-973-46-3345-6,Maitreyi,Mircea Eliade,2\n123,Nuntă în cer,Mircea Eliade,1\n9789734633456,Maitreyi,Mircea Eliade,x\n");
+    let mapping = ColumnMapping::from([("isbn".to_string(), "Cod".to_string())]);
+
+    let report = csv_io::import_csv(&mut connection, Entity::Book, &file.0, &mapping, true).unwrap();
+    assert_eq!((report.total_rows, report.valid_rows, report.committed), (3, 1, false));
+    let errors = report.errors.iter().map(|error| (error.row, error.field.as_deref().unwrap())).collect::<Vec<_>>();
+    assert_eq!(errors, [(3, "isbn"), (4, "items")]);
+    assert_eq!(count(&mut connection), (0, 0));
+
+    // a column the mapping does not find is reported on the header
+    let report = csv_io::import_csv(&mut connection, Entity::Book, &file.0, &ColumnMapping::new(), true).unwrap();
+    assert_eq!((report.errors[0].row, report.errors[0].field.as_deref()), (1, Some("isbn")));
+}
+
+#[test]
+fn one_invalid_row_leaves_the_database_as_it_was() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let pattern = validation::client_id_pattern(validation::DEFAULT_CLIENT_ID_PATTERN).unwrap();
+    client_service::create_client(&mut connection, &client("C0001", "ana@example.ro", "0722000001"), &pattern).unwrap();
+
+    // the last client has the phone of the one already in the database
+    let header = "id,firstName,lastName,email,phone\n";
+    let rows = "C0002,Ion,Ionescu,ion@example.ro,0722000002\nC0003,Maria,Pop,maria@example.ro,0722000003\n";
+    let file = TempFile::new("rollback", &format!("{}{}C0004,Dan,Dinu,dan@example.ro,+40 722 000 001\n", header, rows));
+
+    let report = csv_io::import_csv(&mut connection, Entity::Client, &file.0, &ColumnMapping::new(), false).unwrap();
+    assert!(!report.committed);
+    assert_eq!((report.errors[0].row, report.errors[0].field.as_deref()), (4, Some("phone")));
+    assert_eq!(count(&mut connection), (0, 1));
+
+    let file = TempFile::new("rollback-fixed", &format!("{}{}", header, rows));
+    let report = csv_io::import_csv(&mut connection, Entity::Client, &file.0, &ColumnMapping::new(), false).unwrap();
+    assert!(report.committed);
+    assert_eq!(count(&mut connection), (0, 3));
+}
+
+#[test]
+fn rows_match_what_was_saved_before_it_was_normalized() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let pattern = validation::client_id_pattern(validation::DEFAULT_CLIENT_ID_PATTERN).unwrap();
+    book_service::create_book(&mut connection, &book("9789734633456", "Maitreyi"), DEFAULT_BRANCH).unwrap();
+    client_service::create_client(&mut connection, &client("C0001", "ana@example.ro", "0722000001"), &pattern).unwrap();
+
+    // as typed in before phone numbers were brought to E.164
+    diesel::insert_into(clients::table).values(&client("C0002", "Ion@Example.ro", "0722 000 002")).execute(&mut connection).unwrap();
+
+    let file = TempFile::new("same-phone", "id,firstName,lastName,email,phone\nC0003,Dan,Dinu,dan@example.ro,+40722000002\nC0004,Dan,Dinu,ion@example.ro,0722000004\n");
+    let report = csv_io::import_csv(&mut connection, Entity::Client, &file.0, &ColumnMapping::new(), true).unwrap();
+    let errors = report.errors.iter().map(|error| (error.row, error.field.as_deref().unwrap())).collect::<Vec<_>>();
+    assert_eq!(errors, [(2, "phone"), (3, "email")]);
+
+    // the book is saved under its ISBN-13
+    let file = TempFile::new("isbn-10", "clientId,bookIsbn,startDate,endDate,returned\nC0001,973-46-3345-7,2026-10-01,2026-10-15,false\nC0002,978-973-46-3345-6,2026-10-01,2026-10-15,false\n");
+    let report = csv_io::import_csv(&mut connection, Entity::Borrow, &file.0, &ColumnMapping::new(), false).unwrap();
+    assert!(report.committed, "{:?}", report.errors);
+    assert_eq!(borrow_service::fetch_borrowers(&mut connection, "9789734633456").unwrap().len(), 2);
+}
+
+#[test]
+fn exports_import_back_without_what_was_archived() {
+    let mut library = establish_connection(":memory:").unwrap();
+    let pattern = validation::client_id_pattern(validation::DEFAULT_CLIENT_ID_PATTERN).unwrap();
+    book_service::create_book(&mut library, &book("9789734633456", "Maitreyi"), DEFAULT_BRANCH).unwrap();
+    book_service::create_book(&mut library, &book("9780306406157", "Nuntă în cer"), DEFAULT_BRANCH).unwrap();
+    client_service::create_client(&mut library, &client("C0001", "ana@example.ro", "0722000001"), &pattern).unwrap();
+    client_service::create_client(&mut library, &client("C0002", "ion@example.ro", "0722000002"), &pattern).unwrap();
+
+    let date = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+    borrow_service::add_borrow(&mut library, "9789734633456", "C0001", DEFAULT_BRANCH, date).unwrap();
+    borrow_service::add_borrow(&mut library, "9789734633456", "C0002", DEFAULT_BRANCH, date).unwrap();
+    let returned = borrow_service::fetch_borrowed_books(&mut library, "C0002").unwrap()[0].borrow.id;
+    borrow_service::update_borrow(&mut library, returned, true, date).unwrap();
+    book_service::archive_book(&mut library, "9780306406157").unwrap();
+    client_service::archive_client(&mut library, "C0002").unwrap();
+
+    let mut copy = establish_connection(":memory:").unwrap();
+    for (entity, exported) in [(Entity::Book, 1), (Entity::Client, 1), (Entity::Borrow, 1)] {
+        let file = TempFile::new("export", "");
+        assert_eq!(csv_io::export_csv(&mut library, entity, &file.0, &ExportFilter::default()).unwrap(), exported);
+
+        let text = std::fs::read_to_string(&file.0).unwrap();
+        assert_eq!(text.lines().next().unwrap(), entity.fields().join(","));
+        assert!(!text.contains("deletedAt"));
+
+        let report = csv_io::import_csv(&mut copy, entity, &file.0, &ColumnMapping::new(), false).unwrap();
+        assert!(report.committed, "{:?}", report.errors);
+    }
+
+    assert_eq!(count(&mut copy), (1, 1));
+    assert_eq!(book_service::fetch_book(&mut copy, "9789734633456", false).unwrap().unwrap().title, "Maitreyi");
+    assert_eq!(borrow_service::fetch_borrowed_books(&mut copy, "C0001").unwrap().len(), 1);
+}
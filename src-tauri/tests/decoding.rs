@@ -0,0 +1,127 @@
+//! Reading barcodes back from pictures: the labels the app prints, and photos of them.
+
+use barcoders::sym::code128::Code128;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgba, RgbaImage};
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+
+use libra_manager::barcode::{self, Symbology};
+use libra_manager::decoding::{self, ScannedCode};
+
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// `image` on white paper, `margin` pixels on each side.
+fn on_paper(image: &RgbaImage, margin: u32) -> RgbaImage {
+    let mut paper = RgbaImage::from_pixel(image.width() + 2 * margin, image.height() + 2 * margin, WHITE);
+    imageops::overlay(&mut paper, image, margin as i64, margin as i64);
+    paper
+}
+
+fn decode(image: RgbaImage) -> Vec<ScannedCode> {
+    decoding::decode(&DynamicImage::ImageRgba8(image))
+}
+
+fn code(symbology: Symbology, text: &str) -> ScannedCode {
+    ScannedCode { symbology, text: text.to_string() }
+}
+
+#[test]
+fn printed_labels_read_back() {
+    let isbn = barcode::create_isbn("9789734633456", Symbology::Ean13).unwrap();
+    assert_eq!(decode(on_paper(&isbn, 20)), vec![code(Symbology::Ean13, "9789734633456")]);
+
+    let client = barcode::draw(Symbology::Code128, "LM-CEN-0004215", 2, 60, BLACK, WHITE).unwrap();
+    assert_eq!(decode(on_paper(&client, 30)), vec![code(Symbology::Code128, "LM-CEN-0004215")]);
+
+    // other scanners print digits in code set C
+    let bars = Code128::new("\u{0106}00421523").unwrap().encode();
+    let digits = RgbaImage::from_fn(bars.len() as u32 * 2, 40, |x, _| if bars[x as usize / 2] == 1 { BLACK } else { WHITE });
+    assert_eq!(decode(on_paper(&digits, 30)), vec![code(Symbology::Code128, "00421523")]);
+}
+
+#[test]
+fn photos_of_lists_give_every_code_in_any_direction() {
+    let mut page = RgbaImage::from_pixel(700, 900, WHITE);
+    let first = barcode::create_isbn("9789734633456", Symbology::Ean13).unwrap();
+    let second = barcode::create_isbn("9780306406157", Symbology::Ean13).unwrap();
+    let client = barcode::draw(Symbology::Code128, "LM-CEN-0004215", 2, 60, BLACK, WHITE).unwrap();
+    imageops::overlay(&mut page, &first, 40, 60);
+    imageops::overlay(&mut page, &imageops::rotate90(&second), 500, 300);
+    imageops::overlay(&mut page, &imageops::rotate180(&client), 60, 700);
+
+    // a smaller, blurred photo with the light falling off to one side
+    let mut photo = imageops::resize(&page, 530, 680, FilterType::Triangle);
+    for (x, _, pixel) in photo.enumerate_pixels_mut() {
+        let shade = 1.0 - 0.35 * x as f32 / 530.0;
+        for channel in 0..3 {
+            pixel[channel] = (pixel[channel] as f32 * shade) as u8;
+        }
+    }
+
+    let codes = decode(photo);
+    assert_eq!(codes.len(), 3, "{:?}", codes);
+    assert!(codes.contains(&code(Symbology::Ean13, "9789734633456")));
+    assert!(codes.contains(&code(Symbology::Ean13, "9780306406157")));
+    assert!(codes.contains(&code(Symbology::Code128, "LM-CEN-0004215")));
+}
+
+#[test]
+fn every_symbology_the_app_prints_reads_back() {
+    let badge = "LM-CEN-0004215|Biblioteca Centrală Universitară|5c3a0e9f1b2d4c6a";
+    let labels = [
+        (Symbology::Code39, "LM-CEN-0004215", "LM-CEN-0004215"),
+        // ISBNs get a leading zero to fill the pairs of ITF, which is dropped again
+        (Symbology::Itf, "9789734633456", "9789734633456"),
+        (Symbology::Itf, "00421", "000421"),
+        (Symbology::Qr, badge, badge),
+        (Symbology::DataMatrix, "9789734633456-002", "9789734633456-002"),
+        (Symbology::DataMatrix, badge, badge),
+    ];
+    for (symbology, data, text) in labels {
+        let label = barcode::draw(symbology, data, 2, 120, BLACK, WHITE).unwrap();
+        assert_eq!(decode(on_paper(&label, 30)), vec![code(symbology, text)], "{:?}", symbology);
+    }
+}
+
+#[test]
+fn symbols_are_read_from_photos_at_an_angle() {
+    let badge = "LM-CEN-0004215|Biblioteca Centrală Universitară|5c3a0e9f1b2d4c6a";
+    let mut page = RgbaImage::from_pixel(900, 500, WHITE);
+    let qr = barcode::draw(Symbology::Qr, badge, 2, 200, BLACK, WHITE).unwrap();
+    let data_matrix = barcode::draw(Symbology::DataMatrix, "9789734633456-002", 2, 160, BLACK, WHITE).unwrap();
+    imageops::overlay(&mut page, &on_paper(&qr, 20), 60, 60);
+    imageops::overlay(&mut page, &imageops::rotate270(&on_paper(&data_matrix, 20)), 560, 80);
+
+    let tilted = rotate_about_center(&page, 0.3, Interpolation::Bilinear, WHITE);
+    let photo = imageops::resize(&tilted, 720, 400, FilterType::Triangle);
+
+    let codes = decode(photo);
+    assert_eq!(codes.len(), 2, "{:?}", codes);
+    assert!(codes.contains(&code(Symbology::Qr, badge)));
+    assert!(codes.contains(&code(Symbology::DataMatrix, "9789734633456-002")));
+}
+
+#[test]
+fn pictures_without_codes_give_nothing() {
+    let mut seed = 7u32;
+    let noise = RgbaImage::from_fn(300, 200, |_, _| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        let value = (seed >> 16) as u8;
+        Rgba([value, value, value, 255])
+    });
+    assert!(decode(noise).is_empty());
+    assert!(decode(RgbaImage::from_pixel(100, 100, WHITE)).is_empty());
+
+    // a code cut off at its end is not read
+    let isbn = barcode::create_isbn("9789734633456", Symbology::Ean13).unwrap();
+    assert!(decode(on_paper(&imageops::crop_imm(&isbn, 0, 0, 150, 75).to_image(), 20)).is_empty());
+}
+
+#[test]
+fn files_are_decoded_from_their_bytes() {
+    let isbn = on_paper(&barcode::create_isbn("9789734633456", Symbology::Ean13).unwrap(), 20);
+    let png = libra_manager::drawing::encode_png(&isbn).unwrap();
+    assert_eq!(decoding::decode_file(&png).unwrap(), vec![code(Symbology::Ean13, "9789734633456")]);
+    assert!(decoding::decode_file(b"not a picture").is_err());
+}
@@ -0,0 +1,67 @@
+//! Saving downloads: file names from templates, picked targets and never overwriting a file.
+
+use std::path::PathBuf;
+
+use libra_manager::downloads;
+use libra_manager::Error;
+
+/// A folder of its own for each test, emptied first.
+fn directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("libra-downloads-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn refused_field(result: Result<String, Error>) -> String {
+    let Err(Error::Validation(errors)) = result else {
+        panic!("the name should be refused");
+    };
+    errors[0].field.clone()
+}
+
+#[test]
+fn templates_are_filled_in_and_made_safe_for_file_names() {
+    let values = [("id", "LM-CEN-0004215"), ("firstName", "Ion"), ("lastName", "Popescu/Ionescu"), ("date", "2026-10-19")];
+
+    assert_eq!(downloads::file_name("{id}_{lastName}", &values, "badgeFileName").unwrap(), "LM-CEN-0004215_Popescu_Ionescu");
+    assert_eq!(downloads::file_name("Legitimatie {firstName} ({date})", &values, "badgeFileName").unwrap(), "Legitimatie Ion (2026-10-19)");
+    assert_eq!(downloads::sanitize("a:b*c?. "), "a_b_c_");
+
+    assert_eq!(refused_field(downloads::file_name("{name}", &values, "badgeFileName")), "badgeFileName");
+    assert_eq!(refused_field(downloads::file_name("{id", &values, "badgeFileName")), "badgeFileName");
+    assert_eq!(refused_field(downloads::file_name("{isbn}", &[("isbn", "")], "isbnFileName")), "isbnFileName");
+}
+
+#[test]
+fn files_with_the_same_name_are_numbered() {
+    let directory = directory("numbered");
+
+    let first = downloads::save(None, &directory, "Legitimatie Ion Popescu", "png", b"first").unwrap();
+    let second = downloads::save(None, &directory, "Legitimatie Ion Popescu", "png", b"second").unwrap();
+    let third = downloads::save(None, &directory, "Legitimatie Ion Popescu", "png", b"third").unwrap();
+
+    assert_eq!(first, directory.join("Legitimatie Ion Popescu.png"));
+    assert_eq!(second, directory.join("Legitimatie Ion Popescu (2).png"));
+    assert_eq!(third, directory.join("Legitimatie Ion Popescu (3).png"));
+    assert_eq!(std::fs::read(&first).unwrap(), b"first");
+    assert_eq!(std::fs::read(&second).unwrap(), b"second");
+}
+
+#[test]
+fn targets_are_folders_or_files() {
+    let directory = directory("targets");
+    let other = directory.join("other");
+    std::fs::create_dir(&other).unwrap();
+
+    // a folder gets the name from the template
+    assert_eq!(downloads::target_path(Some(&other), &directory, "9789734633456", "svg"), other.join("9789734633456.svg"));
+    // a file keeps its own name, and gets the extension of the format
+    assert_eq!(downloads::target_path(Some(&directory.join("isbn.svg")), &directory, "9789734633456", "svg"), directory.join("isbn.svg"));
+    assert_eq!(downloads::target_path(Some(&directory.join("isbn")), &directory, "9789734633456", "pdf"), directory.join("isbn.pdf"));
+    assert_eq!(downloads::target_path(None, &directory, "9789734633456", "png"), directory.join("9789734633456.png"));
+
+    let picked = directory.join("eticheta.png");
+    downloads::save(Some(&picked), &directory, "ignored", "png", b"one").unwrap();
+    assert_eq!(downloads::save(Some(&picked), &directory, "ignored", "png", b"two").unwrap(), directory.join("eticheta (2).png"));
+}
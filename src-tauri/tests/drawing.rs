@@ -0,0 +1,126 @@
+//! Labels and badges as drawings: the same layout written as pixels, SVG and PDF.
+
+use std::path::Path;
+
+use ab_glyph::{FontArc, PxScale};
+use barcoders::generators::image::{Color, Image, Rotation};
+use barcoders::sym::code128::Code128;
+use chrono::NaiveDate;
+use image::{DynamicImage, Rgb, Rgba, RgbImage, RgbaImage};
+
+use libra_manager::badges::{self, BadgeData, BadgeTemplate};
+use libra_manager::barcode::{self, Symbology};
+use libra_manager::drawing::{self, Drawing, OutputFormat, Segment};
+
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+fn font() -> FontArc {
+    FontArc::try_from_slice(include_bytes!("../src/assets/bold_font.otf")).unwrap()
+}
+
+fn pdf_text(pdf: &[u8]) -> String {
+    String::from_utf8_lossy(pdf).to_string()
+}
+
+#[test]
+fn bars_are_the_pixels_barcoders_draws() {
+    let expected = Image::ImageBuffer { height: 40, xdim: 2, rotation: Rotation::Zero, foreground: Color::new(BLACK.0), background: Color::new(WHITE.0) }
+        .generate_buffer(Code128::new("\u{0181}LM-CEN-0004215").unwrap().encode())
+        .unwrap();
+    assert_eq!(barcode::draw(Symbology::Code128, "LM-CEN-0004215", 2, 40, BLACK, WHITE).unwrap(), expected);
+}
+
+#[test]
+fn runs_of_bars_are_single_rectangles() {
+    let bars = barcode::encode_bars(Symbology::Ean13, "9789734633456").unwrap();
+    let runs = bars.iter().zip([0].iter().chain(&bars)).filter(|(bar, before)| **bar == 1 && **before == 0).count();
+
+    let label = barcode::isbn_label("9789734633456", Symbology::Ean13).unwrap();
+    let svg = label.to_svg();
+    // the white label, the background of the bars, then one rectangle per bar
+    assert_eq!(svg.matches("<rect").count(), 2 + runs);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"220\" height=\"100\""));
+}
+
+#[test]
+fn text_is_outlined_where_it_is_drawn() {
+    let mut drawing = Drawing::new(120, 60);
+    drawing.fill_rect(0, 0, 120, 60, WHITE);
+    drawing.draw_text(10, 12, PxScale::from(30.0), &font(), "LM", BLACK);
+
+    let image = drawing.to_image();
+    let dark: Vec<(u32, u32)> = image.enumerate_pixels().filter(|(_, _, pixel)| pixel[0] < 128).map(|(x, y, _)| (x, y)).collect();
+    let points: Vec<_> = drawing::outline_text(10.0, 12.0, PxScale::from(30.0), &font(), "LM").into_iter().filter_map(|segment| match segment {
+        Segment::MoveTo(point) | Segment::LineTo(point) | Segment::QuadTo(_, point) | Segment::CubicTo(_, _, point) => Some(point),
+        Segment::Close => None,
+    }).collect();
+
+    // the outlines and the pixels cover the same box, give or take the rounding of glyphs to pixels
+    let close = |a: f32, b: u32| (a - b as f32).abs() <= 1.5;
+    assert!(close(points.iter().map(|p| p.x).fold(f32::MAX, f32::min), dark.iter().map(|p| p.0).min().unwrap()));
+    assert!(close(points.iter().map(|p| p.x).fold(f32::MIN, f32::max), dark.iter().map(|p| p.0).max().unwrap() + 1));
+    assert!(close(points.iter().map(|p| p.y).fold(f32::MAX, f32::min), dark.iter().map(|p| p.1).min().unwrap()));
+    assert!(close(points.iter().map(|p| p.y).fold(f32::MIN, f32::max), dark.iter().map(|p| p.1).max().unwrap() + 1));
+
+    let svg = drawing.to_svg();
+    assert!(!svg.contains("<text"));
+    assert_eq!(svg.matches("<path").count(), 1);
+}
+
+#[test]
+fn badges_keep_their_photo_as_pixels() {
+    let photo = DynamicImage::ImageRgb8(RgbImage::from_pixel(30, 40, Rgb([200, 30, 30])));
+    let data = BadgeData {
+        client_id: "LM-CEN-0004215",
+        client_name: "Ana Popescu",
+        library_name: "Centrală",
+        issued: NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(),
+        photo: Some(&photo),
+        symbology: Symbology::Qr,
+        signed_id: "LM-CEN-0004215|Centrală|0123456789abcdef",
+    };
+    let badge = badges::layout(&BadgeTemplate::default(), &data, Path::new("")).unwrap();
+
+    assert_eq!(badge.to_image(), badges::render(&BadgeTemplate::default(), &data, Path::new("")).unwrap());
+    let svg = badge.to_svg();
+    assert_eq!(svg.matches("href=\"data:image/png;base64,").count(), 1);
+    assert!(svg.contains("aria-label=\"Biblioteca Centrală\""));
+}
+
+#[test]
+fn pdf_pages_are_the_size_of_one_label() {
+    let label = barcode::isbn_label("9789734633456", Symbology::Ean13).unwrap();
+    let pdf = label.encode(OutputFormat::Pdf, (63.5, 38.1)).unwrap();
+    let text = pdf_text(&pdf);
+
+    assert!(pdf.starts_with(b"%PDF-1.4"));
+    assert!(text.contains("/MediaBox [0 0 180.00 108.00]"));
+    assert!(text.contains(" re f"));
+    // glyphs are filled paths, there are no fonts
+    assert!(text.contains(" c ") && text.contains("h f"));
+    assert!(!text.contains("/Font"));
+    assert!(!text.contains("/XObject /Subtype /Image"));
+}
+
+#[test]
+fn transparent_images_get_a_soft_mask() {
+    let mut drawing = Drawing::new(50, 50);
+    drawing.fill_rect(0, 0, 50, 50, Rgba([0, 0, 200, 255]));
+    drawing.draw_image(5, 5, RgbaImage::from_pixel(10, 10, Rgba([255, 0, 0, 128])));
+    let text = pdf_text(&drawing.encode(OutputFormat::Pdf, (20.0, 20.0)).unwrap());
+    assert!(text.contains("/ColorSpace /DeviceGray"));
+    assert!(text.contains("/SMask 3 0 R"));
+
+    let mut opaque = Drawing::new(50, 50);
+    opaque.draw_image(5, 5, RgbaImage::from_pixel(10, 10, BLACK));
+    assert!(!pdf_text(&opaque.encode(OutputFormat::Pdf, (20.0, 20.0)).unwrap()).contains("/SMask"));
+}
+
+#[test]
+fn png_is_the_raster_label() {
+    let label = barcode::isbn_label("9789734633456", Symbology::Qr).unwrap();
+    let png = label.encode(OutputFormat::Png, (0.0, 0.0)).unwrap();
+    assert_eq!(image::load_from_memory(&png).unwrap().to_rgba8(), barcode::create_isbn("9789734633456", Symbology::Qr).unwrap());
+    assert_eq!(OutputFormat::Svg.extension(), "svg");
+}
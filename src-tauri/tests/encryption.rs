@@ -0,0 +1,128 @@
+//! Opening databases made by an older build, encrypted or not, and telling encrypted files
+//! from damaged ones.
+
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+use libra_manager::backup;
+use libra_manager::database::{self, establish_connection, DatabaseConnection};
+use libra_manager::encryption;
+use libra_manager::Error;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+
+/// A folder of its own for each test, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("libra-{}-{}-{:?}", name, std::process::id(), thread::current().id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Creates a database at `path` as the build before the latest migration left it.
+fn older_database(path: &Path) {
+    let mut connection = establish_connection(path.to_str().unwrap()).unwrap();
+    connection.revert_last_migration(MIGRATIONS).unwrap();
+}
+
+/// The copy saved before migrating, the only file in the `backups` folder.
+fn pre_migration_backup(directory: &TempDir, key: Option<&str>) -> PathBuf {
+    let backups = std::fs::read_dir(directory.join("backups")).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    assert_eq!(backups.len(), 1);
+    backup::validate_backup(&backups[0], key).unwrap();
+    backups[0].clone()
+}
+
+fn applied(path: &Path) -> usize {
+    let connection = rusqlite::Connection::open(path).unwrap();
+    connection.query_row("SELECT count(*) FROM __diesel_schema_migrations", [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn older_databases_are_saved_before_they_are_migrated() {
+    let directory = TempDir::new("migrate");
+    let path = directory.join("libra.sqlite");
+    older_database(&path);
+
+    let opened = DatabaseConnection::from(path.to_str().unwrap()).unwrap();
+    assert_eq!(database::applied_versions(&mut opened.get().unwrap()).unwrap().len(), database::embedded_versions().len());
+
+    assert_eq!(applied(&pre_migration_backup(&directory, None)), database::embedded_versions().len() - 1);
+}
+
+#[test]
+fn tools_migrate_only_when_asked() {
+    let directory = TempDir::new("unmigrated");
+    let path = directory.join("libra.sqlite");
+    older_database(&path);
+    let url = path.to_str().unwrap();
+
+    let reading = DatabaseConnection::unmigrated(url, None, true).unwrap();
+    assert!(reading.get().is_ok());
+    assert!(matches!(DatabaseConnection::unmigrated(url, None, false), Err(Error::Migration(_))));
+
+    drop(reading);
+    assert_eq!(applied(&path), database::embedded_versions().len() - 1);
+    assert!(!directory.join("backups").exists());
+}
+
+#[cfg(feature = "sqlcipher")]
+#[test]
+fn older_encrypted_databases_are_saved_before_they_are_migrated() {
+    let directory = TempDir::new("migrate-encrypted");
+    let path = directory.join("libra.sqlite");
+    older_database(&path);
+
+    let mut connection = diesel::Connection::establish(path.to_str().unwrap()).unwrap();
+    encryption::export_encrypted(&mut connection, &path, "parola").unwrap();
+    drop(connection);
+    encryption::finish_pending(&path).unwrap();
+    assert!(encryption::is_encrypted(&path).unwrap());
+
+    let opened = DatabaseConnection::encrypted(path.to_str().unwrap(), "parola").unwrap();
+    assert_eq!(database::applied_versions(&mut opened.get().unwrap()).unwrap().len(), database::embedded_versions().len());
+
+    let saved = pre_migration_backup(&directory, Some("parola"));
+    assert!(encryption::is_encrypted(&saved).unwrap());
+}
+
+#[test]
+fn damaged_files_are_not_taken_for_encrypted_ones() {
+    let directory = TempDir::new("damaged");
+    let (missing, plain) = (directory.join("missing.sqlite"), directory.join("plain.sqlite"));
+    establish_connection(plain.to_str().unwrap()).unwrap();
+    assert!(!encryption::is_encrypted(&missing).unwrap());
+    assert!(!encryption::is_encrypted(&plain).unwrap());
+
+    // cut short in the middle of a page
+    let truncated = directory.join("truncated.sqlite");
+    std::fs::write(&truncated, [7u8; 1000]).unwrap();
+    assert!(matches!(encryption::is_encrypted(&truncated), Err(Error::Damaged(_))));
+
+    // whole pages without a header look like SQLCipher wrote them
+    let pages = directory.join("pages.sqlite");
+    std::fs::write(&pages, [7u8; 8192]).unwrap();
+    if cfg!(feature = "sqlcipher") {
+        assert!(encryption::is_encrypted(&pages).unwrap());
+    } else {
+        assert!(matches!(encryption::is_encrypted(&pages), Err(Error::EncryptionUnavailable)));
+    }
+}
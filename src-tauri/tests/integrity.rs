@@ -0,0 +1,67 @@
+//! Borrows left pointing at nothing by databases edited without foreign keys, and their repair.
+
+use chrono::NaiveDate;
+use diesel::connection::SimpleConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+use libra_manager::branches::DEFAULT_BRANCH;
+use libra_manager::database::{self, establish_connection};
+use libra_manager::integrity;
+use libra_manager::models::database::{Book, Client};
+use libra_manager::services::{book_service, borrow_service, client_service};
+use libra_manager::validation;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+
+fn client(id: &str, phone: &str) -> Client {
+    Client { id: id.to_string(), first_name: "Ana".to_string(), last_name: "Popescu".to_string(), email: format!("{}@example.ro", id.to_lowercase()), phone: phone.to_string(), deleted_at: None }
+}
+
+#[test]
+fn orphaned_borrows_are_reported_then_removed() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let pattern = validation::client_id_pattern(validation::DEFAULT_CLIENT_ID_PATTERN).unwrap();
+    let book = Book { isbn: "9789734633456".to_string(), title: "Maitreyi".to_string(), author: "Mircea Eliade".to_string(), items: 2, deleted_at: None };
+    book_service::create_book(&mut connection, &book, DEFAULT_BRANCH).unwrap();
+    client_service::create_client(&mut connection, &client("C0001", "0722000001"), &pattern).unwrap();
+    client_service::create_client(&mut connection, &client("C0002", "0722000002"), &pattern).unwrap();
+
+    let date = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+    borrow_service::add_borrow(&mut connection, &book.isbn, "C0001", DEFAULT_BRANCH, date).unwrap();
+    borrow_service::add_borrow(&mut connection, &book.isbn, "C0002", DEFAULT_BRANCH, date).unwrap();
+
+    // as older versions, or another tool, could delete a client
+    connection.batch_execute("PRAGMA foreign_keys = OFF; DELETE FROM clients WHERE id = 'C0002'; PRAGMA foreign_keys = ON;").unwrap();
+
+    let report = integrity::repair(&mut connection, true).unwrap();
+    assert_eq!(report.removed, 0);
+    assert_eq!(report.orphaned_borrows.iter().map(|borrow| borrow.client_id.as_str()).collect::<Vec<_>>(), ["C0002"]);
+    assert_eq!(integrity::find_orphaned_borrows(&mut connection).unwrap().len(), 1);
+
+    let report = integrity::repair(&mut connection, false).unwrap();
+    assert_eq!((report.orphaned_borrows.len(), report.removed), (1, 1));
+    assert!(integrity::find_orphaned_borrows(&mut connection).unwrap().is_empty());
+    assert_eq!(borrow_service::fetch_borrowers(&mut connection, &book.isbn).unwrap().len(), 1);
+}
+
+#[test]
+fn upgrades_keep_orphaned_borrows_for_the_repair() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    while database::applied_versions(&mut connection).unwrap().iter().any(|version| version.as_str() >= "20261019100000") {
+        connection.revert_last_migration(MIGRATIONS).unwrap();
+    }
+
+    // a borrow of a client deleted before branches were added
+    connection.batch_execute("
+        PRAGMA foreign_keys = OFF;
+        INSERT INTO books (isbn, title, author, items) VALUES ('9789734633456', 'Maitreyi', 'Mircea Eliade', 1);
+        INSERT INTO borrows (id, clientID, bookISBN, startDate, endDate, returned) VALUES (1, 'C0002', '9789734633456', '2026-10-01', '2026-10-15', 0);
+        PRAGMA foreign_keys = ON;
+    ").unwrap();
+
+    database::run_migrations(&mut connection).unwrap();
+
+    let report = integrity::repair(&mut connection, false).unwrap();
+    assert_eq!(report.orphaned_borrows.iter().map(|borrow| borrow.client_id.as_str()).collect::<Vec<_>>(), ["C0002"]);
+    assert_eq!(report.removed, 1);
+}
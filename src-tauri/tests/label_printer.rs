@@ -0,0 +1,141 @@
+//! Label printers: ZPL and EPL jobs, and sending them over raw TCP or to a device file.
+
+use std::io::Read;
+use std::net::TcpListener;
+use std::thread;
+
+use chrono::NaiveDate;
+
+use libra_manager::barcode::{LabelSymbologies, Symbology};
+use libra_manager::label_printer::{self, LabelPrinter, PrinterLabel, PrinterLanguage, PrinterTarget};
+use libra_manager::Error;
+
+fn printer(language: PrinterLanguage, address: &str) -> LabelPrinter {
+    LabelPrinter { language, address: address.to_string(), ..Default::default() }
+}
+
+fn badge() -> PrinterLabel {
+    PrinterLabel::Badge {
+        client_id: "LM-CEN-0004215".to_string(),
+        client_name: "Ștefan Țurcanu".to_string(),
+        issued: NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(),
+        signed_id: None,
+    }
+}
+
+/// Accepts one connection on a free local port and hands back everything sent on it.
+fn listen() -> (String, thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let received = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut job = String::new();
+        stream.read_to_string(&mut job).unwrap();
+        job
+    });
+    (address, received)
+}
+
+#[test]
+fn zpl_jobs_hold_one_format_per_label() {
+    let labels = [
+        PrinterLabel::Isbn { isbn: "978-973-46-3345-6".to_string() },
+        PrinterLabel::Copy { isbn: "9789734633456".to_string(), title: "Maitreyi".to_string(), copy: 3 },
+        badge(),
+    ];
+    let job = label_printer::render(&printer(PrinterLanguage::Zpl, ""), &LabelSymbologies::default(), &labels, "Centrală").unwrap();
+
+    assert_eq!(job.matches("^XA").count(), 3);
+    assert_eq!(job.matches("^XZ").count(), 3);
+    // 50 x 25 mm at 203 dpi
+    assert!(job.contains("^PW400^LL200"));
+    // the printer works out the check digit of the EAN-13
+    assert!(job.contains("^BEN") && job.contains("^FD978973463345^FS"));
+    assert!(job.contains("^FD9789734633456-003^FS"));
+    assert!(job.contains("^FDȘtefan Țurcanu^FS"));
+    assert!(job.contains("^FDBiblioteca Centrală^FS"));
+}
+
+#[test]
+fn zpl_escapes_command_characters() {
+    let labels = [PrinterLabel::Copy { isbn: "9789734633456".to_string(), title: "A^B~C_D".to_string(), copy: 1 }];
+    let job = label_printer::render(&printer(PrinterLanguage::Zpl, ""), &LabelSymbologies::default(), &labels, "").unwrap();
+
+    assert!(job.contains("^FH^FDA_5EB_7EC_5FD^FS"));
+}
+
+#[test]
+fn epl_jobs_print_each_label_without_diacritics() {
+    let labels = [PrinterLabel::Isbn { isbn: "0-306-40615-2".to_string() }, badge()];
+    let job = label_printer::render(&printer(PrinterLanguage::Epl, ""), &LabelSymbologies::default(), &labels, "Centrală").unwrap();
+
+    assert_eq!(job.matches("\nN\n").count(), 2);
+    assert_eq!(job.matches("P1\n").count(), 2);
+    assert!(job.contains(",E30,") && job.contains("\"978030640615\""));
+    assert!(job.contains("\"Stefan Turcanu\""));
+    assert!(job.contains("\"Biblioteca Centrala\""));
+}
+
+#[test]
+fn symbologies_of_the_settings_are_used() {
+    let symbologies = LabelSymbologies { isbn: Symbology::Itf, copy: Symbology::Code39, badge: Symbology::Qr, ..Default::default() };
+    let PrinterLabel::Badge { client_id, client_name, issued, .. } = badge() else { unreachable!() };
+    let signed = PrinterLabel::Badge { client_id, client_name, issued, signed_id: Some("LM-CEN-0004215|Centrală|5c3a0e9f1b2d4c6a".to_string()) };
+    let labels = [
+        PrinterLabel::Isbn { isbn: "9789734633456".to_string() },
+        PrinterLabel::Copy { isbn: "9789734633456".to_string(), title: "Maitreyi".to_string(), copy: 3 },
+        signed.clone(),
+    ];
+    let job = label_printer::render(&printer(PrinterLanguage::Zpl, ""), &symbologies, &labels, "Centrală").unwrap();
+
+    assert!(job.contains("^B2N,") && job.contains("^FD09789734633456^FS"));
+    assert!(job.contains("^B3N,") && job.contains("^FD9789734633456-003^FS"));
+    assert!(job.contains("^BQN,2,") && job.contains("^FDMA,LM-CEN-0004215|Centrală|5c3a0e9f1b2d4c6a^FS"));
+
+    // EPL has no QR codes
+    let Err(Error::Validation(errors)) = label_printer::render(&printer(PrinterLanguage::Epl, ""), &symbologies, &[signed], "") else {
+        panic!("EPL printers cannot print QR codes");
+    };
+    assert_eq!(errors[0].field, "symbologies");
+}
+
+#[test]
+fn invalid_isbns_are_refused() {
+    let labels = [PrinterLabel::Isbn { isbn: "123".to_string() }];
+    let Err(Error::Validation(errors)) = label_printer::render(&LabelPrinter::default(), &LabelSymbologies::default(), &labels, "") else {
+        panic!("123 is no ISBN");
+    };
+    assert_eq!(errors[0].field, "isbn");
+}
+
+#[test]
+fn addresses_are_hosts_or_device_files() {
+    assert_eq!(PrinterTarget::parse("192.168.1.50").unwrap(), PrinterTarget::Network("192.168.1.50:9100".to_string()));
+    assert_eq!(PrinterTarget::parse("zebra.local:6101").unwrap(), PrinterTarget::Network("zebra.local:6101".to_string()));
+    assert_eq!(PrinterTarget::parse("/dev/usb/lp0").unwrap(), PrinterTarget::Device("/dev/usb/lp0".to_string()));
+    assert!(PrinterTarget::parse(" ").is_err());
+}
+
+#[test]
+fn jobs_reach_a_printer_listening_on_tcp() {
+    let (address, received) = listen();
+    let labels = [badge()];
+
+    label_printer::print(&printer(PrinterLanguage::Zpl, &address), &LabelSymbologies::default(), &labels, "Centrală").unwrap();
+
+    let expected = label_printer::render(&printer(PrinterLanguage::Zpl, &address), &LabelSymbologies::default(), &labels, "Centrală").unwrap();
+    assert_eq!(received.join().unwrap(), expected);
+}
+
+#[test]
+fn jobs_are_written_to_device_files() {
+    let device = std::env::temp_dir().join(format!("libra-label-printer-{}", std::process::id()));
+    std::fs::write(&device, "").unwrap();
+    let labels = [PrinterLabel::Isbn { isbn: "9789734633456".to_string() }];
+    let printer = printer(PrinterLanguage::Epl, device.to_str().unwrap());
+
+    label_printer::print(&printer, &LabelSymbologies::default(), &labels, "").unwrap();
+
+    assert_eq!(std::fs::read_to_string(&device).unwrap(), label_printer::render(&printer, &LabelSymbologies::default(), &labels, "").unwrap());
+    std::fs::remove_file(device).unwrap();
+}
@@ -0,0 +1,98 @@
+//! MARC records read into the catalog and written back out of it.
+
+use libra_manager::branches::DEFAULT_BRANCH;
+use libra_manager::database::establish_connection;
+use libra_manager::marc::{self, CatalogRecord, Field, MarcFormat, Record};
+use libra_manager::models::database::Book;
+use libra_manager::services::book_service;
+use libra_manager::Error;
+
+const MARCXML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<collection xmlns="http://www.loc.gov/MARC21/slim">
+  <record>
+    <leader>00000nam a2200000 i 4500</leader>
+    <controlfield tag="001">ro-123</controlfield>
+    <datafield tag="020" ind1=" " ind2=" "><subfield code="a">9789734633450 (legată)</subfield></datafield>
+    <datafield tag="020" ind1=" " ind2=" "><subfield code="a">978-973-46-3345-6 (broșată)</subfield></datafield>
+    <datafield tag="100" ind1="1" ind2=" "><subfield code="a">Eliade, Mircea,</subfield></datafield>
+    <datafield tag="245" ind1="1" ind2="0"><subfield code="a">Maitreyi :</subfield><subfield code="b">roman /</subfield></datafield>
+    <datafield tag="250" ind1=" " ind2=" "><subfield code="a">Ed. a 3-a.</subfield></datafield>
+    <datafield tag="264" ind1=" " ind2="1"><subfield code="a">București :</subfield><subfield code="b">Humanitas,</subfield><subfield code="c">2008.</subfield></datafield>
+    <datafield tag="300" ind1=" " ind2=" "><subfield code="a">196 p. ;</subfield></datafield>
+    <datafield tag="650" ind1=" " ind2="4"><subfield code="a">Literatură română.</subfield></datafield>
+    <datafield tag="650" ind1=" " ind2="4"><subfield code="a">Romane.</subfield></datafield>
+  </record>
+</collection>"#;
+
+fn maitreyi() -> CatalogRecord {
+    CatalogRecord {
+        isbn: "9789734633456".to_string(),
+        title: "Maitreyi: roman".to_string(),
+        author: "Eliade, Mircea".to_string(),
+        edition: Some("Ed. a 3-a".to_string()),
+        publication: Some("București, Humanitas, 2008".to_string()),
+        extent: Some("196 p".to_string()),
+        subjects: vec!["Literatură română".to_string(), "Romane".to_string()],
+    }
+}
+
+#[test]
+fn records_give_every_field_the_catalog_keeps() {
+    let records = marc::parse(MARCXML.as_bytes()).unwrap();
+    assert_eq!(records.len(), 1);
+    // the first ISBN fails its check digit, the second is normalized
+    assert_eq!(records[0].to_catalog_record(), maitreyi());
+}
+
+#[test]
+fn importing_again_keeps_what_was_edited_in_staging() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let mut staged = maitreyi().to_staged_book();
+    assert_eq!(book_service::stage_books(&mut connection, std::slice::from_ref(&staged)).unwrap(), 1);
+
+    staged.title = "Maitreyi".to_string();
+    staged.items = 3;
+    book_service::update_staged_book(&mut connection, &staged).unwrap();
+
+    let other = CatalogRecord { isbn: "9780306406157".to_string(), ..maitreyi() }.to_staged_book();
+    assert_eq!(book_service::stage_books(&mut connection, &[maitreyi().to_staged_book(), other]).unwrap(), 1);
+    let kept = book_service::fetch_staged_books(&mut connection).unwrap().into_iter().find(|book| book.isbn == staged.isbn).unwrap();
+    assert_eq!((kept.title.as_str(), kept.items), ("Maitreyi", 3));
+}
+
+#[test]
+fn the_catalog_goes_out_and_comes_back_with_its_details() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let staged = maitreyi().to_staged_book();
+    book_service::stage_books(&mut connection, std::slice::from_ref(&staged)).unwrap();
+    book_service::commit_staged_books(&mut connection, std::slice::from_ref(&staged.isbn), DEFAULT_BRANCH).unwrap();
+
+    // archived books are left out
+    let archived = Book { isbn: "9780306406157".to_string(), title: "Arhivată".to_string(), author: "Cineva".to_string(), items: 1, deleted_at: None };
+    book_service::create_book(&mut connection, &archived, DEFAULT_BRANCH).unwrap();
+    book_service::archive_book(&mut connection, &archived.isbn).unwrap();
+
+    let records = marc::catalog(&mut connection).unwrap();
+    assert_eq!(records.len(), 1);
+
+    for format in [MarcFormat::Iso2709, MarcFormat::MarcXml] {
+        let mut file = Vec::new();
+        marc::write(&mut file, &records, format).unwrap();
+        let read = marc::parse(&file).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].fields, records[0].fields);
+        assert_eq!(read[0].to_catalog_record(), maitreyi());
+    }
+}
+
+#[test]
+fn fields_too_long_for_iso_2709_are_refused() {
+    let mut record = Record::from_book(&Book { isbn: "9789734633456".to_string(), title: "x".repeat(10_000), author: "A".to_string(), items: 1, deleted_at: None }, None);
+    assert!(matches!(marc::write_iso2709(&mut Vec::new(), &record), Err(Error::Marc(_))));
+    // still fine as MARCXML
+    assert!(marc::write_marcxml(&mut Vec::new(), std::slice::from_ref(&record)).is_ok());
+
+    record.fields.retain(|field| field.tag() != "245");
+    record.fields.push(Field::Data { tag: "245".to_string(), indicators: ['1', '0'], subfields: vec![('a', "x".repeat(9_000))] });
+    assert!(marc::write_iso2709(&mut Vec::new(), &record).is_ok());
+}
@@ -0,0 +1,100 @@
+//! Client photos: stored scaled down, cropped and resized in place, and printed on badges.
+
+use std::io::Cursor;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use diesel::SqliteConnection;
+use image::{DynamicImage, GenericImageView, ImageFormat, Rgba, RgbaImage};
+
+use libra_manager::badges::{self, BadgeData, BadgeTemplate};
+use libra_manager::database::establish_connection;
+use libra_manager::models::database::Client;
+use libra_manager::photos::{self, Crop, MAX_SIDE};
+use libra_manager::services::client_service;
+use libra_manager::validation::{self, DEFAULT_CLIENT_ID_PATTERN};
+use libra_manager::Error;
+
+const ID: &str = "aB3xYz";
+
+fn database() -> SqliteConnection {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let client = Client { id: ID.to_string(), first_name: "Ana".to_string(), last_name: "Popescu".to_string(), email: "ana@example.ro".to_string(), phone: "0722000001".to_string(), deleted_at: None };
+    let pattern = validation::client_id_pattern(DEFAULT_CLIENT_ID_PATTERN).unwrap();
+    client_service::create_client(&mut connection, &client, &pattern).unwrap();
+    connection
+}
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let image = RgbaImage::from_pixel(width, height, Rgba([200, 30, 30, 255]));
+    let mut data = Vec::new();
+    DynamicImage::ImageRgba8(image).write_to(&mut Cursor::new(&mut data), ImageFormat::Png).unwrap();
+    data
+}
+
+fn stored_size(connection: &mut SqliteConnection) -> (u32, u32) {
+    photos::load_photo(connection, ID).unwrap().unwrap().dimensions()
+}
+
+#[test]
+fn large_photos_are_scaled_down() {
+    let mut connection = database();
+
+    photos::upload_photo(&mut connection, ID, &png(1500, 1000)).unwrap();
+
+    assert_eq!(stored_size(&mut connection), (MAX_SIDE, 683));
+    assert!(photos::fetch_photo(&mut connection, ID).unwrap().unwrap().starts_with(&[0xFF, 0xD8]));
+}
+
+#[test]
+fn photos_are_cropped_and_resized_in_place() {
+    let mut connection = database();
+    photos::upload_photo(&mut connection, ID, &png(400, 300)).unwrap();
+
+    photos::crop_photo(&mut connection, ID, Crop { x: 100, y: 0, width: 225, height: 300 }).unwrap();
+    assert_eq!(stored_size(&mut connection), (225, 300));
+
+    photos::resize_photo(&mut connection, ID, 120, 120).unwrap();
+    assert_eq!(stored_size(&mut connection), (90, 120));
+
+    assert!(matches!(photos::crop_photo(&mut connection, ID, Crop { x: 50, y: 0, width: 50, height: 50 }), Err(Error::Validation(_))));
+}
+
+#[test]
+fn files_that_are_not_images_are_refused() {
+    let mut connection = database();
+
+    assert!(matches!(photos::upload_photo(&mut connection, ID, b"not a photo"), Err(Error::Validation(_))));
+    assert!(matches!(photos::decode_base64("data:image/png;base64,@@@"), Err(Error::Validation(_))));
+    assert!(photos::fetch_photo(&mut connection, ID).unwrap().is_none());
+}
+
+#[test]
+fn photos_go_with_their_client() {
+    let mut connection = database();
+    photos::upload_photo(&mut connection, ID, &png(40, 40)).unwrap();
+
+    client_service::archive_client(&mut connection, ID).unwrap();
+    client_service::purge_client(&mut connection, ID).unwrap();
+
+    assert!(photos::fetch_photo(&mut connection, ID).unwrap().is_none());
+}
+
+#[test]
+fn badges_show_the_photo_on_the_left() {
+    let mut connection = database();
+    photos::upload_photo(&mut connection, ID, &png(300, 400)).unwrap();
+    let photo = photos::load_photo(&mut connection, ID).unwrap();
+    let date = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+
+    let badge = |photo| {
+        let data = BadgeData { client_id: ID, client_name: "Ana Popescu", library_name: "Centrală", issued: date, photo, symbology: Default::default(), signed_id: ID };
+        badges::render(&BadgeTemplate::default(), &data, Path::new("")).unwrap()
+    };
+    let with_photo = badge(photo.as_ref());
+    let without = badge(None);
+
+    let pixel = with_photo.get_pixel(50, 100);
+    assert!(pixel[0] > 150 && pixel[1] < 80, "{:?}", pixel);
+    assert_eq!(without.get_pixel(50, 100), &Rgba([255, 255, 255, 255]));
+}
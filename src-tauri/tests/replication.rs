@@ -0,0 +1,172 @@
+//! Merging sync bundles between installations, each a fresh in-memory database with every
+//! migration applied and a replica id of its own.
+
+use std::thread;
+use std::time::Duration;
+
+use diesel::SqliteConnection;
+use image::{Rgba, RgbaImage};
+
+use libra_manager::branches::DEFAULT_BRANCH;
+use libra_manager::database::establish_connection;
+use libra_manager::models::database::{Book, Client};
+use libra_manager::replication::{self, SyncReport};
+use libra_manager::services::{book_service, client_service};
+use libra_manager::{drawing, photos, validation};
+
+const ISBN: &str = "9789734633456";
+
+fn book(title: &str, items: i32) -> Book {
+    Book { isbn: ISBN.to_string(), title: title.to_string(), author: "Mircea Eliade".to_string(), items, deleted_at: None }
+}
+
+fn client(first_name: &str, phone: &str) -> Client {
+    Client {
+        id: "C0001".to_string(),
+        first_name: first_name.to_string(),
+        last_name: "Popescu".to_string(),
+        email: "ana@example.ro".to_string(),
+        phone: phone.to_string(),
+        deleted_at: None,
+    }
+}
+
+/// Exports everything `from` holds and merges it into `into`.
+fn sync(from: &mut SqliteConnection, into: &mut SqliteConnection) -> SyncReport {
+    let path = std::env::temp_dir().join(format!("libra-sync-{}-{:?}.json", std::process::id(), thread::current().id()));
+    replication::export_bundle(from, &path).unwrap();
+    let report = replication::import_bundle(into, &path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    report
+}
+
+/// A central library with one book and one client, and a branch which has synced with it.
+fn installations() -> (SqliteConnection, SqliteConnection) {
+    let mut central = establish_connection(":memory:").unwrap();
+    book_service::create_book(&mut central, &book("Maitreyi", 2), DEFAULT_BRANCH).unwrap();
+    let pattern = validation::client_id_pattern(validation::DEFAULT_CLIENT_ID_PATTERN).unwrap();
+    client_service::create_client(&mut central, &client("Ana", "0722000001"), &pattern).unwrap();
+
+    let mut branch = establish_connection(":memory:").unwrap();
+    let report = sync(&mut central, &mut branch);
+    assert_eq!(report.applied, 2);
+    (central, branch)
+}
+
+/// Lets the clock move on, so the next write is later than the last one.
+fn later() {
+    thread::sleep(Duration::from_millis(5));
+}
+
+#[test]
+fn edits_of_different_fields_are_both_kept() {
+    let (mut central, mut branch) = installations();
+
+    client_service::update_client(&mut central, &client("Ana-Maria", "0722000001")).unwrap();
+    client_service::update_client(&mut branch, &client("Ana", "0733000002")).unwrap();
+    sync(&mut central, &mut branch);
+    sync(&mut branch, &mut central);
+
+    for connection in [&mut central, &mut branch] {
+        let merged = client_service::fetch_client(connection, "C0001", false).unwrap().unwrap();
+        assert_eq!(merged.first_name, "Ana-Maria");
+        assert_eq!(merged.phone, "+40733000002");
+    }
+}
+
+#[test]
+fn the_latest_edit_of_a_field_wins() {
+    let (mut central, mut branch) = installations();
+
+    book_service::update_book(&mut central, &book("Maitreyi (ediția I)", 2), DEFAULT_BRANCH).unwrap();
+    later();
+    book_service::update_book(&mut branch, &book("Maitreyi (ediția a II-a)", 2), DEFAULT_BRANCH).unwrap();
+    sync(&mut central, &mut branch);
+    sync(&mut branch, &mut central);
+
+    for connection in [&mut central, &mut branch] {
+        assert_eq!(book_service::fetch_book(connection, ISBN, false).unwrap().unwrap().title, "Maitreyi (ediția a II-a)");
+    }
+    // a bundle merged twice changes nothing
+    assert_eq!(sync(&mut branch, &mut central).received, 0);
+}
+
+#[test]
+fn deletes_win_over_later_edits() {
+    let (mut central, mut branch) = installations();
+
+    book_service::archive_book(&mut central, ISBN).unwrap();
+    book_service::purge_book(&mut central, ISBN).unwrap();
+    later();
+    book_service::update_book(&mut branch, &book("Maitreyi, retitrată", 2), DEFAULT_BRANCH).unwrap();
+
+    let report = sync(&mut central, &mut branch);
+    assert_eq!(report.deleted, 1);
+    sync(&mut branch, &mut central);
+
+    for connection in [&mut central, &mut branch] {
+        assert!(book_service::fetch_book(connection, ISBN, true).unwrap().is_none());
+    }
+}
+
+#[test]
+fn stock_counts_changed_on_both_sides_wait_for_review() {
+    let (mut central, mut branch) = installations();
+
+    book_service::update_book(&mut central, &book("Maitreyi", 5), DEFAULT_BRANCH).unwrap();
+    book_service::update_book(&mut branch, &book("Maitreyi", 3), DEFAULT_BRANCH).unwrap();
+    let report = sync(&mut central, &mut branch);
+    assert_eq!(report.conflicts, 1);
+
+    // the branch keeps its own count until a librarian settles it
+    assert_eq!(book_service::fetch_book(&mut branch, ISBN, false).unwrap().unwrap().items, 3);
+    let conflicts = replication::fetch_conflicts(&mut branch).unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!((conflicts[0].local_value.as_deref(), conflicts[0].remote_value.as_deref()), (Some("3"), Some("5")));
+
+    later();
+    replication::resolve_conflict(&mut branch, conflicts[0].id, 6).unwrap();
+    assert!(replication::fetch_conflicts(&mut branch).unwrap().is_empty());
+    sync(&mut branch, &mut central);
+    assert_eq!(book_service::fetch_book(&mut central, ISBN, false).unwrap().unwrap().items, 6);
+}
+
+#[test]
+fn the_newest_photo_of_a_client_is_synced() {
+    let (mut central, mut branch) = installations();
+    let picture = |shade: u8| drawing::encode_png(&RgbaImage::from_pixel(40, 50, Rgba([shade, shade, shade, 255]))).unwrap();
+
+    photos::upload_photo(&mut central, "C0001", &picture(40)).unwrap();
+    assert_eq!(sync(&mut central, &mut branch).photos, 1);
+    assert_eq!(photos::fetch_photo(&mut branch, "C0001").unwrap(), photos::fetch_photo(&mut central, "C0001").unwrap());
+
+    later();
+    photos::upload_photo(&mut branch, "C0001", &picture(200)).unwrap();
+    // the older photo does not replace the newer one, which goes back the other way
+    assert_eq!(sync(&mut central, &mut branch).photos, 0);
+    assert_eq!(sync(&mut branch, &mut central).photos, 1);
+    assert_eq!(photos::fetch_photo(&mut central, "C0001").unwrap(), photos::fetch_photo(&mut branch, "C0001").unwrap());
+}
+
+#[test]
+fn books_the_catalog_would_refuse_are_skipped() {
+    let mut central = establish_connection(":memory:").unwrap();
+    book_service::create_book(&mut central, &book("Maitreyi", 2), DEFAULT_BRANCH).unwrap();
+
+    // a bundle written by an older version, or by hand, with a book missing its title
+    let path = std::env::temp_dir().join(format!("libra-sync-invalid-{}.json", std::process::id()));
+    replication::export_bundle(&mut central, &path).unwrap();
+    let mut bundle: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    for change in bundle["changes"].as_array_mut().unwrap() {
+        if change["field"] == "title" {
+            change["value"] = serde_json::json!("  ");
+        }
+    }
+    std::fs::write(&path, serde_json::to_vec(&bundle).unwrap()).unwrap();
+
+    let mut branch = establish_connection(":memory:").unwrap();
+    let report = replication::import_bundle(&mut branch, &path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(report.skipped, [format!("books {}", ISBN)]);
+    assert!(book_service::fetch_book(&mut branch, ISBN, true).unwrap().is_none());
+}
@@ -0,0 +1,111 @@
+//! Codes typed by keyboard-wedge scanners, and the books, copies and clients they open.
+
+use diesel::SqliteConnection;
+
+use libra_manager::barcode;
+use libra_manager::branches::DEFAULT_BRANCH;
+use libra_manager::client_ids::ClientIdFormat;
+use libra_manager::database::establish_connection;
+use libra_manager::models::database::{Book, Client};
+use libra_manager::scans::{self, ScanKind, ScanTarget};
+use libra_manager::services::{book_service, client_service};
+use libra_manager::{signing, validation, Error};
+
+fn book(isbn: &str, items: i32) -> Book {
+    Book { isbn: isbn.to_string(), title: "Maitreyi".to_string(), author: "Mircea Eliade".to_string(), items, deleted_at: None }
+}
+
+/// A library with a book added by its ISBN-10, one added by its ISBN-13 and a client.
+fn library(format: &ClientIdFormat) -> (SqliteConnection, String) {
+    let mut connection = establish_connection(":memory:").unwrap();
+    book_service::create_book(&mut connection, &book("0-306-40615-2", 1), DEFAULT_BRANCH).unwrap();
+    book_service::create_book(&mut connection, &book("9789734633456", 3), DEFAULT_BRANCH).unwrap();
+
+    let client = Client {
+        id: String::new(),
+        first_name: "Ana".to_string(),
+        last_name: "Popescu".to_string(),
+        email: "ana@example.ro".to_string(),
+        phone: "0722000001".to_string(),
+        deleted_at: None,
+    };
+    let pattern = validation::client_id_pattern(validation::DEFAULT_CLIENT_ID_PATTERN).unwrap();
+    let id = client_service::register_client(&mut connection, &client, format, DEFAULT_BRANCH, &pattern).unwrap().id;
+    (connection, id)
+}
+
+fn book_of(target: ScanTarget) -> (String, Option<u32>) {
+    match target {
+        ScanTarget::Book { book, copy } => (book.isbn, copy),
+        ScanTarget::Client { client } => panic!("{} is not a book", client.id),
+    }
+}
+
+fn client_of(target: ScanTarget) -> String {
+    match target {
+        ScanTarget::Client { client } => client.id,
+        ScanTarget::Book { book, .. } => panic!("{} is not a client", book.isbn),
+    }
+}
+
+#[test]
+fn codes_are_told_apart_by_their_form() {
+    assert_eq!(scans::classify("978-0-306-40615-7\r\n"), Some(ScanKind::Isbn("9780306406157".to_string())));
+    assert_eq!(scans::classify("080442957x"), Some(ScanKind::Isbn("080442957X".to_string())));
+    assert_eq!(scans::classify("9789734633456-003"), Some(ScanKind::Copy("9789734633456".to_string(), 3)));
+    assert_eq!(scans::classify("LM-CEN-0004215"), Some(ScanKind::Client("LM-CEN-0004215".to_string())));
+    assert_eq!(scans::classify("LM-CEN-0004215|Centrală|0123456789abcdef"), Some(ScanKind::SignedBadge("LM-CEN-0004215|Centrală|0123456789abcdef".to_string())));
+    // an ISBN with a wrong check digit is not a book
+    assert_eq!(scans::classify("9789734633457"), Some(ScanKind::Client("9789734633457".to_string())));
+    assert_eq!(scans::classify("  "), None);
+    // ITF labels fill the pairs of digits of an ISBN with a leading zero
+    assert_eq!(scans::classify("09789734633456"), Some(ScanKind::Isbn("9789734633456".to_string())));
+}
+
+#[test]
+fn books_are_found_by_either_isbn_and_copies_by_their_barcode() {
+    let format = ClientIdFormat::default();
+    let (mut connection, _) = library(&format);
+
+    assert_eq!(book_of(scans::resolve(&mut connection, "9780306406157", &format).unwrap()), ("0306406152".to_string(), None));
+    assert_eq!(book_of(scans::resolve(&mut connection, "0306406152", &format).unwrap()), ("0306406152".to_string(), None));
+    assert_eq!(book_of(scans::resolve(&mut connection, &barcode::copy_code("0306406152", 1), &format).unwrap()), ("0306406152".to_string(), Some(1)));
+    assert_eq!(book_of(scans::resolve(&mut connection, "9789734633456-003", &format).unwrap()), ("9789734633456".to_string(), Some(3)));
+
+    // a copy the library does not have, and a book it does not have
+    assert!(matches!(scans::resolve(&mut connection, "9789734633456-004", &format), Err(Error::UnknownCode(_))));
+    assert!(matches!(scans::resolve(&mut connection, "9780141439600", &format), Err(Error::UnknownCode(_))));
+}
+
+#[test]
+fn clients_are_found_by_their_id_or_signed_badge() {
+    let format = ClientIdFormat::default();
+    let (mut connection, id) = library(&format);
+
+    assert_eq!(client_of(scans::resolve(&mut connection, &id, &format).unwrap()), id);
+    let payload = signing::sign_badge(&mut connection, &id, "Centrală").unwrap();
+    assert_eq!(client_of(scans::resolve(&mut connection, &payload, &format).unwrap()), id);
+
+    // a misread check digit is caught instead of opening nobody
+    let mut misread = id.clone();
+    let last = misread.pop().unwrap();
+    misread.push(if last == '0' { '1' } else { '0' });
+    assert!(matches!(scans::resolve(&mut connection, &misread, &format), Err(Error::InvalidClientId(_))));
+
+    // badges signed by another library do not open anyone
+    let mut elsewhere = establish_connection(":memory:").unwrap();
+    let forged = signing::sign_badge(&mut elsewhere, &id, "Centrală").unwrap();
+    assert!(matches!(scans::resolve(&mut connection, &forged, &format), Err(Error::UnknownCode(_))));
+    assert!(matches!(scans::resolve(&mut connection, &format.format("CEN", 9999), &format), Err(Error::UnknownCode(_))));
+}
+
+#[test]
+fn itf_badges_of_odd_length_ids_open_their_client() {
+    let format = ClientIdFormat { prefix: String::new(), branch_code: false, ..ClientIdFormat::default() };
+    let (mut connection, id) = library(&format);
+    assert_eq!(id.len() % 2, 1, "{}", id);
+
+    let printed = barcode::itf_digits(&id);
+    assert_eq!(client_of(scans::resolve(&mut connection, &printed, &format).unwrap()), id);
+    assert!(matches!(scans::resolve(&mut connection, &format!("0{}", printed), &format), Err(Error::UnknownCode(_))));
+}
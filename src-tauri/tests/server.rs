@@ -0,0 +1,112 @@
+//! What each role may run through the server other desks connect to, and a thin client
+//! talking to it on localhost as a second instance would.
+
+use std::net::{Ipv4Addr, TcpListener};
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+
+use libra_manager::branches::DEFAULT_BRANCH;
+use libra_manager::database::DatabaseConnection;
+use libra_manager::models::database::{Book, User};
+use libra_manager::server::{self, Access, Client, Dispatch};
+use libra_manager::services::{book_service, user_service};
+use libra_manager::Error;
+
+fn user(username: &str, role: &str) -> User {
+    User { username: username.to_string(), password: "parola".to_string(), first_name: "A".to_string(), last_name: "B".to_string(), role: role.to_string(), branch_id: None }
+}
+
+#[test]
+fn users_cannot_run_administration_commands() {
+    let (admin, ion) = (user("ana", "admin"), user("ion", "user"));
+
+    assert!(server::authorize(&ion, Access::User, "fetch_books", &json!({})).is_ok());
+    assert!(matches!(server::authorize(&ion, Access::Admin, "create_user", &json!({ "user": { "username": "ion2" } })), Err(Error::Forbidden(_))));
+    assert!(matches!(server::authorize(&ion, Access::Admin, "delete_branch", &json!({ "id": 1 })), Err(Error::Forbidden(_))));
+    assert!(server::authorize(&admin, Access::Admin, "create_user", &json!({ "user": { "username": "ion2" } })).is_ok());
+}
+
+#[test]
+fn users_may_only_act_on_themselves() {
+    let (admin, ion) = (user("ana", "admin"), user("ion", "user"));
+
+    assert!(server::authorize(&ion, Access::Own, "fetch_user", &json!({ "username": "ion" })).is_ok());
+    assert!(matches!(server::authorize(&ion, Access::Own, "fetch_user", &json!({ "username": "ana" })), Err(Error::Forbidden(_))));
+    assert!(matches!(server::authorize(&ion, Access::Own, "select_branch", &json!({ "branchId": 2 })), Err(Error::Forbidden(_))));
+    assert!(server::authorize(&admin, Access::Own, "select_branch", &json!({ "username": "ion", "branchId": 2 })).is_ok());
+}
+
+/// A few commands wired up as the app wires all of them.
+fn dispatch(database: Arc<DatabaseConnection>) -> Dispatch {
+    Arc::new(move |user, command, args| {
+        let connection = &mut *database.get()?;
+        match command {
+            "login" => {
+                server::authorize(user, Access::User, command, &args)?;
+                Ok(serde_json::to_value(user_service::login(connection, &user.username, &user.password, None)?)?)
+            }
+            "fetch_books" => {
+                server::authorize(user, Access::User, command, &args)?;
+                Ok(serde_json::to_value(book_service::fetch_books(connection, false)?)?)
+            }
+            "create_user" => {
+                server::authorize(user, Access::Admin, command, &args)?;
+                Ok(serde_json::to_value(user_service::create_user(connection, &serde_json::from_value(args["user"].clone())?)?)?)
+            }
+            _ => Err(Error::UnknownCommand(command.to_string()))
+        }
+    })
+}
+
+#[test]
+fn thin_clients_run_commands_on_the_server() {
+    let path = std::env::temp_dir().join(format!("libra-server-{}.sqlite", std::process::id()));
+    let database = Arc::new(DatabaseConnection::from(path.to_str().unwrap()).unwrap());
+    {
+        let connection = &mut *database.get().unwrap();
+        user_service::create_user(connection, &user("ana", "admin")).unwrap();
+        user_service::create_user(connection, &user("ion", "user")).unwrap();
+        let book = Book { isbn: "9789734633456".to_string(), title: "Maitreyi".to_string(), author: "Mircea Eliade".to_string(), items: 1, deleted_at: None };
+        book_service::create_book(connection, &book, DEFAULT_BRANCH).unwrap();
+    }
+
+    // a port nothing else listens on
+    let address = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap();
+    let url = format!("http://{}", address);
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async {
+        tokio::spawn(server::serve(address, database.pool.clone(), dispatch(database.clone())));
+        tokio::task::yield_now().await;
+
+        let ion = Client::new(&url, "ion", "parola");
+        let logged_in: Value = ion.call("login", &json!({ "username": "ion", "password": "parola" })).await.unwrap();
+        assert_eq!(logged_in["role"], "user");
+
+        let books: Vec<Book> = ion.call("fetch_books", &json!({})).await.unwrap();
+        assert_eq!(books.len(), 1);
+
+        let created = ion.call::<_, ()>("create_user", &json!({ "user": { "username": "ion2", "password": "x", "firstName": "A", "lastName": "B", "role": "admin" } })).await;
+        assert!(matches!(created, Err(Error::Remote(message)) if message == "Only administrators may run create_user"));
+
+        let wrong = Client::new(&url, "ion", "gresit");
+        assert!(matches!(wrong.call::<_, Vec<Book>>("fetch_books", &json!({})).await, Err(Error::AuthError)));
+
+        let status = reqwest::Client::new().post(format!("{}/api/fetch_books", url))
+            .basic_auth("ion", Some("gresit"))
+            .json(&json!({}))
+            .send().await.unwrap()
+            .status();
+        assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+
+        // the administrator may
+        let ana = Client::new(&url, "ana", "parola");
+        ana.call::<_, ()>("create_user", &json!({ "user": { "username": "ion2", "password": "x", "firstName": "A", "lastName": "B", "role": "user" } })).await.unwrap();
+    });
+
+    drop(database);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+}
@@ -0,0 +1,125 @@
+//! Label sheets: grids of the usual Avery and CR80 sheets, start offsets and the PDF they end up in.
+
+use image::Rgba;
+
+use libra_manager::barcode::{self, LabelSymbologies, Symbology};
+use libra_manager::drawing::Drawing;
+use libra_manager::models::database::Book;
+use libra_manager::pdf::{PdfWriter, Placement};
+use libra_manager::sheets::{self, BookLabel, SheetLayout};
+use libra_manager::Error;
+
+fn label() -> Drawing {
+    let mut drawing = Drawing::new(220, 100);
+    drawing.fill_rect(0, 0, 220, 100, Rgba([0, 0, 0, 255]));
+    drawing
+}
+
+fn pages(pdf: &[u8]) -> usize {
+    String::from_utf8_lossy(pdf).matches("/Type /Page ").count()
+}
+
+fn book(isbn: &str) -> Book {
+    Book { isbn: isbn.to_string(), title: "Maitreyi".to_string(), author: "Mircea Eliade".to_string(), items: 1, deleted_at: None }
+}
+
+#[test]
+fn preset_sheets_fit_on_a4() {
+    for layout in [SheetLayout::avery_l7160(), SheetLayout::avery_l7651(), SheetLayout::cr80_a4()] {
+        layout.validate("sheet").unwrap();
+    }
+    assert_eq!(SheetLayout::avery_l7160().per_page(), 21);
+    assert_eq!(SheetLayout::cr80_a4().per_page(), 10);
+}
+
+#[test]
+fn cells_go_row_by_row() {
+    let layout = SheetLayout::avery_l7160();
+
+    let second_row = layout.cell(3);
+    assert_eq!(second_row.x, layout.margin_left);
+    assert!((second_row.y - (layout.margin_top + layout.label_height)).abs() < 0.001);
+
+    let third_column = layout.cell(2);
+    assert!((third_column.x - (layout.margin_left + 2.0 * (layout.label_width + layout.column_gap))).abs() < 0.001);
+}
+
+#[test]
+fn labels_fit_their_cell_keeping_proportions() {
+    let cell = Placement { x: 10.0, y: 10.0, width: 60.0, height: 60.0 };
+    let fitted = cell.fit(&label().to_image());
+
+    assert!((fitted.width - 60.0).abs() < 0.001);
+    assert!((fitted.height - 60.0 * 100.0 / 220.0).abs() < 0.001);
+    assert!(fitted.y > cell.y);
+}
+
+#[test]
+fn start_offset_skips_used_labels_of_the_first_sheet() {
+    let layout = SheetLayout::avery_l7160();
+    let labels = (0..21).map(|_| label()).collect::<Vec<Drawing>>();
+
+    assert_eq!(pages(&sheets::sheet_pdf(&layout, &labels, 0, "isbnSheet").unwrap()), 1);
+    assert_eq!(pages(&sheets::sheet_pdf(&layout, &labels, 1, "isbnSheet").unwrap()), 2);
+    assert_eq!(pages(&sheets::sheet_pdf(&layout, &labels[..2], 19, "isbnSheet").unwrap()), 1);
+
+    let Err(Error::Validation(errors)) = sheets::sheet_pdf(&layout, &labels, 21, "isbnSheet") else {
+        panic!("a sheet has no 22nd label");
+    };
+    assert_eq!(errors[0].field, "start");
+}
+
+#[test]
+fn grids_running_off_the_page_are_refused() {
+    let layout = SheetLayout { columns: 4, ..SheetLayout::avery_l7160() };
+
+    let Err(Error::Validation(errors)) = sheets::sheet_pdf(&layout, &[label()], 0, "isbnSheet") else {
+        panic!("four columns of 63.5 mm do not fit on A4");
+    };
+    assert_eq!(errors[0].field, "isbnSheet");
+}
+
+#[test]
+fn pdf_cross_reference_points_at_the_objects() {
+    let mut writer = PdfWriter::new();
+    writer.add_image(&label().to_image(), Placement { x: 0.0, y: 0.0, width: 50.0, height: 20.0 });
+    let pdf = writer.finish();
+    let text = String::from_utf8_lossy(&pdf);
+
+    assert!(pdf.starts_with(b"%PDF-1.4"));
+    assert!(text.ends_with("%%EOF\n"));
+
+    let startxref: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+    assert!(pdf[startxref..].starts_with(b"xref"));
+
+    // every in-use entry points at the start of its object
+    let entries = text[startxref..].lines().skip(3).take_while(|line| line.ends_with(" n "));
+    for (number, entry) in entries.enumerate() {
+        let offset: usize = entry[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(format!("{} 0 obj", number + 1).as_bytes()));
+    }
+}
+
+#[test]
+fn label_sheets_are_drawn_as_paths() {
+    let symbologies = LabelSymbologies { spine: Some(Symbology::Code128), ..LabelSymbologies::default() };
+    for label in [BookLabel::Isbn, BookLabel::Spine] {
+        let drawing = label.render(&book("9789734633456"), &symbologies).unwrap();
+        let pdf = sheets::sheet_pdf(&SheetLayout::avery_l7160(), &[drawing], 0, "isbnSheet").unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(!text.contains("/Subtype /Image"), "{:?} labels are embedded as images", label);
+        assert!(text.contains(" re f") && text.contains(" c "), "{:?} labels have no bars or glyphs", label);
+    }
+}
+
+#[test]
+fn book_labels_use_the_isbn_13_and_the_author_code() {
+    // an ISBN-10 is printed as the EAN-13 it stands for
+    assert!(BookLabel::Isbn.render(&book("0-306-40615-2"), &LabelSymbologies::default()).is_ok());
+    assert!(BookLabel::Isbn.render(&book("123"), &LabelSymbologies::default()).is_err());
+    assert!(BookLabel::Spine.render(&book("9789734633456"), &LabelSymbologies::default()).is_ok());
+
+    assert_eq!(barcode::author_code("Mircea Eliade"), "ELI");
+    assert_eq!(barcode::author_code("Ion Creangă"), "CRE");
+}
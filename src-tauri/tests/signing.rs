@@ -0,0 +1,76 @@
+//! Signed badges: what their QR codes carry and how a scan of one is checked.
+
+use libra_manager::database::establish_connection;
+use libra_manager::replication;
+use libra_manager::signing::{self, SignedBadge};
+
+const ID: &str = "LM-CEN-0004215";
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[test]
+fn hmac_matches_rfc_4231() {
+    assert_eq!(hex(&signing::hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+               "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    // keys longer than a block are hashed first
+    assert_eq!(hex(&signing::hmac_sha256(&[0xAA; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")),
+               "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+}
+
+#[test]
+fn signed_badges_verify_with_the_key_of_their_database() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let payload = signing::sign_badge(&mut connection, ID, "Biblioteca | Centrală").unwrap();
+
+    assert!(payload.starts_with("LM-CEN-0004215|Biblioteca | Centrală|"));
+    // the key is kept, so signing again gives the same badge
+    assert_eq!(signing::sign_badge(&mut connection, ID, "Biblioteca | Centrală").unwrap(), payload);
+
+    let badge = signing::verify_badge(&mut connection, &payload).unwrap().unwrap();
+    assert_eq!(badge.client_id, ID);
+    assert_eq!(badge.library_name, "Biblioteca | Centrală");
+
+    let mut elsewhere = establish_connection(":memory:").unwrap();
+    assert!(signing::verify_badge(&mut elsewhere, &payload).unwrap().is_none());
+}
+
+#[test]
+fn tampered_badges_are_refused() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let payload = signing::sign_badge(&mut connection, ID, "Centrală").unwrap();
+    let badge = SignedBadge::parse(&payload).unwrap();
+
+    let other_client = SignedBadge { client_id: "LM-CEN-0004223".to_string(), ..badge.clone() }.to_string();
+    assert!(signing::verify_badge(&mut connection, &other_client).unwrap().is_none());
+    let other_library = SignedBadge { library_name: "Județeană".to_string(), ..badge }.to_string();
+    assert!(signing::verify_badge(&mut connection, &other_library).unwrap().is_none());
+
+    // plain client IDs and ISBNs are no signed badges
+    assert!(signing::verify_badge(&mut connection, ID).unwrap().is_none());
+    assert!(SignedBadge::parse("9789734633456").is_none());
+    assert!(SignedBadge::parse("LM-CEN-0004215|Centrală|not-hex-digits!!").is_none());
+}
+
+#[test]
+fn badges_of_synced_installations_verify_everywhere() {
+    let (mut central, mut branch) = (establish_connection(":memory:").unwrap(), establish_connection(":memory:").unwrap());
+    let printed_centrally = signing::sign_badge(&mut central, ID, "Centrală").unwrap();
+    let printed_at_branch = signing::sign_badge(&mut branch, "LM-NRD-0000018", "Centrală").unwrap();
+    assert!(signing::verify_badge(&mut branch, &printed_centrally).unwrap().is_none());
+
+    let bundle = std::env::temp_dir().join(format!("libra-badges-{}.json", std::process::id()));
+    replication::export_bundle(&mut central, &bundle).unwrap();
+    replication::import_bundle(&mut branch, &bundle).unwrap();
+    replication::export_bundle(&mut branch, &bundle).unwrap();
+    replication::import_bundle(&mut central, &bundle).unwrap();
+    std::fs::remove_file(&bundle).unwrap();
+
+    for connection in [&mut central, &mut branch] {
+        assert!(signing::verify_badge(connection, &printed_centrally).unwrap().is_some());
+        assert!(signing::verify_badge(connection, &printed_at_branch).unwrap().is_some());
+    }
+    // each still signs with its own key
+    assert_eq!(signing::sign_badge(&mut central, ID, "Centrală").unwrap(), printed_centrally);
+}
@@ -0,0 +1,320 @@
+//! Barcode symbologies: the QR code and Data Matrix encoders, read back module by module,
+//! and the linear barcodes kept for old label stock.
+
+use image::Rgba;
+
+use libra_manager::barcode::{self, Symbology};
+use libra_manager::matrix_codes::{self, Matrix};
+use libra_manager::Error;
+
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// Data and error correction codewords per block of the QR versions 1 to 10 at level M.
+const QR_BLOCKS: [(usize, &[(usize, usize)]); 10] = [
+    (10, &[(1, 16)]),
+    (16, &[(1, 28)]),
+    (26, &[(1, 44)]),
+    (18, &[(2, 32)]),
+    (24, &[(2, 43)]),
+    (16, &[(4, 27)]),
+    (18, &[(4, 31)]),
+    (22, &[(2, 38), (2, 39)]),
+    (22, &[(3, 36), (2, 37)]),
+    (26, &[(4, 43), (1, 44)]),
+];
+
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut product: u16 = 0;
+    for bit in (0..8).rev() {
+        product = (product << 1) ^ ((product >> 7) * 0x11D);
+        product ^= ((y as u16 >> bit) & 1) * x as u16;
+    }
+    product as u8
+}
+
+/// Whether every syndrome of a QR block is zero, i.e. the codeword polynomial has the
+/// roots 2^0 up to 2^(ecc - 1).
+fn is_codeword(block: &[u8], ecc: usize) -> bool {
+    let mut root = 1u8;
+    (0..ecc).all(|_| {
+        let value = block.iter().fold(0u8, |value, &coefficient| gf_multiply(value, root) ^ coefficient);
+        root = gf_multiply(root, 2);
+        value == 0
+    })
+}
+
+/// Modules a QR reader skips when reading data: finders with their separators and the
+/// format information, timing patterns, alignment patterns and version information.
+fn is_function(version: usize, x: usize, y: usize) -> bool {
+    let size = version * 4 + 17;
+    let (near, far) = (|v: usize| v < 9, |v: usize| v >= size - 8);
+    if (near(x) && near(y)) || (far(x) && near(y)) || (near(x) && far(y)) || x == 6 || y == 6 {
+        return true;
+    }
+    if version >= 7 && ((x >= size - 11 && y < 6) || (y >= size - 11 && x < 6)) {
+        return true;
+    }
+    let positions: Vec<usize> = match version {
+        1 => vec![],
+        2..=6 => vec![6, version * 4 + 10],
+        _ => {
+            let last = version * 4 + 10;
+            vec![6, (6 + last) / 2 + (6 + last) / 2 % 2, last]
+        }
+    };
+    let last = positions.len().saturating_sub(1);
+    positions.iter().enumerate().any(|(i, &cx)| positions.iter().enumerate().any(|(j, &cy)| {
+        let corner = [(0, 0), (0, last), (last, 0)].contains(&(i, j));
+        !corner && x.abs_diff(cx) <= 2 && y.abs_diff(cy) <= 2
+    }))
+}
+
+/// Reads a QR code of level M in byte mode back to its bytes, checking the format
+/// information and the error correction on the way.
+fn read_qr(matrix: &Matrix) -> Vec<u8> {
+    let size = matrix.width;
+    let version = (size - 17) / 4;
+
+    let mut format = 0u32;
+    let positions = [(8, 0), (8, 1), (8, 2), (8, 3), (8, 4), (8, 5), (8, 7), (8, 8), (7, 8), (5, 8), (4, 8), (3, 8), (2, 8), (1, 8), (0, 8)];
+    for (index, &(x, y)) in positions.iter().enumerate() {
+        format |= (matrix.is_dark(x, y) as u32) << index;
+    }
+    format ^= 0x5412;
+    let data = format >> 10;
+    let mut remainder = data;
+    for _ in 0..10 {
+        remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+    }
+    assert_eq!(format & 0x3FF, remainder, "format information");
+    assert_eq!(data >> 3, 0, "level M");
+    let mask = data & 7;
+
+    let mut bits = Vec::new();
+    let mut right = size as i32 - 1;
+    while right >= 1 {
+        if right == 6 {
+            right = 5;
+        }
+        for vertical in 0..size {
+            for offset in 0..2 {
+                let x = right as usize - offset;
+                let y = if (right + 1) & 2 == 0 { size - 1 - vertical } else { vertical };
+                if is_function(version, x, y) {
+                    continue;
+                }
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                bits.push(matrix.is_dark(x, y) != invert);
+            }
+        }
+        right -= 2;
+    }
+    let codewords: Vec<u8> = bits.chunks_exact(8).map(|byte| byte.iter().fold(0, |value, &bit| (value << 1) | bit as u8)).collect();
+
+    let (ecc, groups) = QR_BLOCKS[version - 1];
+    let lengths: Vec<usize> = groups.iter().flat_map(|&(count, length)| (0..count).map(move |_| length)).collect();
+    let mut blocks: Vec<Vec<u8>> = vec![Vec::new(); lengths.len()];
+    let mut next = codewords.iter();
+    for index in 0..*lengths.iter().max().unwrap() {
+        for (block, &length) in blocks.iter_mut().zip(&lengths) {
+            if index < length {
+                block.push(*next.next().unwrap());
+            }
+        }
+    }
+    for _ in 0..ecc {
+        for block in blocks.iter_mut() {
+            block.push(*next.next().unwrap());
+        }
+    }
+
+    let mut data = Vec::new();
+    for (block, &length) in blocks.iter().zip(&lengths) {
+        assert!(is_codeword(block, ecc), "error correction of a block");
+        data.extend_from_slice(&block[..length]);
+    }
+
+    assert_eq!(data[0] >> 4, 0b0100, "byte mode");
+    let (count, start) = if version < 10 {
+        ((((data[0] & 0x0F) as usize) << 4) | (data[1] >> 4) as usize, 12)
+    } else {
+        ((((data[0] & 0x0F) as usize) << 12) | ((data[1] as usize) << 4) | (data[2] >> 4) as usize, 20)
+    };
+    (0..count).map(|index| {
+        let bit = start + index * 8;
+        let (byte, shift) = (bit / 8, bit % 8);
+        ((data[byte] as u16) << 8 | data[byte + 1] as u16).wrapping_shl(shift as u32).to_be_bytes()[0]
+    }).collect()
+}
+
+#[test]
+fn reed_solomon_matches_the_published_examples() {
+    // HELLO WORLD as version 1-M, from the worked example of the QR code standard
+    let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17];
+    assert_eq!(matrix_codes::qr_error_correction(&data, 10), [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]);
+
+    // 123456 in a 10x10 Data Matrix, from annex O of ISO/IEC 16022
+    assert_eq!(matrix_codes::data_matrix_error_correction(&[142, 164, 186], 5), [114, 25, 5, 88, 102]);
+}
+
+#[test]
+fn qr_codes_read_back_to_their_data() {
+    let payloads = [
+        "9789734633456".to_string(),
+        "LM-CEN-0004215|Biblioteca Centrală Universitară|5c3a0e9f1b2d4c6a".to_string(),
+        // long enough for versions 7 to 10, with their version information and several blocks
+        "x".repeat(130),
+        "y".repeat(200),
+    ];
+
+    for payload in payloads {
+        let matrix = matrix_codes::qr_code(payload.as_bytes()).unwrap();
+        assert_eq!(read_qr(&matrix), payload.as_bytes(), "{} modules", matrix.width);
+        assert_eq!(matrix_codes::decode_qr(&matrix).unwrap(), payload.as_bytes(), "{} modules", matrix.width);
+    }
+}
+
+#[test]
+fn reed_solomon_corrects_up_to_half_its_codewords() {
+    let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17];
+    let mut block = data.to_vec();
+    block.extend(matrix_codes::qr_error_correction(&data, 10));
+    let valid = block.clone();
+
+    assert!(matrix_codes::correct_errors(&mut block, 10, 0x11D, 0));
+    for (index, position) in [0, 7, 15, 20, 25].into_iter().enumerate() {
+        block[position] ^= 0x5A + index as u8;
+    }
+    assert!(matrix_codes::correct_errors(&mut block, 10, 0x11D, 0));
+    assert_eq!(block, valid);
+
+    // Data Matrix counts its roots from 2^1
+    let mut block = vec![142, 164, 186, 114, 25, 5, 88, 102];
+    block[1] = 0;
+    block[6] ^= 0xFF;
+    assert!(matrix_codes::correct_errors(&mut block, 5, 0x12D, 1));
+    assert_eq!(block, [142, 164, 186, 114, 25, 5, 88, 102]);
+
+    let mut garbled: Vec<u8> = valid.iter().enumerate().map(|(index, &codeword)| if index % 3 == 0 { !codeword } else { codeword }).collect();
+    assert!(!matrix_codes::correct_errors(&mut garbled, 10, 0x11D, 0));
+}
+
+#[test]
+fn symbols_are_decoded_through_scratches() {
+    let payload = "LM-CEN-0004215|Biblioteca Centrală Universitară|5c3a0e9f1b2d4c6a";
+
+    let mut qr = matrix_codes::qr_code(payload.as_bytes()).unwrap();
+    assert_eq!(matrix_codes::decode_qr(&qr).unwrap(), payload.as_bytes());
+    // a scratch across the middle, and one of the copies of the format information
+    let middle = qr.width / 2;
+    for x in 9..qr.width - 9 {
+        let dark = qr.is_dark(x, middle);
+        qr.set(x, middle, !dark);
+    }
+    for y in 0..6 {
+        qr.set(8, y, false);
+    }
+    assert_eq!(matrix_codes::decode_qr(&qr).unwrap(), payload.as_bytes());
+
+    for data in ["9789734633456", payload] {
+        let mut symbol = matrix_codes::data_matrix(data.as_bytes()).unwrap();
+        assert_eq!(matrix_codes::decode_data_matrix(&symbol).unwrap(), data.as_bytes());
+        for x in 3..6 {
+            symbol.set(x, 3, !symbol.is_dark(x, 3));
+        }
+        assert_eq!(matrix_codes::decode_data_matrix(&symbol).unwrap(), data.as_bytes());
+    }
+
+    // nothing but noise, and a QR code read as a Data Matrix
+    assert!(matrix_codes::decode_qr(&Matrix::new(21, 21)).is_none());
+    assert!(matrix_codes::decode_data_matrix(&matrix_codes::qr_code(b"x").unwrap()).is_none());
+}
+
+#[test]
+fn qr_codes_take_the_smallest_version() {
+    assert_eq!(matrix_codes::qr_code(b"LM-CEN-0004215").unwrap().width, 21);
+    assert_eq!(matrix_codes::qr_code(&[b'a'; 15]).unwrap().width, 25);
+    assert_eq!(matrix_codes::qr_code(&[b'a'; 213]).unwrap().width, 57);
+
+    let Err(Error::Validation(errors)) = matrix_codes::qr_code(&[b'a'; 214]) else {
+        panic!("version 10 holds 213 bytes at level M");
+    };
+    assert_eq!(errors[0].field, "barcode");
+}
+
+#[test]
+fn data_matrix_symbols_have_their_finder_and_timing_patterns() {
+    for (data, size) in [("123456", 10), ("9789734633456", 14), ("LM-CEN-0004215|Biblioteca Centrală|5c3a0e9f1b2d4c6a", 32)] {
+        let matrix = matrix_codes::data_matrix(data.as_bytes()).unwrap();
+        assert_eq!((matrix.width, matrix.height), (size, size), "{}", data);
+
+        // from 32x32 up the symbol is split in 2x2 regions, each framed the same way
+        let region = if size >= 32 { size / 2 } else { size };
+        for start in (0..size).step_by(region) {
+            for index in 0..size {
+                assert!(matrix.is_dark(start, index), "solid left edge");
+                assert!(matrix.is_dark(index, start + region - 1), "solid bottom edge");
+                assert_eq!(matrix.is_dark(index, start), index % region % 2 == 0, "top timing");
+                assert_eq!(matrix.is_dark(start + region - 1, index), index % region % 2 == 1, "right timing");
+            }
+        }
+    }
+}
+
+#[test]
+fn data_matrix_symbols_hold_up_to_144_codewords() {
+    // pairs of digits take one codeword
+    assert_eq!(matrix_codes::data_matrix(&[b'7'; 288]).unwrap().width, 44);
+    assert!(matrix_codes::data_matrix(&[b'7'; 290]).is_err());
+    // bytes above 127 take two
+    assert!(matrix_codes::data_matrix("ă".repeat(36).as_bytes()).is_ok());
+    assert!(matrix_codes::data_matrix("ă".repeat(37).as_bytes()).is_err());
+}
+
+#[test]
+fn two_dimensional_symbols_are_squares_with_a_quiet_zone() {
+    let qr = barcode::draw(Symbology::Qr, "LM-CEN-0004215", 2, 100, BLACK, WHITE).unwrap();
+    // 21 modules and 4 on each side, 3 pixels each
+    assert_eq!(qr.dimensions(), (87, 87));
+    assert_eq!(qr.get_pixel(11, 11), &WHITE);
+    assert_eq!(qr.get_pixel(12, 12), &BLACK);
+
+    let data_matrix = barcode::draw(Symbology::DataMatrix, "9789734633456", 2, 64, BLACK, WHITE).unwrap();
+    assert_eq!(data_matrix.dimensions(), (64, 64));
+    assert_eq!(data_matrix.get_pixel(3, 60), &WHITE);
+    assert_eq!(data_matrix.get_pixel(4, 59), &BLACK);
+}
+
+#[test]
+fn linear_barcodes_refuse_what_they_cannot_carry() {
+    assert!(barcode::draw(Symbology::Code39, "LM-CEN-0004215", 1, 40, BLACK, WHITE).is_ok());
+    assert!(barcode::draw(Symbology::Code39, "lm-cen-0004215", 1, 40, BLACK, WHITE).is_err());
+    assert!(barcode::draw(Symbology::Itf, "LM-CEN-0004215", 1, 40, BLACK, WHITE).is_err());
+    assert!(barcode::draw(Symbology::Ean13, "LM-CEN-0004215", 1, 40, BLACK, WHITE).is_err());
+
+    // an odd number of digits gets a leading zero instead of a check digit
+    assert_eq!(barcode::itf_digits("9789734633456"), "09789734633456");
+    let odd = barcode::encode_bars(Symbology::Itf, "9789734633456").unwrap();
+    let even = barcode::encode_bars(Symbology::Itf, "09789734633456").unwrap();
+    assert_eq!(odd, even);
+}
+
+#[test]
+fn spine_labels_carry_the_isbn_in_the_symbology_set() {
+    assert!(barcode::create_spine("9789734633456", "Maitreyi", "Mircea Eliade", Some(Symbology::DataMatrix)).is_ok());
+    assert!(barcode::create_spine("0-306-40615-2", "Maitreyi", "Mircea Eliade", Some(Symbology::Itf)).is_ok());
+    assert!(barcode::create_spine("9789734633456", "Maitreyi", "Mircea Eliade", None).is_ok());
+    assert!(barcode::create_spine("9789734633456", "Maitreyi", "Mircea Eliade", Some(Symbology::Code39)).is_ok());
+    // books without an ISBN have nothing ITF can carry
+    assert!(barcode::create_spine("fără ISBN", "Maitreyi", "Mircea Eliade", Some(Symbology::Itf)).is_err());
+}
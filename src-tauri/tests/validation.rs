@@ -0,0 +1,146 @@
+//! Per-field checks on books and clients, and the way the services report them.
+
+use diesel::RunQueryDsl;
+
+use libra_manager::database::establish_connection;
+use libra_manager::marc::CatalogRecord;
+use libra_manager::models::database::{Book, Client, StagedBook};
+use libra_manager::schema::staged_books;
+use libra_manager::services::{book_service, client_service};
+use libra_manager::validation::{self, normalize_isbn, normalize_phone, ValidationError, DEFAULT_CLIENT_ID_PATTERN};
+use libra_manager::Error;
+
+fn client(id: &str, email: &str, phone: &str) -> Client {
+    Client { id: id.to_string(), first_name: "Ana".to_string(), last_name: "Popescu".to_string(), email: email.to_string(), phone: phone.to_string(), deleted_at: None }
+}
+
+fn fields(errors: &[ValidationError]) -> Vec<&str> {
+    errors.iter().map(|error| error.field.as_str()).collect()
+}
+
+#[test]
+fn romanian_numbers_become_e164() {
+    assert_eq!(normalize_phone("0722 123 456").as_deref(), Some("+40722123456"));
+    assert_eq!(normalize_phone("+40 (722) 123-456").as_deref(), Some("+40722123456"));
+    assert_eq!(normalize_phone("0040722123456").as_deref(), Some("+40722123456"));
+    assert_eq!(normalize_phone("021.312.34.56").as_deref(), Some("+40213123456"));
+    assert_eq!(normalize_phone("+33 1 42 68 53 00").as_deref(), Some("+33142685300"));
+
+    assert_eq!(normalize_phone("0722 123 45"), None);
+    assert_eq!(normalize_phone("0522123456"), None);
+    assert_eq!(normalize_phone("722123456"), None);
+    assert_eq!(normalize_phone("07221234a6"), None);
+}
+
+#[test]
+fn isbns_need_a_valid_check_digit() {
+    assert_eq!(normalize_isbn("978-973-46-3345-6").as_deref(), Some("9789734633456"));
+    assert_eq!(normalize_isbn("0-306-40615-2").as_deref(), Some("0306406152"));
+    assert_eq!(normalize_isbn("080442957x").as_deref(), Some("080442957X"));
+
+    assert_eq!(normalize_isbn("9789734633458"), None);
+    assert_eq!(normalize_isbn("0306406153"), None);
+    assert_eq!(normalize_isbn("97897346334"), None);
+    assert_eq!(normalize_isbn("97897346334é"), None);
+}
+
+#[test]
+fn isbn_10_becomes_the_isbn_13_it_stands_for() {
+    assert_eq!(validation::to_isbn13("0-306-40615-2").as_deref(), Some("9780306406157"));
+    assert_eq!(validation::to_isbn13("9789734633456").as_deref(), Some("9789734633456"));
+    assert_eq!(validation::to_isbn13("0306406153"), None);
+    assert_eq!(validation::to_isbn10("978-0-306-40615-7").as_deref(), Some("0306406152"));
+    assert_eq!(validation::to_isbn10("9791234567896"), None);
+}
+
+#[test]
+fn emails_need_a_local_part_and_a_domain() {
+    assert!(validation::is_valid_email("ana.popescu+biblioteca@example.ro"));
+
+    for email in ["ana", "@example.ro", "ana@", "ana@example", "ana@@example.ro", "ana..p@example.ro", "ana@-example.ro", "ana p@example.ro"] {
+        assert!(!validation::is_valid_email(email), "{}", email);
+    }
+}
+
+#[test]
+fn every_invalid_field_is_reported() {
+    let pattern = validation::client_id_pattern("LIB-[0-9]{4}").unwrap();
+    let errors = validation::validate_client(&client("AB12", "ana", "123"), Some(&pattern)).err().unwrap();
+    assert_eq!(fields(&errors), ["id", "email", "phone"]);
+
+    let book = Book { isbn: "123".to_string(), title: " ".to_string(), author: "Eliade".to_string(), items: 0, deleted_at: None };
+    let errors = validation::validate_book(&book).err().unwrap();
+    assert_eq!(fields(&errors), ["items", "title", "isbn"]);
+    assert_eq!(fields(&validation::validate_book_changes(&book).err().unwrap()), ["items", "title"]);
+}
+
+#[test]
+fn invalid_patterns_are_reported_on_the_setting() {
+    match validation::client_id_pattern("LIB-[0-9") {
+        Err(Error::Validation(errors)) => assert_eq!(fields(&errors), ["clientIdPattern"]),
+        _ => panic!("pattern should not compile"),
+    }
+}
+
+#[test]
+fn clients_are_stored_normalized() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let pattern = validation::client_id_pattern(DEFAULT_CLIENT_ID_PATTERN).unwrap();
+
+    client_service::create_client(&mut connection, &client("aB3xYz", " Ana@Example.RO ", "0722-123-456"), &pattern).unwrap();
+
+    let stored = client_service::fetch_client(&mut connection, "aB3xYz", false).unwrap().unwrap();
+    assert_eq!(stored.email, "ana@example.ro");
+    assert_eq!(stored.phone, "+40722123456");
+
+    match client_service::update_client(&mut connection, &client("aB3xYz", "ana@example.ro", "")) {
+        Err(Error::Validation(errors)) => assert_eq!(fields(&errors), ["phone"]),
+        _ => panic!("an empty phone number should be refused"),
+    }
+}
+
+#[test]
+fn invalid_books_never_reach_the_database() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let book = Book { isbn: "978-973-46-3345-6".to_string(), title: "Maitreyi".to_string(), author: "Mircea Eliade".to_string(), items: -1, deleted_at: None };
+
+    assert!(matches!(book_service::create_book(&mut connection, &book, 1), Err(Error::Validation(_))));
+    assert_eq!(book_service::count_books(&mut connection).unwrap(), 0);
+
+    book_service::create_book(&mut connection, &Book { items: 2, ..book }, 1).unwrap();
+    assert!(book_service::fetch_book(&mut connection, "9789734633456", false).unwrap().is_some());
+}
+
+#[test]
+fn staged_books_are_checked_like_the_form_checks_them() {
+    let mut connection = establish_connection(":memory:").unwrap();
+    let record = |isbn: &str, author: &str| CatalogRecord { isbn: isbn.to_string(), title: "Maitreyi".to_string(), author: author.to_string(), ..CatalogRecord::default() };
+
+    // MARC records without an author are staged for the librarian to complete
+    let staged = [record("9789734633456", "").to_staged_book(), record("9780306406157", "Mircea Eliade").to_staged_book(), StagedBook { items: 0, ..record("0306406152", "Mircea Eliade").to_staged_book() }];
+    diesel::insert_into(staged_books::table).values(&staged[..]).execute(&mut connection).unwrap();
+
+    let isbns = staged.iter().map(|book| book.isbn.clone()).collect::<Vec<String>>();
+    let Err(Error::Validation(errors)) = book_service::commit_staged_books(&mut connection, &isbns, 1) else { panic!("incomplete books were committed") };
+    let mut messages = errors.iter().map(|error| format!("{} {}", error.field, error.message)).collect::<Vec<String>>();
+    messages.sort();
+    assert_eq!(messages, ["author 9789734633456: Must not be empty", "items 0306406152: Must be at least 1"]);
+    assert_eq!(book_service::count_books(&mut connection).unwrap(), 0);
+
+    book_service::update_staged_book(&mut connection, &StagedBook { author: "Mircea Eliade".to_string(), ..staged[0].clone() }).unwrap();
+    book_service::update_staged_book(&mut connection, &StagedBook { items: 1, ..staged[2].clone() }).unwrap();
+    assert_eq!(book_service::commit_staged_books(&mut connection, &isbns, 1).unwrap(), 3);
+    assert_eq!(book_service::count_books(&mut connection).unwrap(), 3);
+}
+
+#[test]
+fn validation_errors_serialize_per_field() {
+    let error = Error::Validation(vec![ValidationError { field: "email".to_string(), message: "'ana' is not a valid email address".to_string() }]);
+
+    assert_eq!(serde_json::to_value(&error).unwrap(), serde_json::json!([{"field": "email", "message": "'ana' is not a valid email address"}]));
+    assert_eq!(error.to_string(), "Invalid email: 'ana' is not a valid email address");
+
+    // the client page tells a misread ID from other failures by this message
+    let error = Error::InvalidClientId("LM-CEN-0004216".to_string());
+    assert_eq!(serde_json::to_value(&error).unwrap(), serde_json::json!("Client ID LM-CEN-0004216 has an invalid check digit"));
+}
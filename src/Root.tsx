@@ -1,9 +1,33 @@
 import Navigation from "./components/navigation/NavigationBar.tsx";
-import {Outlet} from "react-router-dom";
+import {Outlet, useNavigate} from "react-router-dom";
+import {useState} from "react";
+import {invoke} from "./api.ts";
+import {useRootData} from "./components/util/useRootData.ts";
+import {ScanTarget, useWedgeScanner} from "./components/util/useWedgeScanner.ts";
 
 function Root() {
+    const {user} = useRootData();
+    const navigate = useNavigate();
+    const [scanError, setScanError] = useState<string | null>(null);
+
+    // badges open the client and ISBNs or copies the book, from any page
+    useWedgeScanner(async (code) => {
+        if (!user) return;
+        setScanError(null);
+        try {
+            const target: ScanTarget = await invoke("resolve_scan", {code});
+            navigate(target.kind === "client" ? `/clients/${target.client.id}` : `/books/${target.book.isbn}`);
+        } catch (error) {
+            setScanError(`Codul scanat (${code}) nu a fost recunoscut`);
+        }
+    });
+
     return <>
         <Navigation/>
+        {scanError && <div className="absolute z-20 bottom-4 left-1/2 -translate-x-1/2 px-4 py-2 bg-red text-black-5 font-medium rounded-2xl cursor-pointer"
+                           onClick={() => setScanError(null)}>
+            {scanError}
+        </div>}
         <Outlet/>
     </>
 }
@@ -0,0 +1,44 @@
+import {invoke as invokeLocal, InvokeArgs} from "@tauri-apps/api/tauri";
+
+// The database commands served by `remote_commands!` in main.rs. On a thin client
+// they run on the desk sharing its database, everything else stays local.
+const remoteCommands = new Set([
+    "login", "select_branch",
+    "fetch_books", "fetch_book", "create_book", "delete_book", "restore_book", "purge_book", "update_book",
+    "fetch_clients", "fetch_client", "create_client", "delete_client", "restore_client", "purge_client", "update_client",
+    "fetch_borrowers", "fetch_borrowed_books", "is_book_available", "add_borrow", "delete_borrow", "update_borrow",
+    "update_user", "fetch_user", "fetch_users", "create_user", "delete_user",
+    "fetch_branches", "create_branch", "update_branch", "delete_branch", "fetch_holdings", "transfer_copies", "fetch_transfers",
+    "fetch_staged_books", "update_staged_book", "delete_staged_book", "commit_staged_books",
+    "upload_client_photo", "fetch_client_photo", "crop_client_photo", "resize_client_photo", "delete_client_photo",
+    "sign_client_badge", "resolve_scan",
+    "fetch_counts",
+]);
+
+let remote = false;
+
+export function setRemote(enabled: boolean) {
+    remote = enabled;
+}
+
+export async function invoke<T>(command: string, args?: InvokeArgs): Promise<T> {
+    if (remote && remoteCommands.has(command)) {
+        return await invokeLocal<T>("remote_invoke", {command, args: args ?? {}});
+    }
+    return await invokeLocal<T>(command, args);
+}
+
+// Shape of the errors the backend returns when the fields of a form are invalid.
+export type ValidationError = {
+    field: string,
+    message: string,
+}
+
+// Joins the messages of the invalid fields of a failed command, using `messages` to
+// name each field. Undefined when the command failed for another reason.
+export function validationMessage(error: unknown, messages: Record<string, string>): string | undefined {
+    if (!Array.isArray(error)) {
+        return undefined;
+    }
+    return (error as ValidationError[]).map(({field, message}) => messages[field] ?? message).join(", ");
+}
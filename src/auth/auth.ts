@@ -1,10 +1,21 @@
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke as invokeLocal} from "@tauri-apps/api/tauri";
+import {invoke, setRemote} from "../api.ts";
+import {settingsProvider} from "../components/settings/settings.ts";
 
 export interface User {
     username: string,
     firstName: string,
     lastName: string,
     role: "admin" | "user",
+    branchId: number | null,
+}
+
+// The branch this desk works for, kept across logins on this computer.
+const DESK_BRANCH = "deskBranchId";
+
+function deskBranch(): number | null {
+    const stored = localStorage.getItem(DESK_BRANCH);
+    return stored ? Number(stored) : null;
 }
 
 class AuthProvider {
@@ -21,13 +32,23 @@ class AuthProvider {
     }
 
     public async login(username: string, password: string): Promise<void> {
-        this.user = await invoke("login", {username, password});
+        const {serverUrl} = await settingsProvider.getCurrentSettings();
+        const branchId = deskBranch();
+
+        if (serverUrl) {
+            this.user = await invokeLocal("connect_server", {url: serverUrl, username, password, branchId});
+            setRemote(true);
+        } else {
+            this.user = await invoke("login", {username, password, branchId});
+        }
         this.authenticated = true;
     }
 
     public async logout(): Promise<void> {
         this.authenticated = false;
         this.user = null;
+        setRemote(false);
+        await invokeLocal("disconnect_server");
     }
 
     public async getCurrentUser(): Promise<User | null> {
@@ -39,6 +60,17 @@ class AuthProvider {
     public getUsername() : string | undefined {
         return this.user?.username;
     }
+
+    public getBranchId(): number | null {
+        return this.user?.branchId ?? null;
+    }
+
+    public async selectBranch(branchId: number): Promise<void> {
+        if (!this.user) return;
+        await invoke("select_branch", {username: this.user.username, branchId});
+        this.user = {...this.user, branchId};
+        localStorage.setItem(DESK_BRANCH, String(branchId));
+    }
 }
 
 export const authProvider = new AuthProvider();
@@ -1,11 +1,11 @@
-import {Book} from "./BooksPage.tsx";
-import {invoke} from "@tauri-apps/api/tauri";
+import {Book, bookFieldErrors} from "./BooksPage.tsx";
+import {invoke, validationMessage} from "../../api.ts";
 import * as Yup from "yup";
 import {Form, Formik} from "formik";
 import {useActionData, useSubmit} from "react-router-dom";
 import Scanner from "../util/Scanner.tsx";
-import {BarcodeFormat, DecodeHintType} from "@zxing/library";
 import Input from "../util/Input.tsx";
+import {authProvider} from "../../auth/auth.ts";
 
 type BookResponse = {
     ok: boolean,
@@ -22,7 +22,7 @@ export async function action({request}: { request: Request }): Promise<BookRespo
     const book: Book = {isbn, title, author, items};
 
     try {
-        await invoke("create_book", {book});
+        await invoke("create_book", {book, branchId: authProvider.getBranchId()});
         return {
             ok: true,
             message: "Carte adăugată cu succes"
@@ -30,7 +30,7 @@ export async function action({request}: { request: Request }): Promise<BookRespo
     } catch (error) {
         return {
             ok: false,
-            message: "Acest ISBN există deja în baza de date"
+            message: validationMessage(error, bookFieldErrors) ?? "Acest ISBN există deja în baza de date"
         }
     }
 }
@@ -40,15 +40,12 @@ export default function BookAdd() {
         isbn: Yup.string().required("ISBN-ul este obligatoriu").matches(/^[0-9]{13}$/, {message: "ISBN-ul nu este valid"}),
         title: Yup.string().required("Titlul este obligatoriu").matches(/^.+$/, {message: "Titlul este invalid"}),
         author: Yup.string().required("Autorul este obligatoriu").matches(/^.+$/, {message: "Autorul este invalid"}),
-        items: Yup.number().required("Exemplarele sunt obligatorii"),
+        items: Yup.number().required("Exemplarele sunt obligatorii").min(1, "Numărul de exemplare trebuie să fie cel puțin 1"),
     })
 
     const submit = useSubmit();
     const data = useActionData() as BookResponse | undefined;
 
-    const decodeHints = new Map<DecodeHintType, any>();
-    decodeHints.set(DecodeHintType.POSSIBLE_FORMATS, [BarcodeFormat.EAN_13]);
-
     return (
         <Formik initialValues={{title: "", author: "", isbn: "", items: 1}} validationSchema={validationSchema}
                 onSubmit={async (values) => {
@@ -77,8 +74,8 @@ export default function BookAdd() {
                                            className="border text-sm rounded-lg block w-full p-2.5"
                                            placeholder="ISBN">
 
-                                        <Scanner hints={decodeHints} onDecode={(result) => {
-                                            formik.setFieldValue("isbn", result.getText(), false)
+                                        <Scanner formats={["ean13"]} onDecode={(isbn) => {
+                                            formik.setFieldValue("isbn", isbn, false)
                                         }}/>
                                     </Input>
                                 </div>
@@ -3,7 +3,8 @@ import {ActionFunctionArgs, redirect, useLoaderData, useNavigate, useSubmit} fro
 import {Form, Formik} from "formik";
 import Input from "../util/Input.tsx";
 import {Book} from "./BooksPage.tsx";
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke} from "../../api.ts";
+import {authProvider} from "../../auth/auth.ts";
 
 type PathParams = {
     isbn: string
@@ -18,7 +19,7 @@ export async function action({params, request}: ActionFunctionArgs<PathParams>)
     const items = Number(formData.get("items") as string);
 
     const book: Book = {isbn, title, author, items};
-    await invoke("update_book", {book});
+    await invoke("update_book", {book, branchId: authProvider.getBranchId()});
     return redirect(`/books/${isbn}`);
 }
 
@@ -1,4 +1,4 @@
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke, validationMessage} from "../../api.ts";
 import {Book} from "./BooksPage.tsx";
 import {
     ActionFunctionArgs,
@@ -13,6 +13,9 @@ import {Borrow} from "../clients/ClientInfo.tsx";
 import {Client} from "../clients/ClientsPage.tsx";
 import {compareAsc, compareDesc} from "date-fns";
 import {useEffect, useState} from "react";
+import {useRootData} from "../util/useRootData.ts";
+import {downloadMessages, labelMessages} from "../settings/settings.ts";
+import FormatSelect, {OutputFormat} from "../util/FormatSelect.tsx";
 
 type PathParams = {
     isbn: string,
@@ -60,6 +63,9 @@ function checkISBN(isbn: string): boolean {
 export default function BookInfo() {
     const {book, clients, history} = useLoaderData() as LoaderData;
     const [message, setMessage] = useState<string | null>(null);
+    const [outputFormat, setOutputFormat] = useState<OutputFormat>("png");
+    const {settings} = useRootData();
+    const hasLabelPrinter = settings.labelPrinter.address.trim() !== "";
 
     const isValid = checkISBN(book.isbn);
 
@@ -130,16 +136,33 @@ export default function BookInfo() {
                     {isValid && <button onClick={async () => {
                         setMessage(null);
                         try {
-                            await invoke("download_book_isbn", {isbn: book.isbn});
-                            setMessage(`Cod de bare generat cu succes. Poate fi accesat în folderul Documents.`);
+                            const path: string = await invoke("download_book_isbn", {isbn: book.isbn, format: outputFormat});
+                            setMessage(`Cod de bare salvat în ${path}`);
                         } catch (error) {
                             console.log(error);
-                            setMessage("S-a produs o eroare. Cel mai probabil nu există drepturi de scriere a fișierului.");
+                            setMessage(validationMessage(error, downloadMessages) ?? "S-a produs o eroare. Cel mai probabil nu există drepturi de scriere a fișierului.");
                         }
                     }}
                                         className="px-1.5 py-1.5 text-black-5 text-lg font-medium text-center bg-green rounded-2xl">
                         Emite cod de bare
                     </button>}
+                    {isValid && <FormatSelect value={outputFormat} onChange={setOutputFormat}/>}
+                    {isValid && hasLabelPrinter && <button onClick={async () => {
+                        setMessage(null);
+                        const copies = Array.from({length: book.items}, (_, index) => ({
+                            kind: "copy", isbn: book.isbn, title: book.title, copy: index + 1,
+                        }));
+                        try {
+                            await invoke("print_labels", {labels: [{kind: "isbn", isbn: book.isbn}, ...copies]});
+                            setMessage(`Etichetele au fost trimise la imprimantă.`);
+                        } catch (error) {
+                            console.log(error);
+                            setMessage(validationMessage(error, labelMessages) ?? "Imprimanta de etichete nu a putut fi contactată.");
+                        }
+                    }}
+                                                            className="px-1.5 py-1.5 ml-3 text-black-5 text-lg font-medium text-center bg-green rounded-2xl">
+                        Tipărește etichete
+                    </button>}
                     {message && <h1 className="font-medium mt-4 text-sm">{message}</h1>}
                 </div>
             </div>
@@ -1,9 +1,11 @@
 import {Link, Outlet, redirect, useLoaderData, useNavigate} from "react-router-dom";
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke} from "../../api.ts";
 import {useMemo, useState} from "react";
-import {BarcodeFormat, DecodeHintType, Result} from "@zxing/library";
 import Scanner from "../util/Scanner.tsx";
 import {authProvider} from "../../auth/auth.ts";
+import SheetDownload from "../util/SheetDownload.tsx";
+import {useRootData} from "../util/useRootData.ts";
+import {ScanTarget} from "../util/useWedgeScanner.ts";
 
 export type Book = {
     isbn: string,
@@ -12,6 +14,14 @@ export type Book = {
     items: number,
 }
 
+// What the forms say about each field the backend refused.
+export const bookFieldErrors: Record<string, string> = {
+    isbn: "ISBN-ul nu este valid",
+    title: "Titlul este invalid",
+    author: "Autorul este invalid",
+    items: "Numărul de exemplare trebuie să fie cel puțin 1",
+};
+
 type LoaderData = {
     books: Book[],
 }
@@ -24,17 +34,20 @@ export async function loader(): Promise<LoaderData | Response> {
 
 export default function BooksPage() {
     const {books} = useLoaderData() as LoaderData;
+    const {settings} = useRootData();
     const [search, setSearch] = useState("");
     const navigate = useNavigate();
 
-    const onDecode = (result: Result) => {
-        const isbn = result.getText();
-        navigate(`/books/${isbn}`);
+    // the publisher's barcode, or one of the library's ISBN or copy labels
+    const onDecode = async (code: string) => {
+        try {
+            const target: ScanTarget = await invoke("resolve_scan", {code});
+            if (target.kind === "book") navigate(`/books/${target.book.isbn}`);
+        } catch (error) {
+            navigate(`/books/${code}`);
+        }
     }
 
-    const decodeHints = new Map<DecodeHintType, any>();
-    decodeHints.set(DecodeHintType.POSSIBLE_FORMATS, [BarcodeFormat.EAN_13]);
-
     const filtered = useMemo((): Book[] => {
         const expression = new RegExp(search, "i");
         return books.filter((book) => {
@@ -42,18 +55,27 @@ export default function BooksPage() {
         })
     }, [books, search]);
 
+    // a label for every copy of the books shown
+    const copies = useMemo(() => filtered.flatMap(book => Array(book.items).fill(book)), [filtered]);
+
     return (
         <div className="flex h-full overflow-auto">
             <div className="flex flex-col w-52 lg:w-80 bg-black-10 items-center justify-start flex-shrink-0">
                 <form className="flex justify-between py-4 w-full px-4">
                     <input value={search} className="mr-3 w-full rounded-lg border p-0.5" placeholder={"Caută..."}
                            onChange={(event) => setSearch(event.target.value)}/>
-                    <Scanner onDecode={onDecode} hints={decodeHints}/>
+                    <Scanner onDecode={onDecode} formats={["ean13", settings.symbologies.isbn, settings.symbologies.copy]}/>
                 </form>
                 <Link to="create"
                       className="px-2 py-2 bg-orange text-black-5 text-center font-medium text-lg rounded-2xl">
                     Adaugă carte
                 </Link>
+                <div className="flex flex-col w-full px-4 mt-3 space-y-2">
+                    <SheetDownload label="Etichete ISBN" command="download_label_sheet" args={{books: copies, label: "isbn"}}
+                                   perSheet={settings.isbnSheet.columns * settings.isbnSheet.rows}/>
+                    <SheetDownload label="Etichete cotor" command="download_label_sheet" args={{books: copies, label: "spine"}}
+                                   perSheet={settings.spineSheet.columns * settings.spineSheet.rows}/>
+                </div>
                 {filtered.length === 0 && <p className="font-medium mt-3">Nu există cărți</p>}
                 <div
                     className="flex flex-col items-start w-full overflow-auto h-4/5 max-h-fit scrollbar-thin px-4 divide-y">
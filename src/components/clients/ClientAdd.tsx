@@ -1,8 +1,9 @@
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke, validationMessage} from "../../api.ts";
 import * as Yup from "yup";
 import {Form, Formik} from "formik";
 import {useActionData, useSubmit} from "react-router-dom";
-import {Client, clientUniqueId} from "./ClientsPage.tsx";
+import {Client, clientFieldErrors, phonePattern} from "./ClientsPage.tsx";
+import {authProvider} from "../../auth/auth.ts";
 import Input from "../util/Input.tsx";
 
 type ClientResponse = {
@@ -12,24 +13,24 @@ type ClientResponse = {
 
 export async function action({request}: { request: Request }): Promise<ClientResponse> {
     const formData = await request.formData();
-    const id = clientUniqueId.rnd();
     const firstName = formData.get("firstName") as string;
     const lastName = formData.get("lastName") as string;
     const email = formData.get("email") as string;
     const phone = formData.get("phone") as string;
 
-    const client: Client = {id, firstName, lastName, email, phone};
+    // the backend gives the client the next ID of the branch
+    const client: Client = {id: "", firstName, lastName, email, phone};
 
     try {
-        await invoke("create_client", {client});
+        const created = await invoke<Client>("create_client", {client, branchId: authProvider.getBranchId()});
         return {
             ok: true,
-            message: "Client adăugat cu succes"
+            message: `Client adăugat cu succes, codul ${created.id}`
         }
     } catch (error) {
         return {
             ok: false,
-            message: "Acest client există deja în baza de date"
+            message: validationMessage(error, clientFieldErrors) ?? "Acest client există deja în baza de date"
         }
     }
 }
@@ -39,7 +40,7 @@ export default function ClientAdd() {
         firstName: Yup.string().required("Prenumele este obligatoriu").matches(/^.+$/, {message: "Prenumele este invalid"}),
         lastName: Yup.string().required("Numele este obligatoriu").matches(/^.+$/, {message: "Numele este invalid"}),
         email: Yup.string().matches(/^[\w-.]+@([\w-]+\.)+[\w-]{2,4}$/, {message: "Email-ul este invalid"}).required("Email-ul este obligatoriu"),
-        phone: Yup.string().required("Numărul este obligatoriu").matches(/^(0\d{9})$/, {message: "Numărul este invalid"}),
+        phone: Yup.string().required("Numărul este obligatoriu").matches(phonePattern, {message: "Numărul este invalid"}),
     })
 
     const submit = useSubmit();
@@ -1,9 +1,9 @@
 import {ActionFunctionArgs, redirect, useActionData, useLoaderData, useNavigate, useSubmit} from "react-router-dom";
-import {Client} from "./ClientsPage.tsx";
+import {Client, clientFieldErrors, phonePattern} from "./ClientsPage.tsx";
 import * as Yup from "yup";
 import {Form, Formik} from "formik";
 import Input from "../util/Input.tsx";
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke, validationMessage} from "../../api.ts";
 
 type PathParams = {
     clientId: string;
@@ -25,7 +25,7 @@ export async function action({request, params}: ActionFunctionArgs<PathParams>)
         return redirect(`/clients/${params.clientId}`);
     } catch (error) {
         return {
-            message: "Numărul de telefon / Adresa de email există deja"
+            message: validationMessage(error, clientFieldErrors) ?? "Numărul de telefon / Adresa de email există deja"
         }
     }
 }
@@ -40,7 +40,7 @@ export default function ClientEdit() {
         firstName: Yup.string().matches(/^.+$/, {message: "Prenumele este invalid"}).required("Prenumele este obligatoriu"),
         lastName: Yup.string().matches(/^.+$/, {message: "Numele este invalid"}).required("Numele este obligatoriu"),
         email: Yup.string().matches(/^[\w-.]+@([\w-]+\.)+[\w-]{2,4}$/, {message: "Email-ul este invalid"}).required("Email-ul este obligatoriu"),
-        phone: Yup.string().matches(/^(0\d{9})$/, {message: "Numărul este invalid"}).required("Numărul este obligatoriu"),
+        phone: Yup.string().matches(phonePattern, {message: "Numărul este invalid"}).required("Numărul este obligatoriu"),
     });
 
     return (
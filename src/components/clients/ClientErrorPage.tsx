@@ -1,11 +1,18 @@
-import {Link} from "react-router-dom";
+import {isRouteErrorResponse, Link, useRouteError} from "react-router-dom";
 
 export default function BookErrorPage() {
+    const error = useRouteError();
+    const invalidId = isRouteErrorResponse(error) && error.status === 400;
+    const notFound = isRouteErrorResponse(error) && error.status === 404;
 
     return <div className="flex-grow flex">
         <div
             className="m-auto bg-black-5 rounded-xl shadow-black-10 border flex flex-col items-center justify-evenly px-4 py-4 h-44">
-            <h1 className="font-bold text-xl">Clientul nu a putut fi găsit în baza de date.</h1>
+            <h1 className="font-bold text-xl">{invalidId
+                ? "Codul clientului nu este valid. Scanează din nou legitimația."
+                : notFound
+                    ? "Clientul nu a putut fi găsit în baza de date."
+                    : `Clientul nu a putut fi încărcat: ${String(error)}`}</h1>
             <Link to="/clients/create" className="font-medium px-2 py-2 bg-orange text-black-5 rounded-2xl">Adaugă un nou client</Link>
         </div>
     </div>
@@ -1,4 +1,4 @@
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke, validationMessage} from "../../api.ts";
 import {Client} from "./ClientsPage.tsx";
 import {
     ActionFunctionArgs,
@@ -7,16 +7,22 @@ import {
     LoaderFunctionArgs,
     redirect,
     useLoaderData,
+    useNavigate,
     useNavigation,
     useSubmit
 } from "react-router-dom";
 import {Book} from "../books/BooksPage.tsx";
 import {Form as FormikForm, Formik} from "formik";
 import Input from "../util/Input.tsx";
+import {useRootData} from "../util/useRootData.ts";
+import {downloadMessages, labelMessages} from "../settings/settings.ts";
+import FormatSelect, {OutputFormat} from "../util/FormatSelect.tsx";
 import Scanner from "../util/Scanner.tsx";
-import {BarcodeFormat, DecodeHintType} from "@zxing/library";
 import {addDays, compareAsc, compareDesc, format} from "date-fns";
 import {useEffect, useState} from "react";
+import {authProvider} from "../../auth/auth.ts";
+import ClientPhoto from "./ClientPhoto.tsx";
+import {ScanTarget, useWedgeScanner} from "../util/useWedgeScanner.ts";
 
 type PathParams = {
     clientId: string;
@@ -24,6 +30,7 @@ type PathParams = {
 
 type LoaderData = {
     client: Client,
+    photo: string | null,
     borrowedBooks: BookBorrow[],
     history: BookBorrow[],
 }
@@ -42,13 +49,24 @@ export type Borrow = {
     returned: boolean,
 }
 
+// How `Error::InvalidClientId` reaches the frontend.
+const invalidClientId = /^Client ID .* has an invalid check digit$/;
+
 export async function loader({params}: LoaderFunctionArgs<PathParams>): Promise<LoaderData> {
-    const client = await invoke("fetch_client", {id: params.clientId});
+    const client = await invoke("fetch_client", {id: params.clientId}).catch((error) => {
+        // a generated ID with the wrong check digit, most likely misread by the scanner
+        if (typeof error === "string" && invalidClientId.test(error)) {
+            throw new Response("", {status: 400, statusText: "Invalid ID"});
+        }
+        throw error;
+    });
     if (client === null) throw new Response("", {status: 404, statusText: "Not Found"});
     const books: BookBorrow[] = await invoke("fetch_borrowed_books", {id: params.clientId});
+    const photo: string | null = await invoke("fetch_client_photo", {clientId: params.clientId});
 
     return {
         client: client as Client,
+        photo,
         borrowedBooks: books.filter(book => !book.borrow.returned).sort((a, b) => compareAsc(a.borrow.endDate, b.borrow.endDate)),
         history: books.filter(book => book.borrow.returned).sort((a, b) => compareDesc(a.borrow.endDate, b.borrow.endDate))
     };
@@ -64,7 +82,7 @@ export async function action({params, request}: ActionFunctionArgs<PathParams>)
 
     if (request.method === "POST") {
         const isbn: string = formData.get("isbn") as string;
-        await invoke("add_borrow", {isbn, clientId: params.clientId});
+        await invoke("add_borrow", {isbn, clientId: params.clientId, branchId: authProvider.getBranchId()});
 
     } else if (request.method === "PUT") {
         const id: number = Number(formData.get("id"));
@@ -87,19 +105,50 @@ export async function action({params, request}: ActionFunctionArgs<PathParams>)
 }
 
 export default function ClientInfo() {
-    const {client, borrowedBooks, history} = useLoaderData() as LoaderData;
+    const {client, photo, borrowedBooks, history} = useLoaderData() as LoaderData;
     const submit = useSubmit();
     const [message, setMessage] = useState<string | null>(null);
-
-    const decodeHints = new Map<DecodeHintType, any>();
-    decodeHints.set(DecodeHintType.POSSIBLE_FORMATS, [BarcodeFormat.EAN_13]);
+    const [outputFormat, setOutputFormat] = useState<OutputFormat>("png");
+    const {settings} = useRootData();
 
     const navigation = useNavigation();
+    const navigate = useNavigate();
 
     useEffect(() => {
         if (navigation.state === "loading" && !navigation.formData) setMessage(null);
     }, [navigation]);
 
+    // with the client open, a scanned book is returned when on loan to them and lent otherwise
+    useWedgeScanner(async (code) => {
+        setMessage(null);
+        let target: ScanTarget;
+        try {
+            target = await invoke("resolve_scan", {code});
+        } catch (error) {
+            setMessage(`Codul scanat (${code}) nu a fost recunoscut`);
+            return;
+        }
+        if (target.kind === "client") {
+            navigate(`/clients/${target.client.id}`);
+            return;
+        }
+
+        const {book} = target;
+        const loan = borrowedBooks.find((borrowed) => borrowed.book.isbn === book.isbn);
+        if (loan) {
+            submit({id: loan.borrow.id}, {method: "put"});
+            setMessage(`„${book.title}” a fost returnată.`);
+            return;
+        }
+        const available: boolean | null = await invoke("is_book_available", {isbn: book.isbn, clientId: client.id, branchId: authProvider.getBranchId()});
+        if (available) {
+            submit({isbn: book.isbn}, {method: "post"});
+            setMessage(`„${book.title}” a fost împrumutată.`);
+        } else {
+            setMessage(`„${book.title}” nu mai este disponibilă.`);
+        }
+    });
+
     return (
         <div className="overflow-auto flex-grow flex p-5">
             <div className="bg-black-5 rounded-xl shadow-black-10 shadow-md w-4/5 max-w-xl m-auto flex-shrink-0">
@@ -137,6 +186,7 @@ export default function ClientInfo() {
                             </button>
                         </Form>
                     </div>
+                    <ClientPhoto clientId={client.id} photo={photo}/>
                     <details className="mt-3 open:ring-1 open:ring-black-100/5 p-3 open:shadow-lg w-full">
                         <summary className="text-xl font-bold">Împrumuturi</summary>
                         <div className="w-full">
@@ -150,7 +200,8 @@ export default function ClientInfo() {
 
                                         const check: boolean | null = await invoke("is_book_available", {
                                             isbn,
-                                            clientId: client.id
+                                            clientId: client.id,
+                                            branchId: authProvider.getBranchId()
                                         });
 
                                         if (check === null) return {isbn: "ISBN-ul nu există în baza de date"};
@@ -166,8 +217,8 @@ export default function ClientInfo() {
                                                    className="border text-sm rounded-lg block p-2.5 flex-grow"
                                                    placeholder="ISBN">
 
-                                                <Scanner hints={decodeHints} onDecode={(result) => {
-                                                    formik.setFieldValue("isbn", result.getText(), true);
+                                                <Scanner formats={["ean13", settings.symbologies.isbn]} onDecode={(isbn) => {
+                                                    formik.setFieldValue("isbn", isbn, true);
                                                 }}/>
                                                 <button type="submit"
                                                         className="!ml-4 px-2.5 py-2.5 text-black-5 text-sm font-medium text-center bg-orange rounded-2xl">
@@ -201,19 +252,40 @@ export default function ClientInfo() {
                         try {
                             const args = {
                                 clientId: client.id,
-                                clientName: `${client.firstName} ${client.lastName}`,
+                                firstName: client.firstName,
+                                lastName: client.lastName,
                                 date: format(new Date(), "yyyy-MM-dd"),
+                                format: outputFormat,
                             };
 
-                            await invoke("download_client_badge", args);
-                            setMessage(`Legitimație generată cu succes. Poate fi accesată în folderul Documents.`);
+                            const path: string = await invoke("download_client_badge", args);
+                            setMessage(`Legitimație salvată în ${path}`);
                         } catch (error) {
-                            setMessage("S-a produs o eroare. Cel mai probabil nu există drepturi de scriere a fișierului.");
+                            setMessage(validationMessage(error, downloadMessages) ?? "S-a produs o eroare. Cel mai probabil nu există drepturi de scriere a fișierului.");
                         }
                     }}
                             className="px-1.5 py-1.5 text-black-5 text-lg font-medium text-center bg-green rounded-2xl">
                         Emite legitimație
                     </button>
+                    <FormatSelect value={outputFormat} onChange={setOutputFormat}/>
+                    {settings.labelPrinter.address.trim() !== "" && <button onClick={async () => {
+                        setMessage(null);
+                        try {
+                            const label = {
+                                kind: "badge",
+                                clientId: client.id,
+                                clientName: `${client.firstName} ${client.lastName}`,
+                                issued: format(new Date(), "yyyy-MM-dd"),
+                            };
+                            await invoke("print_labels", {labels: [label]});
+                            setMessage(`Legitimația a fost trimisă la imprimantă.`);
+                        } catch (error) {
+                            setMessage(validationMessage(error, labelMessages) ?? "Imprimanta de etichete nu a putut fi contactată.");
+                        }
+                    }}
+                            className="px-1.5 py-1.5 ml-3 text-black-5 text-lg font-medium text-center bg-green rounded-2xl">
+                        Tipărește legitimația
+                    </button>}
                     {message && <h1 className="font-medium mt-4 text-sm">{message}</h1>}
                 </div>
             </div>
@@ -0,0 +1,74 @@
+import {useRef, useState} from "react";
+import {useRevalidator} from "react-router-dom";
+import {invoke} from "../../api.ts";
+import {readAsDataUrl} from "../util/files.ts";
+
+type Props = {
+    clientId: string,
+    // the stored JPEG, base64 encoded
+    photo: string | null,
+}
+
+// Badges print photos at 3:4, portrait.
+const PORTRAIT = 3 / 4;
+
+export default function ClientPhoto({clientId, photo}: Props) {
+    const revalidator = useRevalidator();
+    const image = useRef<HTMLImageElement>(null);
+    const [error, setError] = useState<string | null>(null);
+
+    const run = async (command: string, args: Record<string, unknown>) => {
+        setError(null);
+        try {
+            await invoke(command, {clientId, ...args});
+            revalidator.revalidate();
+        } catch (error) {
+            setError("Fotografia nu a putut fi salvată. Alege o imagine JPEG sau PNG.");
+        }
+    }
+
+    const cropPortrait = async () => {
+        const {naturalWidth: width, naturalHeight: height} = image.current!;
+        const cropWidth = Math.min(width, Math.round(height * PORTRAIT));
+        const cropHeight = Math.min(height, Math.round(cropWidth / PORTRAIT));
+        const crop = {x: Math.floor((width - cropWidth) / 2), y: Math.floor((height - cropHeight) / 2), width: cropWidth, height: cropHeight};
+        await run("crop_client_photo", {crop});
+    }
+
+    return (
+        <details className="mt-3 open:ring-1 open:ring-black-100/5 p-3 open:shadow-lg w-full">
+            <summary className="text-xl font-bold">Fotografie</summary>
+            <div className="flex items-start gap-4 mt-3">
+                {photo ?
+                    <img ref={image} src={`data:image/jpeg;base64,${photo}`} alt="Fotografia clientului"
+                         className="w-32 border rounded-lg"/> :
+                    <div className="w-32 h-40 border rounded-lg flex items-center justify-center text-sm">Fără fotografie</div>}
+                <div className="flex flex-col gap-2">
+                    <label className="px-2.5 py-1.5 text-black-5 text-sm font-medium text-center bg-orange rounded-2xl cursor-pointer">
+                        Încarcă fotografie
+                        <input type="file" accept="image/*" className="hidden" onChange={async (event) => {
+                            const file = event.target.files?.[0];
+                            if (file) await run("upload_client_photo", {photo: await readAsDataUrl(file)});
+                            event.target.value = "";
+                        }}/>
+                    </label>
+                    {photo && <>
+                        <button onClick={cropPortrait}
+                                className="px-2.5 py-1.5 text-black-5 text-sm font-medium text-center bg-orange rounded-2xl">
+                            Decupează portret
+                        </button>
+                        <button onClick={() => run("resize_client_photo", {width: 300, height: 400})}
+                                className="px-2.5 py-1.5 text-black-5 text-sm font-medium text-center bg-orange rounded-2xl">
+                            Micșorează
+                        </button>
+                        <button onClick={() => run("delete_client_photo", {})}
+                                className="px-2.5 py-1.5 text-black-5 text-sm font-medium text-center bg-red rounded-2xl">
+                            Șterge fotografia
+                        </button>
+                    </>}
+                </div>
+            </div>
+            {error && <h1 className="font-medium mt-2 text-sm text-red">{error}</h1>}
+        </details>
+    );
+}
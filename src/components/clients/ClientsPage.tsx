@@ -1,12 +1,11 @@
 import Scanner from "../util/Scanner.tsx";
 import {Link, Outlet, redirect, useLoaderData, useNavigate} from "react-router-dom";
 import {useMemo, useState} from "react";
-import {DecodeHintType, Result, BarcodeFormat} from "@zxing/library";
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke} from "../../api.ts";
 import {authProvider} from "../../auth/auth.ts";
-import ShortUniqueId from "short-unique-id";
-
-export const clientUniqueId = new ShortUniqueId({length: 6});
+import SheetDownload from "../util/SheetDownload.tsx";
+import {useRootData} from "../util/useRootData.ts";
+import {ScanTarget} from "../util/useWedgeScanner.ts";
 
 export type Client = {
     id: string,
@@ -16,6 +15,18 @@ export type Client = {
     phone: string,
 }
 
+// What the forms say about each field the backend refused.
+export const clientFieldErrors: Record<string, string> = {
+    id: "Codul clientului nu respectă formatul configurat",
+    firstName: "Prenumele este invalid",
+    lastName: "Numele este invalid",
+    email: "Email-ul este invalid",
+    phone: "Numărul este invalid",
+};
+
+// National or international numbers, the backend stores them in the +40 format.
+export const phonePattern = /^(\+|00|0)[\d\s().-]{8,}$/;
+
 type LoaderData = {
     clients: Client[],
 }
@@ -29,16 +40,19 @@ export async function loader(): Promise<LoaderData | Response> {
 export default function ClientsPage() {
     const [search, setSearch] = useState("");
     const {clients} = useLoaderData() as LoaderData;
+    const {settings} = useRootData();
     const navigate = useNavigate();
 
-    const onDecode = (result: Result) => {
-        const id = result.getText();
-        navigate(`/clients/${id}`);
+    // badges may carry a signed payload or a padded ID, which the backend reads
+    const onDecode = async (code: string) => {
+        try {
+            const target: ScanTarget = await invoke("resolve_scan", {code});
+            if (target.kind === "client") navigate(`/clients/${target.client.id}`);
+        } catch (error) {
+            navigate(`/clients/${code}`);
+        }
     }
 
-    const decodeHints = new Map<DecodeHintType, any>();
-    decodeHints.set(DecodeHintType.POSSIBLE_FORMATS, [BarcodeFormat.CODE_128]);
-
     const filtered = useMemo((): Client[] => {
         const expression = new RegExp(search, "i");
         return clients.filter((client) => {
@@ -52,12 +66,16 @@ export default function ClientsPage() {
             <form className="flex justify-between py-4 w-full px-4">
                 <input value={search} className="w-full mr-3 rounded-lg border p-0.5" placeholder={"Caută..."}
                        onChange={(event) => setSearch(event.target.value)}/>
-                <Scanner onDecode={onDecode} hints={decodeHints}/>
+                <Scanner onDecode={onDecode} formats={[settings.symbologies.badge]}/>
             </form>
             <Link to="create"
                   className="px-2 py-2 bg-orange text-black-5 text-center font-medium text-lg rounded-2xl">
                 Adaugă client
             </Link>
+            <div className="flex flex-col w-full px-4 mt-3">
+                <SheetDownload label="Legitimații PDF" command="download_badge_sheet" args={{clients: filtered}}
+                               perSheet={settings.badgeSheet.columns * settings.badgeSheet.rows}/>
+            </div>
             {filtered.length === 0 && <p className="font-medium mt-3">Nu există clienți</p>}
             <div
                 className="flex flex-col items-start w-full overflow-auto h-4/5 max-h-fit scrollbar-thin px-4 divide-y">
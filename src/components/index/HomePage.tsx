@@ -1,5 +1,5 @@
 import {useRootData} from "../util/useRootData.ts";
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke} from "../../api.ts";
 import {useLoaderData} from "react-router-dom";
 
 type LoaderData = {
@@ -1,7 +1,7 @@
 import {useEffect, useState} from "react";
 import {ErrorMessage, Field, Form as FormikForm, Formik} from "formik";
 import * as Yup from "yup";
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke} from "../../api.ts";
 import {Book} from "../books/BooksPage.tsx";
 import {ActionFunctionArgs, Form, Link, redirect, useNavigation} from "react-router-dom";
 import {authProvider} from "../../auth/auth.ts";
@@ -27,7 +27,7 @@ export async function action({request}: ActionFunctionArgs) {
 
     const book: Book = {isbn, title, author, items: 1};
 
-    await invoke("create_book", {book});
+    await invoke("create_book", {book, branchId: authProvider.getBranchId()});
     return redirect(`/books/${isbn}/edit`);
 }
 
@@ -1,7 +1,15 @@
-import {NavLink, useFetcher, useRouteLoaderData} from "react-router-dom";
-import {User} from "../../auth/auth.ts";
+import {NavLink, useFetcher, useRevalidator, useRouteLoaderData} from "react-router-dom";
+import {useEffect, useState} from "react";
+import {authProvider, User} from "../../auth/auth.ts";
+import {invoke} from "../../api.ts";
 import TitleBar from "./TitleBar.tsx";
 
+type Branch = {
+    id: number,
+    code: string,
+    name: string,
+}
+
 export default function Navigation() {
     return (
         <div className="w-full flex flex-col">
@@ -12,7 +20,9 @@ export default function Navigation() {
                     <NavLink to={"/books"} className="text-xl lg:text-3xl">Cărți</NavLink>
                     <NavLink to={"/clients"} className="text-xl lg:text-3xl">Clienți</NavLink>
                     <NavLink to={"/lookup"} className="text-xl lg:text-3xl">Caută ISBN</NavLink>
+                    <NavLink to={"/staging"} className="text-xl lg:text-3xl">Import</NavLink>
                 </nav>
+                <BranchPicker/>
                 <AuthStatus/>
             </div>
         </div>
@@ -20,6 +30,30 @@ export default function Navigation() {
     )
 }
 
+// The branch this desk lends from and registers clients at.
+function BranchPicker() {
+    const {user} = useRouteLoaderData("root") as { user: User | null };
+    const revalidator = useRevalidator();
+    const [branches, setBranches] = useState<Branch[]>([]);
+
+    useEffect(() => {
+        if (!user) return;
+        invoke<Branch[]>("fetch_branches").then(setBranches);
+    }, [user?.username]);
+
+    if (!user || branches.length < 2) return null;
+
+    return (
+        <select value={user.branchId ?? ""} className="border text-sm rounded-lg p-2 mr-4"
+                onChange={async (event) => {
+                    await authProvider.selectBranch(Number(event.target.value));
+                    revalidator.revalidate();
+                }}>
+            {user.branchId == null && <option value="" disabled>Alege filiala</option>}
+            {branches.map((branch) => <option key={branch.id} value={branch.id}>{branch.name}</option>)}
+        </select>
+    )
+}
 
 function AuthStatus() {
     const {user} = useRouteLoaderData("root") as { user: User };
@@ -0,0 +1,105 @@
+import {invoke} from "@tauri-apps/api/tauri";
+import {ActionFunctionArgs, useActionData, useLoaderData, useSubmit} from "react-router-dom";
+import * as Yup from "yup";
+import {Form, Formik} from "formik";
+import Input from "../util/Input.tsx";
+
+type LoaderData = {
+    startupError: string | null,
+    // the database is encrypted and waits for its passphrase
+    locked: boolean,
+}
+
+export async function loader(): Promise<LoaderData> {
+    return {
+        startupError: await invoke("startup_error") as string | null,
+        locked: await invoke("is_database_locked") as boolean,
+    };
+}
+
+export async function action({request}: ActionFunctionArgs) {
+    const formData = await request.formData();
+    const intent = formData.get("intent") as string;
+    const path = formData.get("path") as string;
+    const passphrase = formData.get("passphrase") as string | null;
+
+    try {
+        if (intent === "unlock") await invoke("unlock_database", {passphrase});
+        else if (intent === "backup") await invoke("recover_from_backup", {path, passphrase});
+        else if (intent === "file") await invoke("open_database_file", {path, passphrase});
+        else await invoke("open_read_only");
+        return null;
+    } catch (error) {
+        return {error: error as string};
+    }
+}
+
+function PathForm({intent, label, button}: { intent: string, label: string, button: string }) {
+    const submit = useSubmit();
+
+    const validationSchema = Yup.object({
+        path: Yup.string().required("Calea este obligatorie"),
+    });
+
+    return (
+        <Formik initialValues={{path: "", passphrase: ""}} validationSchema={validationSchema} onSubmit={(values) => {
+            submit({intent, ...values}, {method: "post"});
+        }}>
+            <Form className="w-full space-y-2">
+                <Input label={label} type="text" name="path" className="border text-sm rounded-lg block w-full p-2.5"/>
+                <Input label="Parola de criptare (doar pentru baze de date criptate)" type="password" name="passphrase"
+                       className="border text-sm rounded-lg block w-full p-2.5"/>
+                <button type="submit"
+                        className="inline-flex items-center px-2.5 py-2.5 text-black-5 text-sm font-medium bg-orange rounded-2xl">
+                    {button}
+                </button>
+            </Form>
+        </Formik>
+    )
+}
+
+function UnlockForm() {
+    const submit = useSubmit();
+
+    const validationSchema = Yup.object({
+        passphrase: Yup.string().required("Parola este obligatorie"),
+    });
+
+    return (
+        <Formik initialValues={{passphrase: ""}} validationSchema={validationSchema} onSubmit={(values) => {
+            submit({intent: "unlock", ...values}, {method: "post"});
+        }}>
+            <Form className="w-full space-y-2">
+                <Input label="Parola de criptare" type="password" name="passphrase"
+                       className="border text-sm rounded-lg block w-full p-2.5"/>
+                <button type="submit"
+                        className="inline-flex items-center px-2.5 py-2.5 text-black-5 text-sm font-medium bg-orange rounded-2xl">
+                    Deblochează
+                </button>
+            </Form>
+        </Formik>
+    )
+}
+
+export default function RecoveryPage() {
+    const {startupError, locked} = useLoaderData() as LoaderData;
+    const result = useActionData() as { error: string } | undefined;
+    const submit = useSubmit();
+
+    return (
+        <div className="m-auto w-full max-w-xl bg-black-5 rounded-xl shadow-black-10 shadow-md py-6 px-4 space-y-4">
+            <h2 className="text-2xl font-bold">{locked ? "Baza de date este criptată" : "Baza de date nu a putut fi deschisă"}</h2>
+            {startupError && <p className="text-sm text-red">{startupError}</p>}
+            {result?.error && <p className="text-sm text-red">{result.error}</p>}
+
+            {locked && <UnlockForm/>}
+            <PathForm intent="backup" label="Copie de rezervă" button="Restaurează copia de rezervă"/>
+            <PathForm intent="file" label="Alt fișier de bază de date" button="Deschide fișierul"/>
+
+            {!locked && <button type="button" onClick={() => submit({intent: "readonly"}, {method: "post"})}
+                                className="inline-flex items-center px-2.5 py-2.5 text-sm font-medium border rounded-2xl">
+                Deschide doar pentru citire
+            </button>}
+        </div>
+    )
+}
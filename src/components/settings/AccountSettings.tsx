@@ -3,7 +3,7 @@ import {ActionFunctionArgs, redirect, useActionData, useNavigate, useSubmit} fro
 import * as Yup from "yup";
 import {Form, Formik} from "formik";
 import Input from "../util/Input.tsx";
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke} from "../../api.ts";
 import {useRootData} from "../util/useRootData.ts";
 
 export async function loader() {
@@ -15,6 +15,12 @@ export default function AdminSettings() {
                 <NavLink to="/admin" end
                          className={({isActive}) => (isActive ? "text-orange" : "") + " font-medium text-lg px-2 py-2.5 transition ease-in"}>Setări
                     generale</NavLink>
+                <NavLink to="/admin/badge" end
+                         className={({isActive}) => (isActive ? "text-orange" : "") + " font-medium text-lg px-2 py-2.5 transition ease-in border-black-100"}>Legitimație</NavLink>
+                <NavLink to="/admin/labels" end
+                         className={({isActive}) => (isActive ? "text-orange" : "") + " font-medium text-lg px-2 py-2.5 transition ease-in border-black-100"}>Etichete</NavLink>
+                <NavLink to="/admin/database" end
+                         className={({isActive}) => (isActive ? "text-orange" : "") + " font-medium text-lg px-2 py-2.5 transition ease-in border-black-100"}>Bază de date</NavLink>
                 <NavLink to="/admin/users" end
                          className={({isActive}) => (isActive ? "text-orange" : "") + " font-medium text-lg px-2 py-2.5 transition ease-in border-black-100"}>Utilizatori</NavLink>
             </div>
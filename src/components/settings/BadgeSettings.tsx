@@ -0,0 +1,68 @@
+import {useState} from "react";
+import {redirect, useLoaderData} from "react-router-dom";
+import {invoke, validationMessage} from "../../api.ts";
+import {authProvider} from "../../auth/auth.ts";
+
+type LoaderData = {
+    source: string,
+    // PNG of a made up client, base64 encoded
+    preview: string,
+}
+
+export async function loader(): Promise<LoaderData | Response> {
+    if (!authProvider.isAuthenticated()) return redirect("/login");
+    const source = await invoke("fetch_badge_template") as string;
+    const preview = await invoke("preview_badge") as string;
+    return {source, preview};
+}
+
+function templateError(error: unknown): string {
+    return validationMessage(error, {}) ?? String(error);
+}
+
+export default function BadgeSettings() {
+    const loaded = useLoaderData() as LoaderData;
+    const [source, setSource] = useState(loaded.source);
+    const [preview, setPreview] = useState(loaded.preview);
+    const [message, setMessage] = useState<string | null>(null);
+    const [error, setError] = useState<string | null>(null);
+
+    const run = async (command: "preview_badge" | "save_badge_template") => {
+        setMessage(null);
+        setError(null);
+        try {
+            setPreview(await invoke("preview_badge", {source}) as string);
+            if (command == "save_badge_template") {
+                await invoke(command, {source});
+                setMessage("Șablonul a fost salvat");
+            }
+        } catch (error) {
+            setError(templateError(error));
+        }
+    }
+
+    return <div className="flex flex-col space-y-3">
+        <h1 className="text-2xl font-bold">Șablonul legitimației</h1>
+        <p className="text-sm">
+            Textele pot folosi {"{libraryName}"}, {"{clientId}"}, {"{clientName}"} și {"{issued}"}. Fonturile și
+            sigla se caută în dosarul setărilor.
+        </p>
+        <img src={`data:image/png;base64,${preview}`} alt="Previzualizarea legitimației"
+             className="self-start border rounded-lg max-w-full"/>
+        <textarea value={source} spellCheck={false} rows={20}
+                  className="w-full rounded-lg border p-2 font-mono text-sm"
+                  onChange={(event) => setSource(event.target.value)}/>
+        {error && <p className="text-red text-sm">{error}</p>}
+        {message && <p className="text-sm">{message}</p>}
+        <div className="flex space-x-3">
+            <button type="button" onClick={() => run("preview_badge")}
+                    className="px-2.5 py-1.5 font-medium border border-orange rounded-2xl">
+                Previzualizează
+            </button>
+            <button type="button" onClick={() => run("save_badge_template")}
+                    className="px-2.5 py-1.5 bg-orange text-black-5 font-medium rounded-2xl">
+                Salvează
+            </button>
+        </div>
+    </div>
+}
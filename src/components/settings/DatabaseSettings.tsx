@@ -0,0 +1,332 @@
+import {ReactNode, useEffect, useState} from "react";
+import {invoke} from "../../api.ts";
+import {authProvider} from "../../auth/auth.ts";
+import {useRevalidator} from "react-router-dom";
+import {useRootData} from "../util/useRootData.ts";
+import {settingsProvider} from "./settings.ts";
+
+const inputClasses = "border text-sm rounded-lg block w-full p-2.5 mt-1";
+const buttonClasses = "px-2.5 py-2 text-black-5 text-sm font-medium text-center bg-orange rounded-2xl";
+
+// Runs `task` and shows what it returned, or the error, under the section.
+function useTask() {
+    const [message, setMessage] = useState<string | null>(null);
+    const [error, setError] = useState(false);
+
+    const run = async (task: () => Promise<string>) => {
+        setMessage(null);
+        try {
+            setMessage(await task());
+            setError(false);
+        } catch (error) {
+            setMessage(String(error));
+            setError(true);
+        }
+    }
+
+    const status = message && <p className={"text-sm font-medium " + (error ? "text-red" : "")}>{message}</p>;
+    return {run, status};
+}
+
+function Section({title, children}: { title: string, children: ReactNode }) {
+    return <fieldset className="flex flex-col space-y-2">
+        <legend className="text-xl font-bold mb-2">{title}</legend>
+        {children}
+    </fieldset>
+}
+
+function EncryptionFields() {
+    const [password, setPassword] = useState("");
+    const [passphrase, setPassphrase] = useState("");
+    const [confirmation, setConfirmation] = useState("");
+    const {run, status} = useTask();
+
+    const encrypt = () => run(async () => {
+        if (passphrase === "" || passphrase !== confirmation) throw "Parolele de criptare nu coincid";
+        await invoke("encrypt_database", {username: authProvider.getUsername(), password, passphrase});
+        return "Baza de date a fost criptată. Aplicația repornește.";
+    });
+
+    return <Section title="Criptare">
+        <p className="text-sm">
+            Baza de date va fi criptată la următoarea pornire, iar parola de criptare va fi cerută de fiecare dată când aplicația
+            pornește. Fără ea datele nu mai pot fi recuperate.
+        </p>
+        <label className="text-sm font-medium">
+            Parola contului de administrator
+            <input type="password" value={password} className={inputClasses} onChange={(event) => setPassword(event.target.value)}/>
+        </label>
+        <div className="grid grid-cols-2 gap-3">
+            <label className="text-sm font-medium">
+                Parola de criptare
+                <input type="password" value={passphrase} className={inputClasses} onChange={(event) => setPassphrase(event.target.value)}/>
+            </label>
+            <label className="text-sm font-medium">
+                Repetă parola de criptare
+                <input type="password" value={confirmation} className={inputClasses} onChange={(event) => setConfirmation(event.target.value)}/>
+            </label>
+        </div>
+        <div>
+            <button type="button" className={buttonClasses} onClick={encrypt}>Criptează baza de date</button>
+        </div>
+        {status}
+    </Section>
+}
+
+// What `import_sync_bundle` merged.
+type SyncReport = {
+    received: number,
+    applied: number,
+    deleted: number,
+    conflicts: number,
+    photos: number,
+    skipped: string[],
+}
+
+// A stock count changed on both installations, left for a librarian to settle.
+type SyncConflict = {
+    id: number,
+    rowKey: string,
+    localValue: string | null,
+    remoteValue: string | null,
+    remoteChangedAt: string,
+}
+
+function ConflictRow({conflict, onResolved}: { conflict: SyncConflict, onResolved: () => void }) {
+    const [items, setItems] = useState(conflict.localValue ?? "0");
+    const {run, status} = useTask();
+
+    const resolve = () => run(async () => {
+        await invoke("resolve_sync_conflict", {id: conflict.id, items: Number(items)});
+        onResolved();
+        return "";
+    });
+
+    return <li className="flex items-center space-x-3 text-sm">
+        <span className="flex-grow">
+            {conflict.rowKey}: {conflict.localValue ?? "-"} aici, {conflict.remoteValue ?? "-"} la cealaltă instalare
+        </span>
+        <input type="number" min={0} value={items} className="border rounded-lg w-20 p-1" onChange={(event) => setItems(event.target.value)}/>
+        <button type="button" className={buttonClasses} onClick={resolve}>Păstrează</button>
+        {status}
+    </li>
+}
+
+function SyncFields() {
+    const [path, setPath] = useState("");
+    const [conflicts, setConflicts] = useState<SyncConflict[]>([]);
+    const {run, status} = useTask();
+
+    const loadConflicts = async () => setConflicts(await invoke<SyncConflict[]>("fetch_sync_conflicts"));
+    useEffect(() => {
+        loadConflicts();
+    }, []);
+
+    const exportBundle = () => run(async () => {
+        const changes: number = await invoke("export_sync_bundle", {path});
+        return `Pachetul a fost salvat cu ${changes} modificări.`;
+    });
+
+    const importBundle = () => run(async () => {
+        const report: SyncReport = await invoke("import_sync_bundle", {path});
+        await loadConflicts();
+        const skipped = report.skipped.length > 0 ? ` Nu au putut fi adăugate: ${report.skipped.join(", ")}.` : "";
+        return `${report.received} modificări noi: ${report.applied} aplicate, ${report.deleted} ștergeri, ${report.photos} fotografii, `
+            + `${report.conflicts} stocuri de verificat.${skipped}`;
+    });
+
+    const resetReplica = () => run(async () => {
+        const site: string = await invoke("reset_replica_id");
+        return `Noul identificator al instalării este ${site}.`;
+    });
+
+    return <Section title="Sincronizare">
+        <p className="text-sm">
+            Pachetele de sincronizare duc cărțile, clienții, împrumuturile, fotografiile și cheile de semnare a legitimațiilor
+            între instalări fără rețea. Păstrează-le la fel de atent ca baza de date.
+        </p>
+        <label className="text-sm font-medium">
+            Calea pachetului
+            <input type="text" value={path} placeholder="D:\sincronizare.json" className={inputClasses} onChange={(event) => setPath(event.target.value)}/>
+        </label>
+        <div className="flex space-x-3">
+            <button type="button" className={buttonClasses} onClick={exportBundle}>Exportă</button>
+            <button type="button" className={buttonClasses} onClick={importBundle}>Importă</button>
+            <button type="button" className={buttonClasses} onClick={resetReplica}>Identificator nou pentru o copie</button>
+        </div>
+        {status}
+        {conflicts.length > 0 && <>
+            <p className="text-sm font-medium">Stocuri modificate pe ambele instalări</p>
+            <ul className="flex flex-col space-y-2">
+                {conflicts.map((conflict) => <ConflictRow key={conflict.id} conflict={conflict} onResolved={loadConflicts}/>)}
+            </ul>
+        </>}
+    </Section>
+}
+
+function BackupFields() {
+    const {settings} = useRootData();
+    const revalidator = useRevalidator();
+    const [path, setPath] = useState("");
+    const [directory, setDirectory] = useState(settings.backupDirectory);
+    const [hours, setHours] = useState(settings.backupIntervalHours);
+    const [retention, setRetention] = useState(settings.backupRetention);
+    const {run, status} = useTask();
+
+    const backup = () => run(async () => {
+        await invoke("backup_database", {path});
+        return "Copia de rezervă a fost salvată.";
+    });
+
+    const restore = () => run(async () => {
+        if (!confirm("Datele de acum vor fi înlocuite cu cele din copia de rezervă. Continui?")) return "";
+        await invoke("restore_database", {path});
+        revalidator.revalidate();
+        return "Baza de date a fost restaurată.";
+    });
+
+    const schedule = () => run(async () => {
+        const current = await settingsProvider.getCurrentSettings();
+        await settingsProvider.saveCurrentSettings({...current, backupDirectory: directory.trim(), backupIntervalHours: hours, backupRetention: retention});
+        revalidator.revalidate();
+        return hours > 0 ? `O copie de rezervă va fi salvată la fiecare ${hours} ore.` : "Copiile de rezervă automate sunt oprite.";
+    });
+
+    return <Section title="Copii de rezervă">
+        <p className="text-sm">
+            Copiile se salvează în timp ce aplicația este folosită. O copie este verificată înainte de a fi restaurată, iar
+            copiile bazelor de date criptate rămân criptate.
+        </p>
+        <label className="text-sm font-medium">
+            Calea copiei
+            <input type="text" value={path} placeholder="D:\libra.sqlite" className={inputClasses} onChange={(event) => setPath(event.target.value)}/>
+        </label>
+        <div className="flex space-x-3">
+            <button type="button" className={buttonClasses} onClick={backup}>Salvează o copie</button>
+            <button type="button" className={buttonClasses} onClick={restore}>Restaurează</button>
+        </div>
+        <div className="grid grid-cols-3 gap-3">
+            <label className="text-sm font-medium col-span-3">
+                Dosarul copiilor automate
+                <input type="text" value={directory} placeholder="lângă baza de date" className={inputClasses} onChange={(event) => setDirectory(event.target.value)}/>
+            </label>
+            <label className="text-sm font-medium">
+                La fiecare (ore, 0 pentru niciodată)
+                <input type="number" min={0} value={hours} className={inputClasses} onChange={(event) => setHours(Number(event.target.value))}/>
+            </label>
+            <label className="text-sm font-medium">
+                Copii păstrate
+                <input type="number" min={1} value={retention} className={inputClasses} onChange={(event) => setRetention(Number(event.target.value))}/>
+            </label>
+        </div>
+        <div>
+            <button type="button" className={buttonClasses} onClick={schedule}>Salvează programarea</button>
+        </div>
+        {status}
+    </Section>
+}
+
+// Borrows `repair_integrity` found pointing at a missing client or book.
+type IntegrityReport = {
+    orphanedBorrows: { id: number, clientId: string, bookIsbn: string }[],
+    removed: number,
+}
+
+function IntegrityFields() {
+    const [orphans, setOrphans] = useState<IntegrityReport["orphanedBorrows"]>([]);
+    const {run, status} = useTask();
+
+    const check = (dryRun: boolean) => run(async () => {
+        const report: IntegrityReport = await invoke("repair_integrity", {dryRun});
+        setOrphans(dryRun ? report.orphanedBorrows : []);
+        if (!dryRun) return `Au fost șterse ${report.removed} împrumuturi fără client sau carte.`;
+        return report.orphanedBorrows.length === 0 ? "Nu s-au găsit probleme." : `${report.orphanedBorrows.length} împrumuturi fără client sau carte.`;
+    });
+
+    return <Section title="Verificarea datelor">
+        <p className="text-sm">Caută împrumuturile rămase fără clientul sau cartea lor, de exemplu după editarea bazei de date cu alte programe.</p>
+        <div className="flex space-x-3">
+            <button type="button" className={buttonClasses} onClick={() => check(true)}>Verifică</button>
+            {orphans.length > 0 && <button type="button" className={buttonClasses} onClick={() => check(false)}>Șterge împrumuturile</button>}
+        </div>
+        {status}
+        {orphans.length > 0 &&
+            <ul className="text-sm max-h-48 overflow-auto">
+                {orphans.map((borrow) => <li key={borrow.id}>{borrow.clientId}: {borrow.bookIsbn}</li>)}
+            </ul>}
+    </Section>
+}
+
+// What `import_csv` found in a file, row by row.
+type ImportReport = {
+    totalRows: number,
+    validRows: number,
+    errors: { row: number, field: string | null, message: string }[],
+    committed: boolean,
+}
+
+const entityLabels = {book: "Cărți", client: "Clienți", borrow: "Împrumuturi"};
+
+function CsvFields() {
+    const [entity, setEntity] = useState<keyof typeof entityLabels>("book");
+    const [path, setPath] = useState("");
+    const [report, setReport] = useState<ImportReport | null>(null);
+    const {run, status} = useTask();
+
+    const importFile = (dryRun: boolean) => run(async () => {
+        setReport(null);
+        const result: ImportReport = await invoke("import_csv", {entity, path, dryRun});
+        setReport(result);
+        if (result.committed) return `Au fost importate ${result.validRows} rânduri.`;
+        if (result.errors.length > 0) return `${result.errors.length} erori, nu a fost importat nimic.`;
+        return `Toate cele ${result.totalRows} rânduri sunt valide.`;
+    });
+
+    const exportFile = () => run(async () => {
+        setReport(null);
+        const rows: number = await invoke("export_csv", {entity, path});
+        return `Au fost exportate ${rows} rânduri. Cele arhivate nu sunt incluse.`;
+    });
+
+    return <Section title="Import și export CSV">
+        <p className="text-sm">
+            Un fișier se importă în întregime sau deloc: dacă un singur rând este greșit, baza de date rămâne neschimbată.
+            Verifică fișierul înainte pentru a vedea toate erorile.
+        </p>
+        <div className="grid grid-cols-3 gap-3">
+            <label className="text-sm font-medium">
+                Date
+                <select value={entity} className={inputClasses} onChange={(event) => setEntity(event.target.value as keyof typeof entityLabels)}>
+                    {Object.entries(entityLabels).map(([value, label]) => <option key={value} value={value}>{label}</option>)}
+                </select>
+            </label>
+            <label className="text-sm font-medium col-span-2">
+                Calea fișierului
+                <input type="text" value={path} placeholder="D:\carti.csv" className={inputClasses} onChange={(event) => setPath(event.target.value)}/>
+            </label>
+        </div>
+        <div className="flex space-x-3">
+            <button type="button" className={buttonClasses} onClick={() => importFile(true)}>Verifică</button>
+            <button type="button" className={buttonClasses} onClick={() => importFile(false)}>Importă</button>
+            <button type="button" className={buttonClasses} onClick={exportFile}>Exportă</button>
+        </div>
+        {status}
+        {report && report.errors.length > 0 &&
+            <ul className="text-sm text-red max-h-48 overflow-auto">
+                {report.errors.map((error, index) =>
+                    <li key={index}>Rândul {error.row}{error.field && ` (${error.field})`}: {error.message}</li>)}
+            </ul>}
+    </Section>
+}
+
+export default function DatabaseSettings() {
+    return (
+        <div className="max-w-xl mx-auto flex flex-col space-y-8 pb-10">
+            <EncryptionFields/>
+            <BackupFields/>
+            <SyncFields/>
+            <CsvFields/>
+            <IntegrityFields/>
+        </div>
+    )
+}
@@ -1,6 +1,6 @@
 import {useRootData} from "../util/useRootData.ts";
 import {ActionFunctionArgs, redirect, useSubmit} from "react-router-dom";
-import {Settings, settingsProvider} from "./settings.ts";
+import {ClientIdFormat, Settings, settingsProvider} from "./settings.ts";
 import * as Yup from "yup";
 import {Form, Formik, useField} from "formik";
 import Input from "../util/Input.tsx";
@@ -12,8 +12,22 @@ export async function action({request}: ActionFunctionArgs) {
 
     const libraryName = formData.get("libraryName") as string;
     const deviceId = formData.get("deviceId") as string;
+    const serverPort = Number(formData.get("serverPort") as string);
+    const serverAddress = (formData.get("serverAddress") as string).trim();
+    const serverUrl = (formData.get("serverUrl") as string).trim();
+    const clientIdPattern = (formData.get("clientIdPattern") as string).trim();
+    const clientIdFormat: ClientIdFormat = {
+        prefix: (formData.get("idPrefix") as string).trim(),
+        separator: formData.get("idSeparator") as string,
+        digits: Number(formData.get("idDigits") as string),
+        branchCode: formData.get("idBranchCode") === "true",
+        checkDigit: formData.get("idCheckDigit") as ClientIdFormat["checkDigit"],
+        installations: Number(formData.get("idInstallations") as string),
+        installation: Number(formData.get("idInstallation") as string),
+    };
 
-    const settings: Settings = {libraryName, cameraDeviceId: deviceId};
+    const current = await settingsProvider.getCurrentSettings();
+    const settings: Settings = {...current, libraryName, cameraDeviceId: deviceId, serverPort, serverAddress, serverUrl, clientIdPattern, clientIdFormat};
 
     await settingsProvider.saveCurrentSettings(settings);
     return redirect("/");
@@ -26,7 +40,16 @@ export default function GeneralSettings() {
 
     const validationSchema = Yup.object({
         libraryName: Yup.string().matches(/^.+$/, {message: "Numele este invalid"}),
-        deviceId: Yup.string()
+        deviceId: Yup.string(),
+        serverPort: Yup.number().integer("Portul este invalid").min(0, "Portul este invalid").max(65535, "Portul este invalid"),
+        serverAddress: Yup.string().matches(/^(\d{1,3}\.){3}\d{1,3}$|^[0-9a-fA-F:]+$/, {message: "Adresa IP este invalidă"}),
+        serverUrl: Yup.string().matches(/^https?:\/\/.+$/, {message: "Adresa serverului este invalidă", excludeEmptyString: true}),
+        clientIdPattern: Yup.string().required("Formatul este obligatoriu"),
+        idPrefix: Yup.string().matches(/^[A-Za-z0-9]*$/, {message: "Prefixul poate conține doar litere și cifre"}),
+        idDigits: Yup.number().integer("Numărul de cifre este invalid").min(1, "Numărul de cifre este invalid").max(12, "Numărul de cifre este invalid"),
+        idInstallations: Yup.number().integer("Numărul este invalid").min(1, "Numărul este invalid"),
+        idInstallation: Yup.number().integer("Numărul este invalid").min(1, "Numărul este invalid")
+            .max(Yup.ref("idInstallations"), "Numărul nu poate depăși numărul de instalări"),
     })
 
     return (
@@ -34,6 +57,17 @@ export default function GeneralSettings() {
             <Formik initialValues={{
                 libraryName: settings.libraryName,
                 deviceId: settings.cameraDeviceId,
+                serverPort: settings.serverPort,
+                serverAddress: settings.serverAddress,
+                serverUrl: settings.serverUrl,
+                clientIdPattern: settings.clientIdPattern,
+                idPrefix: settings.clientIdFormat.prefix,
+                idSeparator: settings.clientIdFormat.separator,
+                idDigits: settings.clientIdFormat.digits,
+                idBranchCode: String(settings.clientIdFormat.branchCode),
+                idCheckDigit: settings.clientIdFormat.checkDigit,
+                idInstallations: settings.clientIdFormat.installations,
+                idInstallation: settings.clientIdFormat.installation,
             }} onSubmit={(values) => {
                 submit(values, {method: "post"});
             }} validationSchema={validationSchema}>
@@ -52,6 +86,66 @@ export default function GeneralSettings() {
                                 ))}
                             </Select>
                         </div>
+                        <div className="w-full">
+                            <Input label="Port pentru partajarea bazei de date (0 = dezactivat)" name="serverPort" type="number"
+                                   className="border text-sm rounded-lg block w-full p-2.5"/>
+                        </div>
+                        <div className="w-full">
+                            <Input label="Adresa IP pe care se partajează (0.0.0.0 = toate rețelele)" name="serverAddress" type="text"
+                                   className="border text-sm rounded-lg block w-full p-2.5"/>
+                            <p className="text-sm mt-1">
+                                Datele și parolele circulă necriptate: partajează baza de date doar în rețeaua bibliotecii.
+                            </p>
+                        </div>
+                        <div className="w-full">
+                            <Input label="Adresa serverului (gol = bază de date locală)" name="serverUrl" type="text"
+                                   className="border text-sm rounded-lg block w-full p-2.5"/>
+                        </div>
+                        <div className="w-full">
+                            <Input label="Formatul codului de client (expresie regulată)" name="clientIdPattern" type="text"
+                                   className="border text-sm rounded-lg block w-full p-2.5"/>
+                        </div>
+                        <div className="grid grid-cols-3 gap-4">
+                            <div>
+                                <Input label="Prefix coduri noi" name="idPrefix" type="text"
+                                       className="border text-sm rounded-lg block w-full p-2.5"/>
+                            </div>
+                            <div>
+                                <Input label="Separator" name="idSeparator" type="text"
+                                       className="border text-sm rounded-lg block w-full p-2.5"/>
+                            </div>
+                            <div>
+                                <Input label="Cifre pentru număr" name="idDigits" type="number"
+                                       className="border text-sm rounded-lg block w-full p-2.5"/>
+                            </div>
+                        </div>
+                        <div className="grid grid-cols-2 gap-4">
+                            <div>
+                                <Select label="Codul filialei în cod" name="idBranchCode"
+                                        className="border text-sm rounded-lg block w-full p-2.5">
+                                    <option value="true">Da</option>
+                                    <option value="false">Nu</option>
+                                </Select>
+                            </div>
+                            <div>
+                                <Select label="Cifră de control" name="idCheckDigit"
+                                        className="border text-sm rounded-lg block w-full p-2.5">
+                                    <option value="luhn">Luhn</option>
+                                    <option value="mod11">Modulo 11</option>
+                                    <option value="none">Fără</option>
+                                </Select>
+                            </div>
+                        </div>
+                        <div className="grid grid-cols-2 gap-4">
+                            <div>
+                                <Input label="Instalări sincronizate" name="idInstallations" type="number"
+                                       className="border text-sm rounded-lg block w-full p-2.5"/>
+                            </div>
+                            <div>
+                                <Input label="Numărul acestei instalări" name="idInstallation" type="number"
+                                       className="border text-sm rounded-lg block w-full p-2.5"/>
+                            </div>
+                        </div>
                     </div>
                     <button type="submit"
                             className="inline-flex items-center px-2.5 py-2.5 mt-6 text-black-5 text-lg font-medium text-center bg-orange rounded-2xl">
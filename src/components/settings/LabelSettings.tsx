@@ -0,0 +1,175 @@
+import {useState} from "react";
+import {useRevalidator} from "react-router-dom";
+import {useRootData} from "../util/useRootData.ts";
+import {LabelPrinter, LabelSymbologies, Settings, settingsProvider, SheetLayout, Symbology} from "./settings.ts";
+
+const A4 = {pageWidth: 210, pageHeight: 297};
+
+const presets: Record<string, SheetLayout> = {
+    "Avery L7160 (21 pe A4)": {...A4, columns: 3, rows: 7, labelWidth: 63.5, labelHeight: 38.1, marginTop: 15.15, marginLeft: 7.25, columnGap: 2.5, rowGap: 0},
+    "Avery L7651 (65 pe A4)": {...A4, columns: 5, rows: 13, labelWidth: 38.1, labelHeight: 21.2, marginTop: 10.7, marginLeft: 4.75, columnGap: 2.5, rowGap: 0},
+    "Legitimații CR80 (10 pe A4)": {...A4, columns: 2, rows: 5, labelWidth: 85.6, labelHeight: 53.98, marginTop: 13.55, marginLeft: 16.9, columnGap: 5, rowGap: 0},
+};
+
+const fields: [keyof SheetLayout, string][] = [
+    ["pageWidth", "Lățime pagină"],
+    ["pageHeight", "Înălțime pagină"],
+    ["columns", "Coloane"],
+    ["rows", "Rânduri"],
+    ["labelWidth", "Lățime etichetă"],
+    ["labelHeight", "Înălțime etichetă"],
+    ["marginTop", "Margine sus"],
+    ["marginLeft", "Margine stânga"],
+    ["columnGap", "Spațiu între coloane"],
+    ["rowGap", "Spațiu între rânduri"],
+];
+
+type SheetKey = "isbnSheet" | "spineSheet" | "badgeSheet";
+
+const sheets: [SheetKey, string][] = [
+    ["isbnSheet", "Etichete ISBN"],
+    ["spineSheet", "Etichete pentru cotor"],
+    ["badgeSheet", "Legitimații"],
+];
+
+function SheetFields({title, layout, onChange}: { title: string, layout: SheetLayout, onChange: (layout: SheetLayout) => void }) {
+    return <fieldset className="flex flex-col space-y-2">
+        <legend className="text-xl font-bold mb-2">{title}</legend>
+        <select className="border text-sm rounded-lg block w-full p-2.5" value=""
+                onChange={(event) => event.target.value && onChange(presets[event.target.value])}>
+            <option value="">Alege un format standard...</option>
+            {Object.keys(presets).map(name => <option value={name} key={name}>{name}</option>)}
+        </select>
+        <div className="grid grid-cols-2 gap-3">
+            {fields.map(([field, label]) => <label key={field} className="text-sm font-medium">
+                {label}{field == "columns" || field == "rows" ? "" : " (mm)"}
+                <input type="number" step="any" min={0} value={layout[field]}
+                       className="border text-sm rounded-lg block w-full p-2.5 mt-1"
+                       onChange={(event) => onChange({...layout, [field]: Number(event.target.value)})}/>
+            </label>)}
+        </div>
+    </fieldset>
+}
+
+function PrinterFields({printer, onChange}: { printer: LabelPrinter, onChange: (printer: LabelPrinter) => void }) {
+    const sizes: [keyof LabelPrinter, string][] = [["dpi", "Rezoluție (dpi)"], ["labelWidth", "Lățime etichetă (mm)"], ["labelHeight", "Înălțime etichetă (mm)"]];
+
+    return <fieldset className="flex flex-col space-y-2">
+        <legend className="text-xl font-bold mb-2">Imprimantă de etichete</legend>
+        <label className="text-sm font-medium">
+            Adresă (IP, IP:port sau fișierul dispozitivului, gol = fără imprimantă)
+            <input type="text" value={printer.address} placeholder="192.168.1.50 sau /dev/usb/lp0"
+                   className="border text-sm rounded-lg block w-full p-2.5 mt-1"
+                   onChange={(event) => onChange({...printer, address: event.target.value})}/>
+        </label>
+        <label className="text-sm font-medium">
+            Limbaj
+            <select value={printer.language} className="border text-sm rounded-lg block w-full p-2.5 mt-1"
+                    onChange={(event) => onChange({...printer, language: event.target.value as LabelPrinter["language"]})}>
+                <option value="zpl">ZPL</option>
+                <option value="epl">EPL</option>
+            </select>
+        </label>
+        <div className="grid grid-cols-3 gap-3">
+            {sizes.map(([field, label]) => <label key={field} className="text-sm font-medium">
+                {label}
+                <input type="number" step="any" min={1} value={printer[field]}
+                       className="border text-sm rounded-lg block w-full p-2.5 mt-1"
+                       onChange={(event) => onChange({...printer, [field]: Number(event.target.value)})}/>
+            </label>)}
+        </div>
+    </fieldset>
+}
+
+const symbologyNames: Record<Symbology, string> = {
+    ean13: "EAN-13",
+    code128: "Code 128",
+    code39: "Code 39",
+    itf: "ITF (Interleaved 2 din 5)",
+    qr: "Cod QR",
+    datamatrix: "Data Matrix",
+};
+
+// Which symbologies each kind of label can carry; EAN-13 only holds ISBNs.
+const labelKinds: [keyof LabelSymbologies, string, Symbology[]][] = [
+    ["isbn", "Etichete ISBN", ["ean13", "code128", "code39", "itf", "qr", "datamatrix"]],
+    ["copy", "Etichete de exemplar", ["code128", "code39", "qr", "datamatrix"]],
+    ["spine", "Etichete pentru cotor", ["code128", "code39", "itf", "qr", "datamatrix"]],
+    ["badge", "Legitimații", ["code128", "code39", "qr", "datamatrix"]],
+];
+
+function SymbologyFields({symbologies, onChange}: { symbologies: LabelSymbologies, onChange: (symbologies: LabelSymbologies) => void }) {
+    return <fieldset className="flex flex-col space-y-2">
+        <legend className="text-xl font-bold mb-2">Coduri de bare</legend>
+        <p className="text-sm">Codurile QR de pe legitimații conțin ID-ul clientului și numele bibliotecii, semnate de baza de date.</p>
+        <div className="grid grid-cols-2 gap-3">
+            {labelKinds.map(([kind, label, options]) => <label key={kind} className="text-sm font-medium">
+                {label}
+                <select value={symbologies[kind] ?? ""} className="border text-sm rounded-lg block w-full p-2.5 mt-1"
+                        onChange={(event) => onChange({...symbologies, [kind]: event.target.value || null})}>
+                    {kind == "spine" && <option value="">Fără cod</option>}
+                    {options.map(option => <option value={option} key={option}>{symbologyNames[option]}</option>)}
+                </select>
+            </label>)}
+        </div>
+    </fieldset>
+}
+
+type DownloadKey = "downloadDirectory" | "badgeFileName" | "isbnFileName";
+
+const downloadFields: [DownloadKey, string, string][] = [
+    ["downloadDirectory", "Folder (gol = Documente)", "C:\\Etichete"],
+    ["badgeFileName", "Nume legitimații: {id}, {firstName}, {lastName}, {date}", "{id}_{lastName}"],
+    ["isbnFileName", "Nume coduri de bare: {isbn}", "{isbn}"],
+];
+
+function DownloadFields({values, onChange}: { values: Pick<Settings, DownloadKey>, onChange: (values: Pick<Settings, DownloadKey>) => void }) {
+    return <fieldset className="flex flex-col space-y-2">
+        <legend className="text-xl font-bold mb-2">Fișiere salvate</legend>
+        <p className="text-sm">Fișierele cu același nume nu sunt suprascrise, ci numerotate.</p>
+        {downloadFields.map(([field, label, placeholder]) => <label key={field} className="text-sm font-medium">
+            {label}
+            <input type="text" value={values[field]} placeholder={placeholder}
+                   className="border text-sm rounded-lg block w-full p-2.5 mt-1"
+                   onChange={(event) => onChange({...values, [field]: event.target.value})}/>
+        </label>)}
+    </fieldset>
+}
+
+export default function LabelSettings() {
+    const {settings} = useRootData();
+    const revalidator = useRevalidator();
+    const [layouts, setLayouts] = useState<Pick<Settings, SheetKey>>({
+        isbnSheet: settings.isbnSheet,
+        spineSheet: settings.spineSheet,
+        badgeSheet: settings.badgeSheet,
+    });
+    const [labelPrinter, setLabelPrinter] = useState(settings.labelPrinter);
+    const [symbologies, setSymbologies] = useState(settings.symbologies);
+    const [downloads, setDownloads] = useState<Pick<Settings, DownloadKey>>({
+        downloadDirectory: settings.downloadDirectory,
+        badgeFileName: settings.badgeFileName,
+        isbnFileName: settings.isbnFileName,
+    });
+    const [message, setMessage] = useState<string | null>(null);
+
+    const save = async () => {
+        const current = await settingsProvider.getCurrentSettings();
+        await settingsProvider.saveCurrentSettings({...current, ...layouts, labelPrinter, symbologies, ...downloads});
+        setMessage("Setările au fost salvate");
+        revalidator.revalidate();
+    }
+
+    return <div className="max-w-xl mx-auto flex flex-col space-y-6">
+        {sheets.map(([key, title]) => <SheetFields key={key} title={title} layout={layouts[key]}
+                                                   onChange={(layout) => setLayouts({...layouts, [key]: layout})}/>)}
+        <SymbologyFields symbologies={symbologies} onChange={setSymbologies}/>
+        <DownloadFields values={downloads} onChange={setDownloads}/>
+        <PrinterFields printer={labelPrinter} onChange={setLabelPrinter}/>
+        {message && <p className="text-sm">{message}</p>}
+        <button type="button" onClick={save}
+                className="self-start px-2.5 py-2.5 text-black-5 text-lg font-medium text-center bg-orange rounded-2xl">
+            Salvează
+        </button>
+    </div>
+}
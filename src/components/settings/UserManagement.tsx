@@ -1,4 +1,4 @@
-import {invoke} from "@tauri-apps/api/tauri";
+import {invoke} from "../../api.ts";
 import {authProvider, User} from "../../auth/auth.ts";
 import {ActionFunctionArgs, Form, redirect, useActionData, useLoaderData, useSubmit} from "react-router-dom";
 import {useMemo, useState} from "react";
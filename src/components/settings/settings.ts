@@ -1,8 +1,88 @@
 import {invoke} from "@tauri-apps/api/tauri";
 
+export type ClientIdFormat = {
+    prefix: string,
+    branchCode: boolean,
+    digits: number,
+    checkDigit: "none" | "luhn" | "mod11",
+    separator: string,
+    // installations sharing the sequences through sync, and which of them this one is
+    installations: number,
+    installation: number,
+}
+
+// Grid of a sheet of labels, in millimetres.
+export type SheetLayout = {
+    pageWidth: number,
+    pageHeight: number,
+    columns: number,
+    rows: number,
+    labelWidth: number,
+    labelHeight: number,
+    marginTop: number,
+    marginLeft: number,
+    columnGap: number,
+    rowGap: number,
+}
+
+export type LabelPrinter = {
+    language: "zpl" | "epl",
+    // host, host:port or the path of a device file, empty without a label printer
+    address: string,
+    dpi: number,
+    labelWidth: number,
+    labelHeight: number,
+}
+
+export type Symbology = "ean13" | "code128" | "code39" | "itf" | "qr" | "datamatrix";
+
+// Symbology of each kind of label; spine labels carry no barcode when null.
+export type LabelSymbologies = {
+    isbn: Symbology,
+    copy: Symbology,
+    spine: Symbology | null,
+    badge: Symbology,
+}
+
+// What the label printer and the barcode settings refuse, by the field named in the error.
+export const labelMessages: Record<string, string> = {
+    labelPrinter: "Nu este setată o imprimantă de etichete",
+    symbologies: "Imprimantele EPL nu pot tipări coduri QR sau Data Matrix, alege alt cod de bare în setări",
+    barcode: "Codul nu poate fi scris cu tipul de cod de bare ales în setări",
+    isbn: "Cartea nu are un ISBN valid",
+};
+
+// What saving a label or badge refuses, by the field named in the error.
+export const downloadMessages: Record<string, string> = {
+    downloadDirectory: "Nu există un folder Documente, alege în setări unde se salvează fișierele",
+    badgeFileName: "Numele fișierului legitimației nu este valid, verifică setările",
+    isbnFileName: "Numele fișierului codului de bare nu este valid, verifică setările",
+    barcode: "Codul nu poate fi scris cu tipul de cod de bare ales în setări",
+};
+
 export type Settings = {
     libraryName: string,
     cameraDeviceId: string,
+    backupDirectory: string,
+    backupIntervalHours: number,
+    backupRetention: number,
+    databasePath: string,
+    serverPort: number,
+    // network interface the server listens on, "0.0.0.0" for all of them
+    serverAddress: string,
+    serverUrl: string,
+    clientIdPattern: string,
+    clientIdFormat: ClientIdFormat,
+    isbnSheet: SheetLayout,
+    spineSheet: SheetLayout,
+    badgeSheet: SheetLayout,
+    labelPrinter: LabelPrinter,
+    symbologies: LabelSymbologies,
+    // empty for Documents
+    downloadDirectory: string,
+    // file names of badges, from {id}, {firstName}, {lastName} and {date}, and of ISBN labels, from {isbn}
+    badgeFileName: string,
+    isbnFileName: string,
 }
 
 class SettingsProvider {
@@ -0,0 +1,258 @@
+import {useEffect, useState} from "react";
+import {redirect, useLoaderData, useRevalidator} from "react-router-dom";
+import {listen} from "@tauri-apps/api/event";
+import {invoke, ValidationError} from "../../api.ts";
+import {authProvider} from "../../auth/auth.ts";
+
+// A book waiting for review before it joins the catalog, from a bulk lookup or a MARC file.
+export type StagedBook = {
+    isbn: string,
+    title: string,
+    author: string,
+    items: number,
+    status: "pending" | "found" | "not_found" | "failed",
+    edition: string | null,
+    publication: string | null,
+    extent: string | null,
+    subjects: string,
+}
+
+// Sent by `bulk_lookup` after each ISBN it looked up.
+type LookupProgress = {
+    isbn: string,
+    status: string,
+    completed: number,
+    total: number,
+}
+
+const statusLabels: Record<StagedBook["status"], string> = {
+    pending: "Se caută...",
+    found: "Găsită",
+    not_found: "Negăsită",
+    failed: "Eroare",
+};
+
+const inputClasses = "border text-sm rounded-lg block w-full p-1.5";
+const buttonClasses = "px-2.5 py-2 text-black-5 text-sm font-medium text-center bg-orange rounded-2xl";
+
+export async function loader() {
+    if (!authProvider.isAuthenticated()) return redirect("/login");
+    return await invoke<StagedBook[]>("fetch_staged_books");
+}
+
+export default function StagingPage() {
+    const staged = useLoaderData() as StagedBook[];
+    const revalidator = useRevalidator();
+    const [selected, setSelected] = useState<Set<string>>(new Set());
+    const [progress, setProgress] = useState<LookupProgress | null>(null);
+    const [message, setMessage] = useState<string | null>(null);
+    const [errors, setErrors] = useState<string[]>([]);
+
+    // the lookup runs in the background, each book shows up as it is found
+    useEffect(() => {
+        const unlisten = listen<LookupProgress>("bulk-lookup-progress", (event) => {
+            setProgress(event.payload.completed < event.payload.total ? event.payload : null);
+            revalidator.revalidate();
+        });
+        return () => {
+            unlisten.then(f => f());
+        }
+    }, []);
+
+    const toggle = (isbn: string) => {
+        const next = new Set(selected);
+        if (!next.delete(isbn)) next.add(isbn);
+        setSelected(next);
+    }
+
+    const commit = async () => {
+        setMessage(null);
+        setErrors([]);
+        try {
+            const count = await invoke<number>("commit_staged_books", {isbns: [...selected], branchId: authProvider.getBranchId()});
+            setMessage(`${count} cărți au fost adăugate în catalog`);
+            setSelected(new Set());
+            revalidator.revalidate();
+        } catch (error) {
+            // each message starts with the ISBN of the book it is about
+            setErrors(Array.isArray(error) ? (error as ValidationError[]).map(({message}) => message) : [String(error)]);
+        }
+    }
+
+    const remove = async (isbn: string) => {
+        await invoke("delete_staged_book", {isbn});
+        const next = new Set(selected);
+        next.delete(isbn);
+        setSelected(next);
+        revalidator.revalidate();
+    }
+
+    return (
+        <div className="flex-grow flex flex-col items-center space-y-6 overflow-auto py-8">
+            <div className="w-11/12 max-w-5xl grid grid-cols-2 gap-6">
+                <LookupForm onStarted={() => revalidator.revalidate()}/>
+                <MarcForm onImported={() => revalidator.revalidate()}/>
+            </div>
+            {progress && <p className="text-sm font-medium">Căutate {progress.completed} din {progress.total}</p>}
+            <div className="w-11/12 max-w-5xl flex flex-col space-y-3">
+                <div className="flex items-center justify-between">
+                    <h1 className="text-xl font-bold">Cărți în așteptare</h1>
+                    <div className="flex space-x-3">
+                        <button type="button" className="text-sm font-medium underline"
+                                onClick={() => setSelected(new Set(staged.filter(book => book.status !== "pending").map(book => book.isbn)))}>
+                            Selectează tot
+                        </button>
+                        <button type="button" disabled={selected.size === 0} className={buttonClasses + " disabled:opacity-50"} onClick={commit}>
+                            Adaugă în catalog ({selected.size})
+                        </button>
+                    </div>
+                </div>
+                {message && <p className="text-sm font-medium text-green">{message}</p>}
+                {errors.map((error) => <p key={error} className="text-sm font-medium text-red">{error}</p>)}
+                {staged.length === 0 && <p className="font-medium">Nu există cărți în așteptare</p>}
+                {staged.length > 0 &&
+                    <table className="w-full text-sm">
+                        <thead>
+                        <tr className="text-left">
+                            <th></th>
+                            <th className="p-1">ISBN</th>
+                            <th className="p-1">Titlu</th>
+                            <th className="p-1">Autor</th>
+                            <th className="p-1 w-20">Exemplare</th>
+                            <th className="p-1">Stare</th>
+                            <th></th>
+                        </tr>
+                        </thead>
+                        <tbody className="divide-y">
+                        {staged.map((book) =>
+                            <StagedRow key={book.isbn} book={book} selected={selected.has(book.isbn)}
+                                       onToggle={() => toggle(book.isbn)} onDelete={() => remove(book.isbn)}/>)}
+                        </tbody>
+                    </table>}
+            </div>
+        </div>
+    );
+}
+
+// ISBNs typed or scanned one per line, looked up online and staged.
+function LookupForm({onStarted}: { onStarted: () => void }) {
+    const [text, setText] = useState("");
+    const [error, setError] = useState<string | null>(null);
+
+    const start = async () => {
+        setError(null);
+        const isbns = [...new Set(text.split(/[\s,;]+/).filter(isbn => isbn !== ""))];
+        if (isbns.length === 0) return;
+        try {
+            await invoke("bulk_lookup", {isbns});
+            setText("");
+            onStarted();
+        } catch (error) {
+            setError(String(error));
+        }
+    }
+
+    return (
+        <div className="flex flex-col space-y-2">
+            <label className="text-sm font-medium">
+                ISBN-uri de căutat, câte unul pe rând
+                <textarea value={text} rows={4} className={inputClasses + " mt-1"} onChange={(event) => setText(event.target.value)}/>
+            </label>
+            <div>
+                <button type="button" className={buttonClasses} onClick={start}>Caută online</button>
+            </div>
+            {error && <p className="text-sm font-medium text-red">{error}</p>}
+        </div>
+    );
+}
+
+// Records from a MARC 21 file, in ISO 2709 or MARCXML, staged as they are.
+function MarcForm({onImported}: { onImported: () => void }) {
+    const [path, setPath] = useState("");
+    const [message, setMessage] = useState<string | null>(null);
+    const [error, setError] = useState(false);
+
+    const load = async () => {
+        setMessage(null);
+        try {
+            const records = await invoke<unknown[]>("import_marc", {path});
+            setMessage(`${records.length} înregistrări au fost importate`);
+            setError(false);
+            onImported();
+        } catch (error) {
+            setMessage(String(error));
+            setError(true);
+        }
+    }
+
+    return (
+        <div className="flex flex-col space-y-2">
+            <label className="text-sm font-medium">
+                Fișier MARC (.mrc sau .xml)
+                <input value={path} className={inputClasses + " mt-1"} placeholder="C:\catalog\export.mrc"
+                       onChange={(event) => setPath(event.target.value)}/>
+            </label>
+            <div>
+                <button type="button" disabled={path === ""} className={buttonClasses + " disabled:opacity-50"} onClick={load}>
+                    Importă
+                </button>
+            </div>
+            {message && <p className={"text-sm font-medium " + (error ? "text-red" : "")}>{message}</p>}
+        </div>
+    );
+}
+
+// One staged book, saved as soon as a field loses focus.
+function StagedRow({book, selected, onToggle, onDelete}: {
+    book: StagedBook,
+    selected: boolean,
+    onToggle: () => void,
+    onDelete: () => void
+}) {
+    const [draft, setDraft] = useState(book);
+    const [error, setError] = useState<string | null>(null);
+
+    // the background lookup may fill in a book while it is shown
+    useEffect(() => setDraft(book), [book.title, book.author, book.items, book.status]);
+
+    const save = async () => {
+        if (draft.title === book.title && draft.author === book.author && draft.items === book.items) return;
+        try {
+            await invoke("update_staged_book", {book: draft});
+            setError(null);
+        } catch (error) {
+            setError(String(error));
+        }
+    }
+
+    const pending = book.status === "pending";
+
+    return (
+        <tr className={selected ? "bg-black-10" : ""}>
+            <td className="p-1">
+                <input type="checkbox" checked={selected} disabled={pending} onChange={onToggle}/>
+            </td>
+            <td className="p-1 font-mono">{book.isbn}</td>
+            <td className="p-1">
+                <input value={draft.title} disabled={pending} className={inputClasses}
+                       onChange={(event) => setDraft({...draft, title: event.target.value})} onBlur={save}/>
+                {book.edition && <p className="text-xs mt-0.5">{book.edition}</p>}
+            </td>
+            <td className="p-1">
+                <input value={draft.author} disabled={pending} className={inputClasses}
+                       onChange={(event) => setDraft({...draft, author: event.target.value})} onBlur={save}/>
+            </td>
+            <td className="p-1">
+                <input type="number" min={1} value={draft.items} disabled={pending} className={inputClasses}
+                       onChange={(event) => setDraft({...draft, items: Number(event.target.value)})} onBlur={save}/>
+            </td>
+            <td className={"p-1 " + (book.status === "found" ? "text-green" : book.status === "pending" ? "" : "text-red")}>
+                {statusLabels[book.status]}
+                {error && <p className="text-xs text-red">{error}</p>}
+            </td>
+            <td className="p-1">
+                <button type="button" className="text-red text-sm font-medium" onClick={onDelete}>Șterge</button>
+            </td>
+        </tr>
+    );
+}
@@ -0,0 +1,17 @@
+export type OutputFormat = "png" | "svg" | "pdf";
+
+type Props = {
+    value: OutputFormat,
+    onChange: (format: OutputFormat) => void,
+}
+
+// PNG for screens; SVG and PDF are drawn as shapes and stay sharp at any print size.
+export default function FormatSelect({value, onChange}: Props) {
+    return <select value={value} title="Formatul fișierului"
+                   className="ml-3 border text-sm rounded-lg p-2"
+                   onChange={(event) => onChange(event.target.value as OutputFormat)}>
+        <option value="png">PNG</option>
+        <option value="svg">SVG</option>
+        <option value="pdf">PDF</option>
+    </select>
+}
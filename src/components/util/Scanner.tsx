@@ -1,12 +1,11 @@
 import {useState} from "react";
-import {Result} from "@zxing/library";
-import {DecodeHintType} from "react-zxing";
 import ScanIcon from "./ScanIcon.tsx";
 import ScannerModal from "./ScannerModal.tsx";
+import {Symbology} from "../settings/settings.ts";
 
 type Props = {
-    hints?: Map<DecodeHintType, any>,
-    onDecode: (result: Result) => void
+    formats?: Symbology[],
+    onDecode: (text: string) => void
 }
 
 export default function Scanner(props: Props) {
@@ -14,8 +13,8 @@ export default function Scanner(props: Props) {
 
     const closeScanner = () => setShowScanner(false);
 
-    const process = (result: Result) => {
-        props.onDecode(result);
+    const process = (text: string) => {
+        props.onDecode(text);
         closeScanner();
     }
 
@@ -23,6 +22,6 @@ export default function Scanner(props: Props) {
         <>
             <ScanIcon onClick={() => setShowScanner(true)}/>
             {showScanner &&
-                <ScannerModal onDecodeResult={process} onClose={() => setShowScanner(false)} hints={props.hints}/>}
+                <ScannerModal onDecode={process} onClose={() => setShowScanner(false)} formats={props.formats}/>}
         </>)
 }
@@ -1,27 +1,33 @@
-import {DecodeHintType, useZxing} from "react-zxing";
-import {Result} from "@zxing/library";
-import {useEffect, useState} from "react";
+import {useEffect, useRef, useState} from "react";
 import {useRootData} from "./useRootData.ts";
+import {invoke} from "../../api.ts";
+import {Symbology} from "../settings/settings.ts";
+import {readAsDataUrl} from "./files.ts";
 
+export type ScannedCode = {
+    symbology: Symbology,
+    text: string,
+}
 
 type Props = {
-    hints?: Map<DecodeHintType, any>,
-    onDecodeResult: (result: Result) => void,
+    formats?: Symbology[],
+    onDecode: (text: string) => void,
     onClose: () => void,
 }
 
+// How often a camera frame is sent to the backend to be decoded.
+const FRAME_INTERVAL = 300;
+
 export default function ScannerModal(props: Props) {
     const {settings} = useRootData();
     const [error, setError] = useState(false);
-
-    const {ref: videoRef} = useZxing({
-        onDecodeResult: props.onDecodeResult,
-        hints: props.hints,
-        deviceId: settings.cameraDeviceId,
-        onError: () => setError(true),
-    });
-
     const [loading, setLoading] = useState(true);
+    // codes found in a picked photo, when there is more than one to choose from
+    const [choices, setChoices] = useState<ScannedCode[] | null>(null);
+    const videoRef = useRef<HTMLVideoElement>(null);
+    const canvasRef = useRef<HTMLCanvasElement>(null);
+
+    const wanted = (codes: ScannedCode[]) => codes.filter((code) => !props.formats || props.formats.includes(code.symbology));
 
     useEffect(() => {
         const processKeyDown = (event: KeyboardEvent) => {
@@ -34,30 +40,83 @@ export default function ScannerModal(props: Props) {
     }, []);
 
     useEffect(() => {
-        const loadedDataListener = (_event: any) => {
-            if (videoRef.current?.readyState && videoRef.current?.readyState >= 3) setLoading(false);
+        let stream: MediaStream | null = null;
+        let timer: number | undefined;
+        let decoding = false;
+        let done = false;
+
+        const grabFrame = async () => {
+            const video = videoRef.current, canvas = canvasRef.current;
+            if (decoding || done || !video || !canvas || video.readyState < 2) return;
+            decoding = true;
+            canvas.width = video.videoWidth;
+            canvas.height = video.videoHeight;
+            canvas.getContext("2d")!.drawImage(video, 0, 0);
+            try {
+                const codes = wanted(await invoke<ScannedCode[]>("decode_frame", {frame: canvas.toDataURL("image/jpeg", 0.9)}));
+                if (codes.length > 0 && !done) {
+                    done = true;
+                    props.onDecode(codes[0].text);
+                }
+            } finally {
+                decoding = false;
+            }
         }
 
-        videoRef.current?.addEventListener("loadeddata", loadedDataListener);
+        const video = {deviceId: settings.cameraDeviceId ? {exact: settings.cameraDeviceId} : undefined, width: {ideal: 1280}};
+        navigator.mediaDevices.getUserMedia({video}).then((media) => {
+            stream = media;
+            if (!videoRef.current) return;
+            videoRef.current.srcObject = media;
+            videoRef.current.onloadeddata = () => setLoading(false);
+            videoRef.current.play();
+            timer = window.setInterval(grabFrame, FRAME_INTERVAL);
+        }).catch(() => setError(true));
 
         return () => {
-            videoRef.current?.removeEventListener("loadeddata", loadedDataListener);
+            done = true;
+            window.clearInterval(timer);
+            stream?.getTracks().forEach((track) => track.stop());
         }
     }, []);
 
+    const decodePhoto = async (file: File) => {
+        const codes = wanted(await invoke<ScannedCode[]>("decode_frame", {frame: await readAsDataUrl(file)}));
+        if (codes.length === 1) props.onDecode(codes[0].text);
+        else setChoices(codes);
+    }
+
     return (
         <div className="absolute z-10 left-0 top-0 h-full w-full !m-0 overflow-auto bg-black-50 bg-opacity-90 flex">
-            {!error && <div className="m-auto flex flex-col items-center space-y-3">
-                <video width={400} height={200} ref={videoRef}/>
-                <div className="font-bold text-2xl">
-                    {loading ? "Se încarcă..." : "Plasați codul pe mijlocul camerei"}
-                </div>
-            </div>}
-            {error && <div className="m-auto flex">
-                <div className="font-bold text-2xl m-auto text-center px-5">
+            <div className="m-auto flex flex-col items-center space-y-3">
+                {!error && <>
+                    <video width={400} height={200} ref={videoRef} muted playsInline/>
+                    <canvas ref={canvasRef} className="hidden"/>
+                    <div className="font-bold text-2xl">
+                        {loading ? "Se încarcă..." : "Plasați codul pe mijlocul camerei"}
+                    </div>
+                </>}
+                {error && <div className="font-bold text-2xl m-auto text-center px-5">
                     A apărut o eroare la deschiderea scanner-ului! Cel mai probabil camera implicită nu a fost setată din panoul de administrare
-                </div>
-            </div>}
+                </div>}
+                <label className="px-2.5 py-1.5 text-black-5 text-sm font-medium text-center bg-orange rounded-2xl cursor-pointer">
+                    Citește dintr-o fotografie
+                    <input type="file" accept="image/*" className="hidden" onChange={async (event) => {
+                        const file = event.target.files?.[0];
+                        if (file) await decodePhoto(file);
+                        event.target.value = "";
+                    }}/>
+                </label>
+                {choices?.length === 0 && <div className="font-medium">Nu a fost găsit niciun cod în fotografie</div>}
+                {choices && choices.length > 0 && <div className="flex flex-col items-center space-y-1">
+                    <div className="font-medium">Alegeți codul:</div>
+                    {choices.map((code) =>
+                        <button key={code.text} onClick={() => props.onDecode(code.text)}
+                                className="px-2.5 py-1 text-sm font-medium bg-black-5 rounded-lg">
+                            {code.text}
+                        </button>)}
+                </div>}
+            </div>
             <div className="absolute z-0 right-0 top-0 cursor-pointer" onClick={props.onClose}>
                 <svg xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" strokeWidth={1.5}
                      stroke="red" className="w-8 h-8">
@@ -68,5 +127,3 @@ export default function ScannerModal(props: Props) {
         </div>
     )
 }
-
-
@@ -0,0 +1,50 @@
+import {useState} from "react";
+import {invoke, validationMessage} from "../../api.ts";
+
+type Props = {
+    label: string,
+    command: string,
+    args: Record<string, unknown>,
+    // how many labels fit on a sheet, the most that can already be used
+    perSheet: number,
+}
+
+const sheetMessages: Record<string, string> = {
+    isbnSheet: "Etichetele ISBN nu încap pe pagină, verifică formatul foii",
+    spineSheet: "Etichetele pentru cotor nu încap pe pagină, verifică formatul foii",
+    badgeSheet: "Legitimațiile nu încap pe pagină, verifică formatul foii",
+    start: "Foaia nu are atâtea etichete",
+    labels: "Nu este nimic de tipărit",
+    isbn: "Una dintre cărți nu are un ISBN valid",
+    barcode: "Unul dintre coduri nu poate fi scris cu tipul de cod de bare ales în setări",
+    downloadDirectory: "Nu există un folder Documente, alege în setări unde se salvează fișierele",
+};
+
+// Saves a PDF of labels in the download folder, starting after the labels already used on the first sheet.
+export default function SheetDownload({label, command, args, perSheet}: Props) {
+    const [used, setUsed] = useState(0);
+    const [message, setMessage] = useState<string | null>(null);
+
+    const download = async () => {
+        setMessage(null);
+        try {
+            const path: string = await invoke(command, {...args, start: used});
+            setMessage(`PDF-ul a fost salvat în ${path}`);
+        } catch (error) {
+            setMessage(validationMessage(error, sheetMessages) ?? "PDF-ul nu a putut fi salvat");
+        }
+    }
+
+    return <div className="flex flex-col w-full space-y-1">
+        <div className="flex items-center space-x-2">
+            <button type="button" onClick={download}
+                    className="flex-grow px-2 py-1 border border-orange text-sm font-medium rounded-2xl">
+                {label}
+            </button>
+            <input type="number" min={0} max={perSheet - 1} value={used} title="Etichete deja folosite pe prima foaie"
+                   className="w-14 rounded-lg border p-0.5 text-sm"
+                   onChange={(event) => setUsed(Number(event.target.value))}/>
+        </div>
+        {message && <p className="text-xs">{message}</p>}
+    </div>
+}
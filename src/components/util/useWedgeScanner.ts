@@ -0,0 +1,54 @@
+import {useEffect, useRef} from "react";
+import {Book} from "../books/BooksPage.tsx";
+import {Client} from "../clients/ClientsPage.tsx";
+
+// What `resolve_scan` finds for a scanned code.
+export type ScanTarget =
+    { kind: "book", book: Book, copy: number | null } |
+    { kind: "client", client: Client };
+
+// Keyboard-wedge scanners type a code and an Enter much faster than anyone types by hand.
+const MAX_KEY_INTERVAL = 50;
+const MIN_LENGTH = 4;
+
+type Handler = (code: string) => void;
+
+// The page mounted last gets the scans, so a client page can lend books while the rest
+// of the app only opens what was scanned.
+const handlers: { current: Handler }[] = [];
+let typed = "";
+let lastKey = 0;
+
+function processKeyDown(event: KeyboardEvent) {
+    // scans into a field are typed there, as they always were
+    const target = event.target as HTMLElement | null;
+    if (target && (["INPUT", "TEXTAREA", "SELECT"].includes(target.tagName) || target.isContentEditable)) return;
+
+    const now = event.timeStamp;
+    if (now - lastKey > MAX_KEY_INTERVAL) typed = "";
+    lastKey = now;
+
+    if (event.key === "Enter") {
+        if (typed.length >= MIN_LENGTH) {
+            event.preventDefault();
+            handlers[handlers.length - 1]?.current(typed);
+        }
+        typed = "";
+    } else if (event.key.length === 1) {
+        typed += event.key;
+    }
+}
+
+export function useWedgeScanner(handler: Handler) {
+    const ref = useRef(handler);
+    ref.current = handler;
+
+    useEffect(() => {
+        handlers.push(ref);
+        if (handlers.length === 1) document.addEventListener("keydown", processKeyDown);
+        return () => {
+            handlers.splice(handlers.indexOf(ref), 1);
+            if (handlers.length === 0) document.removeEventListener("keydown", processKeyDown);
+        }
+    }, []);
+}
@@ -30,6 +30,9 @@ import AccountSettings, {
 import {settingsProvider} from "./components/settings/settings.ts";
 import AdminSettings, {loader as adminSettingsLoader} from "./components/settings/AdminSettings.tsx";
 import GeneralSettings, {action as saveSettingsAction} from "./components/settings/GeneralSettings.tsx";
+import BadgeSettings, {loader as badgeSettingsLoader} from "./components/settings/BadgeSettings.tsx";
+import LabelSettings from "./components/settings/LabelSettings.tsx";
+import DatabaseSettings from "./components/settings/DatabaseSettings.tsx";
 import UserManagement, {
     loader as userManagementLoader,
     action as userManagementAction,
@@ -39,6 +42,11 @@ import BookLookupPage, {
     action as bookLookupAction,
     loader as bookLookupLoader
 } from "./components/lookup/BookLookupPage.tsx";
+import StagingPage, {loader as stagingLoader} from "./components/staging/StagingPage.tsx";
+import RecoveryPage, {
+    action as recoveryAction,
+    loader as recoveryLoader
+} from "./components/recovery/RecoveryPage.tsx";
 
 const router = createBrowserRouter([
     {
@@ -71,6 +79,19 @@ const router = createBrowserRouter([
                         element: <GeneralSettings/>,
                         action: saveSettingsAction
                     },
+                    {
+                        path: "badge",
+                        element: <BadgeSettings/>,
+                        loader: badgeSettingsLoader
+                    },
+                    {
+                        path: "labels",
+                        element: <LabelSettings/>
+                    },
+                    {
+                        path: "database",
+                        element: <DatabaseSettings/>
+                    },
                     {
                         path: "users",
                         element: <UserManagement/>,
@@ -97,6 +118,11 @@ const router = createBrowserRouter([
                 action: bookLookupAction,
                 loader: bookLookupLoader,
             },
+            {
+                path: "staging",
+                element: <StagingPage/>,
+                loader: stagingLoader,
+            },
             {
                 path: "books",
                 element: <BooksPage/>,
@@ -170,6 +196,12 @@ const router = createBrowserRouter([
             }
         ]
     },
+    {
+        path: "/recovery",
+        element: <RecoveryPage/>,
+        loader: recoveryLoader,
+        action: recoveryAction,
+    },
     {
         path: "/logout",
         async action() {